@@ -318,7 +318,11 @@ fn recv_system(args: RecvSystemArgs) {
                 tracing::debug!("received startsim, sending subscribe messages");
                 *metadata_store = new_metadata_store.clone();
                 *time_step_res = TimeStep(*time_step);
-                for id in metadata_store.component_index.keys() {
+                // resources belong to the world rather than an entity, so they aren't shown
+                for (id, &index) in &metadata_store.component_index {
+                    if metadata_store.metadata[index].is_resource() {
+                        continue;
+                    }
                     let packet = Packet {
                         stream_id: StreamId::CONTROL,
                         payload: Payload::ControlMsg::<Bytes>(ControlMsg::sub_component_id(*id)),
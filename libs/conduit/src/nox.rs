@@ -34,6 +34,8 @@ impl PrimitiveTy {
             PrimitiveTy::Bool => ElementType::Pred,
             PrimitiveTy::F32 => ElementType::F32,
             PrimitiveTy::F64 => ElementType::F64,
+            PrimitiveTy::F16 => ElementType::F16,
+            PrimitiveTy::BF16 => ElementType::Bf16,
         }
     }
 }
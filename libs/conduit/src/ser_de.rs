@@ -239,6 +239,8 @@ impl ComponentType {
             PrimitiveTy::Bool => cow_array(buf, shape).map(ComponentValue::Bool),
             PrimitiveTy::F32 => cow_array(buf, shape).map(ComponentValue::F32),
             PrimitiveTy::F64 => cow_array(buf, shape).map(ComponentValue::F64),
+            PrimitiveTy::F16 => cow_array(buf, shape).map(ComponentValue::F16),
+            PrimitiveTy::BF16 => cow_array(buf, shape).map(ComponentValue::BF16),
         }?;
         Ok((size, value))
     }
@@ -1,5 +1,6 @@
 use bytes::Bytes;
 use core::{fmt, hash::Hash, mem::size_of};
+use half::{bf16, f16};
 use ndarray::{CowArray, IxDyn};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 use serde::{Deserialize, Serialize};
@@ -110,6 +111,8 @@ pub enum PrimitiveTy {
     Bool,
     F32,
     F64,
+    F16,
+    BF16,
 }
 
 impl fmt::Display for PrimitiveTy {
@@ -126,6 +129,8 @@ impl fmt::Display for PrimitiveTy {
             PrimitiveTy::Bool => write!(f, "bool"),
             PrimitiveTy::F32 => write!(f, "f32"),
             PrimitiveTy::F64 => write!(f, "f64"),
+            PrimitiveTy::F16 => write!(f, "f16"),
+            PrimitiveTy::BF16 => write!(f, "bf16"),
         }
     }
 }
@@ -144,6 +149,8 @@ impl PrimitiveTy {
             PrimitiveTy::Bool => size_of::<bool>(),
             PrimitiveTy::F32 => size_of::<f32>(),
             PrimitiveTy::F64 => size_of::<f64>(),
+            PrimitiveTy::F16 => size_of::<f16>(),
+            PrimitiveTy::BF16 => size_of::<bf16>(),
         }
     }
 }
@@ -194,6 +201,8 @@ pub enum ComponentValue<'a> {
     Bool(ndarray::CowArray<'a, bool, IxDyn>),
     F32(ndarray::CowArray<'a, f32, IxDyn>),
     F64(ndarray::CowArray<'a, f64, IxDyn>),
+    F16(ndarray::CowArray<'a, f16, IxDyn>),
+    BF16(ndarray::CowArray<'a, bf16, IxDyn>),
 }
 
 impl<'a> ComponentValue<'a> {
@@ -210,6 +219,8 @@ impl<'a> ComponentValue<'a> {
             ComponentValue::Bool(_) => PrimitiveTy::Bool,
             ComponentValue::F32(_) => PrimitiveTy::F32,
             ComponentValue::F64(_) => PrimitiveTy::F64,
+            ComponentValue::F16(_) => PrimitiveTy::F16,
+            ComponentValue::BF16(_) => PrimitiveTy::BF16,
         }
     }
 
@@ -226,6 +237,8 @@ impl<'a> ComponentValue<'a> {
             ComponentValue::Bool(a) => a.shape(),
             ComponentValue::F32(a) => a.shape(),
             ComponentValue::F64(a) => a.shape(),
+            ComponentValue::F16(a) => a.shape(),
+            ComponentValue::BF16(a) => a.shape(),
         }
         .iter()
         .map(|n| *n as _)
@@ -252,6 +265,8 @@ impl<'a> ComponentValue<'a> {
             ComponentValue::Bool(a) => ComponentValue::Bool(CowArray::from(a.into_owned())),
             ComponentValue::F32(a) => ComponentValue::F32(CowArray::from(a.into_owned())),
             ComponentValue::F64(a) => ComponentValue::F64(CowArray::from(a.into_owned())),
+            ComponentValue::F16(a) => ComponentValue::F16(CowArray::from(a.into_owned())),
+            ComponentValue::BF16(a) => ComponentValue::BF16(CowArray::from(a.into_owned())),
         }
     }
 
@@ -268,6 +283,8 @@ impl<'a> ComponentValue<'a> {
             ComponentValue::Bool(b) => b.as_slice().map(bytemuck::cast_slice),
             ComponentValue::F32(b) => b.as_slice().map(bytemuck::cast_slice),
             ComponentValue::F64(b) => b.as_slice().map(bytemuck::cast_slice),
+            ComponentValue::F16(b) => b.as_slice().map(bytemuck::cast_slice),
+            ComponentValue::BF16(b) => b.as_slice().map(bytemuck::cast_slice),
         }
     }
 
@@ -285,6 +302,8 @@ impl<'a> ComponentValue<'a> {
             ComponentValue::Bool(bool) => Box::new(bool.iter().map(|&x| ElementValue::Bool(x))),
             ComponentValue::F32(f32) => Box::new(f32.iter().map(|&x| ElementValue::F32(x))),
             ComponentValue::F64(f64) => Box::new(f64.iter().map(|&x| ElementValue::F64(x))),
+            ComponentValue::F16(f16) => Box::new(f16.iter().map(|&x| ElementValue::F16(x))),
+            ComponentValue::BF16(bf16) => Box::new(bf16.iter().map(|&x| ElementValue::BF16(x))),
         }
     }
 
@@ -302,6 +321,8 @@ impl<'a> ComponentValue<'a> {
             ComponentValue::Bool(bool) => Box::new(bool.iter_mut().map(ElementValueMut::Bool)),
             ComponentValue::F32(f32) => Box::new(f32.iter_mut().map(ElementValueMut::F32)),
             ComponentValue::F64(f64) => Box::new(f64.iter_mut().map(ElementValueMut::F64)),
+            ComponentValue::F16(f16) => Box::new(f16.iter_mut().map(ElementValueMut::F16)),
+            ComponentValue::BF16(bf16) => Box::new(bf16.iter_mut().map(ElementValueMut::BF16)),
         }
     }
 
@@ -354,6 +375,14 @@ impl<'a> ComponentValue<'a> {
                 f64.indexed_iter_mut()
                     .map(|(i, x)| (i, ElementValueMut::F64(x))),
             ),
+            ComponentValue::F16(f16) => Box::new(
+                f16.indexed_iter_mut()
+                    .map(|(i, x)| (i, ElementValueMut::F16(x))),
+            ),
+            ComponentValue::BF16(bf16) => Box::new(
+                bf16.indexed_iter_mut()
+                    .map(|(i, x)| (i, ElementValueMut::BF16(x))),
+            ),
         }
     }
 }
@@ -370,6 +399,8 @@ pub enum ElementValue {
     I64(i64),
     F64(f64),
     F32(f32),
+    F16(f16),
+    BF16(bf16),
     Bool(bool),
 }
 
@@ -386,6 +417,8 @@ impl ElementValue {
             ElementValue::I64(x) => x as f64,
             ElementValue::F64(x) => x,
             ElementValue::F32(x) => x as f64,
+            ElementValue::F16(x) => x.to_f64(),
+            ElementValue::BF16(x) => x.to_f64(),
             ElementValue::Bool(x) => {
                 if x {
                     1.0
@@ -410,6 +443,8 @@ impl fmt::Display for ElementValue {
             ElementValue::I64(x) => write!(f, "{}", x),
             ElementValue::F64(x) => write!(f, "{}", x),
             ElementValue::F32(x) => write!(f, "{}", x),
+            ElementValue::F16(x) => write!(f, "{}", x),
+            ElementValue::BF16(x) => write!(f, "{}", x),
             ElementValue::Bool(x) => write!(f, "{}", x),
         }
     }
@@ -426,6 +461,8 @@ pub enum ElementValueMut<'a> {
     I64(&'a mut i64),
     F64(&'a mut f64),
     F32(&'a mut f32),
+    F16(&'a mut f16),
+    BF16(&'a mut bf16),
     Bool(&'a mut bool),
 }
 
@@ -574,9 +611,29 @@ impl Metadata {
             .unwrap_or_default()
     }
 
+    /// Returns the symbol of the unit the component is measured in, like `m s^-1`.
+    pub fn unit(&self) -> Option<&str> {
+        self.tags.get("unit").and_then(TagValue::as_str)
+    }
+
+    pub fn set_unit(&mut self, unit: impl ToString) {
+        self.tags
+            .insert("unit".to_string(), TagValue::String(unit.to_string()));
+    }
+
     pub fn component_name(&self) -> &str {
         &self.name
     }
+
+    /// Returns true if the component is a resource, a single value that belongs to the world
+    /// rather than to an entity.
+    pub fn is_resource(&self) -> bool {
+        self.tags.contains_key("resource")
+    }
+
+    pub fn set_resource(&mut self) {
+        self.tags.insert("resource".to_string(), TagValue::Unit);
+    }
 }
 
 #[cfg(feature = "std")]
@@ -1,4 +1,5 @@
 use crate::{Component, ComponentType, ComponentValue, PrimitiveTy};
+use half::{bf16, f16};
 use ndarray::array;
 use smallvec::smallvec;
 
@@ -51,4 +52,6 @@ impl_primitive!(i32, I32);
 impl_primitive!(i64, I64);
 impl_primitive!(f32, F32);
 impl_primitive!(f64, F64);
+impl_primitive!(f16, F16);
+impl_primitive!(bf16, BF16);
 impl_primitive!(bool, Bool);
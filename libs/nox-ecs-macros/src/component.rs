@@ -58,6 +58,10 @@ pub fn component(input: TokenStream) -> TokenStream {
             fn component_type() -> #crate_name::conduit::ComponentType {
                 <#ty as #crate_name::Component>::component_type()
             }
+
+            fn unit() -> Option<String> {
+                <#ty as #crate_name::Component>::unit()
+            }
         }
     }
     .into()
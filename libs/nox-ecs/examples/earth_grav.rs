@@ -2,6 +2,7 @@ use conduit::well_known::{Material, Mesh, Pbr};
 use nox::{nalgebra, SpatialForce, SpatialInertia, SpatialTransform};
 use nox::{nalgebra::vector, SpatialMotion};
 use nox_ecs::{six_dof::*, spawn_tcp_server, Integrator, Query, World, WorldPos};
+use nox_ecs::{Substeps, SubstepsExhausted};
 
 fn earth_gravity(pos: Query<(WorldPos, Inertia, Force)>) -> Query<Force> {
     pos.map(|_, _, _| {
@@ -34,8 +35,10 @@ fn main() {
             inner: vector![0.0, 0.0, 0.0, 0.0, 0.0, 0.0].into(),
         }),
         mass: Inertia(SpatialInertia {
-            inner: vector![1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 1.0].into(),
+            inner: vector![1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0].into(),
         }),
+        substeps: Substeps::default(),
+        substeps_exhausted: SubstepsExhausted::default(),
     });
 
     let time_step = 1.0 / 60.0;
@@ -2,6 +2,7 @@ use conduit::well_known::{Material, Mesh, Pbr};
 use nox::{nalgebra, SpatialForce, SpatialInertia, SpatialTransform};
 use nox::{nalgebra::vector, SpatialMotion};
 use nox_ecs::{six_dof::*, spawn_tcp_server, Integrator, Query, World, WorldPos};
+use nox_ecs::{Substeps, SubstepsExhausted};
 
 fn gravity(pos: Query<(WorldPos, Inertia, Force)>) -> Query<Force> {
     const G: f64 = 6.649e-11;
@@ -47,8 +48,10 @@ fn main() {
             inner: vector![0.0, 0.0, 0.0, 0.0, 0.0, 0.0].into(),
         }),
         mass: Inertia(SpatialInertia {
-            inner: vector![1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 1.0].into(),
+            inner: vector![1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0].into(),
         }),
+        substeps: Substeps::default(),
+        substeps_exhausted: SubstepsExhausted::default(),
     });
 
     world.spawn(Body {
@@ -66,8 +69,10 @@ fn main() {
             inner: vector![0.0, 0.0, 0.0, 0.0, 0.0, 0.0].into(),
         }),
         mass: Inertia(SpatialInertia {
-            inner: vector![1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 1.0].into(),
+            inner: vector![1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0].into(),
         }),
+        substeps: Substeps::default(),
+        substeps_exhausted: SubstepsExhausted::default(),
     });
 
     let time_step = 1.0 / 60.0;
@@ -11,6 +11,10 @@ pub trait Component: IntoOp + for<'a> nox::FromBuilder<Item<'a> = Self> {
     fn is_asset() -> bool {
         false
     }
+    /// The symbol of the unit the component is measured in, which is stored in its metadata.
+    fn unit() -> Option<String> {
+        None
+    }
 }
 
 pub trait ComponentExt: Component {
@@ -19,12 +23,16 @@ pub trait ComponentExt: Component {
     }
 
     fn metadata() -> Metadata {
-        Metadata {
+        let mut metadata = Metadata {
             name: Self::name(),
             component_type: Self::component_type(),
             asset: Self::is_asset(),
             tags: Default::default(),
+        };
+        if let Some(unit) = Self::unit() {
+            metadata.set_unit(unit);
         }
+        metadata
     }
 }
 
@@ -79,7 +87,7 @@ impl<T: ArrayElement + NativeType> Component for nox::SpatialInertia<T> {
     fn component_type() -> ComponentType {
         ComponentType {
             primitive_ty: T::PRIMITIVE_TY,
-            shape: smallvec![7],
+            shape: smallvec![10],
         }
     }
 }
@@ -96,6 +104,33 @@ impl<T: ArrayElement + NativeType> Component for nox::SpatialForce<T> {
     }
 }
 
+impl<T: Component, U: nox::Unit> Component for nox::Quantity<T, U> {
+    fn name() -> String {
+        T::name()
+    }
+    fn component_type() -> ComponentType {
+        T::component_type()
+    }
+    fn is_asset() -> bool {
+        T::is_asset()
+    }
+    fn unit() -> Option<String> {
+        Some(U::symbol())
+    }
+}
+
+impl Component for nox::RngKey {
+    fn name() -> String {
+        "rng_key".to_string()
+    }
+    fn component_type() -> ComponentType {
+        ComponentType {
+            primitive_ty: PrimitiveTy::U32,
+            shape: smallvec![2],
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct WorldPos(pub nox::SpatialTransform<f64>);
 
@@ -136,6 +171,8 @@ const fn primitive_ty<T: ArrayElement>() -> PrimitiveTy {
         ElementType::U64 => PrimitiveTy::U64,
         ElementType::F32 => PrimitiveTy::F32,
         ElementType::F64 => PrimitiveTy::F64,
+        ElementType::F16 => PrimitiveTy::F16,
+        ElementType::Bf16 => PrimitiveTy::BF16,
         _ => unimplemented!(),
     }
 }
@@ -166,4 +203,17 @@ mod tests {
         assert_eq!(WorldPos::name(), "world_pos");
         assert_eq!(Seed::name(), "seed");
     }
+
+    #[test]
+    fn component_units() {
+        #[derive(Component)]
+        struct Velocity(nox::Quantity<nox::Vector<f64, 3>, nox::MetersPerSecond>);
+
+        assert_eq!(Velocity::metadata().unit(), Some("m s^-1"));
+        assert_eq!(
+            Velocity::component_type(),
+            nox::Vector::<f64, 3>::component_type()
+        );
+        assert_eq!(WorldPos::metadata().unit(), None);
+    }
 }
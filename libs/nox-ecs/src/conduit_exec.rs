@@ -29,7 +29,8 @@ pub struct ConduitExec {
 impl ConduitExec {
     pub fn new(exec: WorldExec, rx: flume::Receiver<MsgPair>) -> Self {
         let mut metadata_store = MetadataStore::default();
-        for arch in exec.world.host.archetypes.values() {
+        let world = &exec.world.host;
+        for arch in world.archetypes.values().chain([&world.resources]) {
             for col in arch.columns.values() {
                 metadata_store.push(col.metadata.clone());
             }
@@ -342,3 +343,91 @@ pub fn spawn_tcp_server(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Component, ComponentExt, ResMut, World, RESOURCE_ENTITY_ID};
+    use conduit::client::ColumnMsg;
+    use nox::{Scalar, ScalarExt};
+    use std::{collections::HashSet, sync::Arc};
+
+    #[derive(Component)]
+    struct Elapsed(Scalar<f64>);
+
+    #[derive(Component)]
+    struct X(Scalar<f64>);
+
+    fn advance(elapsed: ResMut<Elapsed>) -> ResMut<Elapsed> {
+        ResMut::new(Elapsed(elapsed.0.clone() + 0.5))
+    }
+
+    #[test]
+    fn test_stream_resource() {
+        let mut world = World::default();
+        world.insert_resource(Elapsed(0.0.constant())).unwrap();
+        world.spawn(X(1.0.constant()));
+        let client = nox::Client::cpu().unwrap();
+        let mut exec = world.builder().tick_pipeline(advance).build().unwrap();
+        exec.run(&client).unwrap();
+        let (_, rx) = flume::unbounded();
+        let mut conduit_exec = ConduitExec::new(exec, rx);
+        let (tx, packets) = flume::unbounded();
+
+        // the resource is streamed, but isn't one of the sim's entities
+        conduit_exec.add_connection(tx.clone()).unwrap();
+        let Payload::ControlMsg(ControlMsg::StartSim {
+            metadata_store,
+            entity_ids,
+            ..
+        }) = packets.recv().unwrap().payload
+        else {
+            panic!("expected start sim");
+        };
+        let metadata = metadata_store
+            .get_metadata(&Elapsed::component_id())
+            .unwrap();
+        assert!(metadata.is_resource());
+        assert_eq!(entity_ids, HashSet::from([EntityId(0)]));
+
+        let subscribe = MsgPair {
+            msg: Msg::Control(ControlMsg::sub_component_id(Elapsed::component_id())),
+            tx: tx.downgrade(),
+        };
+        conduit_exec.process_msg_pair(subscribe).unwrap();
+        let Payload::ControlMsg(ControlMsg::OpenStream { stream_id, .. }) =
+            packets.recv().unwrap().payload
+        else {
+            panic!("expected open stream");
+        };
+        conduit_exec.send();
+        let Payload::ControlMsg(ControlMsg::Tick { .. }) = packets.recv().unwrap().payload else {
+            panic!("expected tick");
+        };
+        let packet = packets.recv().unwrap();
+        assert_eq!(packet.stream_id, stream_id);
+        let Payload::Column(payload) = packet.payload else {
+            panic!("expected column");
+        };
+        assert_eq!(payload.len, 1);
+        assert_eq!(&payload.entity_buf[..], RESOURCE_ENTITY_ID.0.to_le_bytes());
+        assert_eq!(&payload.value_buf[..], 0.5f64.to_le_bytes());
+
+        // values sent back for the resource's entity id are written to the resource
+        let update = MsgPair {
+            msg: Msg::Column(ColumnMsg {
+                metadata: Arc::new(metadata.clone()),
+                payload: ColumnPayload {
+                    time: 1,
+                    len: 1,
+                    entity_buf: Bytes::copy_from_slice(&RESOURCE_ENTITY_ID.0.to_le_bytes()),
+                    value_buf: Bytes::copy_from_slice(&2.0f64.to_le_bytes()),
+                },
+            }),
+            tx: tx.downgrade(),
+        };
+        conduit_exec.process_msg_pair(update).unwrap();
+        let elapsed = conduit_exec.exec.column(Elapsed::component_id()).unwrap();
+        assert_eq!(elapsed.typed_buf::<f64>().unwrap(), &[2.0]);
+    }
+}
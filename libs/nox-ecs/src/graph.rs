@@ -1,9 +1,12 @@
 use bytes::Buf;
 use conduit::{ComponentType, ComponentValue, EntityId};
-use nox::{xla::Literal, ArrayTy, CompFn, FromBuilder, IntoOp, Noxpr, NoxprFn, NoxprTy};
+use nox::{
+    xla::Literal, ArrayTy, CompFn, FromBuilder, IntoOp, Noxpr, NoxprFn, NoxprTy, SparseMatrix,
+    TensorItem,
+};
 use std::{collections::BTreeMap, marker::PhantomData};
 
-use crate::{Component, ComponentArray, ComponentGroup, Query, SystemParam};
+use crate::{Component, ComponentArray, ComponentGroup, Error, Query, SystemParam};
 
 #[derive(Clone)]
 pub struct GraphQuery<E> {
@@ -116,7 +119,9 @@ impl<E: EdgeComponent + 'static> SystemParam for GraphQuery<E> {
         }
     }
 
-    fn insert_into_builder(self, _builder: &mut crate::PipelineBuilder) {}
+    fn insert_into_builder(self, _builder: &mut crate::PipelineBuilder) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 pub fn exprs_from_edges_queries<A, B>(
@@ -166,6 +171,30 @@ pub fn exprs_from_edges_queries<A, B>(
 }
 
 impl<E: EdgeComponent> GraphQuery<E> {
+    /// Returns the graph's adjacency matrix, with a row for each entity in `from_query` and a
+    /// column for each entity in `to_query`.
+    ///
+    /// Edges with an endpoint missing from either query are left out. Summing over each entity's
+    /// outgoing edges is then a sparse matmul against the columns of `to_query`, which avoids
+    /// the per-degree batches that [`GraphQuery::edge_fold`] lowers to.
+    pub fn adjacency<T: TensorItem, F, G>(
+        &self,
+        from_query: &Query<F>,
+        to_query: &Query<G>,
+    ) -> Result<SparseMatrix<T>, Error> {
+        let (rows, cols) = self
+            .edges
+            .iter()
+            .filter_map(|edge| {
+                let from = from_query.entity_map.get(&edge.from)?;
+                let to = to_query.entity_map.get(&edge.to)?;
+                Some((*from as i64, *to as i64))
+            })
+            .unzip();
+        let matrix = SparseMatrix::from_pattern(from_query.len, to_query.len, rows, cols)?;
+        Ok(matrix)
+    }
+
     /// Folds over each edge of a graph, using the `from` EntityId
     /// to form th resulting `ComponentArray`
     pub fn edge_fold<I: ComponentGroup + IntoOp, F: ComponentGroup, T: ComponentGroup>(
@@ -244,7 +273,7 @@ impl<E: EdgeComponent> GraphQuery<E> {
 #[cfg(test)]
 mod tests {
 
-    use nox::{Scalar, ScalarExt};
+    use nox::{FromOp, Scalar, ScalarExt};
 
     use crate::{ComponentExt, IntoSystem};
 
@@ -282,6 +311,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_adjacency_sum() {
+        #[derive(Component)]
+        struct A(Scalar<f64>);
+
+        fn sum_system(g: GraphQuery<Edge>, a: Query<A>) -> ComponentArray<A> {
+            let adjacency = g.adjacency::<f64, _, _>(&a, &a).unwrap();
+            let values = nox::Tensor::<f64, nox::nalgebra::Dyn>::from_op(a.exprs[0].clone());
+            let sum = adjacency.matmul(&values).unwrap();
+            ComponentArray {
+                buffer: sum.into_op(),
+                entity_map: a.entity_map.clone(),
+                len: a.len,
+                phantom_data: PhantomData,
+            }
+        }
+
+        let mut world = sum_system.world();
+        let a = world.spawn(A(10.0.constant())).id();
+        let b = world.spawn(A(100.0.constant())).id();
+        let c = world.spawn(A(1000.0.constant())).id();
+        world.spawn(A(10000.0.constant()));
+        world.spawn(Edge::new(a, b));
+        world.spawn(Edge::new(a, c));
+        world.spawn(Edge::new(b, a));
+        world.spawn(Edge::new(b, c));
+
+        let client = nox::Client::cpu().unwrap();
+        let mut exec = world.build().unwrap();
+        exec.run(&client).unwrap();
+        let c = exec.column(A::component_id()).unwrap();
+        assert_eq!(c.typed_buf::<f64>().unwrap(), &[1100.0, 1010.0, 0.0, 0.0]);
+    }
+
     #[test]
     fn test_single_graph() {
         #[derive(Component)]
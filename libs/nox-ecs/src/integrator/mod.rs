@@ -1,10 +1,26 @@
 mod rk4;
+mod rk45;
 mod semi_implicit;
+mod symplectic;
 
 pub use rk4::*;
+pub use rk45::*;
 pub use semi_implicit::*;
+pub use symplectic::*;
 
 pub enum Integrator {
     Rk4,
+    /// The adaptive step [`Rk45`] integrator. It writes the number of substeps it took to the
+    /// [`Substeps`] of every [`six_dof::Body`](crate::six_dof::Body), and flags ticks that ran
+    /// out of substeps in [`SubstepsExhausted`].
+    Rk45,
     SemiImplicit,
+    /// The velocity [`Symplectic::verlet`] integrator, which assumes the acceleration only
+    /// depends on the position. In [`six_dof`](crate::six_dof::six_dof) a spinning body's
+    /// acceleration also depends on its angular velocity, through the gyroscopic and centripetal
+    /// terms, so for those bodies it's only first order and no longer symplectic.
+    Verlet,
+    /// The fourth order [`Symplectic::yoshida4`] integrator. It has the same limitation as
+    /// [`Integrator::Verlet`], and is no more accurate than it for spinning bodies.
+    Yoshida4,
 }
@@ -94,7 +94,7 @@ where
             .join_query(k3)
             .join_query(k4)
             .map(|(((du, k1), k2), k3), k4| du + (dt / 6.0) * (k1 + 2.0 * k2 + 2.0 * k3 + k4))?;
-        u.insert_into_builder(builder);
+        u.insert_into_builder(builder)?;
         Ok(())
     }
 }
@@ -0,0 +1,401 @@
+use crate::{Component, ComponentArray, ComponentExt, ComponentGroup, Error, Query};
+use crate::{PipelineBuilder, System, SystemParam};
+use conduit::ComponentId;
+use nox::xla::ElementType;
+use nox::{IntoOp, Noxpr, NoxprScalarExt, Scalar, ScalarExt};
+use nox_ecs_macros::Component;
+use smallvec::smallvec;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::ops::Add;
+use std::sync::Arc;
+
+/// The coefficients of the Dormand–Prince method for stages 2 through 7. The last row is also the
+/// fifth order solution, so the final stage is evaluated at the state that's kept.
+const A: [&[f64]; 6] = [
+    &[1.0 / 5.0],
+    &[3.0 / 40.0, 9.0 / 40.0],
+    &[44.0 / 45.0, -56.0 / 15.0, 32.0 / 9.0],
+    &[
+        19372.0 / 6561.0,
+        -25360.0 / 2187.0,
+        64448.0 / 6561.0,
+        -212.0 / 729.0,
+    ],
+    &[
+        9017.0 / 3168.0,
+        -355.0 / 33.0,
+        46732.0 / 5247.0,
+        49.0 / 176.0,
+        -5103.0 / 18656.0,
+    ],
+    &[
+        35.0 / 384.0,
+        0.0,
+        500.0 / 1113.0,
+        125.0 / 192.0,
+        -2187.0 / 6784.0,
+        11.0 / 84.0,
+    ],
+];
+
+/// The coefficients of the embedded fourth order solution, used to estimate the error.
+const B4: [f64; 7] = [
+    5179.0 / 57600.0,
+    0.0,
+    7571.0 / 16695.0,
+    393.0 / 640.0,
+    -92097.0 / 339200.0,
+    187.0 / 2100.0,
+    1.0 / 40.0,
+];
+
+/// The number of substeps the adaptive integrator took during the last tick.
+///
+/// The integrator writes this to every entity that has the component.
+#[derive(Component)]
+pub struct Substeps(pub Scalar<u64>);
+
+impl Default for Substeps {
+    fn default() -> Self {
+        Self(ScalarExt::constant(0))
+    }
+}
+
+/// Set to 1 if the adaptive integrator ran out of substeps during the last tick, leaving the
+/// state short of the full tick, and 0 otherwise.
+///
+/// The integrator writes this to every entity that has the component.
+#[derive(Component)]
+pub struct SubstepsExhausted(pub Scalar<u64>);
+
+impl Default for SubstepsExhausted {
+    fn default() -> Self {
+        Self(ScalarExt::constant(0))
+    }
+}
+
+/// The error allowed in each substep, which is `atol + rtol * |u|` for every element of a
+/// component.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerance {
+    pub atol: f64,
+    pub rtol: f64,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            atol: 1e-6,
+            rtol: 1e-6,
+        }
+    }
+}
+
+/// An adaptive step Dormand–Prince (RK45) integrator.
+///
+/// Each tick is split into as many substeps as it takes to keep the estimated error of every
+/// component of `U` within its [`Tolerance`], up to a maximum number of substeps after which
+/// the tick ends early and [`SubstepsExhausted`] is set. Every tick starts by attempting a single
+/// step of `dt`.
+///
+/// The components of `DU` are combined elementwise, so `DU` must be a plain vector space, like
+/// the velocities and accelerations in `nox`, and its elements must be `f64`s.
+pub struct Rk45<U, DU, Pipe> {
+    dt: f64,
+    max_substeps: u64,
+    tolerance: Tolerance,
+    tolerances: HashMap<ComponentId, Tolerance>,
+    pipe: Arc<Pipe>,
+    phantom_data: PhantomData<(U, DU)>,
+}
+
+impl<Pipe, U, DU> Rk45<U, DU, Pipe> {
+    pub fn new(pipe: Pipe, dt: f64) -> Self {
+        Self {
+            dt,
+            max_substeps: 1000,
+            tolerance: Tolerance::default(),
+            tolerances: HashMap::new(),
+            pipe: Arc::new(pipe),
+            phantom_data: PhantomData,
+        }
+    }
+
+    /// Sets the tolerance of every component without its own tolerance.
+    pub fn tolerance(mut self, tolerance: Tolerance) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Sets the tolerance of the component `C`.
+    pub fn component_tolerance<C: Component>(mut self, tolerance: Tolerance) -> Self {
+        self.tolerances.insert(C::component_id(), tolerance);
+        self
+    }
+
+    pub fn max_substeps(mut self, max_substeps: u64) -> Self {
+        self.max_substeps = max_substeps;
+        self
+    }
+}
+
+pub trait Rk45Ext {
+    fn rk45<U, DU>(self) -> Rk45<U, DU, Self>
+    where
+        Self: Sized;
+    fn rk45_with_dt<U, DU>(self, dt: f64) -> Rk45<U, DU, Self>
+    where
+        Self: Sized;
+}
+
+impl<Sys> Rk45Ext for Sys
+where
+    Sys: System,
+{
+    fn rk45<U, DU>(self) -> Rk45<U, DU, Self>
+    where
+        Self: Sized,
+    {
+        Rk45::new(self, 1.0 / 60.0)
+    }
+
+    fn rk45_with_dt<U, DU>(self, dt: f64) -> Rk45<U, DU, Self>
+    where
+        Self: Sized,
+    {
+        Rk45::new(self, dt)
+    }
+}
+
+impl<Pipe, U, DU> System for Rk45<U, DU, Pipe>
+where
+    Query<U>: SystemParam<Item = Query<U>> + Clone,
+    Query<DU>: SystemParam<Item = Query<DU>> + Clone,
+    U: Add<DU, Output = U> + ComponentGroup + IntoOp + for<'a> nox::FromBuilder<Item<'a> = U>,
+    DU: ComponentGroup + IntoOp + for<'a> nox::FromBuilder<Item<'a> = DU>,
+    Pipe: System,
+{
+    type Arg = Pipe::Arg;
+    type Ret = Pipe::Ret;
+
+    fn init_builder(&self, builder: &mut PipelineBuilder) -> Result<(), Error> {
+        self.pipe.init_builder(builder)?;
+        Query::<U>::init(builder)?;
+        Query::<DU>::init(builder)?;
+        if builder.world.column::<Substeps>().is_some() {
+            ComponentArray::<Substeps>::init(builder)?;
+        }
+        if builder.world.column::<SubstepsExhausted>().is_some() {
+            ComponentArray::<SubstepsExhausted>::init(builder)?;
+        }
+        Ok(())
+    }
+
+    fn add_to_builder(&self, builder: &mut PipelineBuilder) -> Result<(), Error> {
+        let dt = self.dt;
+        // the state is (elapsed time, step size, attempted substeps, accepted substeps)
+        let init = vec![
+            0.0f64.constant(),
+            dt.constant(),
+            0i64.constant(),
+            0i64.constant(),
+        ];
+        let max_substeps = self.max_substeps as i64;
+        let unfinished = |elapsed: &Noxpr| elapsed.clone().less((dt * (1.0 - 1e-9)).constant());
+        let cond = |state: &[Noxpr]| {
+            unfinished(&state[0]).and(state[2].clone().less(max_substeps.constant()))
+        };
+        let body = |builder: &mut PipelineBuilder, state: &[Noxpr]| {
+            let [elapsed, h, attempts, accepted] = [0, 1, 2, 3].map(|i| state[i].clone());
+            let h = h.min(dt.constant() - elapsed.clone());
+            let start = builder
+                .vars
+                .values()
+                .map(|var| var.borrow().buffer.clone())
+                .collect::<Vec<_>>();
+
+            let u = Query::<U>::from_builder(builder);
+            self.pipe.add_to_builder(builder)?;
+            let mut ks = vec![Query::<DU>::from_builder(builder)];
+            let mut next_u = u.clone();
+            for row in A {
+                next_u = step(&u, &ks, row, &h)?;
+                next_u.clone().insert_into_builder(builder)?;
+                self.pipe.add_to_builder(builder)?;
+                ks.push(Query::<DU>::from_builder(builder));
+            }
+            let err = self.error(&u, &next_u, &step(&u, &ks, &B4, &h)?)?;
+
+            // the vars now hold the state after the fifth order step, which is kept if it's
+            // within tolerance
+            let accept = err.clone().less_or_equal(1.0f64.constant());
+            for (var, start) in builder.vars.values().zip(start) {
+                let mut var = var.borrow_mut();
+                let shape = var.buffer.shape().ok_or(nox::Error::UnknownShape)?;
+                var.buffer = accept
+                    .clone()
+                    .broadcast(shape)
+                    .select(var.buffer.clone(), start);
+            }
+            let factor = (0.9f64.constant() * err.pow((-0.2f64).constant()))
+                .clamp(0.2f64.constant(), 5.0f64.constant());
+            Ok(vec![
+                accept.clone().select(elapsed.clone() + h.clone(), elapsed),
+                h * factor,
+                attempts + 1i64.constant(),
+                accept.select(accepted.clone() + 1i64.constant(), accepted),
+            ])
+        };
+        let state = builder.trace_loop(init, cond, body)?;
+
+        // the loop only stops short of the full tick once it runs out of substeps
+        let reports = [
+            (Substeps::component_id(), state[3].clone()),
+            (SubstepsExhausted::component_id(), unfinished(&state[0])),
+        ];
+        for (id, value) in reports {
+            if let Some(var) = builder.vars.get(&id) {
+                let mut var = var.borrow_mut();
+                var.buffer = value
+                    .convert(ElementType::U64)
+                    .broadcast(smallvec![var.len as i64]);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<U: ComponentGroup, DU, Pipe> Rk45<U, DU, Pipe> {
+    /// Returns the largest error between the fifth and fourth order solutions, relative to
+    /// each component's tolerance.
+    fn error(&self, u: &Query<U>, u5: &Query<U>, u4: &Query<U>) -> Result<Noxpr, Error> {
+        let mut err = 0.0f64.constant();
+        for (i, id) in U::component_ids().enumerate() {
+            let tolerance = self.tolerances.get(&id).unwrap_or(&self.tolerance);
+            let [u, u5, u4] = [u, u5, u4].map(|q| q.exprs[i].clone().convert(ElementType::F64));
+            let shape = u.shape().ok_or(nox::Error::UnknownShape)?;
+            let scale = tolerance.atol.constant().broadcast(shape.clone())
+                + tolerance.rtol.constant().broadcast(shape.clone())
+                    * u.abs().max(u5.clone().abs());
+            let component_err =
+                ((u5 - u4).abs() / scale).reduce_max((0..shape.len() as i64).collect());
+            err = err.max(component_err);
+        }
+        Ok(err)
+    }
+}
+
+/// Returns `u + h * sum(coeffs[i] * ks[i])`.
+fn step<U, DU>(u: &Query<U>, ks: &[Query<DU>], coeffs: &[f64], h: &Noxpr) -> Result<Query<U>, Error>
+where
+    U: Add<DU, Output = U> + ComponentGroup + IntoOp + for<'a> nox::FromBuilder<Item<'a> = U>,
+    DU: ComponentGroup + IntoOp + for<'a> nox::FromBuilder<Item<'a> = DU>,
+{
+    let mut du = ks[0].clone();
+    for (i, expr) in du.exprs.iter_mut().enumerate() {
+        let shape = expr.shape().ok_or(nox::Error::UnknownShape)?;
+        let sum = ks
+            .iter()
+            .zip(coeffs)
+            .filter(|(_, c)| **c != 0.0)
+            .map(|(k, c)| k.exprs[i].clone() * c.constant().broadcast(shape.clone()))
+            .reduce(|a, b| a + b)
+            .expect("every row has a nonzero coefficient");
+        *expr = sum * h.clone().broadcast(shape);
+    }
+    u.clone().join_query(du).map(|u: U, du: DU| u + du)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Archetype, IntoSystem, World, WorldExec};
+
+    #[derive(Clone, Component)]
+    struct X(Scalar<f64>);
+
+    #[derive(Clone, Component)]
+    struct V(Scalar<f64>);
+
+    impl Add<V> for X {
+        type Output = X;
+
+        fn add(self, v: V) -> Self::Output {
+            X(self.0 + v.0)
+        }
+    }
+
+    #[derive(Archetype)]
+    struct Body {
+        x: X,
+        v: V,
+        substeps: Substeps,
+        exhausted: SubstepsExhausted,
+    }
+
+    /// Integrates `dx/dt = -rate * x` from `x = 1` over a single tick of one second.
+    fn decay(rate: f64, tolerance: Tolerance, max_substeps: u64) -> WorldExec {
+        let mut world = World::default();
+        world.spawn(Body {
+            x: X(nox::ScalarExt::constant(1.0f64)),
+            v: V(nox::ScalarExt::constant(0.0f64)),
+            substeps: Substeps::default(),
+            exhausted: SubstepsExhausted::default(),
+        });
+        let decay = move |x: ComponentArray<X>| -> ComponentArray<V> {
+            x.map(|x: X| V(x.0 * -rate)).unwrap()
+        };
+        let integrator = decay
+            .into_system()
+            .rk45_with_dt::<X, V>(1.0)
+            .tolerance(tolerance)
+            .max_substeps(max_substeps);
+        let client = nox::Client::cpu().unwrap();
+        let mut exec = world.builder().tick_pipeline(integrator).build().unwrap();
+        exec.run(&client).unwrap();
+        exec
+    }
+
+    fn read<T: nox::xla::ArrayElement + bytemuck::Pod>(exec: &mut WorldExec, id: ComponentId) -> T {
+        exec.column(id).unwrap().typed_buf::<T>().unwrap()[0]
+    }
+
+    #[test]
+    fn test_exponential_decay() {
+        let tolerance = Tolerance {
+            atol: 1e-10,
+            rtol: 1e-10,
+        };
+        let mut exec = decay(1.0, tolerance, 1000);
+        let x = read::<f64>(&mut exec, X::component_id());
+        assert!((x - (-1.0f64).exp()).abs() < 1e-8, "{}", x);
+        assert!(read::<u64>(&mut exec, Substeps::component_id()) > 1);
+        assert_eq!(read::<u64>(&mut exec, SubstepsExhausted::component_id()), 0);
+    }
+
+    #[test]
+    fn test_stiff_decay() {
+        // a single step of the whole tick is far outside the stability region and would blow up,
+        // so the result is only accurate if those steps are rejected and retried smaller
+        let mut exec = decay(50.0, Tolerance::default(), 1000);
+        let x = read::<f64>(&mut exec, X::component_id());
+        assert!(x.abs() < 1e-5, "{}", x);
+        assert!(read::<u64>(&mut exec, Substeps::component_id()) > 10);
+        assert_eq!(read::<u64>(&mut exec, SubstepsExhausted::component_id()), 0);
+    }
+
+    #[test]
+    fn test_max_substeps() {
+        let tolerance = Tolerance {
+            atol: 1e-10,
+            rtol: 1e-10,
+        };
+        let mut exec = decay(1.0, tolerance, 3);
+        // the tick ends early, so x has only partly decayed
+        let x = read::<f64>(&mut exec, X::component_id());
+        assert!(x > (-1.0f64).exp() + 1e-3, "{}", x);
+        assert!(read::<u64>(&mut exec, Substeps::component_id()) <= 3);
+        assert_eq!(read::<u64>(&mut exec, SubstepsExhausted::component_id()), 1);
+    }
+}
@@ -0,0 +1,228 @@
+use crate::{ComponentGroup, Error, Query};
+use crate::{IntoSystem, PipelineBuilder, System, SystemParam};
+use nox::IntoOp;
+use std::marker::PhantomData;
+use std::ops::{Add, Mul};
+use std::sync::Arc;
+
+/// A symplectic integrator made of one or more velocity Verlet (kick-drift-kick leapfrog) steps,
+/// where `Pipe` calculates the acceleration `A` from the position `X` and velocity `V`.
+///
+/// Each step of size `w * dt` is:
+/// $$v_{1/2} = v + \frac{w dt}{2} a(x)$$
+/// $$x' = x + w dt v_{1/2}$$
+/// $$v' = v_{1/2} + \frac{w dt}{2} a(x')$$
+///
+/// Like [`semi_implicit_euler_with_dt`](crate::semi_implicit_euler_with_dt), this assumes that
+/// $dx/dt = v$ and that the acceleration only depends on the position. Any velocity dependence
+/// is evaluated at the half step velocity, so the integrator is no longer symplectic and drops
+/// to first order.
+pub struct Symplectic<X, V, A, Pipe> {
+    dt: f64,
+    weights: Vec<f64>,
+    pipe: Arc<Pipe>,
+    phantom_data: PhantomData<(X, V, A)>,
+}
+
+impl<X, V, A, Pipe> Symplectic<X, V, A, Pipe> {
+    /// Creates a second order velocity Verlet integrator.
+    pub fn verlet(pipe: Pipe, dt: f64) -> Self {
+        Self::with_weights(pipe, dt, vec![1.0])
+    }
+
+    /// Creates a fourth order Yoshida integrator, which composes three Verlet steps, the middle
+    /// one going backwards in time.
+    pub fn yoshida4(pipe: Pipe, dt: f64) -> Self {
+        let cbrt_2 = 2.0f64.cbrt();
+        let w1 = 1.0 / (2.0 - cbrt_2);
+        let w0 = -cbrt_2 / (2.0 - cbrt_2);
+        Self::with_weights(pipe, dt, vec![w1, w0, w1])
+    }
+
+    fn with_weights(pipe: Pipe, dt: f64, weights: Vec<f64>) -> Self {
+        Self {
+            dt,
+            weights,
+            pipe: Arc::new(pipe),
+            phantom_data: PhantomData,
+        }
+    }
+}
+
+pub trait SymplecticExt {
+    fn verlet<X, V, A>(self) -> Symplectic<X, V, A, Self>
+    where
+        Self: Sized;
+    fn verlet_with_dt<X, V, A>(self, dt: f64) -> Symplectic<X, V, A, Self>
+    where
+        Self: Sized;
+    fn yoshida4<X, V, A>(self) -> Symplectic<X, V, A, Self>
+    where
+        Self: Sized;
+    fn yoshida4_with_dt<X, V, A>(self, dt: f64) -> Symplectic<X, V, A, Self>
+    where
+        Self: Sized;
+}
+
+impl<Sys> SymplecticExt for Sys
+where
+    Sys: System,
+{
+    fn verlet<X, V, A>(self) -> Symplectic<X, V, A, Self>
+    where
+        Self: Sized,
+    {
+        Symplectic::verlet(self, 1.0 / 60.0)
+    }
+
+    fn verlet_with_dt<X, V, A>(self, dt: f64) -> Symplectic<X, V, A, Self>
+    where
+        Self: Sized,
+    {
+        Symplectic::verlet(self, dt)
+    }
+
+    fn yoshida4<X, V, A>(self) -> Symplectic<X, V, A, Self>
+    where
+        Self: Sized,
+    {
+        Symplectic::yoshida4(self, 1.0 / 60.0)
+    }
+
+    fn yoshida4_with_dt<X, V, A>(self, dt: f64) -> Symplectic<X, V, A, Self>
+    where
+        Self: Sized,
+    {
+        Symplectic::yoshida4(self, dt)
+    }
+}
+
+impl<X, V, A, Pipe> System for Symplectic<X, V, A, Pipe>
+where
+    Query<X>: SystemParam<Item = Query<X>> + Clone,
+    Query<V>: SystemParam<Item = Query<V>> + Clone,
+    Query<A>: SystemParam<Item = Query<A>> + Clone,
+    X: Add<V, Output = X> + ComponentGroup + IntoOp + for<'a> nox::FromBuilder<Item<'a> = X>,
+    V: Add<A, Output = V> + ComponentGroup + IntoOp + for<'a> nox::FromBuilder<Item<'a> = V>,
+    A: ComponentGroup + IntoOp + for<'a> nox::FromBuilder<Item<'a> = A>,
+    f64: Mul<V, Output = V>,
+    f64: Mul<A, Output = A>,
+    Pipe: System,
+{
+    type Arg = Pipe::Arg;
+    type Ret = Pipe::Ret;
+
+    fn init_builder(&self, builder: &mut PipelineBuilder) -> Result<(), Error> {
+        self.pipe.init_builder(builder)?;
+        Query::<X>::init(builder)?;
+        Query::<V>::init(builder)?;
+        Query::<A>::init(builder)
+    }
+
+    fn add_to_builder(&self, builder: &mut PipelineBuilder) -> Result<(), Error> {
+        // the acceleration is recalculated after every drift, so it always matches the position
+        self.pipe.add_to_builder(builder)?;
+        for w in &self.weights {
+            let h = w * self.dt;
+            let kick = move |query: Query<(V, A)>| -> Query<V> {
+                query.map(|v, a| v + (h / 2.0) * a).unwrap()
+            };
+            let drift =
+                move |query: Query<(X, V)>| -> Query<X> { query.map(|x, v| x + h * v).unwrap() };
+            kick.pipe(drift)
+                .pipe(self.pipe.clone())
+                .pipe(kick)
+                .add_to_builder(builder)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Archetype, ComponentArray, World};
+    use crate::{Component, ComponentExt};
+    use nox::{Scalar, ScalarExt};
+
+    #[derive(Clone, Component)]
+    struct X(Scalar<f64>);
+
+    #[derive(Clone, Component)]
+    struct V(Scalar<f64>);
+
+    #[derive(Clone, Component)]
+    struct A(Scalar<f64>);
+
+    impl Add<V> for X {
+        type Output = X;
+
+        fn add(self, v: V) -> Self::Output {
+            X(self.0 + v.0)
+        }
+    }
+
+    impl Add<A> for V {
+        type Output = V;
+
+        fn add(self, a: A) -> Self::Output {
+            V(self.0 + a.0)
+        }
+    }
+
+    impl Mul<V> for f64 {
+        type Output = V;
+
+        fn mul(self, rhs: V) -> Self::Output {
+            V(self * rhs.0)
+        }
+    }
+
+    impl Mul<A> for f64 {
+        type Output = A;
+
+        fn mul(self, rhs: A) -> Self::Output {
+            A(self * rhs.0)
+        }
+    }
+
+    #[derive(Archetype)]
+    struct Body {
+        x: X,
+        v: V,
+        a: A,
+    }
+
+    fn spring(x: ComponentArray<X>) -> ComponentArray<A> {
+        x.map(|x: X| A(-x.0)).unwrap()
+    }
+
+    fn oscillate(integrator: impl System) -> f64 {
+        let mut world = World::default();
+        world.spawn(Body {
+            x: X(1.0.constant()),
+            v: V(0.0.constant()),
+            a: A(0.0.constant()),
+        });
+        let builder = world.builder().tick_pipeline(integrator);
+        let client = nox::Client::cpu().unwrap();
+        let mut exec = builder.build().unwrap();
+        for _ in 0..10 {
+            exec.run(&client).unwrap();
+        }
+        let col = exec.column(X::component_id()).unwrap();
+        col.typed_buf::<f64>().unwrap()[0]
+    }
+
+    #[test]
+    fn test_verlet() {
+        let x = oscillate(spring.into_system().verlet_with_dt::<X, V, A>(0.1));
+        assert!((x - 1.0f64.cos()).abs() < 1e-3, "{}", x);
+    }
+
+    #[test]
+    fn test_yoshida4() {
+        let x = oscillate(spring.into_system().yoshida4_with_dt::<X, V, A>(0.1));
+        assert!((x - 1.0f64.cos()).abs() < 1e-5, "{}", x);
+    }
+}
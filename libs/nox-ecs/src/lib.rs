@@ -4,8 +4,13 @@ use bytemuck::{AnyBitPattern, Pod};
 use conduit::well_known::EntityMetadata;
 use conduit::{Asset, ComponentId, ComponentType, ComponentValue, EntityId, Metadata};
 use history::History;
-use nox::xla::{ArrayElement, BufferArgsRef, HloModuleProto, PjRtBuffer, PjRtLoadedExecutable};
-use nox::{ArrayTy, Client, CompFn, FromOp, Noxpr, NoxprFn};
+use nox::xla::{
+    ArrayElement, BufferArgsRef, ElementType, HloModuleProto, PjRtBuffer, PjRtLoadedExecutable,
+};
+use nox::{
+    ArrayTy, Client, CompFn, FromOp, GraphExporter, Noxpr, NoxprFn, NoxprId, NoxprNode,
+    ShapeChecker,
+};
 use once_cell::sync::OnceCell;
 use polars::PolarsWorld;
 use serde::{Deserialize, Serialize};
@@ -15,6 +20,8 @@ use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::iter::once;
+use std::num::NonZeroU64;
+use std::ops::Deref;
 use std::path::Path;
 use std::sync::Arc;
 use std::thread::JoinHandle;
@@ -30,7 +37,10 @@ mod conduit_exec;
 mod dyn_array;
 mod host_column;
 mod integrator;
+mod linearize;
 mod query;
+mod resource;
+mod rng;
 
 pub mod graph;
 pub mod history;
@@ -43,7 +53,10 @@ pub use conduit_exec::*;
 pub use dyn_array::*;
 pub use host_column::*;
 pub use integrator::*;
+pub use linearize::*;
 pub use query::*;
+pub use resource::*;
+pub use rng::*;
 
 pub use nox_ecs_macros::{Archetype, Component};
 
@@ -79,6 +92,18 @@ impl Table<HostStore> {
     pub fn entity_ids(&self) -> impl Iterator<Item = EntityId> + '_ {
         self.entity_buffer.iter::<u64>().map(EntityId)
     }
+
+    pub fn copy_to_client(&self, client: &Client) -> Result<Table<ClientStore>, Error> {
+        let columns = self
+            .columns
+            .iter()
+            .map(|(id, column)| Ok((*id, column.copy_to_client(client)?)))
+            .collect::<Result<BTreeMap<_, _>, Error>>()?;
+        Ok(Table {
+            columns,
+            entity_buffer: self.entity_buffer.copy_to_client(client)?,
+        })
+    }
 }
 
 impl<S: WorldStore> std::fmt::Debug for Table<S>
@@ -96,6 +121,9 @@ where
 
 pub struct World<S: WorldStore = HostStore> {
     pub archetypes: ustr::UstrMap<Table<S>>,
+    /// Resources aren't entities, so they're kept out of the archetypes in a table whose only row
+    /// belongs to [`RESOURCE_ENTITY_ID`].
+    pub resources: Table<S>,
     pub component_map: HashMap<ComponentId, ArchetypeName>,
     pub assets: AssetStore,
     pub tick: u64,
@@ -106,6 +134,7 @@ impl Clone for World {
     fn clone(&self) -> Self {
         Self {
             archetypes: self.archetypes.clone(),
+            resources: self.resources.clone(),
             component_map: self.component_map.clone(),
             assets: self.assets.clone(),
             tick: 0,
@@ -114,10 +143,11 @@ impl Clone for World {
     }
 }
 
-impl<S: WorldStore> Default for World<S> {
+impl Default for World {
     fn default() -> Self {
         Self {
             archetypes: Default::default(),
+            resources: Default::default(),
             component_map: Default::default(),
             assets: Default::default(),
             tick: 0,
@@ -128,13 +158,7 @@ impl<S: WorldStore> Default for World<S> {
 
 impl<S: WorldStore> World<S> {
     pub fn column_mut<C: Component + 'static>(&mut self) -> Option<ColumnRefMut<'_, S>> {
-        let id = self.component_map.get(&C::component_id())?;
-        let archetype = self.archetypes.get_mut(id)?;
-        let column = archetype.columns.get_mut(&C::component_id())?;
-        Some(ColumnRefMut {
-            column,
-            entities: &mut archetype.entity_buffer,
-        })
+        self.column_by_id_mut(C::component_id())
     }
 
     pub fn column<C: Component + 'static>(&self) -> Option<HostColumnRef<'_, S>> {
@@ -142,8 +166,10 @@ impl<S: WorldStore> World<S> {
     }
 
     pub fn column_by_id(&self, id: ComponentId) -> Option<HostColumnRef<'_, S>> {
-        let table_id = self.component_map.get(&id)?;
-        let archetype = self.archetypes.get(table_id)?;
+        let archetype = match self.component_map.get(&id) {
+            Some(table_id) => self.archetypes.get(table_id)?,
+            None => &self.resources,
+        };
         let column = archetype.columns.get(&id)?;
         Some(HostColumnRef {
             column,
@@ -152,8 +178,10 @@ impl<S: WorldStore> World<S> {
     }
 
     pub fn column_by_id_mut(&mut self, id: ComponentId) -> Option<ColumnRefMut<'_, S>> {
-        let table_id = self.component_map.get(&id)?;
-        let archetype = self.archetypes.get_mut(table_id)?;
+        let archetype = match self.component_map.get(&id) {
+            Some(table_id) => self.archetypes.get_mut(table_id)?,
+            None => &mut self.resources,
+        };
         let column = archetype.columns.get_mut(&id)?;
         Some(ColumnRefMut {
             column,
@@ -237,21 +265,11 @@ impl World<HostStore> {
         let archetypes = self
             .archetypes
             .iter()
-            .map(|(id, table)| {
-                let columns = table
-                    .columns
-                    .iter()
-                    .map(|(id, column)| Ok((*id, column.copy_to_client(client)?)))
-                    .collect::<Result<BTreeMap<_, _>, Error>>()?;
-                let table = Table {
-                    columns,
-                    entity_buffer: table.entity_buffer.copy_to_client(client)?,
-                };
-                Ok((*id, table))
-            })
+            .map(|(id, table)| Ok((*id, table.copy_to_client(client)?)))
             .collect::<Result<_, Error>>()?;
         Ok(World {
             archetypes,
+            resources: self.resources.copy_to_client(client)?,
             component_map: self.component_map.clone(),
             assets: AssetStore::default(),
             tick: self.tick,
@@ -489,18 +507,19 @@ impl<T: Component + 'static> SystemParam for ComponentArray<T> {
         builder.vars[&T::component_id()].borrow().clone().cast()
     }
 
-    fn insert_into_builder(self, builder: &mut PipelineBuilder) {
+    fn insert_into_builder(self, builder: &mut PipelineBuilder) -> Result<(), Error> {
         if let Some(var) = builder.vars.get_mut(&T::component_id()) {
             let mut var = var.borrow_mut();
             if var.entity_map != self.entity_map {
                 var.buffer =
                     update_var(&var.entity_map, &self.entity_map, &var.buffer, &self.buffer);
-                return;
+                return Ok(());
             }
         }
         builder
             .vars
             .insert(T::component_id(), self.erase_ty().into());
+        Ok(())
     }
 }
 
@@ -545,6 +564,12 @@ pub struct PipelineBuilder {
     pub param_ids: Vec<ComponentId>,
     pub param_ops: Vec<Noxpr>,
     pub world: World<HostStore>,
+    /// The name of the system that produced each node, used to cluster exported graphs.
+    pub node_systems: HashMap<NoxprId, &'static str>,
+    /// The index of the current tick, once a system has asked for it.
+    pub tick: Option<Noxpr>,
+    /// The rate of every system that doesn't run on every tick.
+    pub rates: Vec<SystemRate>,
 }
 
 impl PipelineBuilder {
@@ -554,7 +579,209 @@ impl PipelineBuilder {
             param_ids: vec![],
             param_ops: vec![],
             world,
+            node_systems: HashMap::default(),
+            tick: None,
+            rates: vec![],
+        }
+    }
+
+    /// Returns the index of the current tick as a `u64` scalar, adding it to the params the
+    /// first time it's asked for. The tick isn't a column, so it's passed in as [`TICK_ID`].
+    pub fn tick(&mut self) -> Noxpr {
+        if let Some(tick) = &self.tick {
+            return tick.clone();
+        }
+        let ty = ArrayTy {
+            element_type: ElementType::U64,
+            shape: smallvec![],
+        };
+        let tick = Noxpr::parameter(
+            self.param_ops.len() as i64,
+            nox::NoxprTy::ArrayTy(ty),
+            "tick".to_string(),
+        );
+        self.param_ops.push(tick.clone());
+        self.param_ids.push(TICK_ID);
+        self.tick = Some(tick.clone());
+        tick
+    }
+
+    /// Attributes every node reachable from the vars that isn't attributed yet to `system`.
+    pub fn record_system(&mut self, system: &'static str) {
+        let mut stack = self
+            .vars
+            .values()
+            .map(|var| var.borrow().buffer.clone())
+            .collect::<Vec<_>>();
+        while let Some(expr) = stack.pop() {
+            if matches!(expr.deref(), NoxprNode::Param(_))
+                || self.node_systems.contains_key(&expr.id())
+            {
+                continue;
+            }
+            self.node_systems.insert(expr.id(), system);
+            stack.extend(expr.inputs());
+        }
+    }
+
+    /// Traces `body` into a loop that runs for as long as `cond` returns true.
+    ///
+    /// The loop's state is `init` followed by every var, and both closures are handed the
+    /// current value of `init`'s entries. Inside `body` the vars hold their values at the start
+    /// of the iteration, and whatever they hold once `body` returns is carried into the next one.
+    /// Once traced, the vars hold their values after the final iteration, and the final values
+    /// of `init`'s entries are returned. The tick is carried through unchanged, so systems in
+    /// `body` can still read it.
+    pub(crate) fn trace_loop(
+        &mut self,
+        init: Vec<Noxpr>,
+        cond: impl FnOnce(&[Noxpr]) -> Noxpr,
+        body: impl FnOnce(&mut Self, &[Noxpr]) -> Result<Vec<Noxpr>, Error>,
+    ) -> Result<Vec<Noxpr>, Error> {
+        let ids = self.vars.keys().copied().collect::<Vec<_>>();
+        let init_len = init.len();
+        let initial_state = init
+            .into_iter()
+            .chain(self.vars.values().map(|var| var.borrow().buffer.clone()))
+            .chain(self.tick.clone())
+            .collect::<Vec<_>>();
+        let state_ty = initial_state
+            .iter()
+            .map(|expr| expr.ty().ok_or(nox::Error::UnknownShape))
+            .collect::<Result<Vec<_>, _>>()?;
+        let state_ty = nox::NoxprTy::Tuple(state_ty);
+        let state_len = initial_state.len();
+        let elems = |state: &Noxpr| {
+            (0..state_len)
+                .map(|i| state.get_tuple_element(i))
+                .collect::<Vec<_>>()
+        };
+
+        let state = Noxpr::parameter(0, state_ty.clone(), "loop_state".to_string());
+        let cond_fn = NoxprFn::new(vec![state.clone()], cond(&elems(&state)[..init_len]));
+
+        let state = Noxpr::parameter(0, state_ty, "loop_state".to_string());
+        let state_elems = elems(&state);
+        for (id, elem) in ids.iter().zip(&state_elems[init_len..]) {
+            self.vars[id].borrow_mut().buffer = elem.clone();
         }
+        let tick = self.tick.take();
+        self.tick = tick.as_ref().map(|_| state_elems[state_len - 1].clone());
+        let out = body(self, &state_elems[..init_len])?;
+        if out.len() != init_len || !self.vars.keys().eq(ids.iter()) {
+            return Err(Error::LoopStateMismatch);
+        }
+        let out = out
+            .into_iter()
+            .chain(self.vars.values().map(|var| var.borrow().buffer.clone()))
+            .chain(std::mem::replace(&mut self.tick, tick))
+            .collect();
+        let body_fn = NoxprFn::new(vec![state], Noxpr::tuple(out));
+
+        let result = Noxpr::while_loop(Noxpr::tuple(initial_state), cond_fn, body_fn);
+        let result = elems(&result);
+        for (id, elem) in ids.iter().zip(&result[init_len..]) {
+            self.vars[id].borrow_mut().buffer = elem.clone();
+        }
+        Ok(result[..init_len].to_vec())
+    }
+
+    /// Traces `body` into a conditional that only runs when `pred` is true.
+    ///
+    /// Inside `body` the vars hold their current values, and once traced they hold either their
+    /// values after `body` or their current values, depending on `pred`. Like
+    /// [`trace_loop`](Self::trace_loop), the tick is passed through to `body`.
+    pub(crate) fn trace_cond(
+        &mut self,
+        pred: Noxpr,
+        body: impl FnOnce(&mut Self) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let ids = self.vars.keys().copied().collect::<Vec<_>>();
+        let operands = self
+            .vars
+            .values()
+            .map(|var| var.borrow().buffer.clone())
+            .chain(self.tick.clone())
+            .collect::<Vec<_>>();
+        let params = || {
+            operands
+                .iter()
+                .enumerate()
+                .map(|(i, operand)| {
+                    let ty = operand.ty().ok_or(nox::Error::UnknownShape)?;
+                    Ok(Noxpr::parameter(
+                        i as i64,
+                        ty,
+                        format!("cond_operand_{}", i),
+                    ))
+                })
+                .collect::<Result<Vec<_>, Error>>()
+        };
+
+        let true_params = params()?;
+        for (id, param) in ids.iter().zip(&true_params) {
+            self.vars[id].borrow_mut().buffer = param.clone();
+        }
+        let tick = self.tick.take();
+        self.tick = tick.as_ref().map(|_| true_params[ids.len()].clone());
+        body(self)?;
+        self.tick = tick;
+        if !self.vars.keys().eq(ids.iter()) {
+            return Err(Error::BranchStateMismatch);
+        }
+        let out = self
+            .vars
+            .values()
+            .map(|var| var.borrow().buffer.clone())
+            .collect();
+        let on_true = NoxprFn::new(true_params, Noxpr::tuple(out));
+
+        let false_params = params()?;
+        let out = false_params[..ids.len()].to_vec();
+        let on_false = NoxprFn::new(false_params, Noxpr::tuple(out));
+
+        let result = Noxpr::cond(pred, operands, on_true, on_false);
+        for (i, id) in ids.iter().enumerate() {
+            self.vars[id].borrow_mut().buffer = result.get_tuple_element(i);
+        }
+        Ok(())
+    }
+
+    /// Returns the pipeline traced so far, as a function of the params that returns every var.
+    pub fn to_noxpr_fn(&self) -> NoxprFn {
+        let ret = self
+            .vars
+            .values()
+            .map(|var| var.borrow().buffer.clone())
+            .collect();
+        NoxprFn::new(self.param_ops.clone(), Noxpr::tuple(ret))
+    }
+
+    /// Renders the pipeline traced so far as a Graphviz DOT graph, with a cluster for each system.
+    pub fn to_dot(&self) -> String {
+        self.graph_exporter().dot(&self.to_noxpr_fn())
+    }
+
+    /// Renders the pipeline traced so far as a Mermaid flowchart, with a subgraph for each system.
+    pub fn to_mermaid(&self) -> String {
+        self.graph_exporter().mermaid(&self.to_noxpr_fn())
+    }
+
+    fn graph_exporter(&self) -> GraphExporter {
+        GraphExporter::new().clusters(
+            self.node_systems
+                .iter()
+                .map(|(id, system)| (*id, system.to_string())),
+        )
+    }
+
+    /// Returns a shape checker that attributes inconsistent nodes to the system that built them.
+    fn shape_checker(&self) -> ShapeChecker {
+        ShapeChecker::new().systems(
+            self.node_systems
+                .iter()
+                .map(|(id, system)| (*id, system.to_string())),
+        )
     }
 }
 
@@ -563,7 +790,7 @@ pub trait SystemParam {
 
     fn init(builder: &mut PipelineBuilder) -> Result<(), Error>;
     fn from_builder(builder: &PipelineBuilder) -> Self::Item;
-    fn insert_into_builder(self, builder: &mut PipelineBuilder);
+    fn insert_into_builder(self, builder: &mut PipelineBuilder) -> Result<(), Error>;
 }
 
 pub trait IntoSystem<Marker, Arg, Ret> {
@@ -579,6 +806,32 @@ pub trait IntoSystem<Marker, Arg, Ret> {
         }
     }
 
+    /// Runs the system `n` times every time it's called, so a pipeline can be stepped at a
+    /// multiple of the tick rate. The substeps are traced into a loop rather than unrolled.
+    fn substep(self, n: usize) -> Substep<Self::System>
+    where
+        Self: Sized,
+    {
+        Substep {
+            sys: self.into_system(),
+            n,
+        }
+    }
+
+    /// Runs the system on every `period`th tick, starting with the first, rather than on every
+    /// tick. Ticks it skips leave its components unchanged.
+    fn rate(self, period: NonZeroU64) -> Rate<Self::System>
+    where
+        Self: Sized,
+    {
+        Rate {
+            sys: self.into_system(),
+            name: std::any::type_name::<Self::System>().to_string(),
+            period,
+            phase: 0,
+        }
+    }
+
     fn world(self) -> WorldBuilder<Self::System>
     where
         Self: Sized,
@@ -648,11 +901,12 @@ macro_rules! impl_system_param {
                 )*)
             }
 
-            fn insert_into_builder(self, builder: &mut PipelineBuilder) {
+            fn insert_into_builder(self, builder: &mut PipelineBuilder) -> Result<(), Error> {
                 let ($($ty,)*) = self;
                 $(
-                    $ty.insert_into_builder(builder);
+                    $ty.insert_into_builder(builder)?;
                 )*
+                Ok(())
             }
           }
 
@@ -695,7 +949,8 @@ macro_rules! impl_system_param {
                             $ty::from_builder(builder),
                         )*
                     );
-                    ret.insert_into_builder(builder);
+                    ret.insert_into_builder(builder)?;
+                    builder.record_system(std::any::type_name::<F>());
                     Ok(())
                 }
             }
@@ -735,7 +990,8 @@ where
 
     fn add_to_builder(&self, builder: &mut PipelineBuilder) -> Result<(), Error> {
         let ret = (self.func)();
-        ret.insert_into_builder(builder);
+        ret.insert_into_builder(builder)?;
+        builder.record_system(std::any::type_name::<F>());
         Ok(())
     }
 }
@@ -796,6 +1052,82 @@ impl<A: System, B: System> System for Pipe<A, B> {
     }
 }
 
+pub struct Substep<Sys: System> {
+    sys: Sys,
+    n: usize,
+}
+
+impl<Sys: System> System for Substep<Sys> {
+    type Arg = Sys::Arg;
+    type Ret = Sys::Ret;
+
+    fn add_to_builder(&self, builder: &mut PipelineBuilder) -> Result<(), Error> {
+        use nox::NoxprScalarExt;
+        let n = self.n as i64;
+        builder.trace_loop(
+            vec![0i64.constant()],
+            |state| state[0].clone().less(n.constant()),
+            |builder, state| {
+                self.sys.add_to_builder(builder)?;
+                Ok(vec![state[0].clone() + 1i64.constant()])
+            },
+        )?;
+        Ok(())
+    }
+
+    fn init_builder(&self, builder: &mut PipelineBuilder) -> Result<(), Error> {
+        self.sys.init_builder(builder)
+    }
+}
+
+pub struct Rate<Sys: System> {
+    sys: Sys,
+    name: String,
+    period: NonZeroU64,
+    phase: u64,
+}
+
+impl<Sys: System> Rate<Sys> {
+    /// Offsets the ticks the system runs on, so it runs on the ticks where
+    /// `tick % period == phase % period`. Staggering systems with the same period spreads their
+    /// work across ticks.
+    pub fn phase(mut self, phase: u64) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    /// Sets the name the system's rate is reported under in the [`ExecMetadata`], which defaults
+    /// to the system's type name.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+}
+
+impl<Sys: System> System for Rate<Sys> {
+    type Arg = Sys::Arg;
+    type Ret = Sys::Ret;
+
+    fn add_to_builder(&self, builder: &mut PipelineBuilder) -> Result<(), Error> {
+        use nox::NoxprScalarExt;
+        let tick = builder.tick();
+        let period = self.period.get().constant();
+        let offset = tick.clone() - (tick / period.clone()) * period;
+        let pred = offset.equal((self.phase % self.period).constant());
+        builder.trace_cond(pred, |builder| self.sys.add_to_builder(builder))
+    }
+
+    fn init_builder(&self, builder: &mut PipelineBuilder) -> Result<(), Error> {
+        builder.tick();
+        builder.rates.push(SystemRate {
+            system: self.name.clone(),
+            period: self.period.get(),
+            phase: self.phase % self.period,
+        });
+        self.sys.init_builder(builder)
+    }
+}
+
 #[derive(Default)]
 pub struct WorldBuilder<Sys = (), StartupSys = ()> {
     world: World<HostStore>,
@@ -851,6 +1183,11 @@ where
         self.world.spawn_with_id(archetype, entity_id);
     }
 
+    /// See [`World::insert_resource`].
+    pub fn insert_resource<R: Component + 'static>(&mut self, res: R) -> Result<(), Error> {
+        self.world.insert_resource(res)
+    }
+
     pub fn build(mut self) -> Result<WorldExec, Error> {
         let mut tick_exec = self.pipe.build(&mut self.world)?;
         tick_exec.metadata.time_step = self.time_step;
@@ -868,14 +1205,14 @@ pub trait SystemExt {
 impl<S: System> SystemExt for S {
     fn build(self, world: &mut World) -> Result<Exec, Error> {
         let owned_world = std::mem::take(world);
-        let mut builder = PipelineBuilder {
-            vars: BTreeMap::default(),
-            param_ids: vec![],
-            param_ops: vec![],
-            world: owned_world,
-        };
-        self.init_builder(&mut builder)?;
-        self.add_to_builder(&mut builder)?;
+        let mut builder = PipelineBuilder::from_world(owned_world);
+        let added = self
+            .init_builder(&mut builder)
+            .and_then(|_| self.add_to_builder(&mut builder));
+        // the world is handed back before anything else can fail, so a failed build keeps it
+        *world = std::mem::take(&mut builder.world);
+        added?;
+        let mut checker = builder.shape_checker();
         let ret = builder
             .vars
             .into_iter()
@@ -891,29 +1228,52 @@ impl<S: System> SystemExt for S {
             args: builder.param_ops,
             inner: ret,
         };
+        // XLA's own shape errors don't say which system built the offending node
+        checker.check(&func)?;
+        // the unoptimized graph is kept, so it can be lowered again with other settings
+        let noxpr = func.clone();
+        let (func, report) = func.optimize();
+        tracing::debug!(%report, "optimized pipeline");
         let op = func.build("pipeline")?;
         let comp = op.build()?;
-        *world = builder.world;
         let metadata = ExecMetadata {
             time_step: None,
             arg_ids: builder.param_ids,
             ret_ids,
+            rates: builder.rates,
         };
-        Ok(Exec::new(metadata, comp.to_hlo_module()))
+        Ok(Exec {
+            noxpr: Some(noxpr),
+            ..Exec::new(metadata, comp.to_hlo_module())
+        })
     }
 }
 
+/// The id the tick counter is passed to a pipeline as, in place of a column.
+pub const TICK_ID: ComponentId = ComponentId::new("tick");
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct ExecMetadata {
     pub time_step: Option<Duration>,
     pub arg_ids: Vec<ComponentId>,
     pub ret_ids: Vec<ComponentId>,
+    #[serde(default)]
+    pub rates: Vec<SystemRate>,
+}
+
+/// A system that only runs on the ticks where `tick % period == phase`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SystemRate {
+    pub system: String,
+    pub period: u64,
+    pub phase: u64,
 }
 
 #[derive(Clone)]
 pub struct Exec {
     metadata: ExecMetadata,
     hlo_module: HloModuleProto,
+    noxpr: Option<NoxprFn>,
     state: ExecState,
 }
 
@@ -938,10 +1298,20 @@ impl Exec {
         Self {
             metadata,
             hlo_module,
+            noxpr: None,
             state: ExecState::Uncompiled,
         }
     }
 
+    pub fn metadata(&self) -> &ExecMetadata {
+        &self.metadata
+    }
+
+    /// Returns the graph this exec was lowered from, if it was built from a system.
+    pub fn noxpr(&self) -> Option<&NoxprFn> {
+        self.noxpr.as_ref()
+    }
+
     fn start_compiling(&mut self, client: &Client) {
         if let ExecState::Uncompiled = self.state {
             let comp = self.hlo_module.computation();
@@ -977,9 +1347,21 @@ impl Exec {
     fn run(&mut self, world: &mut SharedWorld, client: &Client) -> Result<(), Error> {
         world.clear_cache();
         world.load_dirty_components(client)?;
+        // only pipelines with a rate read the tick, so the rest skip the copy
+        let tick = if self.metadata.arg_ids.contains(&TICK_ID) {
+            Some(client.copy_host_buffer(&[world.host.tick], &[])?)
+        } else {
+            None
+        };
         let client_world = world.copy_to_client(client)?;
         let mut buffers = BufferArgsRef::default().untuple_result(true);
         for id in &self.metadata.arg_ids {
+            if *id == TICK_ID {
+                if let Some(tick) = &tick {
+                    buffers.push(tick);
+                }
+                continue;
+            }
             let col = client_world
                 .column_by_id(*id)
                 .ok_or(Error::ComponentNotFound)?;
@@ -1004,6 +1386,9 @@ impl Exec {
         let mut metadata = File::create(path.join("metadata.json"))?;
         serde_json::to_writer(&mut metadata, &self.metadata)?;
         std::fs::write(path.join("hlo.binpb"), self.hlo_module.to_bytes())?;
+        if let Some(noxpr) = &self.noxpr {
+            std::fs::write(path.join("noxpr.bin"), noxpr.to_bytes()?)?;
+        }
         Ok(())
     }
 
@@ -1013,7 +1398,16 @@ impl Exec {
         let metadata: ExecMetadata = serde_json::from_reader(&mut metadata)?;
         let hlo_module_data = std::fs::read(path.join("hlo.binpb"))?;
         let hlo_module = HloModuleProto::parse_binary(&hlo_module_data)?;
-        Ok(Self::new(metadata, hlo_module))
+        let noxpr_path = path.join("noxpr.bin");
+        let noxpr = if noxpr_path.exists() {
+            Some(NoxprFn::from_bytes(&std::fs::read(noxpr_path)?)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            noxpr,
+            ..Self::new(metadata, hlo_module)
+        })
     }
 }
 
@@ -1073,22 +1467,36 @@ impl SharedWorld {
         for (id, host_table) in &mut self.host.archetypes {
             let client_table = client_world
                 .archetypes
-                .get_mut(id)
+                .get(id)
                 .ok_or(Error::ComponentNotFound)?;
-            for (host, client) in host_table
-                .columns
-                .values_mut()
-                .zip(client_table.columns.values_mut())
-            {
-                let literal = client.to_literal_sync()?;
-                host.buf.copy_from_slice(literal.raw_buf());
-                self.loaded_components.insert(host.metadata.component_id());
-            }
+            copy_table(host_table, client_table, &mut self.loaded_components)?;
         }
+        copy_table(
+            &mut self.host.resources,
+            &client_world.resources,
+            &mut self.loaded_components,
+        )?;
         Ok(())
     }
 }
 
+fn copy_table(
+    host_table: &mut Table<HostStore>,
+    client_table: &Table<ClientStore>,
+    loaded_components: &mut HashSet<ComponentId>,
+) -> Result<(), Error> {
+    for (host, client) in host_table
+        .columns
+        .values_mut()
+        .zip(client_table.columns.values())
+    {
+        let literal = client.to_literal_sync()?;
+        host.buf.copy_from_slice(literal.raw_buf());
+        loaded_components.insert(host.metadata.component_id());
+    }
+    Ok(())
+}
+
 pub struct WorldExec {
     pub world: SharedWorld,
     pub tick_exec: Exec,
@@ -1214,7 +1622,12 @@ impl WorldExec {
         } else {
             None
         };
-        let polars_world = PolarsWorld::read_from_dir(dir.join("world"))?;
+        // The stored executables were compiled against the layout the world was written with,
+        // so an outdated world can't be migrated underneath them.
+        let polars_world = PolarsWorld::read_stored(dir.join("world"))?;
+        if polars_world.metadata.version != crate::polars::Metadata::VERSION {
+            return Err(Error::UnsupportedVersion(polars_world.metadata.version));
+        }
         let world = World::try_from(polars_world)?;
         let world = SharedWorld::from_host(world);
         let world_exec = WorldExec::new(world, tick_exec, startup_exec);
@@ -1259,7 +1672,9 @@ impl SystemParam for () {
 
     fn from_builder(_builder: &PipelineBuilder) -> Self::Item {}
 
-    fn insert_into_builder(self, _builder: &mut PipelineBuilder) {}
+    fn insert_into_builder(self, _builder: &mut PipelineBuilder) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 pub struct ErasedSystem<Sys, Arg, Ret> {
@@ -1405,6 +1820,14 @@ pub enum Error {
     Postcard(#[from] postcard::Error),
     #[error("world not found")]
     WorldNotFound,
+    #[error("systems traced into a loop can't add components")]
+    LoopStateMismatch,
+    #[error("systems that don't run every tick can't add components")]
+    BranchStateMismatch,
+    #[error("a resource must hold a single value, and can't also be an entity's component")]
+    InvalidResource,
+    #[error("unsupported world version {0}")]
+    UnsupportedVersion(u32),
     #[cfg(feature = "pyo3")]
     #[error("python error")]
     PyO3(#[from] pyo3::PyErr),
@@ -1519,6 +1942,55 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_compact_subset() {
+        #[derive(Component)]
+        struct Value(Scalar<f64>);
+
+        #[derive(Component)]
+        struct Count(Scalar<i64>);
+
+        // keeps the positive values at the front and decrements everything, so the number of
+        // selected entries shrinks from tick to tick
+        fn compact_positive(
+            v: ComponentArray<Value>,
+            c: ComponentArray<Count>,
+        ) -> (ComponentArray<Value>, ComponentArray<Count>) {
+            let len = v.len as i64;
+            let zeros = nox::NoxprScalarExt::constant(0.0).broadcast(smallvec![len]);
+            let positive = v.buffer.clone().greater(zeros);
+            let (compacted, count) = v.buffer.compact(&positive).unwrap();
+            let value = ComponentArray {
+                buffer: compacted - nox::NoxprScalarExt::constant(1.0).broadcast(smallvec![len]),
+                ..v
+            };
+            let count = ComponentArray {
+                buffer: count.reshape(smallvec![1]),
+                ..c
+            };
+            (value, count)
+        }
+
+        let mut world = compact_positive.world();
+        for value in [-1.0, 2.0, -3.0, 4.0] {
+            world.spawn(Value(value.constant()));
+        }
+        world.spawn(Count(0i64.constant()));
+        let client = nox::Client::cpu().unwrap();
+        let mut exec = world.build().unwrap();
+        exec.run(&client).unwrap();
+        let v = exec.column(Value::component_id()).unwrap();
+        assert_eq!(v.typed_buf::<f64>().unwrap(), &[1.0, 3.0, -1.0, -1.0]);
+        let c = exec.column(Count::component_id()).unwrap();
+        assert_eq!(c.typed_buf::<i64>().unwrap(), &[2]);
+        exec.run(&client).unwrap();
+        exec.run(&client).unwrap();
+        let v = exec.column(Value::component_id()).unwrap();
+        assert_eq!(v.typed_buf::<f64>().unwrap(), &[1.0, -1.0, -1.0, -1.0]);
+        let c = exec.column(Count::component_id()).unwrap();
+        assert_eq!(c.typed_buf::<i64>().unwrap(), &[1]);
+    }
+
     #[test]
     fn test_assets() {
         #[derive(Component)]
@@ -1564,6 +2036,143 @@ mod tests {
         assert_eq!(c.typed_buf::<f64>().unwrap(), &[4.0]);
     }
 
+    #[test]
+    fn test_substep() {
+        #[derive(Component)]
+        struct A(Scalar<f64>);
+
+        #[derive(Component)]
+        struct B(Scalar<f64>);
+
+        fn tick(a: ComponentArray<A>) -> ComponentArray<A> {
+            a.map(|a: A| A(a.0 * 2.0)).unwrap()
+        }
+
+        fn count(b: ComponentArray<B>) -> ComponentArray<B> {
+            b.map(|b: B| B(b.0 + 1.0)).unwrap()
+        }
+
+        let mut world = World::default();
+        world.spawn(A(1.0.constant()));
+        world.spawn(B(0.0.constant()));
+        let client = nox::Client::cpu().unwrap();
+        let mut exec = world
+            .builder()
+            .tick_pipeline(tick.substep(10).pipe(count))
+            .build()
+            .unwrap();
+        exec.run(&client).unwrap();
+        exec.run(&client).unwrap();
+        let a = exec.column(A::component_id()).unwrap();
+        assert_eq!(a.typed_buf::<f64>().unwrap(), &[1048576.0]);
+        let b = exec.column(B::component_id()).unwrap();
+        assert_eq!(b.typed_buf::<f64>().unwrap(), &[2.0]);
+    }
+
+    #[test]
+    fn test_rate() {
+        #[derive(Component)]
+        struct A(Scalar<f64>);
+
+        #[derive(Component)]
+        struct B(Scalar<f64>);
+
+        fn fast(a: ComponentArray<A>) -> ComponentArray<A> {
+            a.map(|a: A| A(a.0 + 1.0)).unwrap()
+        }
+
+        fn slow(b: ComponentArray<B>) -> ComponentArray<B> {
+            b.map(|b: B| B(b.0 + 1.0)).unwrap()
+        }
+
+        let mut world = World::default();
+        world.spawn(A(0.0.constant()));
+        world.spawn(B(0.0.constant()));
+        let client = nox::Client::cpu().unwrap();
+        let slow = slow.rate(NonZeroU64::new(3).unwrap()).phase(1).name("slow");
+        let mut exec = world
+            .builder()
+            .tick_pipeline(fast.pipe(slow))
+            .build()
+            .unwrap();
+        for _ in 0..7 {
+            exec.run(&client).unwrap();
+        }
+        let a = exec.column(A::component_id()).unwrap();
+        assert_eq!(a.typed_buf::<f64>().unwrap(), &[7.0]);
+        // the slow system only ran on ticks 1 and 4
+        let b = exec.column(B::component_id()).unwrap();
+        assert_eq!(b.typed_buf::<f64>().unwrap(), &[2.0]);
+        let rates = &exec.tick_exec.metadata.rates;
+        assert_eq!(rates.len(), 1);
+        assert_eq!(
+            rates[0],
+            SystemRate {
+                system: "slow".to_string(),
+                period: 3,
+                phase: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_pipeline_graph_clusters() {
+        #[derive(Component)]
+        struct A(Scalar<f64>);
+
+        fn double(a: ComponentArray<A>) -> ComponentArray<A> {
+            a.map(|a: A| A(a.0 * 2.0)).unwrap()
+        }
+
+        fn inc(a: ComponentArray<A>) -> ComponentArray<A> {
+            a.map(|a: A| A(a.0 + 1.0)).unwrap()
+        }
+
+        let mut world = World::default();
+        world.spawn(A(1.0.constant()));
+        let sys = double.pipe(inc);
+        let mut builder = PipelineBuilder::from_world(world);
+        sys.init_builder(&mut builder).unwrap();
+        sys.add_to_builder(&mut builder).unwrap();
+        let dot = builder.to_dot();
+        assert!(dot.contains("subgraph cluster_0"));
+        assert!(dot.contains("subgraph cluster_1"));
+        assert!(dot.contains("::double\";"));
+        assert!(dot.contains("::inc\";"));
+        let mermaid = builder.to_mermaid();
+        assert!(mermaid.contains("subgraph c1["));
+    }
+
+    #[test]
+    fn test_shape_error_names_system() {
+        #[derive(Component)]
+        struct A(Scalar<f64>);
+
+        fn double(a: ComponentArray<A>) -> ComponentArray<A> {
+            a.map(|a: A| A(a.0 * 2.0)).unwrap()
+        }
+
+        // adds the column to a copy of itself that's twice as long
+        fn mismatched(a: ComponentArray<A>) -> ComponentArray<A> {
+            let doubled = Noxpr::concat_in_dim(vec![a.buffer.clone(), a.buffer.clone()], 0);
+            ComponentArray {
+                buffer: a.buffer.clone() + doubled,
+                ..a
+            }
+        }
+
+        let mut world = World::default();
+        world.spawn(A(1.0.constant()));
+        let Err(Error::Nox(nox::Error::Shape(err))) = double.pipe(mismatched).build(&mut world)
+        else {
+            panic!("expected a shape error");
+        };
+        assert_eq!(err.node, "Add");
+        assert!(err.system.unwrap().ends_with("::mismatched"));
+        // the failed build hands the world back
+        assert!(world.column::<A>().is_some());
+    }
+
     #[test]
     fn test_write_read() {
         #[derive(Component)]
@@ -1590,6 +2199,7 @@ mod tests {
         let tempdir = tempdir.path();
         exec.write_to_dir(tempdir).unwrap();
         let mut exec = WorldExec::read_from_dir(tempdir).unwrap();
+        assert!(exec.tick_exec.noxpr().is_some());
         exec.run(&client).unwrap();
         let c = exec.column(A::component_id()).unwrap();
         assert_eq!(c.typed_buf::<f64>().unwrap(), &[4.0]);
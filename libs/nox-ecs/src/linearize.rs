@@ -0,0 +1,162 @@
+use conduit::ComponentId;
+use nox::xla::BufferArgsRef;
+use nox::{Client, Noxpr, NoxprFn};
+
+use crate::{Error, IntoSystem, PipelineBuilder, System, WorldExec, TICK_ID};
+
+/// The state-space matrices of a tick pipeline around a fixed world state.
+///
+/// For a state `x` and an input `u` the pipeline is approximated as
+/// `x[k + 1] = f(x*, u*) + a * (x[k] - x*) + b * (u[k] - u*)`, where `x*` and `u*` are the values
+/// the pipeline was linearized around. Each component is flattened entity-major, and the
+/// components are laid out in the order they were passed to [`WorldExec::linearize`].
+#[derive(Debug, Clone)]
+pub struct Linearization {
+    pub a: ndarray::Array2<f64>,
+    pub b: ndarray::Array2<f64>,
+}
+
+impl WorldExec {
+    /// Linearizes a single tick of `pipeline` around the current world state.
+    ///
+    /// `state` lists the components that make up the state vector, and `input` the components
+    /// that make up the input vector. Both must be read by `pipeline` and have a `f64` element type.
+    pub fn linearize<M, A, R>(
+        &mut self,
+        pipeline: impl IntoSystem<M, A, R>,
+        state: &[ComponentId],
+        input: &[ComponentId],
+        client: &Client,
+    ) -> Result<Linearization, Error> {
+        let pipeline = pipeline.into_system();
+        let mut builder = PipelineBuilder::from_world(self.world.host.clone());
+        pipeline.init_builder(&mut builder)?;
+        pipeline.add_to_builder(&mut builder)?;
+
+        let param = |id: &ComponentId| -> Result<Noxpr, Error> {
+            let index = builder
+                .param_ids
+                .iter()
+                .position(|param_id| param_id == id)
+                .ok_or(Error::ComponentNotFound)?;
+            Ok(builder.param_ops[index].clone())
+        };
+        let x = state.iter().map(param).collect::<Result<Vec<_>, Error>>()?;
+        let u = input.iter().map(param).collect::<Result<Vec<_>, Error>>()?;
+        let next_x = state
+            .iter()
+            .map(|id| {
+                let var = builder.vars.get(id).ok_or(Error::ComponentNotFound)?;
+                let buffer = var.borrow().buffer.clone();
+                let len = buffer
+                    .shape()
+                    .ok_or(nox::Error::UnknownShape)?
+                    .iter()
+                    .product::<i64>();
+                Ok(buffer.reshape(smallvec::smallvec![len]))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let next_x = Noxpr::concat_in_dim(next_x, 0);
+        let a = next_x.jacobian_fwd(&x)?;
+        let b = next_x.jacobian_fwd(&u)?;
+        let shape = |expr: &Noxpr| -> Result<(usize, usize), Error> {
+            let shape = expr.shape().ok_or(nox::Error::UnknownShape)?;
+            Ok((shape[0] as usize, shape[1] as usize))
+        };
+        let (a_shape, b_shape) = (shape(&a)?, shape(&b)?);
+
+        let func = NoxprFn::new(builder.param_ops.clone(), Noxpr::tuple(vec![a, b]));
+        builder.shape_checker().check(&func)?;
+        let comp = func.build("linearize")?.build()?;
+        let exec = client.compile(&comp)?;
+        self.world.load_dirty_components(client)?;
+        let tick = client.copy_host_buffer(&[self.world.host.tick], &[])?;
+        let client_world = self.world.copy_to_client(client)?;
+        let mut buffers = BufferArgsRef::default().untuple_result(true);
+        for id in &builder.param_ids {
+            if *id == TICK_ID {
+                buffers.push(&tick);
+                continue;
+            }
+            let col = client_world
+                .column_by_id(*id)
+                .ok_or(Error::ComponentNotFound)?;
+            buffers.push(col.column);
+        }
+        let ret_bufs = exec.execute_buffers(buffers)?;
+        let mut matrices = ret_bufs
+            .into_iter()
+            .zip([a_shape, b_shape])
+            .map(|(buf, shape)| {
+                let literal = buf.to_literal_sync()?;
+                let data = literal.typed_buf::<f64>()?.to_vec();
+                ndarray::Array2::from_shape_vec(shape, data).map_err(|_| Error::ValueSizeMismatch)
+            });
+        let a = matrices.next().ok_or(Error::ValueSizeMismatch)??;
+        let b = matrices.next().ok_or(Error::ValueSizeMismatch)??;
+        Ok(Linearization { a, b })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Archetype, Component, ComponentExt, Query};
+    use nox::{Scalar, ScalarExt};
+
+    #[test]
+    fn test_linearize() {
+        #[derive(Component)]
+        struct X(Scalar<f64>);
+
+        #[derive(Component)]
+        struct V(Scalar<f64>);
+
+        #[derive(Component)]
+        struct U(Scalar<f64>);
+
+        #[derive(Archetype)]
+        struct Body {
+            x: X,
+            v: V,
+            u: U,
+        }
+
+        fn vel(q: Query<(V, U)>) -> Query<V> {
+            q.map(|v: V, u: U| V(v.0 + u.0 * 0.1.constant())).unwrap()
+        }
+
+        fn pos(q: Query<(X, V)>) -> Query<X> {
+            q.map(|x: X, v: V| X(x.0.clone() + v.0 * x.0 * 0.1.constant()))
+                .unwrap()
+        }
+
+        let mut world = vel.pipe(pos).world();
+        world.spawn(Body {
+            x: X(2.0.constant()),
+            v: V(1.0.constant()),
+            u: U(0.0.constant()),
+        });
+        let client = nox::Client::cpu().unwrap();
+        let mut exec = world.build().unwrap();
+        let lin = exec
+            .linearize(
+                vel.pipe(pos),
+                &[X::component_id(), V::component_id()],
+                &[U::component_id()],
+                &client,
+            )
+            .unwrap();
+        // x' = x + (v + u dt) x dt, v' = v + u dt
+        let expected_a = [1.1, 0.2, 0.0, 1.0];
+        let expected_b = [0.02, 0.1];
+        assert_eq!(lin.a.shape(), &[2, 2]);
+        assert_eq!(lin.b.shape(), &[2, 1]);
+        for (a, e) in lin.a.iter().zip(expected_a) {
+            assert!((a - e).abs() < 1e-9, "{a} != {e}");
+        }
+        for (b, e) in lin.b.iter().zip(expected_b) {
+            assert!((b - e).abs() < 1e-9, "{b} != {e}");
+        }
+    }
+}
@@ -13,12 +13,14 @@ use std::collections::HashMap;
 use std::{fs::File, path::Path};
 
 use crate::{
-    ArchetypeName, AssetStore, ColumnRef, ColumnStore, Error, HostColumn, HostStore, Table, World,
+    six_dof::Inertia, ArchetypeName, AssetStore, ColumnRef, ColumnStore, Component, Error,
+    HostColumn, HostStore, Table, World,
 };
 
 #[derive(Debug, Clone, Default)]
 pub struct PolarsWorld {
     pub archetypes: ustr::UstrMap<DataFrame>,
+    pub resources: DataFrame,
     pub component_map: HashMap<ComponentId, ArchetypeName>,
     pub component_names: HashMap<ComponentId, String>,
     pub metadata: Metadata,
@@ -28,16 +30,25 @@ pub struct PolarsWorld {
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Metadata {
     pub archetypes: ustr::UstrMap<ArchetypeMetadata>,
+    /// Worlds written before resources were stored apart from the archetypes have none.
+    #[serde(default)]
+    pub resources: ArchetypeMetadata,
     pub tick: u64,
     pub entity_len: u64,
+    /// The layout version the world was written with, worlds written before versioning read as 0.
+    #[serde(default)]
+    pub version: u32,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct ArchetypeMetadata {
     pub columns: Vec<conduit::Metadata>,
 }
 
 impl Metadata {
+    /// Version 1 widened [`Inertia`] from 7 to 10 elements, appending the products of inertia.
+    pub const VERSION: u32 = 1;
+
     fn component_map(&self) -> HashMap<ComponentId, ArchetypeName> {
         self.archetypes
             .iter()
@@ -52,8 +63,9 @@ impl Metadata {
 
     fn component_names(&self) -> HashMap<ComponentId, String> {
         self.archetypes
-            .iter()
-            .flat_map(|(_, metadata)| {
+            .values()
+            .chain([&self.resources])
+            .flat_map(|metadata| {
                 metadata
                     .columns
                     .iter()
@@ -84,7 +96,7 @@ impl PolarsWorld {
     }
 
     pub fn add_sample_number(&mut self, sample_number: usize) -> Result<(), Error> {
-        for df in self.archetypes.values_mut() {
+        for df in self.tables_mut() {
             let len = df
                 .get_columns()
                 .first()
@@ -97,20 +109,21 @@ impl PolarsWorld {
     }
 
     pub fn add_time(&mut self) -> Result<(), Error> {
-        for df in self.archetypes.values_mut() {
+        let tick = self.metadata.tick;
+        for df in self.tables_mut() {
             let len = df
                 .get_columns()
                 .first()
                 .map(|s| s.len())
                 .unwrap_or_default();
-            let series: Series = std::iter::repeat(self.metadata.tick).take(len).collect();
+            let series: Series = std::iter::repeat(tick).take(len).collect();
             df.with_column(series.with_name("time"))?;
         }
         Ok(())
     }
 
     pub fn vstack(&mut self, other: &Self) -> Result<(), Error> {
-        if self.archetypes.is_empty() {
+        if self.archetypes.is_empty() && self.resources.width() == 0 {
             *self = other.clone();
             return Ok(());
         }
@@ -121,9 +134,17 @@ impl PolarsWorld {
                 .ok_or(Error::ComponentNotFound)?;
             df.vstack_mut(other_df)?;
         }
+        self.resources.vstack_mut(&other.resources)?;
         Ok(())
     }
 
+    /// The archetypes' tables followed by the resources' table.
+    fn tables_mut(&mut self) -> impl Iterator<Item = &mut DataFrame> {
+        self.archetypes
+            .values_mut()
+            .chain(std::iter::once(&mut self.resources))
+    }
+
     pub fn write_to_dir(&mut self, path: impl AsRef<Path>) -> Result<(), Error> {
         let path = path.as_ref();
         std::fs::create_dir_all(path)?;
@@ -134,33 +155,91 @@ impl PolarsWorld {
             let file = std::fs::File::create(&path)?;
             ParquetWriter::new(file).finish(df)?;
         }
+        let file = std::fs::File::create(path.join("resources.parquet"))?;
+        ParquetWriter::new(file).finish(&mut self.resources)?;
         let path = path.join("assets.bin");
         let file = std::fs::File::create(path)?;
         postcard::to_io(&self.assets, file).unwrap();
         Ok(())
     }
 
+    /// Reads a world from `path`, migrating worlds written by older versions to the current layout.
     pub fn read_from_dir(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let mut world = Self::read_stored(path)?;
+        world.migrate()?;
+        Ok(world)
+    }
+
+    /// Reads a world from `path` in the layout it was written with.
+    pub(crate) fn read_stored(path: impl AsRef<Path>) -> Result<Self, Error> {
         let path = path.as_ref();
         let mut archetypes = HashMap::default();
         let mut metadata = File::open(path.join("metadata.json"))?;
         let metadata: Metadata = serde_json::from_reader(&mut metadata)?;
+        if metadata.version > Metadata::VERSION {
+            return Err(Error::UnsupportedVersion(metadata.version));
+        }
         for name in metadata.archetypes.keys() {
             let path = path.join(format!("{}.parquet", name));
             let file = File::open(&path)?;
             let df = polars::prelude::ParquetReader::new(file).finish()?;
             archetypes.insert(*name, df);
         }
+        let resources_path = path.join("resources.parquet");
+        let resources = if resources_path.exists() {
+            ParquetReader::new(File::open(&resources_path)?).finish()?
+        } else {
+            Table::default().to_polars()?.1
+        };
         let assets_buf = std::fs::read(path.join("assets.bin"))?;
         let assets = postcard::from_bytes(&assets_buf)?;
         Ok(Self {
             archetypes,
+            resources,
             component_map: metadata.component_map(),
             component_names: metadata.component_names(),
             metadata,
             assets,
         })
     }
+
+    fn migrate(&mut self) -> Result<(), Error> {
+        if self.metadata.version < 1 {
+            for (name, archetype) in &mut self.metadata.archetypes {
+                let Some(df) = self.archetypes.get_mut(name) else {
+                    continue;
+                };
+                for metadata in &mut archetype.columns {
+                    if metadata.name != Inertia::name()
+                        || metadata.component_type.primitive_ty != PrimitiveTy::F64
+                        || metadata.component_type.shape.as_slice() != [7]
+                    {
+                        continue;
+                    }
+                    // The products of inertia were added after the diagonal and momentum, so
+                    // legacy bodies keep their values and gain a diagonal inertia tensor.
+                    let series = df.column(&metadata.name)?;
+                    let legacy = series.to_bytes();
+                    let stride = 7 * std::mem::size_of::<f64>();
+                    let products = [0u8; 3 * std::mem::size_of::<f64>()];
+                    let mut buf = Vec::with_capacity(legacy.len() / 7 * 10);
+                    for inertia in legacy.chunks_exact(stride) {
+                        buf.extend_from_slice(inertia);
+                        buf.extend_from_slice(&products);
+                    }
+                    metadata.component_type = Inertia::component_type();
+                    let column = HostColumn {
+                        buf,
+                        len: series.len(),
+                        metadata: metadata.clone(),
+                    };
+                    df.replace(&metadata.name, column.to_series()?)?;
+                }
+            }
+        }
+        self.metadata.version = Metadata::VERSION;
+        Ok(())
+    }
 }
 
 impl World<HostStore> {
@@ -172,15 +251,19 @@ impl World<HostStore> {
             archetypes.insert(*id, df);
             archetype_metadata.insert(*id, metadata);
         }
+        let (resource_metadata, resources) = self.resources.to_polars()?;
 
         let metadata = Metadata {
             archetypes: archetype_metadata,
+            resources: resource_metadata,
             tick: self.tick,
             entity_len: self.entity_len,
+            version: Metadata::VERSION,
         };
 
         Ok(PolarsWorld {
             archetypes,
+            resources,
             component_map: metadata.component_map(),
             component_names: metadata.component_names(),
             metadata,
@@ -195,8 +278,10 @@ impl TryFrom<PolarsWorld> for World<HostStore> {
     fn try_from(polars: PolarsWorld) -> Result<Self, Self::Error> {
         let Metadata {
             archetypes,
+            resources,
             tick,
             entity_len,
+            ..
         } = polars.metadata;
         let archetypes = polars
             .archetypes
@@ -212,6 +297,7 @@ impl TryFrom<PolarsWorld> for World<HostStore> {
             .collect::<Result<_, Error>>()?;
         Ok(World {
             archetypes,
+            resources: Table::from_dataframe(polars.resources, resources)?,
             component_map: polars.component_map,
             assets: polars.assets,
             tick,
@@ -284,6 +370,7 @@ impl HostColumn {
             PrimitiveTy::I32 => tensor_array(component_type, self.prim_array::<i32>()),
             PrimitiveTy::I16 => tensor_array(component_type, self.prim_array::<i16>()),
             PrimitiveTy::I8 => tensor_array(component_type, self.prim_array::<i8>()),
+            PrimitiveTy::F16 | PrimitiveTy::BF16 => tensor_array(component_type, self.bits_array()),
             PrimitiveTy::Bool => todo!(),
         };
         Series::from_arrow(&self.metadata.name, array).map_err(Error::from)
@@ -294,6 +381,17 @@ impl HostColumn {
     ) -> Box<dyn Array> {
         Box::new(PrimitiveArray::from_slice(self.typed_buf::<T>().unwrap()))
     }
+
+    /// Polars has no half precision float types, so 16 bit floats are stored as their raw bits,
+    /// and recovered using the column's metadata.
+    fn bits_array(&self) -> Box<dyn Array> {
+        let bits = self
+            .buf
+            .chunks_exact(2)
+            .map(|b| u16::from_ne_bytes([b[0], b[1]]))
+            .collect::<Vec<_>>();
+        Box::new(PrimitiveArray::from_vec(bits))
+    }
 }
 
 fn arrow_data_type(ty: PrimitiveTy) -> ArrowDataType {
@@ -308,6 +406,7 @@ fn arrow_data_type(ty: PrimitiveTy) -> ArrowDataType {
         PrimitiveTy::I64 => ArrowDataType::Int64,
         PrimitiveTy::F32 => ArrowDataType::Float32,
         PrimitiveTy::F64 => ArrowDataType::Float64,
+        PrimitiveTy::F16 | PrimitiveTy::BF16 => ArrowDataType::UInt16,
         PrimitiveTy::Bool => ArrowDataType::Boolean,
     }
 }
@@ -388,15 +487,17 @@ impl<'a> ColumnStore for &'a PolarsWorld {
     }
 
     fn column(&self, id: ComponentId) -> Result<Self::Column<'_>, Error> {
-        let archetype = self
-            .component_map
+        let table = match self.component_map.get(&id) {
+            Some(archetype) => self
+                .archetypes
+                .get(archetype)
+                .ok_or(Error::ComponentNotFound)?,
+            None => &self.resources,
+        };
+        let component_name = self
+            .component_names
             .get(&id)
             .ok_or(Error::ComponentNotFound)?;
-        let table = self
-            .archetypes
-            .get(archetype)
-            .ok_or(Error::ComponentNotFound)?;
-        let component_name = self.component_names.get(&id).unwrap();
         Ok(PolarsColumnRef {
             entity_series: table.column(EntityId::NAME)?,
             buf: table.column(component_name)?, // TODO(sphw): add a map to metadata between component id and series offset
@@ -434,12 +535,13 @@ impl ColumnRef for PolarsColumnRef<'_> {
 mod tests {
     use crate::{
         six_dof::{Body, Force, Inertia, WorldAccel, WorldVel},
-        Archetype, WorldPos,
+        Archetype, Component, Substeps, SubstepsExhausted, WorldPos,
     };
     use conduit::well_known::{Material, Mesh, Pbr};
     use nox::{
         nalgebra::{self, vector},
-        SpatialForce, SpatialInertia, SpatialMotion, SpatialTransform,
+        xla::F16,
+        Scalar, ScalarExt, SpatialForce, SpatialInertia, SpatialMotion, SpatialTransform,
     };
     use polars::prelude::*;
     use polars_arrow::array::Float64Array;
@@ -469,8 +571,10 @@ mod tests {
                 inner: vector![0.0, 0.0, 0.0, 0.0, 0.0, 0.0].into(),
             }),
             mass: Inertia(SpatialInertia {
-                inner: vector![1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 1.0].into(),
+                inner: vector![1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0].into(),
             }),
+            substeps: Substeps::default(),
+            substeps_exhausted: SubstepsExhausted::default(),
         });
         let polars = world.to_polars().unwrap();
         let df = polars.archetypes[&Body::name()].clone();
@@ -518,8 +622,10 @@ mod tests {
                 inner: vector![0.0, 0.0, 0.0, 0.0, 0.0, 0.0].into(),
             }),
             mass: Inertia(SpatialInertia {
-                inner: vector![1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 1.0].into(),
+                inner: vector![1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0].into(),
             }),
+            substeps: Substeps::default(),
+            substeps_exhausted: SubstepsExhausted::default(),
         });
         let mut polars = world.to_polars().unwrap();
         let dir = tempfile::tempdir().unwrap();
@@ -552,8 +658,10 @@ mod tests {
                 inner: vector![0.0, 0.0, 0.0, 0.0, 0.0, 0.0].into(),
             }),
             mass: Inertia(SpatialInertia {
-                inner: vector![1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 1.0].into(),
+                inner: vector![1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0].into(),
             }),
+            substeps: Substeps::default(),
+            substeps_exhausted: SubstepsExhausted::default(),
         });
         let polars = world.to_polars().unwrap();
         let new_world = World::try_from(polars).unwrap();
@@ -582,15 +690,134 @@ mod tests {
                 inner: vector![0.0, 0.0, 0.0, 0.0, 0.0, 0.0].into(),
             }),
             mass: Inertia(SpatialInertia {
-                inner: vector![1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 1.0].into(),
+                inner: vector![1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0].into(),
+            }),
+            substeps: Substeps::default(),
+            substeps_exhausted: SubstepsExhausted::default(),
+        });
+        let mut polars = world.to_polars().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        polars.write_to_dir(dir).unwrap();
+        let new_polars = PolarsWorld::read_from_dir(dir).unwrap();
+        let new_world = World::try_from(new_polars).unwrap();
+        assert_eq!(new_world.archetypes, world.archetypes);
+    }
+
+    #[test]
+    fn test_migrate_legacy_inertia() {
+        let mut world = World::default();
+        let pbr = world.insert_asset(Pbr::Bundle {
+            mesh: Mesh::sphere(0.1, 36, 18),
+            material: Material::color(1.0, 1.0, 1.0),
+        });
+        world.spawn(Body {
+            pos: WorldPos(SpatialTransform {
+                inner: vector![1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0].into(),
+            }),
+            vel: WorldVel(SpatialMotion {
+                inner: vector![0.0, 0.0, 0.0, 0.0, 0.0, 1.0].into(),
+            }),
+            accel: WorldAccel(SpatialMotion {
+                inner: vector![0.0, 0.0, 0.0, 0.0, 0.0, 0.0].into(),
+            }),
+            pbr,
+            force: Force(SpatialForce {
+                inner: vector![0.0, 0.0, 0.0, 0.0, 0.0, 0.0].into(),
+            }),
+            mass: Inertia(SpatialInertia {
+                inner: vector![1.0, 2.0, 3.0, 0.5, 0.0, 0.0, 4.0, 0.0, 0.0, 0.0].into(),
             }),
+            substeps: Substeps::default(),
+            substeps_exhausted: SubstepsExhausted::default(),
         });
+
+        // rewrite the inertia column in the 7 element layout used before versioning
+        let mut polars = world.to_polars().unwrap();
+        polars.metadata.version = 0;
+        let archetype = polars.metadata.archetypes.get_mut(&Body::name()).unwrap();
+        let metadata = archetype
+            .columns
+            .iter_mut()
+            .find(|m| m.name == Inertia::name())
+            .unwrap();
+        metadata.component_type.shape = smallvec::smallvec![7];
+        let legacy = HostColumn {
+            buf: [1.0f64, 2.0, 3.0, 0.5, 0.0, 0.0, 4.0]
+                .iter()
+                .flat_map(|f| f.to_ne_bytes())
+                .collect(),
+            len: 1,
+            metadata: metadata.clone(),
+        };
+        let df = polars.archetypes.get_mut(&Body::name()).unwrap();
+        df.replace(&Inertia::name(), legacy.to_series().unwrap())
+            .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        polars.write_to_dir(dir).unwrap();
+        let new_polars = PolarsWorld::read_from_dir(dir).unwrap();
+        assert_eq!(new_polars.metadata.version, Metadata::VERSION);
+        let new_world = World::try_from(new_polars).unwrap();
+        assert_eq!(new_world.archetypes, world.archetypes);
+    }
+
+    #[test]
+    fn test_reject_newer_version() {
+        let mut polars = World::default().to_polars().unwrap();
+        polars.metadata.version = Metadata::VERSION + 1;
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        polars.write_to_dir(dir).unwrap();
+        assert!(matches!(
+            PolarsWorld::read_from_dir(dir),
+            Err(Error::UnsupportedVersion(_))
+        ));
+    }
+
+    #[test]
+    fn test_f16_round_trip() {
+        #[derive(Component)]
+        struct Half(Scalar<F16>);
+
+        let mut world = World::default();
+        world.spawn(Half(F16::from_f32(1.5).constant()));
+        world.spawn(Half(F16::from_f32(-0.25).constant()));
+        let polars = world.to_polars().unwrap();
+        let new_world = World::try_from(polars).unwrap();
+        assert_eq!(new_world.archetypes, world.archetypes);
+    }
+
+    #[test]
+    fn test_resources_round_trip() {
+        #[derive(Component)]
+        struct Gravity(Scalar<f64>);
+
+        #[derive(Component)]
+        struct Mass(Scalar<f64>);
+
+        let mut world = World::default();
+        world.insert_resource(Gravity((-9.8).constant())).unwrap();
+        world.spawn(Mass(1.0.constant()));
+        world.spawn(Mass(2.0.constant()));
         let mut polars = world.to_polars().unwrap();
+
+        // resources aren't entities, so they stay out of the archetypes
+        let df = polars.join_archetypes().unwrap();
+        assert_eq!(df.height(), 2);
+        assert!(df.column(&Gravity::name()).is_err());
+
         let dir = tempfile::tempdir().unwrap();
         let dir = dir.path();
         polars.write_to_dir(dir).unwrap();
         let new_polars = PolarsWorld::read_from_dir(dir).unwrap();
         let new_world = World::try_from(new_polars).unwrap();
         assert_eq!(new_world.archetypes, world.archetypes);
+        assert_eq!(new_world.resources, world.resources);
+        assert_eq!(new_world.entity_len, 2);
+        let gravity = new_world.column::<Gravity>().unwrap();
+        assert!(gravity.column.metadata.is_resource());
+        assert_eq!(gravity.column.typed_buf::<f64>().unwrap(), &[-9.8]);
     }
 }
@@ -176,8 +176,9 @@ impl<G: ComponentGroup> SystemParam for Query<G> {
             .transmute()
     }
 
-    fn insert_into_builder(self, builder: &mut crate::PipelineBuilder) {
+    fn insert_into_builder(self, builder: &mut crate::PipelineBuilder) -> Result<(), Error> {
         self.insert_into_builder_erased(builder, G::component_ids());
+        Ok(())
     }
 }
 
@@ -0,0 +1,243 @@
+use crate::{Component, ComponentArray, ComponentExt, Error, HostColumn, HostStore};
+use crate::{PipelineBuilder, SystemParam, World};
+use conduit::{EntityId, Metadata};
+use nox::{FromOp, IntoOp, ScalarExt};
+use smallvec::smallvec;
+use std::ops::{Deref, DerefMut};
+
+/// Resources aren't entities, but every value streamed over conduit belongs to an entity, so
+/// resources are stored and streamed under this reserved id.
+pub const RESOURCE_ENTITY_ID: EntityId = EntityId(u64::MAX);
+
+impl World<HostStore> {
+    /// Inserts `res` as a resource, replacing its value if it's already in the world.
+    ///
+    /// A resource is a single value that belongs to the world rather than an entity. It's kept in
+    /// [`World::resources`], so it's persisted and streamed like a component without showing up
+    /// as an entity, and read by systems through [`Res`] and [`ResMut`].
+    pub fn insert_resource<R: Component + 'static>(&mut self, res: R) -> Result<(), Error> {
+        let mut column = HostColumn::new(R::metadata());
+        column.push(res);
+        self.insert_resource_raw(column.metadata, &column.buf)
+    }
+
+    /// Inserts a resource from its metadata and the raw bytes of its value, see
+    /// [`World::insert_resource`].
+    pub fn insert_resource_raw(&mut self, mut metadata: Metadata, buf: &[u8]) -> Result<(), Error> {
+        let id = metadata.component_id();
+        if self.component_map.contains_key(&id) || buf.len() != metadata.component_type.size() {
+            return Err(Error::InvalidResource);
+        }
+        if self.resources.entity_buffer.is_empty() {
+            self.resources
+                .entity_buffer
+                .push(RESOURCE_ENTITY_ID.0.constant());
+        }
+        metadata.set_resource();
+        let column = HostColumn {
+            buf: buf.to_vec(),
+            len: 1,
+            metadata,
+        };
+        self.resources.columns.insert(id, column);
+        Ok(())
+    }
+}
+
+/// Checks that `R` is a resource, and adds its column to the builder.
+fn init_resource<R: Component + 'static>(builder: &mut PipelineBuilder) -> Result<(), Error> {
+    let id = R::component_id();
+    if builder.world.component_map.contains_key(&id) {
+        return Err(Error::InvalidResource);
+    }
+    if !builder.world.resources.columns.contains_key(&id) {
+        return Err(Error::ComponentNotFound);
+    }
+    ComponentArray::<R>::init(builder)
+}
+
+fn resource_from_builder<R: Component + FromOp + 'static>(builder: &PipelineBuilder) -> R {
+    ComponentArray::<R>::from_builder(builder).get(0)
+}
+
+/// A read-only resource, see [`World::insert_resource`].
+pub struct Res<R> {
+    value: R,
+}
+
+impl<R> Res<R> {
+    pub fn into_inner(self) -> R {
+        self.value
+    }
+}
+
+impl<R> Deref for Res<R> {
+    type Target = R;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<R: Component + FromOp + 'static> SystemParam for Res<R> {
+    type Item = Self;
+
+    fn init(builder: &mut PipelineBuilder) -> Result<(), Error> {
+        init_resource::<R>(builder)
+    }
+
+    fn from_builder(builder: &PipelineBuilder) -> Self::Item {
+        Res {
+            value: resource_from_builder(builder),
+        }
+    }
+
+    fn insert_into_builder(self, _builder: &mut PipelineBuilder) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// A resource that's written back to the world when it's returned from a system.
+pub struct ResMut<R> {
+    value: R,
+}
+
+impl<R> ResMut<R> {
+    pub fn new(res: R) -> Self {
+        ResMut { value: res }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.value
+    }
+}
+
+impl<R> Deref for ResMut<R> {
+    type Target = R;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<R> DerefMut for ResMut<R> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+
+impl<R: Component + FromOp + 'static> SystemParam for ResMut<R> {
+    type Item = Self;
+
+    fn init(builder: &mut PipelineBuilder) -> Result<(), Error> {
+        init_resource::<R>(builder)
+    }
+
+    fn from_builder(builder: &PipelineBuilder) -> Self::Item {
+        ResMut {
+            value: resource_from_builder(builder),
+        }
+    }
+
+    fn insert_into_builder(self, builder: &mut PipelineBuilder) -> Result<(), Error> {
+        // resources are read by a system before they're written, so a missing var means `R`
+        // isn't a resource in this world
+        let var = builder
+            .vars
+            .get(&R::component_id())
+            .ok_or(Error::ComponentNotFound)?;
+        var.borrow_mut().buffer = self.value.into_op().broadcast(smallvec![1]);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IntoSystem, Query};
+    use nox::{Scalar, ScalarExt};
+
+    #[test]
+    fn test_resources() {
+        #[derive(Component)]
+        struct Gravity(Scalar<f64>);
+
+        #[derive(Component)]
+        struct Elapsed(Scalar<f64>);
+
+        #[derive(Component)]
+        struct Vel(Scalar<f64>);
+
+        fn fall(gravity: Res<Gravity>, vel: Query<Vel>) -> Query<Vel> {
+            vel.map(|vel: Vel| Vel(vel.0 + gravity.0.clone())).unwrap()
+        }
+
+        fn advance(elapsed: ResMut<Elapsed>) -> ResMut<Elapsed> {
+            ResMut::new(Elapsed(elapsed.0.clone() + 0.5))
+        }
+
+        let mut world = World::default();
+        world.insert_resource(Gravity((-9.8).constant())).unwrap();
+        world.insert_resource(Gravity((-1.0).constant())).unwrap();
+        world.insert_resource(Elapsed(0.0.constant())).unwrap();
+        world.spawn(Vel(0.0.constant()));
+        world.spawn(Vel(2.0.constant()));
+        let client = nox::Client::cpu().unwrap();
+        let mut exec = world
+            .builder()
+            .tick_pipeline(fall.pipe(advance))
+            .build()
+            .unwrap();
+        exec.run(&client).unwrap();
+        exec.run(&client).unwrap();
+        let vel = exec.column(Vel::component_id()).unwrap();
+        assert_eq!(vel.typed_buf::<f64>().unwrap(), &[-2.0, 0.0]);
+        let elapsed = exec.column(Elapsed::component_id()).unwrap();
+        assert_eq!(elapsed.typed_buf::<f64>().unwrap(), &[1.0]);
+    }
+
+    #[test]
+    fn test_resources_are_not_entities() {
+        #[derive(Component)]
+        struct Gravity(Scalar<f64>);
+
+        let mut world = World::default();
+        world.insert_resource(Gravity((-9.8).constant())).unwrap();
+        assert!(world.archetypes.is_empty());
+        assert_eq!(world.entity_len, 0);
+        let gravity = world.column::<Gravity>().unwrap();
+        let entities = gravity.entities.typed_buf::<u64>().unwrap();
+        assert_eq!(entities, &[RESOURCE_ENTITY_ID.0]);
+    }
+
+    #[test]
+    fn test_write_unread_resource() {
+        #[derive(Component)]
+        struct Elapsed(Scalar<f64>);
+
+        fn reset() -> ResMut<Elapsed> {
+            ResMut::new(Elapsed(0.0.constant()))
+        }
+
+        let mut world = World::default();
+        world.insert_resource(Elapsed(1.0.constant())).unwrap();
+        assert!(matches!(
+            world.builder().tick_pipeline(reset).build(),
+            Err(Error::ComponentNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_resource_must_be_singleton() {
+        #[derive(Component)]
+        struct Gravity(Scalar<f64>);
+
+        let mut world = World::default();
+        world.spawn(Gravity((-9.8).constant()));
+        world.spawn(Gravity((-1.0).constant()));
+        assert!(matches!(
+            world.insert_resource(Gravity(0.0.constant())),
+            Err(Error::InvalidResource)
+        ));
+    }
+}
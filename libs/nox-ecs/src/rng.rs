@@ -0,0 +1,110 @@
+use nox::nalgebra::RealField;
+use nox::{Dim, Field, FromOp, IntoOp, NoxprScalarExt, RngKey, Scalar, Tensor};
+use smallvec::smallvec;
+
+use crate::{ComponentArray, ComponentExt, Error, PipelineBuilder, SystemParam};
+
+/// A system param that hands each system its own key for the threefry PRNG.
+///
+/// The key state lives in the world as an [`RngKey`] component, which must be seeded before the
+/// pipeline is built, e.g. with `world.insert_resource(RngKey::new(seed))`, or building fails with
+/// [`Error::ComponentNotFound`]. Every system that takes an `Rng` splits the stored key, writing
+/// one half back and drawing from the other. The draws only depend on the seed, and the key is
+/// recorded by [`History`](crate::history::History) like any other component.
+pub struct Rng {
+    key: RngKey,
+    draws: u32,
+}
+
+impl Rng {
+    /// Returns a new key, distinct from every other key returned by this `Rng`.
+    pub fn key(&mut self) -> RngKey {
+        let key = self.key.fold_in(&Scalar::from_op(self.draws.constant()));
+        self.draws += 1;
+        key
+    }
+
+    /// Generates a tensor of floats uniformly distributed over `[0, 1)`.
+    pub fn uniform<T: Field + RealField, D: Dim>(&mut self) -> Tensor<T, D> {
+        self.key().uniform()
+    }
+
+    /// Generates a tensor of floats from the standard normal distribution.
+    pub fn normal<T: Field + RealField, D: Dim>(&mut self) -> Tensor<T, D> {
+        self.key().normal()
+    }
+}
+
+impl SystemParam for Rng {
+    type Item = Rng;
+
+    fn init(builder: &mut PipelineBuilder) -> Result<(), Error> {
+        builder
+            .world
+            .column::<RngKey>()
+            .ok_or(Error::ComponentNotFound)?;
+        ComponentArray::<RngKey>::init(builder)
+    }
+
+    fn from_builder(builder: &PipelineBuilder) -> Self::Item {
+        let mut var = builder.vars[&RngKey::component_id()].borrow_mut();
+        // the key of the first entity is used as the state, so extra keys spawned by users are ignored
+        let state = var
+            .buffer
+            .clone()
+            .slice(smallvec![0, 0], smallvec![1, 2], smallvec![1, 1])
+            .reshape(smallvec![2]);
+        let [next, key] = RngKey::from_op(state).split::<2>();
+        var.buffer = var.buffer.dynamic_update_slice(
+            vec![0i64.constant(), 0i64.constant()],
+            next.into_op().reshape(smallvec![1, 2]),
+        );
+        Rng { key, draws: 0 }
+    }
+
+    fn insert_into_builder(self, _builder: &mut PipelineBuilder) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rng;
+    use crate::{Component, ComponentArray, ComponentExt, Error, IntoSystem};
+    use nox::{RngKey, Scalar, ScalarExt};
+
+    #[test]
+    fn test_rng_replays_from_seed() {
+        #[derive(Component)]
+        struct Noise(Scalar<f64>);
+
+        fn noise(mut rng: Rng, n: ComponentArray<Noise>) -> ComponentArray<Noise> {
+            let draw = rng.uniform::<f64, ()>();
+            n.map(|_: Noise| Noise(draw.clone())).unwrap()
+        }
+
+        let run = || {
+            let mut world = noise.world();
+            world.spawn(Noise(0.0.constant()));
+            world.insert_resource(RngKey::new(7)).unwrap();
+            let client = nox::Client::cpu().unwrap();
+            let mut exec = world.build().unwrap();
+            (0..3)
+                .map(|_| {
+                    exec.run(&client).unwrap();
+                    let n = exec.column(Noise::component_id()).unwrap();
+                    n.typed_buf::<f64>().unwrap()[0]
+                })
+                .collect::<Vec<_>>()
+        };
+        let draws = run();
+        assert!(draws.iter().all(|x| (0.0..1.0).contains(x)));
+        assert_ne!(draws[0], draws[1]);
+        assert_ne!(draws[1], draws[2]);
+        assert_eq!(draws, run());
+
+        let mut world = noise.world();
+        world.spawn(Noise(0.0.constant()));
+        assert!(matches!(world.build(), Err(Error::ComponentNotFound)));
+    }
+}
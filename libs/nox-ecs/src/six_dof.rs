@@ -1,12 +1,16 @@
 use conduit::well_known::Pbr;
-use nox::{SpatialForce, SpatialInertia, SpatialMotion};
-use nox_ecs::{Archetype, Component};
+use conduit::ComponentId;
+use nox::{Client, SpatialForce, SpatialInertia, SpatialMotion};
+use nox_ecs::{Archetype, Component, ComponentExt, Error, Linearization, WorldExec};
 use nox_ecs::{Handle, IntoSystem, Query, System, WorldPos};
 use nox_ecs_macros::{ComponentGroup, FromBuilder, IntoOp};
 use std::ops::{Add, Mul};
 use std::sync::Arc;
 
-use crate::{semi_implicit_euler_with_dt, ComponentArray, ErasedSystem, Integrator, Rk4Ext, Time};
+use crate::{
+    semi_implicit_euler_with_dt, ComponentArray, ErasedSystem, Integrator, Rk45Ext, Rk4Ext,
+    Substeps, SubstepsExhausted, SymplecticExt, Time,
+};
 
 #[derive(Clone, Component)]
 pub struct WorldVel(pub SpatialMotion<f64>);
@@ -95,9 +99,18 @@ pub struct Force(pub SpatialForce<f64>);
 #[derive(Clone, Component)]
 pub struct Inertia(pub SpatialInertia<f64>);
 
-fn calc_accel(q: Query<(Force, Inertia)>) -> Query<WorldAccel> {
-    q.map(|force: Force, mass: Inertia| WorldAccel(force.0 / mass.0))
-        .unwrap()
+fn calc_accel(q: Query<(Force, Inertia, WorldPos, WorldVel)>) -> Query<WorldAccel> {
+    q.map(
+        |force: Force, inertia: Inertia, pos: WorldPos, vel: WorldVel| {
+            // the inertia is in the body frame, so solve there and rotate back into the world
+            let rot = pos.0.angular();
+            let inv = rot.inverse();
+            let body_force = inv.clone() * force.0;
+            let body_vel = inv * vel.0;
+            WorldAccel(rot * inertia.0.accel(&body_force, &body_vel))
+        },
+    )
+    .unwrap()
 }
 
 fn clear_forces(q: ComponentArray<Force>) -> ComponentArray<Force> {
@@ -112,6 +125,8 @@ pub struct Body {
     pub force: Force,
     pub mass: Inertia,
     pub pbr: Handle<Pbr>,
+    pub substeps: Substeps,
+    pub substeps_exhausted: SubstepsExhausted,
 }
 
 pub fn advance_time(time_step: f64) -> impl System {
@@ -136,10 +151,140 @@ where
     let sys = clear_forces.pipe(effectors()).pipe(calc_accel);
     match integrator {
         Integrator::Rk4 => Arc::new(ErasedSystem::new(sys.rk4_with_dt::<U, DU>(time_step))),
+        Integrator::Rk45 => Arc::new(ErasedSystem::new(sys.rk45_with_dt::<U, DU>(time_step))),
         Integrator::SemiImplicit => {
             let integrate =
                 semi_implicit_euler_with_dt::<WorldPos, WorldVel, WorldAccel>(time_step);
             Arc::new(ErasedSystem::new(sys.pipe(integrate)))
         }
+        Integrator::Verlet => Arc::new(ErasedSystem::new(
+            sys.verlet_with_dt::<WorldPos, WorldVel, WorldAccel>(time_step),
+        )),
+        Integrator::Yoshida4 => Arc::new(ErasedSystem::new(
+            sys.yoshida4_with_dt::<WorldPos, WorldVel, WorldAccel>(time_step),
+        )),
+    }
+}
+
+/// Linearizes a six degree of freedom tick pipeline around the current world state.
+///
+/// The state holds the [`WorldPos`] and [`WorldVel`] of every body, so each body contributes 13
+/// rows, with the attitude quaternion linearized directly. `inputs` lists the components that the
+/// effectors read as control inputs.
+pub fn linearize<M, A, R>(
+    exec: &mut WorldExec,
+    pipeline: impl IntoSystem<M, A, R>,
+    inputs: &[ComponentId],
+    client: &Client,
+) -> Result<Linearization, Error> {
+    let state = [WorldPos::component_id(), WorldVel::component_id()];
+    exec.linearize(pipeline, &state, inputs, client)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::World;
+    use conduit::well_known::{Material, Mesh};
+    use nox::nalgebra::{self, vector};
+    use nox::{SpatialTransform, Vector};
+
+    /// Runs a single body for one second, and returns its final position and velocity.
+    fn simulate<Sys, M, A, R>(
+        effectors: impl FnOnce() -> Sys,
+        vel: Vector<f64, 6>,
+        mass: Vector<f64, 10>,
+        integrator: Integrator,
+    ) -> (Vec<f64>, Vec<f64>)
+    where
+        M: 'static,
+        A: 'static,
+        R: 'static,
+        Sys: IntoSystem<M, A, R> + 'static,
+        <Sys as IntoSystem<M, A, R>>::System: Send + Sync,
+    {
+        let mut world = World::default();
+        let pbr = world.insert_asset(Pbr::Bundle {
+            mesh: Mesh::sphere(0.1, 36, 18),
+            material: Material::color(1.0, 1.0, 1.0),
+        });
+        world.spawn(Body {
+            pos: WorldPos(SpatialTransform {
+                inner: vector![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0].into(),
+            }),
+            vel: WorldVel(SpatialMotion { inner: vel }),
+            accel: WorldAccel(SpatialMotion {
+                inner: vector![0.0, 0.0, 0.0, 0.0, 0.0, 0.0].into(),
+            }),
+            force: Force(SpatialForce {
+                inner: vector![0.0, 0.0, 0.0, 0.0, 0.0, 0.0].into(),
+            }),
+            mass: Inertia(SpatialInertia { inner: mass }),
+            pbr,
+            substeps: Substeps::default(),
+            substeps_exhausted: SubstepsExhausted::default(),
+        });
+        let pipeline = six_dof(effectors, 1.0 / 60.0, integrator);
+        let mut exec = world.builder().tick_pipeline(pipeline).build().unwrap();
+        let client = Client::cpu().unwrap();
+        for _ in 0..60 {
+            exec.run(&client).unwrap();
+        }
+        let mut read = |id| {
+            let column = exec.column(id).unwrap();
+            column.typed_buf::<f64>().unwrap().to_vec()
+        };
+        (
+            read(WorldPos::component_id()),
+            read(WorldVel::component_id()),
+        )
+    }
+
+    fn push(q: ComponentArray<Force>) -> ComponentArray<Force> {
+        q.map(|_| Force(SpatialForce::from_linear(vector![2.0, 0.0, 0.0])))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_symplectic_constant_force() {
+        // without any rotation the acceleration only depends on the position, and a constant one
+        // is integrated exactly
+        for integrator in [Integrator::Verlet, Integrator::Yoshida4] {
+            let (pos, vel) = simulate(
+                || push,
+                vector![0.0, 0.0, 0.0, 0.0, 0.0, 0.0].into(),
+                vector![2.0, 2.0, 2.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0].into(),
+                integrator,
+            );
+            assert!((pos[4] - 0.5).abs() < 1e-9, "{}", pos[4]);
+            assert!((vel[3] - 1.0).abs() < 1e-9, "{}", vel[3]);
+        }
+    }
+
+    #[test]
+    fn test_symplectic_gyroscopic() {
+        // a body tumbling with an anisotropic inertia has a velocity dependent acceleration, which
+        // drops the symplectic integrators to first order
+        let tumble = |integrator| {
+            let (_, vel) = simulate(
+                || (),
+                vector![1.0, 0.5, 0.2, 0.0, 0.0, 0.0].into(),
+                vector![1.0, 2.0, 3.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0].into(),
+                integrator,
+            );
+            vel
+        };
+        let reference = tumble(Integrator::Rk4);
+        let error = |vel: Vec<f64>| {
+            vel.iter()
+                .zip(&reference)
+                .map(|(a, b)| (a - b).abs())
+                .fold(0.0, f64::max)
+        };
+        let verlet = error(tumble(Integrator::Verlet));
+        let yoshida = error(tumble(Integrator::Yoshida4));
+        assert!(verlet < 1e-2, "{}", verlet);
+        // a fourth order method would be within 1e-8 here
+        assert!(yoshida > 1e-3 && yoshida < 1e-2, "{}", yoshida);
     }
 }
@@ -398,7 +398,7 @@ Force = Annotated[
 ]
 Inertia = Annotated[
     SpatialInertia,
-    Component("inertia", ComponentType.SpatialPosF64, metadata={"priority": 5}),
+    Component("inertia", ComponentType.SpatialInertiaF64, metadata={"priority": 5}),
 ]
 Seed = Annotated[
     jax.Array, Component("seed", ComponentType.U64, metadata={"priority": 5})
@@ -406,6 +406,13 @@ Seed = Annotated[
 Time = Annotated[
     jax.Array, Component("time", ComponentType.F64, metadata={"priority": 5})
 ]
+Substeps = Annotated[
+    jax.Array, Component("substeps", ComponentType.U64, metadata={"priority": 5})
+]
+SubstepsExhausted = Annotated[
+    jax.Array,
+    Component("substeps_exhausted", ComponentType.U64, metadata={"priority": 5}),
+]
 PbrAsset = Annotated[
     Handle, Component("asset_handle_241", ComponentType.U64, True, metadata={"priority": -1})
 ]
@@ -449,6 +456,8 @@ class Body(Archetype):
     pbr: PbrAsset = Pbr(Mesh.sphere(1.0), Material.color(1.0, 1.0, 1.0)) # type: ignore # TODO(sphw): this code is wrong, but fixing it is hard
     force: Force = SpatialForce.zero()
     world_accel: WorldAccel = SpatialMotion.zero()
+    substeps: Substeps = numpy.uint64(0)
+    substeps_exhausted: SubstepsExhausted = numpy.uint64(0)
 
 
 def build_expr(builder: PipelineBuilder, sys: System) -> Any:
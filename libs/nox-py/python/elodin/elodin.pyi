@@ -15,7 +15,10 @@ class PrimitiveType:
 
 class Integrator:
     Rk4: Integrator
+    Rk45: Integrator
     SemiImplicit: Integrator
+    Verlet: Integrator
+    Yoshida4: Integrator
 
 class ComponentType:
     def __init__(self, ty: PrimitiveType, shape: Tuple[int, ...]): ...
@@ -28,6 +31,7 @@ class ComponentType:
     Quaternion: ClassVar[ComponentType]
     SpatialPosF64: ClassVar[ComponentType]
     SpatialMotionF64: ClassVar[ComponentType]
+    SpatialInertiaF64: ClassVar[ComponentType]
 
 class PipelineBuilder:
     def init_var(self, name: str, ty: ComponentType): ...
@@ -37,6 +41,7 @@ class WorldBuilder:
     def spawn(self, archetype: Any) -> Entity: ...
     def spawn_with_entity_id(self, id: EntityId, archetype: Any) -> Entity: ...
     def insert_asset(self, asset: Any): ...
+    def insert_resource(self, resource: Any): ...
     def run(
         self,
         system: Any,
@@ -84,6 +89,14 @@ class SpatialTransform:
     def angular(self) -> Quaternion: ...
     def asarray(self) -> jax.typing.ArrayLike: ...
     def __add__(self, other: SpatialTransform) -> SpatialTransform: ...
+    @staticmethod
+    def identity() -> SpatialTransform: ...
+    def inverse(self) -> SpatialTransform: ...
+    def transform_point(self, point: jax.typing.ArrayLike) -> jax.Array: ...
+    def transform_vector(self, vector: jax.typing.ArrayLike) -> jax.Array: ...
+    def __matmul__(
+        self, other: jax.Array | SpatialTransform | SpatialMotion | SpatialForce
+    ) -> jax.Array: ...
 
 class SpatialForce:
     shape: jax.typing.ArrayLike
@@ -136,8 +149,22 @@ class SpatialInertia:
     def zero() -> SpatialInertia: ...
     @staticmethod
     def from_mass(mass: jax.typing.ArrayLike) -> SpatialInertia: ...
+    @staticmethod
+    def from_tensor(
+        inertia: jax.typing.ArrayLike,
+        momentum: jax.typing.ArrayLike,
+        mass: jax.typing.ArrayLike,
+    ) -> SpatialInertia: ...
+    @staticmethod
+    def from_com(
+        inertia: jax.typing.ArrayLike,
+        com: jax.typing.ArrayLike,
+        mass: jax.typing.ArrayLike,
+    ) -> SpatialInertia: ...
     def mass(self) -> jax.typing.ArrayLike: ...
     def inertia_diag(self) -> jax.typing.ArrayLike: ...
+    def inertia_tensor(self) -> jax.typing.ArrayLike: ...
+    def com(self) -> jax.typing.ArrayLike: ...
 
 class Quaternion:
     shape: jax.typing.ArrayLike
@@ -162,12 +189,26 @@ class Quaternion:
         self, other: jax.Array | SpatialTransform | SpatialMotion | SpatialForce
     ) -> jax.Array: ...
     def inverse(self) -> Quaternion: ...
+    @staticmethod
+    def from_rotation_matrix(m: jax.typing.ArrayLike) -> Quaternion: ...
+    def to_rotation_matrix(self) -> jax.Array: ...
+    @staticmethod
+    def from_euler(seq: str, angles: jax.typing.ArrayLike) -> Quaternion: ...
+    def to_euler(self, seq: str) -> jax.Array: ...
+    @staticmethod
+    def exp(rotation: jax.typing.ArrayLike) -> Quaternion: ...
+    def log(self) -> jax.Array: ...
+    def slerp(self, other: Quaternion, t: jax.typing.ArrayLike) -> Quaternion: ...
+    def nlerp(self, other: Quaternion, t: jax.typing.ArrayLike) -> Quaternion: ...
+    def angular_distance(self, other: Quaternion) -> jax.Array: ...
 
 class RustSystem:
     def call(self, builder: PipelineBuilder): ...
     def init(self, builder: PipelineBuilder): ...
     def pipe(self, other: Any) -> RustSystem: ...
     def __or__(self, other: Any) -> RustSystem: ...
+    def substep(self, n: int) -> RustSystem: ...
+    def rate(self, name: str, period: int, phase: int = 0) -> RustSystem: ...
 
 class Mesh:
     @staticmethod
@@ -264,6 +305,7 @@ class Exec:
     def run(self, client: Client): ...
     def history(self) -> pl.DataFrame: ...
     def column_array(self, name: str) -> numpy.ndarray: ...
+    def rates(self) -> list[tuple[str, int, int]]: ...
 
 class Color:
     def __init__(self, r: float, g: float, b: float): ...
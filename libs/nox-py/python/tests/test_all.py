@@ -69,6 +69,63 @@ def test_six_dof():
     assert (x == [0, 0, 0, 1, 1.0 / 60.0, 0.0, 0.0]).all()
 
 
+def test_substep():
+    w = WorldBuilder()
+    w.spawn(
+        Body(
+            world_pos=WorldPos.from_linear(np.array([0.0, 0.0, 0.0])),
+            world_vel=WorldVel.from_linear(np.array([1.0, 0.0, 0.0])),
+            inertia=Inertia.from_mass(1.0),
+        )
+    )
+    client = Client.cpu()
+    sys = six_dof(1.0 / 240.0).substep(4)
+    exec = w.build(sys)
+    exec.run(client)
+    x = exec.column_array(Component.id(WorldPos))
+    assert np.allclose(x, [0, 0, 0, 1, 1.0 / 60.0, 0.0, 0.0])
+
+
+def test_rate():
+    w = WorldBuilder()
+    w.spawn(
+        Body(
+            world_pos=WorldPos.from_linear(np.array([0.0, 0.0, 0.0])),
+            world_vel=WorldVel.from_linear(np.array([1.0, 0.0, 0.0])),
+            inertia=Inertia.from_mass(1.0),
+        )
+    )
+    client = Client.cpu()
+    sys = six_dof(1.0 / 60.0).rate("physics", 2)
+    exec = w.build(sys)
+    assert exec.rates() == [("physics", 2, 0)]
+    for _ in range(3):
+        exec.run(client)
+    # the system only ran on ticks 0 and 2
+    x = exec.column_array(Component.id(WorldPos))
+    assert np.allclose(x, [0, 0, 0, 1, 2.0 / 60.0, 0.0, 0.0])
+
+
+def test_rk45():
+    w = WorldBuilder()
+    w.spawn(
+        Body(
+            world_pos=WorldPos.from_linear(np.array([0.0, 0.0, 0.0])),
+            world_vel=WorldVel.from_linear(np.array([1.0, 0.0, 0.0])),
+            inertia=Inertia.from_mass(1.0),
+        )
+    )
+    client = Client.cpu()
+    sys = six_dof(1.0 / 60.0, integrator=Integrator.Rk45)
+    exec = w.build(sys)
+    exec.run(client)
+    x = exec.column_array(Component.id(WorldPos))
+    assert np.allclose(x, [0, 0, 0, 1, 1.0 / 60.0, 0.0, 0.0])
+    # without any forces the first full step is exact, so it's accepted
+    assert (exec.column_array(Component.id(Substeps)) == [1]).all()
+    assert (exec.column_array(Component.id(SubstepsExhausted)) == [0]).all()
+
+
 def test_graph():
     X = Annotated[jax.Array, Component("x", ComponentType.F64)]
     E = Annotated[Edge, Component("test_edge", ComponentType.Edge)]
@@ -149,6 +206,21 @@ def test_seed():
     assert (y1 <= [1000.0, 1000.0]).all()
 
 
+def test_resource_mismatch():
+    G = Annotated[jax.Array, Component("gravity", ComponentType.F64)]
+
+    @dataclass
+    class Gravity(Archetype):
+        g: G
+
+    w = WorldBuilder()
+    w.insert_resource(Gravity(np.array(-9.8, dtype="float64")))
+    with pytest.raises(ValueError, match="dtype"):
+        w.insert_resource(Gravity(np.array(-9.8, dtype="float32")))
+    with pytest.raises(ValueError, match="elements"):
+        w.insert_resource(Gravity(np.array([-9.8, 0.0], dtype="float64")))
+
+
 def test_archetype_name():
     X = Annotated[jax.Array, Component("x", ComponentType.F64)]
 
@@ -172,3 +244,16 @@ def test_spatial_vector_algebra():
     exec.run(client)
     v = exec.column_array(Component.id(WorldVel))
     assert (v[0][3:] == [2.0, 0.0, 0.0]).all()
+
+
+def test_spatial_inertia_constructors():
+    inertia = np.diag(np.array([1.0, 2.0, 3.0]))
+    com = np.array([1.0, 0.0, 0.0])
+    mass = np.array(2.0)
+    from_com = SpatialInertia.from_com(inertia, com, mass)
+    assert np.allclose(from_com.com(), com)
+    from_tensor = SpatialInertia.from_tensor(
+        from_com.inertia_tensor(), com * mass, mass
+    )
+    assert np.allclose(from_tensor.asarray(), from_com.asarray())
+    assert np.allclose(SpatialInertia.from_mass(np.array(0.0)).com(), 0.0)
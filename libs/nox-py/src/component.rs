@@ -152,6 +152,16 @@ impl ComponentType {
         }
     }
 
+    #[classattr]
+    #[pyo3(name = "SpatialInertiaF64")]
+    pub fn spatial_inertia_f64(py: Python<'_>) -> Self {
+        let shape = numpy::PyArray1::from_vec(py, vec![10]).to_owned();
+        Self {
+            ty: PrimitiveType::F64,
+            shape,
+        }
+    }
+
     #[classattr]
     #[pyo3(name = "U64")]
     pub fn u64(py: Python<'_>) -> Self {
@@ -230,6 +240,8 @@ pub enum PrimitiveType {
     I16,
     I8,
     Bool,
+    F16,
+    BF16,
 }
 
 impl From<PrimitiveType> for conduit::PrimitiveTy {
@@ -246,6 +258,8 @@ impl From<PrimitiveType> for conduit::PrimitiveTy {
             PrimitiveType::I16 => conduit::PrimitiveTy::I16,
             PrimitiveType::I8 => conduit::PrimitiveTy::I8,
             PrimitiveType::Bool => conduit::PrimitiveTy::Bool,
+            PrimitiveType::F16 => conduit::PrimitiveTy::F16,
+            PrimitiveType::BF16 => conduit::PrimitiveTy::BF16,
         }
     }
 }
@@ -38,6 +38,9 @@ impl From<Error> for PyErr {
                 PyValueError::new_err("value size mismatch")
             }
             Error::NoxEcs(nox_ecs::Error::PyO3(err)) | Error::PyErr(err) => err,
+            err @ Error::Nox(nox::Error::InvalidEulerSeq(_)) => {
+                PyValueError::new_err(err.to_string())
+            }
             err => PyRuntimeError::new_err(err.to_string()),
         }
     }
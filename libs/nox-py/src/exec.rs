@@ -16,6 +16,17 @@ impl Exec {
         Python::with_gil(|_| self.exec.run(&client.client).map_err(Error::from))
     }
 
+    /// Returns the name, period and phase of every system that doesn't run on every tick.
+    pub fn rates(&self) -> Vec<(String, u64, u64)> {
+        self.exec
+            .tick_exec
+            .metadata()
+            .rates
+            .iter()
+            .map(|rate| (rate.system.clone(), rate.period, rate.phase))
+            .collect()
+    }
+
     pub fn history(&self) -> Result<PyDataFrame, Error> {
         let polars_world = self.exec.history.compact_to_world()?;
         let df = polars_world.join_archetypes()?;
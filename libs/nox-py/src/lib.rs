@@ -115,7 +115,10 @@ impl Client {
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Integrator {
     Rk4,
+    Rk45,
     SemiImplicit,
+    Verlet,
+    Yoshida4,
 }
 
 impl FromStr for Integrator {
@@ -124,7 +127,10 @@ impl FromStr for Integrator {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "rk4" => Ok(Integrator::Rk4),
+            "rk45" => Ok(Integrator::Rk45),
             "semi-implicit" => Ok(Integrator::SemiImplicit),
+            "verlet" => Ok(Integrator::Verlet),
+            "yoshida4" => Ok(Integrator::Yoshida4),
             _ => Err(Error::PyErr(PyValueError::new_err("unknown integrator"))),
         }
     }
@@ -134,7 +140,10 @@ impl From<Integrator> for nox_ecs::Integrator {
     fn from(integrator: Integrator) -> Self {
         match integrator {
             Integrator::Rk4 => nox_ecs::Integrator::Rk4,
+            Integrator::Rk45 => nox_ecs::Integrator::Rk45,
             Integrator::SemiImplicit => nox_ecs::Integrator::SemiImplicit,
+            Integrator::Verlet => nox_ecs::Integrator::Verlet,
+            Integrator::Yoshida4 => nox_ecs::Integrator::Yoshida4,
         }
     }
 }
@@ -71,9 +71,14 @@ impl PipelineBuilder {
             vars,
             world,
             param_ids,
+            tick,
             ..
         } = builder;
         for (arg, id) in args.into_iter().zip(param_ids.iter()) {
+            if *id == nox_ecs::TICK_ID {
+                *tick = Some(Noxpr::jax(arg));
+                continue;
+            }
             let column = world
                 .column_by_id(*id)
                 .ok_or(nox_ecs::Error::ComponentNotFound)?;
@@ -1,6 +1,6 @@
 use std::ops::{Add, Mul};
 
-use nox_ecs::nox::{self, FromOp, IntoOp, Noxpr, Scalar, Vector};
+use nox_ecs::nox::{self, FromOp, IntoOp, Matrix, Noxpr, Op, Scalar, Vector};
 use pyo3::{prelude::*, types::PyTuple};
 
 use crate::Error;
@@ -90,6 +90,39 @@ impl SpatialTransform {
     fn __add__(&self, other: &SpatialTransform) -> Self {
         (self.inner.clone() + other.inner.clone()).into()
     }
+
+    #[staticmethod]
+    fn identity() -> Self {
+        nox::SpatialTransform::identity().into()
+    }
+
+    fn inverse(&self) -> Self {
+        self.inner.inverse().into()
+    }
+
+    fn transform_point(&self, point: PyObject) -> Result<PyObject, Error> {
+        let point = Vector::<f64, 3>::from_op(Noxpr::jax(point));
+        Ok(self.inner.transform_point(point).into_op().to_jax()?)
+    }
+
+    fn transform_vector(&self, vector: PyObject) -> Result<PyObject, Error> {
+        let vector = Vector::<f64, 3>::from_op(Noxpr::jax(vector));
+        Ok(self.inner.transform_vector(vector).into_op().to_jax()?)
+    }
+
+    fn __matmul__(&self, py: Python<'_>, rhs: PyObject) -> Result<PyObject, Error> {
+        let noxpr = if let Ok(s) = rhs.extract::<SpatialTransform>(py) {
+            self.inner.clone().mul(s.inner).into_op()
+        } else if let Ok(s) = rhs.extract::<SpatialMotion>(py) {
+            self.inner.clone().mul(s.inner).into_op()
+        } else if let Ok(s) = rhs.extract::<SpatialForce>(py) {
+            self.inner.clone().mul(s.inner).into_op()
+        } else {
+            let point = Vector::from_op(Noxpr::jax(rhs));
+            self.inner.transform_point(point).into_op()
+        };
+        Ok(noxpr.to_jax()?)
+    }
 }
 
 #[pyclass]
@@ -337,6 +370,56 @@ impl Quaternion {
     pub fn inverse(&self) -> Self {
         self.inner.clone().inverse().into()
     }
+
+    #[staticmethod]
+    fn from_rotation_matrix(m: PyObject) -> Self {
+        let m = Matrix::<f64, 3, 3>::from_op(Noxpr::jax(m));
+        nox::Quaternion::<f64, Op>::from_rotation_matrix(m).into()
+    }
+
+    fn to_rotation_matrix(&self) -> Result<PyObject, Error> {
+        Ok(self.inner.to_rotation_matrix().into_op().to_jax()?)
+    }
+
+    #[staticmethod]
+    fn from_euler(seq: &str, angles: PyObject) -> Result<Self, Error> {
+        let seq = seq.parse::<nox::EulerSeq>()?;
+        let angles = Vector::<f64, 3>::from_op(Noxpr::jax(angles));
+        Ok(nox::Quaternion::<f64, Op>::from_euler(seq, angles).into())
+    }
+
+    fn to_euler(&self, seq: &str) -> Result<PyObject, Error> {
+        let seq = seq.parse::<nox::EulerSeq>()?;
+        Ok(self.inner.to_euler(seq).into_op().to_jax()?)
+    }
+
+    #[staticmethod]
+    fn exp(rotation: PyObject) -> Self {
+        let rotation = Vector::<f64, 3>::from_op(Noxpr::jax(rotation));
+        nox::Quaternion::<f64, Op>::exp(rotation).into()
+    }
+
+    fn log(&self) -> Result<PyObject, Error> {
+        Ok(self.inner.log().into_op().to_jax()?)
+    }
+
+    fn slerp(&self, other: &Quaternion, t: PyObject) -> Self {
+        let t = Scalar::<f64>::from_op(Noxpr::jax(t));
+        self.inner.slerp(&other.inner, t).into()
+    }
+
+    fn nlerp(&self, other: &Quaternion, t: PyObject) -> Self {
+        let t = Scalar::<f64>::from_op(Noxpr::jax(t));
+        self.inner.nlerp(&other.inner, t).into()
+    }
+
+    fn angular_distance(&self, other: &Quaternion) -> Result<PyObject, Error> {
+        Ok(self
+            .inner
+            .angular_distance(&other.inner)
+            .into_op()
+            .to_jax()?)
+    }
 }
 
 #[pyclass]
@@ -367,6 +450,26 @@ impl SpatialInertia {
         nox::SpatialInertia::from_mass(Scalar::from_op(Noxpr::jax(arr))).into()
     }
 
+    #[staticmethod]
+    fn from_tensor(inertia: PyObject, momentum: PyObject, mass: PyObject) -> Self {
+        nox::SpatialInertia::from_tensor(
+            Matrix::<f64, 3, 3>::from_op(Noxpr::jax(inertia)),
+            Vector::<f64, 3>::from_op(Noxpr::jax(momentum)),
+            Scalar::<f64>::from_op(Noxpr::jax(mass)),
+        )
+        .into()
+    }
+
+    #[staticmethod]
+    fn from_com(inertia: PyObject, com: PyObject, mass: PyObject) -> Self {
+        nox::SpatialInertia::from_com(
+            Matrix::<f64, 3, 3>::from_op(Noxpr::jax(inertia)),
+            Vector::<f64, 3>::from_op(Noxpr::jax(com)),
+            Scalar::<f64>::from_op(Noxpr::jax(mass)),
+        )
+        .into()
+    }
+
     fn flatten(&self) -> Result<((PyObject,), Option<()>), Error> {
         let jax = self.inner.clone().into_op().to_jax()?;
         Ok(((jax,), None))
@@ -396,12 +499,20 @@ impl SpatialInertia {
         Ok(self.inner.inertia_diag().into_op().to_jax()?)
     }
 
+    fn inertia_tensor(&self) -> Result<PyObject, Error> {
+        Ok(self.inner.inertia_tensor().into_op().to_jax()?)
+    }
+
+    fn com(&self) -> Result<PyObject, Error> {
+        Ok(self.inner.com().into_op().to_jax()?)
+    }
+
     fn asarray(&self) -> Result<PyObject, Error> {
         Ok(self.inner.clone().into_op().to_jax()?)
     }
 
     #[getter]
     fn shape(&self) -> PyObject {
-        Python::with_gil(|py| PyTuple::new(py, [7]).into())
+        Python::with_gil(|py| PyTuple::new(py, [10]).into())
     }
 }
@@ -1,6 +1,7 @@
 use crate::*;
 
 use nox_ecs::{IntoSystem, System};
+use std::num::NonZeroU64;
 use std::sync::Arc;
 
 #[derive(Clone)]
@@ -69,6 +70,25 @@ impl RustSystem {
     fn __or__(&self, sys: PyObject) -> RustSystem {
         self.pipe(sys)
     }
+    fn substep(&self, n: usize) -> RustSystem {
+        RustSystem {
+            inner: Arc::new(ErasedSystem::new(IntoSystem::substep(self.clone(), n))),
+        }
+    }
+    #[pyo3(signature = (name, period, phase = 0))]
+    fn rate(&self, name: String, period: u64, phase: u64) -> Result<RustSystem, Error> {
+        let period = NonZeroU64::new(period).ok_or_else(|| {
+            Error::PyErr(PyValueError::new_err(
+                "a system's period must be at least one tick",
+            ))
+        })?;
+        let rate = IntoSystem::rate(self.clone(), period)
+            .phase(phase)
+            .name(name);
+        Ok(RustSystem {
+            inner: Arc::new(ErasedSystem::new(rate)),
+        })
+    }
 }
 
 impl System for RustSystem {
@@ -118,6 +118,47 @@ impl WorldBuilder {
         }
     }
 
+    /// Inserts a component as a resource, a single value that belongs to the world rather than
+    /// an entity and that systems can read as a global, replacing its value if it's already in
+    /// the world.
+    pub fn insert_resource(&mut self, resource: Spawnable<'_>) -> Result<(), Error> {
+        let Spawnable::Archetype(archetype) = resource else {
+            return Err(Error::PyErr(PyValueError::new_err(
+                "assets can't be resources",
+            )));
+        };
+        let [component] = &archetype.component_datas[..] else {
+            return Err(Error::PyErr(PyValueError::new_err(
+                "a resource must be a single component",
+            )));
+        };
+        let metadata: conduit::Metadata = component.clone().into();
+        let ty = &metadata.component_type;
+        let element_type = ty.primitive_ty.element_type();
+        let array = archetype.arrays[0];
+        let dtype = nox::jax::dtype(&element_type)?;
+        let array_dtype = array.dtype().getattr("name")?.extract::<String>()?;
+        if array_dtype != dtype {
+            return Err(Error::PyErr(PyValueError::new_err(format!(
+                "resource {} has dtype {}, expected {}",
+                component.name, array_dtype, dtype
+            ))));
+        }
+        let size = element_type.element_size_in_bytes();
+        let len = array.shape().iter().product::<usize>();
+        if len * size != ty.size() {
+            return Err(Error::PyErr(PyValueError::new_err(format!(
+                "resource {} has {} elements, expected {}",
+                component.name,
+                len,
+                ty.size() / size
+            ))));
+        }
+        let buf = unsafe { array.buf(size) };
+        self.world.insert_resource_raw(metadata, buf)?;
+        Ok(())
+    }
+
     fn insert_asset(&mut self, py: Python<'_>, asset: PyObject) -> Result<Handle, Error> {
         let asset = PyAsset::try_new(py, asset)?;
         let inner = self
@@ -335,6 +376,7 @@ def build_expr(builder, sys):
             time_step,
             arg_ids: builder.param_ids,
             ret_ids,
+            rates: builder.rates,
         };
         let tick_exec = nox_ecs::Exec::new(metadata, hlo_module);
         let exec = nox_ecs::WorldExec::new(world, tick_exec, None);
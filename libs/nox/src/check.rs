@@ -0,0 +1,1008 @@
+//! Verifies the shapes and element types of a [`Noxpr`] graph before it's lowered.
+//!
+//! [`Noxpr::ty`] gives up with `None` as soon as anything is inconsistent, so a bad graph only
+//! fails once XLA compiles it, with an error that doesn't say where the graph came from. The
+//! [`ShapeChecker`] instead reports the first inconsistent node along with its operands' types
+//! and the system that built it.
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use smallvec::SmallVec;
+use xla::ElementType;
+
+use crate::{ArrayTy, Error, Noxpr, NoxprFn, NoxprId, NoxprNode, NoxprTy};
+
+/// Describes the node a [`ShapeChecker`] found to be inconsistent.
+#[derive(Debug, Clone)]
+pub struct ShapeError {
+    /// The kind of the node, as returned by [`Noxpr::name`].
+    pub node: &'static str,
+    pub id: NoxprId,
+    /// The types of the node's inputs, not including the parameters of nested functions.
+    pub operands: Vec<NoxprTy>,
+    pub reason: String,
+    /// The system the node was attributed to, if any.
+    pub system: Option<String>,
+    /// Where the node was built. This is only captured when `RUST_BACKTRACE` is set.
+    pub backtrace: Arc<Backtrace>,
+}
+
+impl fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid {} node {:?}", self.node, self.id)?;
+        if let Some(system) = &self.system {
+            write!(f, " in system {}", system)?;
+        }
+        write!(f, ": {} (operands: ", self.reason)?;
+        for (i, ty) in self.operands.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            ty.pretty_print(f)?;
+        }
+        write!(f, ")")?;
+        if self.backtrace.status() == BacktraceStatus::Captured {
+            write!(f, "\nnode built at:\n{}", self.backtrace)?;
+        }
+        Ok(())
+    }
+}
+
+/// Why a node failed to check: either the node itself is inconsistent, or a node inside one of
+/// its nested functions is, in which case that error is passed through untouched.
+enum Failure {
+    Reason(String),
+    Nested(Error),
+}
+
+impl From<Error> for Failure {
+    fn from(err: Error) -> Self {
+        Failure::Nested(err)
+    }
+}
+
+macro_rules! ensure {
+    ($cond:expr, $($arg:tt)*) => {
+        if !$cond {
+            return Err(Failure::Reason(format!($($arg)*)));
+        }
+    };
+}
+
+/// Infers the type of every node in a graph, checking each node against its operands.
+///
+/// Nodes are checked after their inputs, so the reported node is the earliest inconsistent one
+/// rather than one of its consumers. Dynamic dimensions, which have a length of `-1`, are
+/// compatible with any length.
+#[derive(Debug, Clone, Default)]
+pub struct ShapeChecker {
+    systems: HashMap<NoxprId, String>,
+    cache: HashMap<NoxprId, NoxprTy>,
+}
+
+impl ShapeChecker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attributes errors in every node in `systems` to the system it maps to.
+    pub fn systems(mut self, systems: impl IntoIterator<Item = (NoxprId, String)>) -> Self {
+        self.systems.extend(systems);
+        self
+    }
+
+    /// Checks every node reachable from `func`, returning the type of its result.
+    pub fn check(&mut self, func: &NoxprFn) -> Result<NoxprTy, Error> {
+        for arg in &func.args {
+            self.visit(arg)?;
+        }
+        self.visit(&func.inner)
+    }
+
+    pub fn visit(&mut self, expr: &Noxpr) -> Result<NoxprTy, Error> {
+        if let Some(ty) = self.cache.get(&expr.id()) {
+            return Ok(ty.clone());
+        }
+        let operands = expr
+            .inputs()
+            .iter()
+            .map(|input| self.visit(input))
+            .collect::<Result<Vec<_>, _>>()?;
+        let ty = match self.infer(expr) {
+            Ok(ty) => ty,
+            Err(Failure::Nested(err)) => return Err(err),
+            Err(Failure::Reason(reason)) => {
+                return Err(Error::Shape(Box::new(ShapeError {
+                    node: expr.name(),
+                    id: expr.id(),
+                    operands,
+                    reason,
+                    system: self.systems.get(&expr.id()).cloned(),
+                    backtrace: expr.backtrace.clone(),
+                })))
+            }
+        };
+        self.cache.insert(expr.id(), ty.clone());
+        Ok(ty)
+    }
+
+    fn array(&mut self, expr: &Noxpr) -> Result<ArrayTy, Failure> {
+        match self.visit(expr)? {
+            NoxprTy::ArrayTy(ty) => Ok(ty),
+            NoxprTy::Tuple(_) => Err(Failure::Reason(format!(
+                "expected an array operand, found a tuple from a {} node",
+                expr.name()
+            ))),
+        }
+    }
+
+    /// Returns the element type shared by `exprs`, which must all be arrays.
+    fn same_element_type(&mut self, exprs: &[&Noxpr]) -> Result<Vec<ArrayTy>, Failure> {
+        let tys = exprs
+            .iter()
+            .map(|expr| self.array(expr))
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(first) = tys.first() {
+            for ty in &tys[1..] {
+                ensure!(
+                    ty.element_type == first.element_type,
+                    "operands have different element types, {:?} and {:?}",
+                    first.element_type,
+                    ty.element_type
+                );
+            }
+        }
+        Ok(tys)
+    }
+
+    fn scalar_index(&mut self, expr: &Noxpr) -> Result<(), Failure> {
+        let ty = self.array(expr)?;
+        ensure!(
+            ty.shape.is_empty() && is_int(ty.element_type),
+            "indices must be integer scalars, found {}",
+            display_array(&ty)
+        );
+        Ok(())
+    }
+
+    /// Checks `func` against the types it's called with, returning the type of its result.
+    fn call(&mut self, name: &str, func: &NoxprFn, args: &[NoxprTy]) -> Result<NoxprTy, Failure> {
+        ensure!(
+            func.args.len() == args.len(),
+            "{} takes {} arguments, but is called with {}",
+            name,
+            func.args.len(),
+            args.len()
+        );
+        for (i, (param, arg)) in func.args.iter().zip(args).enumerate() {
+            let param = self.visit(param)?;
+            ensure!(
+                same_ty(&param, arg),
+                "argument {} of {} has type {}, but is called with {}",
+                i,
+                name,
+                display(&param),
+                display(arg)
+            );
+        }
+        Ok(self.visit(&func.inner)?)
+    }
+
+    fn infer(&mut self, expr: &Noxpr) -> Result<NoxprTy, Failure> {
+        let ty = match expr.deref() {
+            NoxprNode::Param(p) => p.ty.clone(),
+            NoxprNode::Tuple(elems) => NoxprTy::Tuple(
+                elems
+                    .iter()
+                    .map(|e| self.visit(e))
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            NoxprNode::GetTupleElement(g) => {
+                let ty = self.visit(&g.expr)?;
+                let NoxprTy::Tuple(elems) = ty else {
+                    return Err(Failure::Reason(format!(
+                        "expected a tuple operand, found {}",
+                        display(&ty)
+                    )));
+                };
+                ensure!(
+                    g.index < elems.len(),
+                    "element {} is out of bounds for a tuple of {}",
+                    g.index,
+                    elems.len()
+                );
+                elems[g.index].clone()
+            }
+            NoxprNode::Constant(c) => {
+                if c.ty.shape.iter().all(|d| *d >= 0) {
+                    let len = c.ty.shape.iter().product::<i64>() as usize;
+                    ensure!(
+                        c.data.element_count() == len,
+                        "constant has {} elements, but its type {} has {}",
+                        c.data.element_count(),
+                        display_array(&c.ty),
+                        len
+                    );
+                }
+                NoxprTy::ArrayTy(c.ty.clone())
+            }
+            NoxprNode::Iota(i) => {
+                ensure!(
+                    i.dim < i.shape.shape.len(),
+                    "iota dimension {} is out of bounds for {}",
+                    i.dim,
+                    display_array(&i.shape)
+                );
+                NoxprTy::ArrayTy(i.shape.clone())
+            }
+            NoxprNode::Add(b)
+            | NoxprNode::Sub(b)
+            | NoxprNode::Mul(b)
+            | NoxprNode::Div(b)
+            | NoxprNode::Max(b)
+            | NoxprNode::Min(b) => {
+                let tys = self.same_element_type(&[&b.lhs, &b.rhs])?;
+                ensure!(
+                    tys[0].element_type != ElementType::Pred,
+                    "arithmetic isn't defined for Pred"
+                );
+                NoxprTy::ArrayTy(elementwise(&tys)?)
+            }
+            NoxprNode::And(b) | NoxprNode::Or(b) | NoxprNode::Xor(b) => {
+                let tys = self.same_element_type(&[&b.lhs, &b.rhs])?;
+                ensure!(
+                    is_int(tys[0].element_type) || tys[0].element_type == ElementType::Pred,
+                    "bitwise operations require integer or Pred operands, found {:?}",
+                    tys[0].element_type
+                );
+                NoxprTy::ArrayTy(elementwise(&tys)?)
+            }
+            NoxprNode::ShiftLeft(b) | NoxprNode::ShiftRightLogical(b) => {
+                let tys = self.same_element_type(&[&b.lhs, &b.rhs])?;
+                ensure!(
+                    is_int(tys[0].element_type),
+                    "shifts require integer operands, found {:?}",
+                    tys[0].element_type
+                );
+                NoxprTy::ArrayTy(elementwise(&tys)?)
+            }
+            NoxprNode::GreaterOrEqual(b)
+            | NoxprNode::LessOrEqual(b)
+            | NoxprNode::Less(b)
+            | NoxprNode::Greater(b)
+            | NoxprNode::Equal(b)
+            | NoxprNode::NotEqual(b) => {
+                let tys = self.same_element_type(&[&b.lhs, &b.rhs])?;
+                let ty = elementwise(&tys)?;
+                NoxprTy::ArrayTy(ArrayTy::new(ElementType::Pred, ty.shape))
+            }
+            NoxprNode::Atan2(b) | NoxprNode::Pow(b) => {
+                let tys = self.same_element_type(&[&b.lhs, &b.rhs])?;
+                ensure!(
+                    is_float(tys[0].element_type),
+                    "{} requires float operands, found {:?}",
+                    expr.name(),
+                    tys[0].element_type
+                );
+                NoxprTy::ArrayTy(elementwise(&tys)?)
+            }
+            NoxprNode::Dot(b) => {
+                let tys = self.same_element_type(&[&b.lhs, &b.rhs])?;
+                let (lhs, rhs) = (&tys[0].shape, &tys[1].shape);
+                let shape: SmallVec<[i64; 4]> = match (lhs.len(), rhs.len()) {
+                    (1, 1) | (2, 1) | (1, 2) | (2, 2) => {
+                        let (l, r) = (lhs[lhs.len() - 1], rhs[0]);
+                        ensure!(
+                            dim_eq(l, r),
+                            "contracting dimensions have different lengths, {} and {}",
+                            l,
+                            r
+                        );
+                        lhs[..lhs.len() - 1]
+                            .iter()
+                            .chain(&rhs[1..])
+                            .copied()
+                            .collect()
+                    }
+                    (l, r) => {
+                        return Err(Failure::Reason(format!(
+                            "dot requires vectors or matrices, found ranks {} and {}",
+                            l, r
+                        )))
+                    }
+                };
+                NoxprTy::ArrayTy(ArrayTy::new(tys[0].element_type, shape))
+            }
+            NoxprNode::DotGeneral(d) => {
+                let tys = self.same_element_type(&[&d.lhs, &d.rhs])?;
+                let (lhs, rhs) = (&tys[0].shape, &tys[1].shape);
+                let dims = &d.dimensions;
+                let pairs = [
+                    (
+                        "batch",
+                        &dims.lhs_batch_dimensions,
+                        &dims.rhs_batch_dimensions,
+                    ),
+                    (
+                        "contracting",
+                        &dims.lhs_contracting_dimensions,
+                        &dims.rhs_contracting_dimensions,
+                    ),
+                ];
+                for (kind, l, r) in pairs {
+                    ensure!(
+                        l.len() == r.len(),
+                        "lhs has {} {} dimensions, but rhs has {}",
+                        l.len(),
+                        kind,
+                        r.len()
+                    );
+                    for (l, r) in l.iter().zip(r.iter()) {
+                        let (Some(l_len), Some(r_len)) = (dim(lhs, *l), dim(rhs, *r)) else {
+                            return Err(Failure::Reason(format!(
+                                "{} dimensions {} and {} are out of bounds",
+                                kind, l, r
+                            )));
+                        };
+                        ensure!(
+                            dim_eq(l_len, r_len),
+                            "{} dimensions {} and {} have different lengths, {} and {}",
+                            kind,
+                            l,
+                            r,
+                            l_len,
+                            r_len
+                        );
+                    }
+                }
+                let free = |shape: &[i64], batch: &[i64], contracting: &[i64]| {
+                    (0..shape.len() as i64)
+                        .filter(|d| !batch.contains(d) && !contracting.contains(d))
+                        .map(|d| shape[d as usize])
+                        .collect::<Vec<_>>()
+                };
+                let shape = dims
+                    .lhs_batch_dimensions
+                    .iter()
+                    .map(|d| lhs[*d as usize])
+                    .chain(free(
+                        lhs,
+                        &dims.lhs_batch_dimensions,
+                        &dims.lhs_contracting_dimensions,
+                    ))
+                    .chain(free(
+                        rhs,
+                        &dims.rhs_batch_dimensions,
+                        &dims.rhs_contracting_dimensions,
+                    ))
+                    .collect();
+                NoxprTy::ArrayTy(ArrayTy::new(tys[0].element_type, shape))
+            }
+            NoxprNode::Sqrt(e)
+            | NoxprNode::Log(e)
+            | NoxprNode::Sin(e)
+            | NoxprNode::Cos(e)
+            | NoxprNode::Exp(e)
+            | NoxprNode::Tanh(e)
+            | NoxprNode::Floor(e)
+            | NoxprNode::Ceil(e)
+            | NoxprNode::Rsqrt(e) => {
+                let ty = self.array(e)?;
+                ensure!(
+                    is_float(ty.element_type),
+                    "{} requires a float operand, found {:?}",
+                    expr.name(),
+                    ty.element_type
+                );
+                NoxprTy::ArrayTy(ty)
+            }
+            NoxprNode::Neg(e) | NoxprNode::Abs(e) => {
+                let ty = self.array(e)?;
+                ensure!(
+                    ty.element_type != ElementType::Pred,
+                    "{} isn't defined for Pred",
+                    expr.name()
+                );
+                NoxprTy::ArrayTy(ty)
+            }
+            NoxprNode::Convert(c) => {
+                let ty = self.array(&c.expr)?;
+                NoxprTy::ArrayTy(ArrayTy::new(c.element_type, ty.shape))
+            }
+            NoxprNode::Select(s) => {
+                let cond = self.array(&s.cond)?;
+                ensure!(
+                    cond.element_type == ElementType::Pred,
+                    "select condition must be Pred, found {:?}",
+                    cond.element_type
+                );
+                let tys = self.same_element_type(&[&s.on_true, &s.on_false])?;
+                let ty = elementwise(&tys)?;
+                NoxprTy::ArrayTy(elementwise(&[cond, ty.clone()]).map(|cond| ArrayTy {
+                    element_type: ty.element_type,
+                    shape: cond.shape,
+                })?)
+            }
+            NoxprNode::Clamp(c) => {
+                let tys = self.same_element_type(&[&c.min, &c.expr, &c.max])?;
+                NoxprTy::ArrayTy(elementwise(&tys)?)
+            }
+            NoxprNode::Concat(concat) => {
+                let nodes = concat.nodes.iter().collect::<Vec<_>>();
+                let tys = self.same_element_type(&nodes)?;
+                let Some(first) = tys.first() else {
+                    return Err(Failure::Reason("concat requires an operand".to_string()));
+                };
+                let rank = first.shape.len();
+                let axis = concat.dimension;
+                ensure!(
+                    axis < rank,
+                    "dimension {} is out of bounds for rank {}",
+                    axis,
+                    rank
+                );
+                let mut shape = first.shape.clone();
+                for ty in &tys[1..] {
+                    ensure!(
+                        ty.shape.len() == rank
+                            && (0..rank).all(|d| d == axis || dim_eq(ty.shape[d], shape[d])),
+                        "{} and {} can't be concatenated along dimension {}",
+                        display_array(first),
+                        display_array(ty),
+                        axis
+                    );
+                    shape[axis] = match (shape[axis], ty.shape[axis]) {
+                        (-1, _) | (_, -1) => -1,
+                        (l, r) => l + r,
+                    };
+                }
+                NoxprTy::ArrayTy(ArrayTy::new(first.element_type, shape))
+            }
+            NoxprNode::Reshape(r) => {
+                let ty = self.array(&r.expr)?;
+                let len = |shape: &[i64]| {
+                    shape
+                        .iter()
+                        .try_fold(1i64, |len, d| (*d >= 0).then_some(len * d))
+                };
+                if let (Some(from), Some(to)) = (len(&ty.shape), len(&r.new_sizes)) {
+                    ensure!(
+                        from == to,
+                        "can't reshape {} elements into {:?}",
+                        from,
+                        r.new_sizes
+                    );
+                }
+                NoxprTy::ArrayTy(ArrayTy::new(ty.element_type, r.new_sizes.clone()))
+            }
+            NoxprNode::Broadcast(b) => {
+                let ty = self.array(&b.expr)?;
+                let mut shape = b.sizes.clone();
+                shape.extend_from_slice(&ty.shape);
+                NoxprTy::ArrayTy(ArrayTy::new(ty.element_type, shape))
+            }
+            NoxprNode::BroadcastInDim(b) => {
+                let ty = self.array(&b.expr)?;
+                ensure!(
+                    b.broadcast_dims.len() == ty.shape.len(),
+                    "{} broadcast dimensions were given for an operand of rank {}",
+                    b.broadcast_dims.len(),
+                    ty.shape.len()
+                );
+                for (len, d) in ty.shape.iter().zip(&b.broadcast_dims) {
+                    let Some(out) = dim(&b.sizes, *d) else {
+                        return Err(Failure::Reason(format!(
+                            "broadcast dimension {} is out of bounds for {:?}",
+                            d, b.sizes
+                        )));
+                    };
+                    ensure!(
+                        *len == 1 || dim_eq(*len, out),
+                        "can't broadcast {} to {:?} along dimension {}",
+                        display_array(&ty),
+                        b.sizes,
+                        d
+                    );
+                }
+                NoxprTy::ArrayTy(ArrayTy::new(ty.element_type, b.sizes.clone()))
+            }
+            NoxprNode::Transpose(t) => {
+                let ty = self.array(&t.expr)?;
+                let mut sorted = t.permutation.clone();
+                sorted.sort_unstable();
+                ensure!(
+                    sorted.iter().copied().eq(0..ty.shape.len() as i64),
+                    "{:?} isn't a permutation of the dimensions of {}",
+                    t.permutation,
+                    display_array(&ty)
+                );
+                let shape = t
+                    .permutation
+                    .iter()
+                    .map(|d| ty.shape[*d as usize])
+                    .collect();
+                NoxprTy::ArrayTy(ArrayTy::new(ty.element_type, shape))
+            }
+            NoxprNode::Slice(s) => {
+                let ty = self.array(&s.expr)?;
+                let rank = ty.shape.len();
+                ensure!(
+                    s.start_indices.len() == rank
+                        && s.stop_indices.len() == rank
+                        && s.strides.len() == rank,
+                    "slice bounds must have one entry per dimension of {}",
+                    display_array(&ty)
+                );
+                let mut shape = SmallVec::new();
+                for d in 0..rank {
+                    let (start, stop, stride) =
+                        (s.start_indices[d], s.stop_indices[d], s.strides[d]);
+                    let len = ty.shape[d];
+                    ensure!(
+                        0 <= start && start <= stop && (len == -1 || stop <= len) && stride > 0,
+                        "slice {}..{} with stride {} is out of bounds for dimension {} of {}",
+                        start,
+                        stop,
+                        stride,
+                        d,
+                        display_array(&ty)
+                    );
+                    shape.push((stop - start + stride - 1) / stride);
+                }
+                NoxprTy::ArrayTy(ArrayTy::new(ty.element_type, shape))
+            }
+            NoxprNode::DynamicSlice(d) => {
+                let ty = self.array(&d.expr)?;
+                let rank = ty.shape.len();
+                ensure!(
+                    d.start_indices.len() == rank && d.size_indices.len() == rank,
+                    "dynamic slice must have one start and size per dimension of {}",
+                    display_array(&ty)
+                );
+                for index in &d.start_indices {
+                    self.scalar_index(index)?;
+                }
+                for (len, size) in ty.shape.iter().zip(&d.size_indices) {
+                    ensure!(
+                        *len == -1 || (0..=*len).contains(size),
+                        "slice sizes {:?} don't fit in {}",
+                        d.size_indices,
+                        display_array(&ty)
+                    );
+                }
+                NoxprTy::ArrayTy(ArrayTy::new(ty.element_type, d.size_indices.clone()))
+            }
+            NoxprNode::DynamicUpdateSlice(d) => {
+                let tys = self.same_element_type(&[&d.expr, &d.update])?;
+                let (ty, update) = (&tys[0], &tys[1]);
+                ensure!(
+                    update.shape.len() == ty.shape.len()
+                        && d.start_indicies.len() == ty.shape.len(),
+                    "update {} and {} starts don't match the rank of {}",
+                    display_array(update),
+                    d.start_indicies.len(),
+                    display_array(ty)
+                );
+                for index in &d.start_indicies {
+                    self.scalar_index(index)?;
+                }
+                ensure!(
+                    ty.shape
+                        .iter()
+                        .zip(&update.shape)
+                        .all(|(len, u)| *len == -1 || *u == -1 || u <= len),
+                    "update {} doesn't fit in {}",
+                    display_array(update),
+                    display_array(ty)
+                );
+                NoxprTy::ArrayTy(ty.clone())
+            }
+            NoxprNode::Gather(g) => {
+                let ty = self.array(&g.expr)?;
+                let indices = self.array(&g.indices)?;
+                ensure!(
+                    is_int(indices.element_type),
+                    "gather indices must be integers, found {:?}",
+                    indices.element_type
+                );
+                let (batch, index_len) = index_vectors(&indices, g.index_vector_dim)?;
+                let rank = ty.shape.len() as i64;
+                ensure!(
+                    g.start_index_map.len() as i64 == index_len || index_len == -1,
+                    "index vectors have length {}, but start index map has {} entries",
+                    index_len,
+                    g.start_index_map.len()
+                );
+                ensure!(
+                    g.start_index_map.iter().all(|d| (0..rank).contains(d))
+                        && g.collapsed_slice_dims.iter().all(|d| (0..rank).contains(d)),
+                    "gather dimensions are out of bounds for {}",
+                    display_array(&ty)
+                );
+                ensure!(
+                    g.slice_sizes.len() as i64 == rank
+                        && ty
+                            .shape
+                            .iter()
+                            .zip(&g.slice_sizes)
+                            .all(|(len, size)| *len == -1 || (0..=*len).contains(size)),
+                    "slice sizes {:?} don't fit in {}",
+                    g.slice_sizes,
+                    display_array(&ty)
+                );
+                ensure!(
+                    g.collapsed_slice_dims
+                        .iter()
+                        .all(|d| g.slice_sizes[*d as usize] == 1),
+                    "collapsed dimensions {:?} must have a slice size of 1",
+                    g.collapsed_slice_dims
+                );
+                let window = (0..rank)
+                    .filter(|d| !g.collapsed_slice_dims.contains(d))
+                    .map(|d| g.slice_sizes[d as usize])
+                    .collect::<Vec<_>>();
+                ensure!(
+                    g.offset_dims.len() == window.len(),
+                    "{} offset dimensions were given for {} window dimensions",
+                    g.offset_dims.len(),
+                    window.len()
+                );
+                let (mut batch, mut window) = (batch.into_iter(), window.into_iter());
+                let shape = (0..(g.offset_dims.len() + batch.len()) as i64)
+                    .map(|d| {
+                        if g.offset_dims.contains(&d) {
+                            window.next()
+                        } else {
+                            batch.next()
+                        }
+                    })
+                    .collect::<Option<SmallVec<_>>>();
+                let Some(shape) = shape else {
+                    return Err(Failure::Reason(format!(
+                        "offset dimensions {:?} are out of bounds",
+                        g.offset_dims
+                    )));
+                };
+                NoxprTy::ArrayTy(ArrayTy::new(ty.element_type, shape))
+            }
+            NoxprNode::Scatter(s) => {
+                let tys = self.same_element_type(&[&s.expr, &s.updates])?;
+                let (ty, updates) = (&tys[0], &tys[1]);
+                let indices = self.array(&s.indices)?;
+                ensure!(
+                    is_int(indices.element_type),
+                    "scatter indices must be integers, found {:?}",
+                    indices.element_type
+                );
+                let (batch, index_len) = index_vectors(&indices, s.index_vector_dim)?;
+                let rank = ty.shape.len();
+                ensure!(
+                    s.scatter_dims_to_operand_dims.len() as i64 == index_len || index_len == -1,
+                    "index vectors have length {}, but {} scatter dimensions were given",
+                    index_len,
+                    s.scatter_dims_to_operand_dims.len()
+                );
+                ensure!(
+                    s.update_window_dims.len() + s.inserted_window_dims.len() == rank,
+                    "window dimensions don't cover the rank of {}",
+                    display_array(ty)
+                );
+                ensure!(
+                    updates.shape.len() == s.update_window_dims.len() + batch.len(),
+                    "updates {} don't match {} window and {} scatter dimensions",
+                    display_array(updates),
+                    s.update_window_dims.len(),
+                    batch.len()
+                );
+                NoxprTy::ArrayTy(ty.clone())
+            }
+            NoxprNode::Reduce(r) => {
+                let ty = self.array(&r.expr)?;
+                let rank = ty.shape.len() as i64;
+                ensure!(
+                    r.dims.iter().all(|d| (0..rank).contains(d)),
+                    "reduced dimensions {:?} are out of bounds for {}",
+                    r.dims,
+                    display_array(&ty)
+                );
+                let shape = (0..rank)
+                    .filter(|d| !r.dims.contains(d))
+                    .map(|d| ty.shape[d as usize])
+                    .collect();
+                NoxprTy::ArrayTy(ArrayTy::new(ty.element_type, shape))
+            }
+            NoxprNode::Cholesky(e) => {
+                let ty = self.array(e)?;
+                square(&ty)?;
+                NoxprTy::ArrayTy(ty)
+            }
+            NoxprNode::TriangularSolve(t) => {
+                let tys = self.same_element_type(&[&t.a, &t.b])?;
+                let (a, b) = (&tys[0], &tys[1]);
+                let n = square(a)?;
+                let rank = a.shape.len();
+                ensure!(
+                    b.shape.len() == rank && (0..rank - 2).all(|d| dim_eq(a.shape[d], b.shape[d])),
+                    "{} doesn't match the batch dimensions of {}",
+                    display_array(b),
+                    display_array(a)
+                );
+                let len = if t.left_side {
+                    b.shape[rank - 2]
+                } else {
+                    b.shape[rank - 1]
+                };
+                ensure!(
+                    dim_eq(len, n),
+                    "{} doesn't match the system {}",
+                    display_array(b),
+                    display_array(a)
+                );
+                NoxprTy::ArrayTy(b.clone())
+            }
+            NoxprNode::Qr(e) | NoxprNode::Svd(e) => {
+                let ty = self.array(e)?;
+                ensure!(
+                    ty.shape.len() >= 2,
+                    "{} requires a matrix, found {}",
+                    expr.name(),
+                    display_array(&ty)
+                );
+                expr.ty().ok_or(Error::UnknownShape)?
+            }
+            NoxprNode::Eigh(e) => {
+                square(&self.array(e)?)?;
+                expr.ty().ok_or(Error::UnknownShape)?
+            }
+            NoxprNode::Scan(s) => {
+                let mut args = vec![self.visit(&s.initial_state)?];
+                let mut len = None;
+                for input in &s.inputs {
+                    let ty = self.array(input)?;
+                    let Some((first, rest)) = ty.shape.split_first() else {
+                        return Err(Failure::Reason(
+                            "scan inputs must have at least one dimension".to_string(),
+                        ));
+                    };
+                    ensure!(
+                        len.map_or(true, |len| dim_eq(len, *first)),
+                        "scan inputs have different lengths, {:?} and {}",
+                        len,
+                        first
+                    );
+                    len = Some(*first);
+                    args.push(NoxprTy::ArrayTy(ArrayTy::new(
+                        ty.element_type,
+                        SmallVec::from_slice(rest),
+                    )));
+                }
+                ensure!(len.is_some(), "scan requires at least one input");
+                let out = self.call("the scan body", &s.scan_fn, &args)?;
+                ensure!(
+                    same_ty(&out, &args[0]),
+                    "the scan body returns {}, but the carry is {}",
+                    display(&out),
+                    display(&args[0])
+                );
+                out
+            }
+            NoxprNode::Cond(c) => {
+                let pred = self.array(&c.pred)?;
+                ensure!(
+                    pred.element_type == ElementType::Pred && pred.shape.is_empty(),
+                    "cond predicate must be a Pred scalar, found {}",
+                    display_array(&pred)
+                );
+                let args = c
+                    .operands
+                    .iter()
+                    .map(|o| self.visit(o))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let on_true = self.call("the true branch", &c.on_true, &args)?;
+                let on_false = self.call("the false branch", &c.on_false, &args)?;
+                ensure!(
+                    same_ty(&on_true, &on_false),
+                    "the branches return different types, {} and {}",
+                    display(&on_true),
+                    display(&on_false)
+                );
+                on_true
+            }
+            NoxprNode::While(w) => {
+                let state = self.visit(&w.initial_state)?;
+                let args = [state.clone()];
+                let cond = self.call("the loop condition", &w.cond_fn, &args)?;
+                ensure!(
+                    matches!(&cond, NoxprTy::ArrayTy(ty) if ty.element_type == ElementType::Pred && ty.shape.is_empty()),
+                    "the loop condition must return a Pred scalar, found {}",
+                    display(&cond)
+                );
+                let out = self.call("the loop body", &w.body_fn, &args)?;
+                ensure!(
+                    same_ty(&out, &state),
+                    "the loop body returns {}, but the state is {}",
+                    display(&out),
+                    display(&state)
+                );
+                state
+            }
+            #[cfg(feature = "jax")]
+            NoxprNode::Jax(_) => expr.ty().ok_or(Error::UnknownShape)?,
+        };
+        Ok(ty)
+    }
+}
+
+impl NoxprFn {
+    /// Checks the shapes and element types of every node in the function.
+    pub fn check(&self) -> Result<NoxprTy, Error> {
+        ShapeChecker::new().check(self)
+    }
+}
+
+fn is_int(ty: ElementType) -> bool {
+    matches!(
+        ty,
+        ElementType::S8
+            | ElementType::S16
+            | ElementType::S32
+            | ElementType::S64
+            | ElementType::U8
+            | ElementType::U16
+            | ElementType::U32
+            | ElementType::U64
+    )
+}
+
+fn is_float(ty: ElementType) -> bool {
+    matches!(
+        ty,
+        ElementType::F16 | ElementType::Bf16 | ElementType::F32 | ElementType::F64
+    )
+}
+
+fn dim_eq(lhs: i64, rhs: i64) -> bool {
+    lhs == rhs || lhs == -1 || rhs == -1
+}
+
+fn dim(shape: &[i64], d: i64) -> Option<i64> {
+    usize::try_from(d).ok().and_then(|d| shape.get(d)).copied()
+}
+
+fn same_ty(lhs: &NoxprTy, rhs: &NoxprTy) -> bool {
+    match (lhs, rhs) {
+        (NoxprTy::ArrayTy(l), NoxprTy::ArrayTy(r)) => {
+            l.element_type == r.element_type
+                && l.shape.len() == r.shape.len()
+                && l.shape.iter().zip(&r.shape).all(|(l, r)| dim_eq(*l, *r))
+        }
+        (NoxprTy::Tuple(l), NoxprTy::Tuple(r)) => {
+            l.len() == r.len() && l.iter().zip(r).all(|(l, r)| same_ty(l, r))
+        }
+        _ => false,
+    }
+}
+
+/// Broadcasts the shapes of `tys` together, which must share an element type.
+fn elementwise(tys: &[ArrayTy]) -> Result<ArrayTy, Failure> {
+    let mut shape = tys[0].shape.clone();
+    for ty in &tys[1..] {
+        let Some(broadcast) = crate::noxpr::broadcast_dims(&shape, &ty.shape) else {
+            return Err(Failure::Reason(format!(
+                "shapes {:?} and {:?} can't be broadcast together",
+                shape, ty.shape
+            )));
+        };
+        shape = broadcast;
+    }
+    Ok(ArrayTy::new(tys[0].element_type, shape))
+}
+
+/// Returns the batch shape and index vector length of gather or scatter indices.
+fn index_vectors(indices: &ArrayTy, index_vector_dim: i64) -> Result<(Vec<i64>, i64), Failure> {
+    let rank = indices.shape.len() as i64;
+    ensure!(
+        (0..=rank).contains(&index_vector_dim),
+        "index vector dimension {} is out of bounds for indices {}",
+        index_vector_dim,
+        display_array(indices)
+    );
+    let len = dim(&indices.shape, index_vector_dim).unwrap_or(1);
+    let batch = (0..rank)
+        .filter(|d| *d != index_vector_dim)
+        .map(|d| indices.shape[d as usize])
+        .collect();
+    Ok((batch, len))
+}
+
+/// Checks that `ty` is a batch of square float matrices, returning their size.
+fn square(ty: &ArrayTy) -> Result<i64, Failure> {
+    let rank = ty.shape.len();
+    ensure!(
+        rank >= 2 && dim_eq(ty.shape[rank - 2], ty.shape[rank - 1]),
+        "expected a square matrix, found {}",
+        display_array(ty)
+    );
+    ensure!(
+        is_float(ty.element_type),
+        "expected a float matrix, found {:?}",
+        ty.element_type
+    );
+    Ok(ty.shape[rank - 1])
+}
+
+fn display(ty: &NoxprTy) -> String {
+    let mut out = String::new();
+    ty.pretty_print(&mut out)
+        .expect("writing to a string can't fail");
+    out
+}
+
+fn display_array(ty: &ArrayTy) -> String {
+    display(&NoxprTy::ArrayTy(ty.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use smallvec::smallvec;
+
+    use super::*;
+
+    fn param(number: i64, shape: SmallVec<[i64; 4]>) -> Noxpr {
+        let ty = NoxprTy::ArrayTy(ArrayTy::new(ElementType::F64, shape));
+        Noxpr::parameter(number, ty, format!("p{}", number))
+    }
+
+    #[test]
+    fn test_check_valid() {
+        let a = param(0, smallvec![2, 3]);
+        let b = param(1, smallvec![3]);
+        let out = (a.clone().dot(&b) + param(2, smallvec![1])).sqrt();
+        let ty = NoxprFn::new(vec![a, b], out).check().unwrap();
+        assert!(same_ty(
+            &ty,
+            &NoxprTy::ArrayTy(ArrayTy::new(ElementType::F64, smallvec![2]))
+        ));
+    }
+
+    #[test]
+    fn test_check_reports_node() {
+        let a = param(0, smallvec![3]);
+        let b = param(1, smallvec![4]);
+        let sum = a.clone() + b.clone();
+        let out = sum.clone().sqrt();
+        let func = NoxprFn::new(vec![a, b], out);
+        let err = ShapeChecker::new()
+            .systems([(sum.id(), "physics".to_string())])
+            .check(&func)
+            .unwrap_err();
+        let Error::Shape(err) = err else {
+            panic!("expected a shape error, found {:?}", err);
+        };
+        assert_eq!(err.node, "Add");
+        assert_eq!(err.id, sum.id());
+        assert_eq!(err.system.as_deref(), Some("physics"));
+        assert_eq!(err.operands.len(), 2);
+        assert!(err.to_string().starts_with("invalid Add node NoxprId("));
+        assert!(err.to_string().contains("in system physics"));
+        assert!(err.to_string().contains("(operands: F64[3], F64[4])"));
+    }
+
+    #[test]
+    fn test_check_scan_body() {
+        let xs = param(0, smallvec![5, 3]);
+        let carry = param(1, smallvec![3]);
+        let x = param(2, smallvec![2]);
+        let body = NoxprFn::new(vec![carry.clone(), x.clone()], carry + x);
+        let out = Noxpr::scan(vec![xs.clone()], param(3, smallvec![3]), body);
+        let err = NoxprFn::new(vec![xs], out).check().unwrap_err();
+        let Error::Shape(err) = err else {
+            panic!("expected a shape error, found {:?}", err);
+        };
+        assert_eq!(err.node, "Scan");
+        assert!(err.reason.contains("argument 1 of the scan body"));
+    }
+}
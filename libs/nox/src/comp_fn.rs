@@ -33,7 +33,7 @@ pub trait CompFn<T, R>: Send + Sync {
     where
         R: IntoOp,
     {
-        let expr = self.build_expr()?;
+        let (expr, _) = self.build_expr()?.optimize();
         let op = expr.build(any::type_name::<Self>())?;
         let comp = op.build()?;
         Ok(Comp {
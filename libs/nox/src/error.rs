@@ -26,4 +26,44 @@ pub enum Error {
     ScanMissingArg,
     #[error("all scan arguments must have the same first dim")]
     ScanShapeMismatch,
+    #[error("unable to infer the shape of an expression")]
+    UnknownShape,
+    #[error("grad requires a function with a scalar output")]
+    GradNonScalarOutput,
+    #[error("reverse-mode differentiation is not supported for {0}")]
+    UnsupportedGrad(&'static str),
+    #[error("forward-mode differentiation is not supported for {0}")]
+    UnsupportedJvp(&'static str),
+    #[error("expected a square matrix")]
+    NonSquareMatrix,
+    #[error("solve requires a vector or matrix with as many rows as the system")]
+    SolveShapeMismatch,
+    #[error("{0} nodes can't be serialized")]
+    UnserializableNode(&'static str),
+    #[error("serialized noxpr refers to a node that doesn't exist")]
+    MalformedNoxprArchive,
+    #[error("postcard error {0}")]
+    Postcard(#[from] postcard::Error),
+    #[error("json error {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid euler angle sequence {0}")]
+    InvalidEulerSeq(String),
+    #[error("slice step can't be zero")]
+    ZeroSliceStep,
+    #[error("index mask must match the length of the indexed axis")]
+    IndexShapeMismatch,
+    #[error("host evaluation is not supported for {0}")]
+    UnsupportedEval(&'static str),
+    #[error("parameter {0} has no bound value")]
+    UnboundParam(String),
+    #[error("expected an array value, found a tuple")]
+    UnexpectedTuple,
+    #[error("operand shapes are incompatible")]
+    ShapeMismatch,
+    #[error("function expects {0} arguments")]
+    WrongArgCount(usize),
+    #[error("{0}")]
+    Shape(Box<crate::ShapeError>),
+    #[error("sparse indices must be in bounds and match the number of values")]
+    InvalidSparseIndices,
 }
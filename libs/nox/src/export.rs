@@ -0,0 +1,298 @@
+//! Provides exporters that render [`Noxpr`] graphs as Graphviz DOT or Mermaid diagrams.
+use crate::{Noxpr, NoxprFn, NoxprId, NoxprNode};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::ops::Deref;
+
+/// Renders a graph as a diagram, with a node for every distinct expression and an edge from every
+/// input to its consumer.
+///
+/// Every node is labelled with its kind and type. Nodes can be grouped into named clusters, which
+/// is used to show the system each node came from. The bodies of scans, conds and whiles are
+/// drawn as a single node.
+#[derive(Debug, Clone, Default)]
+pub struct GraphExporter {
+    clusters: HashMap<NoxprId, String>,
+}
+
+impl GraphExporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Places the node `id` in the cluster called `name`.
+    pub fn cluster(mut self, id: NoxprId, name: impl Into<String>) -> Self {
+        self.clusters.insert(id, name.into());
+        self
+    }
+
+    /// Places every node in `clusters` in the cluster it maps to.
+    pub fn clusters(mut self, clusters: impl IntoIterator<Item = (NoxprId, String)>) -> Self {
+        self.clusters.extend(clusters);
+        self
+    }
+
+    /// Renders `func` as a Graphviz DOT digraph.
+    pub fn dot(&self, func: &NoxprFn) -> String {
+        let mut out = String::new();
+        self.write_dot(func, &mut out)
+            .expect("writing to a string can't fail");
+        out
+    }
+
+    /// Renders `func` as a Mermaid flowchart.
+    pub fn mermaid(&self, func: &NoxprFn) -> String {
+        let mut out = String::new();
+        self.write_mermaid(func, &mut out)
+            .expect("writing to a string can't fail");
+        out
+    }
+
+    pub fn write_dot(&self, func: &NoxprFn, writer: &mut impl Write) -> std::fmt::Result {
+        let graph = Graph::new(func);
+        writeln!(writer, "digraph noxpr {{")?;
+        writeln!(writer, "  node [shape=box, fontname=\"monospace\"];")?;
+        for (cluster, nodes) in self.group(&graph) {
+            let indent = match cluster {
+                Some((i, name)) => {
+                    writeln!(writer, "  subgraph cluster_{} {{", i)?;
+                    writeln!(writer, "    label=\"{}\";", escape_dot(name))?;
+                    "    "
+                }
+                None => "  ",
+            };
+            for i in nodes {
+                let expr = &graph.nodes[i];
+                let style = match expr.deref() {
+                    NoxprNode::Param(_) => ", shape=ellipse",
+                    NoxprNode::Constant(_) | NoxprNode::Iota(_) => ", style=dashed",
+                    _ => "",
+                };
+                let output = if expr.id() == func.inner.id() {
+                    ", peripheries=2"
+                } else {
+                    ""
+                };
+                let label = label(expr)?
+                    .iter()
+                    .map(|l| escape_dot(l))
+                    .collect::<Vec<_>>();
+                writeln!(
+                    writer,
+                    "{}n{} [label=\"{}\"{}{}];",
+                    indent,
+                    i,
+                    label.join("\\n"),
+                    style,
+                    output
+                )?;
+            }
+            if cluster.is_some() {
+                writeln!(writer, "  }}")?;
+            }
+        }
+        for (from, to, operand) in graph.edges() {
+            match operand {
+                Some(operand) => {
+                    writeln!(writer, "  n{} -> n{} [label=\"{}\"];", from, to, operand)?
+                }
+                None => writeln!(writer, "  n{} -> n{};", from, to)?,
+            }
+        }
+        writeln!(writer, "}}")
+    }
+
+    pub fn write_mermaid(&self, func: &NoxprFn, writer: &mut impl Write) -> std::fmt::Result {
+        let graph = Graph::new(func);
+        writeln!(writer, "flowchart TD")?;
+        for (cluster, nodes) in self.group(&graph) {
+            let indent = match cluster {
+                Some((i, name)) => {
+                    writeln!(writer, "  subgraph c{}[\"{}\"]", i, escape_mermaid(name))?;
+                    "    "
+                }
+                None => "  ",
+            };
+            for i in nodes {
+                let expr = &graph.nodes[i];
+                let label = label(expr)?
+                    .iter()
+                    .map(|l| escape_mermaid(l))
+                    .collect::<Vec<_>>()
+                    .join("<br/>");
+                let (open, close) = match expr.deref() {
+                    NoxprNode::Param(_) => ("([", "])"),
+                    _ if expr.id() == func.inner.id() => ("[[", "]]"),
+                    _ => ("[", "]"),
+                };
+                writeln!(writer, "{}n{}{}\"{}\"{}", indent, i, open, label, close)?;
+            }
+            if cluster.is_some() {
+                writeln!(writer, "  end")?;
+            }
+        }
+        for (from, to, operand) in graph.edges() {
+            match operand {
+                Some(operand) => writeln!(writer, "  n{} -->|{}| n{}", from, operand, to)?,
+                None => writeln!(writer, "  n{} --> n{}", from, to)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Groups the nodes of `graph` by cluster, in the order each cluster first appears, with the
+    /// nodes outside of any cluster first.
+    #[allow(clippy::type_complexity)]
+    fn group<'a>(&'a self, graph: &Graph) -> Vec<(Option<(usize, &'a str)>, Vec<usize>)> {
+        let mut groups: Vec<(Option<(usize, &str)>, Vec<usize>)> = vec![(None, vec![])];
+        let mut cluster_index: HashMap<&str, usize> = HashMap::new();
+        for (i, expr) in graph.nodes.iter().enumerate() {
+            let group = match self.clusters.get(&expr.id()) {
+                Some(name) => *cluster_index.entry(name.as_str()).or_insert_with(|| {
+                    groups.push((Some((groups.len() - 1, name.as_str())), vec![]));
+                    groups.len() - 1
+                }),
+                None => 0,
+            };
+            groups[group].1.push(i);
+        }
+        groups
+    }
+}
+
+/// The distinct nodes of a function in topological order.
+struct Graph {
+    nodes: Vec<Noxpr>,
+    index: HashMap<NoxprId, usize>,
+}
+
+impl Graph {
+    fn new(func: &NoxprFn) -> Self {
+        let mut graph = Graph {
+            nodes: vec![],
+            index: HashMap::new(),
+        };
+        // walks the graph with an explicit stack, since deep pipelines can overflow a recursive one
+        let mut stack = std::iter::once(&func.inner)
+            .chain(func.args.iter().rev())
+            .map(|expr| (expr.clone(), false))
+            .collect::<Vec<_>>();
+        while let Some((expr, visited)) = stack.pop() {
+            if graph.index.contains_key(&expr.id()) {
+                continue;
+            }
+            if visited {
+                graph.index.insert(expr.id(), graph.nodes.len());
+                graph.nodes.push(expr);
+                continue;
+            }
+            let inputs = expr.inputs();
+            stack.push((expr, true));
+            stack.extend(inputs.into_iter().rev().map(|input| (input, false)));
+        }
+        graph
+    }
+
+    /// Returns every edge as the index of the input, the index of its consumer and the position of
+    /// the input among the consumer's operands, if it has more than one.
+    fn edges(&self) -> Vec<(usize, usize, Option<usize>)> {
+        let mut edges = vec![];
+        for (to, expr) in self.nodes.iter().enumerate() {
+            let inputs = expr.inputs();
+            let numbered = inputs.len() > 1;
+            for (operand, input) in inputs.iter().enumerate() {
+                let from = self.index[&input.id()];
+                edges.push((from, to, numbered.then_some(operand)));
+            }
+        }
+        edges
+    }
+}
+
+fn label(expr: &Noxpr) -> Result<Vec<String>, std::fmt::Error> {
+    let mut title = expr.name().to_string();
+    match expr.deref() {
+        NoxprNode::Param(p) => write!(title, " {}", p.name)?,
+        NoxprNode::GetTupleElement(g) => write!(title, " {}", g.index)?,
+        NoxprNode::Reduce(r) => write!(title, " {}", r.op.name())?,
+        _ => {}
+    }
+    let mut lines = vec![title];
+    if let Some(ty) = expr.ty() {
+        let mut line = String::new();
+        ty.pretty_print(&mut line)?;
+        lines.push(line);
+    }
+    Ok(lines)
+}
+
+fn escape_dot(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_mermaid(s: &str) -> String {
+    s.replace('"', "#quot;")
+}
+
+impl NoxprFn {
+    /// Renders this function as a Graphviz DOT digraph.
+    pub fn to_dot(&self) -> String {
+        GraphExporter::new().dot(self)
+    }
+
+    /// Renders this function as a Mermaid flowchart.
+    pub fn to_mermaid(&self) -> String {
+        GraphExporter::new().mermaid(self)
+    }
+}
+
+impl Noxpr {
+    /// Renders this expression and everything it depends on as a Graphviz DOT digraph.
+    pub fn to_dot(&self) -> String {
+        NoxprFn::new(vec![], self.clone()).to_dot()
+    }
+
+    /// Renders this expression and everything it depends on as a Mermaid flowchart.
+    pub fn to_mermaid(&self) -> String {
+        NoxprFn::new(vec![], self.clone()).to_mermaid()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ArrayTy, NoxprScalarExt, NoxprTy};
+    use smallvec::smallvec;
+    use xla::ElementType;
+
+    fn func() -> NoxprFn {
+        let ty = NoxprTy::ArrayTy(ArrayTy::new(ElementType::F64, smallvec![3]));
+        let x = Noxpr::parameter(0, ty, "x".to_string());
+        let y = x.clone() - 2.0f64.constant();
+        NoxprFn::new(vec![x.clone()], y.clone() * y.sqrt())
+    }
+
+    #[test]
+    fn test_dot() {
+        let func = func();
+        let dot = GraphExporter::new()
+            .cluster(func.inner.id(), "tick")
+            .dot(&func);
+        assert!(dot.starts_with("digraph noxpr {"));
+        assert!(dot.contains("n0 [label=\"Param x\\nF64[3]\", shape=ellipse];"));
+        assert!(dot.contains("subgraph cluster_0 {"));
+        assert!(dot.contains("label=\"tick\";"));
+        assert!(dot.contains("n0 -> n2 [label=\"0\"];"));
+        assert!(dot.contains("n2 -> n3;"));
+        assert_eq!(dot.matches(" -> ").count(), 5);
+    }
+
+    #[test]
+    fn test_mermaid() {
+        let mermaid = func().to_mermaid();
+        assert!(mermaid.starts_with("flowchart TD\n"));
+        assert!(mermaid.contains("n0([\"Param x<br/>F64[3]\"])"));
+        assert!(mermaid.contains("n4[[\"Mul<br/>F64[3]\"]]"));
+        assert!(mermaid.contains("n2 -->|0| n4"));
+    }
+}
@@ -1,3 +1,4 @@
+use half::{bf16, f16};
 use std::ops::{Add, Div, Mul, Sub};
 
 use crate::{Scalar, TensorItem};
@@ -30,10 +31,19 @@ pub trait Field:
     fn two_prim() -> Self
     where
         Self: Sized;
+
+    /// The smallest value of this type, which is the identity of a max reduction.
+    fn lowest_prim() -> Self
+    where
+        Self: Sized;
+    /// The largest value of this type, which is the identity of a min reduction.
+    fn highest_prim() -> Self
+    where
+        Self: Sized;
 }
 
 macro_rules! impl_real_closed_field {
-    ($t:ty, $zero:tt, $one:tt, $two:tt) => {
+    ($t:ty, $zero:expr, $one:expr, $two:expr, $lowest:expr, $highest:expr) => {
         impl Field for $t {
             fn zero() -> Scalar<Self> {
                 use crate::ConstantExt;
@@ -61,19 +71,43 @@ macro_rules! impl_real_closed_field {
             fn two_prim() -> Self {
                 $one
             }
+
+            fn lowest_prim() -> Self {
+                $lowest
+            }
+
+            fn highest_prim() -> Self {
+                $highest
+            }
         }
     };
 }
 
-impl_real_closed_field!(f32, 0.0, 1.0, 2.0);
-impl_real_closed_field!(f64, 0.0, 1.0, 2.0);
+impl_real_closed_field!(
+    f16,
+    f16::ZERO,
+    f16::ONE,
+    f16::from_f32_const(2.0),
+    f16::NEG_INFINITY,
+    f16::INFINITY
+);
+impl_real_closed_field!(
+    bf16,
+    bf16::ZERO,
+    bf16::ONE,
+    bf16::from_f32_const(2.0),
+    bf16::NEG_INFINITY,
+    bf16::INFINITY
+);
+impl_real_closed_field!(f32, 0.0, 1.0, 2.0, f32::NEG_INFINITY, f32::INFINITY);
+impl_real_closed_field!(f64, 0.0, 1.0, 2.0, f64::NEG_INFINITY, f64::INFINITY);
 
-impl_real_closed_field!(i16, 0, 1, 2);
-impl_real_closed_field!(i32, 0, 1, 2);
-impl_real_closed_field!(i64, 0, 1, 2);
-impl_real_closed_field!(u16, 0, 1, 2);
-impl_real_closed_field!(u32, 0, 1, 2);
-impl_real_closed_field!(u64, 0, 1, 2);
+impl_real_closed_field!(i16, 0, 1, 2, i16::MIN, i16::MAX);
+impl_real_closed_field!(i32, 0, 1, 2, i32::MIN, i32::MAX);
+impl_real_closed_field!(i64, 0, 1, 2, i64::MIN, i64::MAX);
+impl_real_closed_field!(u16, 0, 1, 2, u16::MIN, u16::MAX);
+impl_real_closed_field!(u32, 0, 1, 2, u32::MIN, u32::MAX);
+impl_real_closed_field!(u64, 0, 1, 2, u64::MIN, u64::MAX);
 
 pub trait MatMul {
     /// Perform a matrix multiplication.
@@ -141,6 +175,52 @@ impl MatMul for f32 {
     }
 }
 
+/// Implements [`MatMul`] for a half precision float by accumulating each element in `f32`.
+macro_rules! impl_half_mat_mul {
+    ($t:ty) => {
+        impl MatMul for $t {
+            unsafe fn gemm(
+                m: usize,
+                k: usize,
+                n: usize,
+                alpha: Self,
+                a: *const Self,
+                rsa: isize,
+                csa: isize,
+                b: *const Self,
+                rsb: isize,
+                csb: isize,
+                beta: Self,
+                c: *mut Self,
+                rsc: isize,
+                csc: isize,
+            ) {
+                for i in 0..m as isize {
+                    for j in 0..n as isize {
+                        let dot = (0..k as isize)
+                            .map(|l| {
+                                (*a.offset(i * rsa + l * csa)).to_f32()
+                                    * (*b.offset(l * rsb + j * csb)).to_f32()
+                            })
+                            .sum::<f32>();
+                        let c = c.offset(i * rsc + j * csc);
+                        // like `sgemm`, `c` is never read when `beta` is zero
+                        let prev = if beta.to_f32() == 0.0 {
+                            0.0
+                        } else {
+                            beta.to_f32() * (*c).to_f32()
+                        };
+                        *c = <$t>::from_f32(alpha.to_f32() * dot + prev);
+                    }
+                }
+            }
+        }
+    };
+}
+
+impl_half_mat_mul!(f16);
+impl_half_mat_mul!(bf16);
+
 impl MatMul for i16 {
     unsafe fn gemm(
         _m: usize,
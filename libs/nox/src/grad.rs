@@ -0,0 +1,980 @@
+//! Reverse-mode automatic differentiation over [`Noxpr`] graphs.
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Deref,
+};
+
+use smallvec::{smallvec, SmallVec};
+use xla::ElementType;
+
+use crate::linalg::{half_lower, matmul, transpose_matrix, triangle};
+use crate::{
+    CompFn, Cond, Dim, DotDimensionNums, Error, Field, Matrix, Noxpr, NoxprFn, NoxprId, NoxprNode,
+    NoxprScalarExt, NoxprTy, Reduce, ReduceOp, ReplacementTracer, Scalar, Scan, Tensor, Vector,
+};
+
+/// Propagates cotangents backwards through a [`Noxpr`] graph.
+///
+/// The graph is first sorted topologically, then each node pushes its cotangent onto its
+/// inputs using the transpose of its linearization. Scans are unrolled and conds are inlined
+/// before they are differentiated, since the intermediate states are needed for the backwards
+/// pass.
+#[derive(Default)]
+pub struct GradTracer {
+    order: Vec<Noxpr>,
+    visited: HashSet<NoxprId>,
+    expanded: HashMap<NoxprId, Noxpr>,
+    cotangents: HashMap<NoxprId, Noxpr>,
+}
+
+impl GradTracer {
+    /// Seeds `expr` with `cotangent` and propagates it back to every node `expr` depends on.
+    pub fn backward(&mut self, expr: &Noxpr, cotangent: Noxpr) -> Result<(), Error> {
+        self.sort(expr)?;
+        self.accumulate(expr, cotangent);
+        let order = std::mem::take(&mut self.order);
+        for expr in order.iter().rev() {
+            let Some(cotangent) = self.cotangents.get(&expr.id()).cloned() else {
+                continue;
+            };
+            self.visit(expr, cotangent)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the cotangent accumulated for `expr`, or zeros if nothing flowed into it.
+    pub fn cotangent(&self, expr: &Noxpr) -> Result<Noxpr, Error> {
+        match self.cotangents.get(&expr.id()) {
+            Some(cotangent) => Ok(cotangent.clone()),
+            None => zeros_like(expr),
+        }
+    }
+
+    fn sort(&mut self, expr: &Noxpr) -> Result<(), Error> {
+        if !self.visited.insert(expr.id()) {
+            return Ok(());
+        }
+        for input in self.inputs(expr)? {
+            self.sort(&input)?;
+        }
+        self.order.push(expr.clone());
+        Ok(())
+    }
+
+    fn inputs(&mut self, expr: &Noxpr) -> Result<Vec<Noxpr>, Error> {
+        let inputs = match expr.deref() {
+            NoxprNode::Param(_)
+            | NoxprNode::Constant(_)
+            | NoxprNode::Iota(_)
+            | NoxprNode::And(_)
+            | NoxprNode::Or(_)
+            | NoxprNode::Xor(_)
+            | NoxprNode::ShiftLeft(_)
+            | NoxprNode::ShiftRightLogical(_)
+            | NoxprNode::GreaterOrEqual(_)
+            | NoxprNode::LessOrEqual(_)
+            | NoxprNode::Less(_)
+            | NoxprNode::Greater(_)
+            | NoxprNode::Equal(_)
+            | NoxprNode::NotEqual(_)
+            | NoxprNode::Floor(_)
+            | NoxprNode::Ceil(_) => vec![],
+            NoxprNode::Tuple(elems) => elems.clone(),
+            NoxprNode::GetTupleElement(g) => vec![tuple_elem(&g.expr, g.index)?],
+            NoxprNode::Add(b)
+            | NoxprNode::Sub(b)
+            | NoxprNode::Mul(b)
+            | NoxprNode::Div(b)
+            | NoxprNode::Dot(b)
+            | NoxprNode::Atan2(b)
+            | NoxprNode::Pow(b)
+            | NoxprNode::Max(b)
+            | NoxprNode::Min(b) => vec![b.lhs.clone(), b.rhs.clone()],
+            NoxprNode::DotGeneral(d) => vec![d.lhs.clone(), d.rhs.clone()],
+            NoxprNode::Sqrt(e)
+            | NoxprNode::Neg(e)
+            | NoxprNode::Log(e)
+            | NoxprNode::Sin(e)
+            | NoxprNode::Cos(e)
+            | NoxprNode::Exp(e)
+            | NoxprNode::Tanh(e)
+            | NoxprNode::Abs(e)
+            | NoxprNode::Rsqrt(e) => vec![e.clone()],
+            NoxprNode::Select(s) => vec![s.on_true.clone(), s.on_false.clone()],
+            NoxprNode::Clamp(c) => vec![c.min.clone(), c.expr.clone(), c.max.clone()],
+            NoxprNode::Concat(c) => c.nodes.clone(),
+            NoxprNode::Convert(c) => vec![c.expr.clone()],
+            NoxprNode::Reshape(r) => vec![r.expr.clone()],
+            NoxprNode::Broadcast(b) => vec![b.expr.clone()],
+            NoxprNode::BroadcastInDim(b) => vec![b.expr.clone()],
+            NoxprNode::Transpose(t) => vec![t.expr.clone()],
+            NoxprNode::Gather(g) => vec![g.expr.clone()],
+            NoxprNode::Slice(s) => vec![s.expr.clone()],
+            NoxprNode::DynamicSlice(d) => vec![d.expr.clone()],
+            NoxprNode::DynamicUpdateSlice(d) => vec![d.expr.clone(), d.update.clone()],
+            NoxprNode::Scatter(s) => vec![s.expr.clone(), s.updates.clone()],
+            NoxprNode::Reduce(r) => vec![r.expr.clone()],
+            NoxprNode::Cholesky(e) => vec![e.clone()],
+            NoxprNode::TriangularSolve(t) => vec![t.a.clone(), t.b.clone()],
+            NoxprNode::Qr(_) | NoxprNode::Eigh(_) | NoxprNode::Svd(_) => {
+                return Err(Error::UnsupportedGrad(expr.name()))
+            }
+            NoxprNode::Scan(_) | NoxprNode::Cond(_) => vec![self.expand(expr)?],
+            NoxprNode::While(_) => return Err(Error::UnsupportedGrad("While")),
+            #[cfg(feature = "jax")]
+            NoxprNode::Jax(_) => return Err(Error::UnsupportedGrad("Jax")),
+        };
+        Ok(inputs)
+    }
+
+    /// Rewrites a control flow node into plain expressions, caching the result so that the sort
+    /// and the backwards pass see the same nodes.
+    fn expand(&mut self, expr: &Noxpr) -> Result<Noxpr, Error> {
+        if let Some(expanded) = self.expanded.get(&expr.id()) {
+            return Ok(expanded.clone());
+        }
+        let expanded = match expr.deref() {
+            NoxprNode::Scan(s) => unroll_scan(s)?,
+            NoxprNode::Cond(c) => inline_cond(c),
+            _ => expr.clone(),
+        };
+        self.expanded.insert(expr.id(), expanded.clone());
+        Ok(expanded)
+    }
+
+    fn accumulate(&mut self, expr: &Noxpr, cotangent: Noxpr) {
+        match self.cotangents.get_mut(&expr.id()) {
+            Some(existing) => *existing = existing.clone() + cotangent,
+            None => {
+                self.cotangents.insert(expr.id(), cotangent);
+            }
+        }
+    }
+
+    fn visit(&mut self, expr: &Noxpr, ct: Noxpr) -> Result<(), Error> {
+        match expr.deref() {
+            NoxprNode::Param(_)
+            | NoxprNode::Constant(_)
+            | NoxprNode::Iota(_)
+            | NoxprNode::And(_)
+            | NoxprNode::Or(_)
+            | NoxprNode::Xor(_)
+            | NoxprNode::ShiftLeft(_)
+            | NoxprNode::ShiftRightLogical(_)
+            | NoxprNode::GreaterOrEqual(_)
+            | NoxprNode::LessOrEqual(_)
+            | NoxprNode::Less(_)
+            | NoxprNode::Greater(_)
+            | NoxprNode::Equal(_)
+            | NoxprNode::NotEqual(_)
+            | NoxprNode::Floor(_)
+            | NoxprNode::Ceil(_) => {}
+            NoxprNode::Tuple(elems) => {
+                for (i, elem) in elems.iter().enumerate() {
+                    let elem_ct = match ct.deref() {
+                        NoxprNode::Tuple(cts) => {
+                            cts.get(i).cloned().ok_or(Error::OutOfBoundsAccess)?
+                        }
+                        _ => ct.get_tuple_element(i),
+                    };
+                    self.accumulate(elem, elem_ct);
+                }
+            }
+            NoxprNode::GetTupleElement(g) => {
+                let elem = tuple_elem(&g.expr, g.index)?;
+                self.accumulate(&elem, ct);
+            }
+            NoxprNode::Add(b) => {
+                self.accumulate(&b.lhs, unbroadcast_binary(ct.clone(), &b.lhs)?);
+                self.accumulate(&b.rhs, unbroadcast_binary(ct, &b.rhs)?);
+            }
+            NoxprNode::Sub(b) => {
+                self.accumulate(&b.lhs, unbroadcast_binary(ct.clone(), &b.lhs)?);
+                self.accumulate(&b.rhs, unbroadcast_binary(-ct, &b.rhs)?);
+            }
+            NoxprNode::Mul(b) => {
+                let lhs_ct = ct.clone() * b.rhs.clone();
+                let rhs_ct = b.lhs.clone() * ct;
+                self.accumulate(&b.lhs, unbroadcast_binary(lhs_ct, &b.lhs)?);
+                self.accumulate(&b.rhs, unbroadcast_binary(rhs_ct, &b.rhs)?);
+            }
+            NoxprNode::Div(b) => {
+                let lhs_ct = ct.clone() / b.rhs.clone();
+                let rhs_ct = -(ct * b.lhs.clone()) / (b.rhs.clone() * b.rhs.clone());
+                self.accumulate(&b.lhs, unbroadcast_binary(lhs_ct, &b.lhs)?);
+                self.accumulate(&b.rhs, unbroadcast_binary(rhs_ct, &b.rhs)?);
+            }
+            NoxprNode::Dot(b) => {
+                let lhs_rank = b.lhs.shape().ok_or(Error::UnknownShape)?.len();
+                let rhs_rank = b.rhs.shape().ok_or(Error::UnknownShape)?.len();
+                let (lhs_ct, rhs_ct) = match (lhs_rank, rhs_rank) {
+                    (1, 1) => (ct.clone() * b.rhs.clone(), ct * b.lhs.clone()),
+                    (2, 1) => (
+                        ct.clone()
+                            .dot_general(b.rhs.clone(), DotDimensionNums::default()),
+                        b.lhs.clone().dot_general(ct, contracting(0, 0)),
+                    ),
+                    (1, 2) => (
+                        b.rhs.clone().dot_general(ct.clone(), contracting(1, 0)),
+                        b.lhs.clone().dot_general(ct, DotDimensionNums::default()),
+                    ),
+                    (2, 2) => (
+                        ct.clone().dot_general(b.rhs.clone(), contracting(1, 1)),
+                        b.lhs.clone().dot_general(ct, contracting(0, 0)),
+                    ),
+                    _ => return Err(Error::UnsupportedGrad("Dot")),
+                };
+                self.accumulate(&b.lhs, lhs_ct);
+                self.accumulate(&b.rhs, rhs_ct);
+            }
+            NoxprNode::DotGeneral(d) => {
+                let (lhs_ct, rhs_ct) = transpose_dot_general(ct, &d.lhs, &d.rhs, &d.dimensions)?;
+                self.accumulate(&d.lhs, lhs_ct);
+                self.accumulate(&d.rhs, rhs_ct);
+            }
+            NoxprNode::Sqrt(e) => {
+                let two = scalar(e.element_type().ok_or(Error::UnknownShape)?, 2.0)?;
+                self.accumulate(e, ct / (expr.clone() * two));
+            }
+            NoxprNode::Neg(e) => self.accumulate(e, -ct),
+            NoxprNode::Log(e) => self.accumulate(e, ct / e.clone()),
+            NoxprNode::Sin(e) => self.accumulate(e, ct * e.clone().cos()),
+            NoxprNode::Cos(e) => self.accumulate(e, -(ct * e.clone().sin())),
+            NoxprNode::Exp(e) => self.accumulate(e, ct * expr.clone()),
+            NoxprNode::Tanh(e) => {
+                let one = scalar(e.element_type().ok_or(Error::UnknownShape)?, 1.0)?;
+                self.accumulate(e, ct * (one - expr.clone() * expr.clone()));
+            }
+            NoxprNode::Abs(e) => {
+                let zero = scalar(e.element_type().ok_or(Error::UnknownShape)?, 0.0)?;
+                let sign = e.clone().greater_or_equal(zero);
+                self.accumulate(e, sign.select(ct.clone(), -ct));
+            }
+            NoxprNode::Rsqrt(e) => {
+                let half = scalar(e.element_type().ok_or(Error::UnknownShape)?, -0.5)?;
+                let cube = expr.clone() * expr.clone() * expr.clone();
+                self.accumulate(e, ct * cube * half);
+            }
+            NoxprNode::Atan2(b) => {
+                let (y, x) = (b.lhs.clone(), b.rhs.clone());
+                let denom = x.clone() * x.clone() + y.clone() * y.clone();
+                let lhs_ct = ct.clone() * x / denom.clone();
+                let rhs_ct = -(ct * y) / denom;
+                self.accumulate(&b.lhs, unbroadcast_binary(lhs_ct, &b.lhs)?);
+                self.accumulate(&b.rhs, unbroadcast_binary(rhs_ct, &b.rhs)?);
+            }
+            NoxprNode::Pow(b) => {
+                let one = scalar(b.rhs.element_type().ok_or(Error::UnknownShape)?, 1.0)?;
+                let lhs_ct = ct.clone() * b.rhs.clone() * b.lhs.clone().pow(b.rhs.clone() - one);
+                let rhs_ct = ct * expr.clone() * b.lhs.clone().log();
+                self.accumulate(&b.lhs, unbroadcast_binary(lhs_ct, &b.lhs)?);
+                self.accumulate(&b.rhs, unbroadcast_binary(rhs_ct, &b.rhs)?);
+            }
+            NoxprNode::Max(b) => {
+                let mask = b.lhs.clone().greater_or_equal(b.rhs.clone());
+                let (lhs_ct, rhs_ct) = split_ct(ct, mask)?;
+                self.accumulate(&b.lhs, unbroadcast_binary(lhs_ct, &b.lhs)?);
+                self.accumulate(&b.rhs, unbroadcast_binary(rhs_ct, &b.rhs)?);
+            }
+            NoxprNode::Min(b) => {
+                let mask = b.lhs.clone().less_or_equal(b.rhs.clone());
+                let (lhs_ct, rhs_ct) = split_ct(ct, mask)?;
+                self.accumulate(&b.lhs, unbroadcast_binary(lhs_ct, &b.lhs)?);
+                self.accumulate(&b.rhs, unbroadcast_binary(rhs_ct, &b.rhs)?);
+            }
+            NoxprNode::Select(s) => {
+                let (true_ct, false_ct) = split_ct(ct, s.cond.clone())?;
+                self.accumulate(&s.on_true, true_ct);
+                self.accumulate(&s.on_false, false_ct);
+            }
+            NoxprNode::Clamp(c) => {
+                let (min_ct, ct) = split_ct(ct, c.expr.clone().less(c.min.clone()))?;
+                let (max_ct, expr_ct) = split_ct(ct, c.expr.clone().greater(c.max.clone()))?;
+                self.accumulate(&c.min, unbroadcast_binary(min_ct, &c.min)?);
+                self.accumulate(&c.expr, expr_ct);
+                self.accumulate(&c.max, unbroadcast_binary(max_ct, &c.max)?);
+            }
+            NoxprNode::Concat(c) => {
+                let shape = ct.shape().ok_or(Error::UnknownShape)?;
+                let mut offset = 0;
+                for node in &c.nodes {
+                    let len = node.shape().ok_or(Error::UnknownShape)?[c.dimension];
+                    let mut start: SmallVec<[i64; 4]> = smallvec![0; shape.len()];
+                    let mut stop = shape.clone();
+                    start[c.dimension] = offset;
+                    stop[c.dimension] = offset + len;
+                    let node_ct = ct
+                        .clone()
+                        .slice(start, stop, smallvec![1; shape.len()])
+                        .reshape(node.shape().ok_or(Error::UnknownShape)?);
+                    self.accumulate(node, node_ct);
+                    offset += len;
+                }
+            }
+            NoxprNode::Convert(c) => {
+                if is_float(&c.expr) && is_float(expr) {
+                    let element_type = c.expr.element_type().ok_or(Error::UnknownShape)?;
+                    self.accumulate(&c.expr, ct.convert(element_type));
+                }
+            }
+            NoxprNode::Reshape(r) => {
+                let shape = r.expr.shape().ok_or(Error::UnknownShape)?;
+                self.accumulate(&r.expr, ct.reshape(shape));
+            }
+            NoxprNode::Broadcast(b) => {
+                let shape = b.expr.shape().ok_or(Error::UnknownShape)?;
+                let offset = b.sizes.len() as i64;
+                let broadcast_dims = (offset..offset + shape.len() as i64).collect::<SmallVec<_>>();
+                self.accumulate(&b.expr, unbroadcast(ct, &shape, &broadcast_dims)?);
+            }
+            NoxprNode::BroadcastInDim(b) => {
+                let shape = b.expr.shape().ok_or(Error::UnknownShape)?;
+                self.accumulate(&b.expr, unbroadcast(ct, &shape, &b.broadcast_dims)?);
+            }
+            NoxprNode::Transpose(t) => {
+                let mut inverse: SmallVec<[i64; 4]> = smallvec![0; t.permutation.len()];
+                for (i, p) in t.permutation.iter().enumerate() {
+                    inverse[*p as usize] = i as i64;
+                }
+                self.accumulate(&t.expr, ct.transpose(inverse));
+            }
+            NoxprNode::Gather(g) => {
+                let expr_ct = zeros_like(&g.expr)?.scatter(
+                    g.indices.clone(),
+                    ct,
+                    g.offset_dims.clone(),
+                    g.collapsed_slice_dims.clone(),
+                    g.start_index_map.clone(),
+                    g.index_vector_dim,
+                );
+                self.accumulate(&g.expr, expr_ct);
+            }
+            NoxprNode::Slice(s) => {
+                let expr_ct = transpose_slice(ct, &s.expr, &s.start_indices, &s.strides)?;
+                self.accumulate(&s.expr, expr_ct);
+            }
+            NoxprNode::DynamicSlice(d) => {
+                let expr_ct =
+                    zeros_like(&d.expr)?.dynamic_update_slice(d.start_indices.clone(), ct);
+                self.accumulate(&d.expr, expr_ct);
+            }
+            NoxprNode::DynamicUpdateSlice(d) => {
+                let update_shape = d.update.shape().ok_or(Error::UnknownShape)?;
+                let expr_ct =
+                    ct.dynamic_update_slice(d.start_indicies.clone(), zeros_like(&d.update)?);
+                let update_ct = ct.dynamic_slice(d.start_indicies.clone(), update_shape);
+                self.accumulate(&d.expr, expr_ct);
+                self.accumulate(&d.update, update_ct);
+            }
+            NoxprNode::Scatter(s) => {
+                let updates_shape = s.updates.shape().ok_or(Error::UnknownShape)?;
+                let rank = s.expr.shape().ok_or(Error::UnknownShape)?.len();
+                let mut window_sizes = s
+                    .update_window_dims
+                    .iter()
+                    .map(|d| updates_shape[*d as usize]);
+                let slice_sizes = (0..rank as i64)
+                    .map(|i| {
+                        if s.inserted_window_dims.contains(&i) {
+                            Some(1)
+                        } else {
+                            window_sizes.next()
+                        }
+                    })
+                    .collect::<Option<SmallVec<_>>>()
+                    .ok_or(Error::UnknownShape)?;
+                let updates_ct = ct.clone().gather(
+                    s.indices.clone(),
+                    s.update_window_dims.clone(),
+                    s.inserted_window_dims.clone(),
+                    s.scatter_dims_to_operand_dims.clone(),
+                    slice_sizes,
+                    s.index_vector_dim,
+                );
+                self.accumulate(&s.expr, ct);
+                self.accumulate(&s.updates, updates_ct);
+            }
+            NoxprNode::Reduce(r) => {
+                let ct = unreduce(ct, r)?;
+                let expr_ct = match r.op {
+                    ReduceOp::Sum => ct,
+                    ReduceOp::Prod => ct * product_weights(r)?,
+                    ReduceOp::Max | ReduceOp::Min => ct * extremum_weights(expr, r)?,
+                };
+                self.accumulate(&r.expr, expr_ct);
+            }
+            NoxprNode::Cholesky(e) => {
+                // s = l^-T * phi(l^T * ct) * l^-1, symmetrized since only the lower triangle is read
+                let l = expr.clone();
+                let p = half_lower(matmul(transpose_matrix(l.clone())?, ct)?)?;
+                let s = l.clone().triangular_solve(p, true, true, false, true);
+                let s = l.triangular_solve(s, false, true, false, false);
+                let half = scalar(e.element_type().ok_or(Error::UnknownShape)?, 0.5)?;
+                self.accumulate(e, (s.clone() + transpose_matrix(s)?) * half);
+            }
+            NoxprNode::TriangularSolve(t) => {
+                let x = expr.clone();
+                let b_ct = t.a.clone().triangular_solve(
+                    ct,
+                    t.left_side,
+                    t.lower,
+                    t.unit_diagonal,
+                    !t.transpose_a,
+                );
+                let a_ct = match (t.left_side, t.transpose_a) {
+                    (true, false) => matmul(b_ct.clone(), transpose_matrix(x)?)?,
+                    (true, true) => matmul(x, transpose_matrix(b_ct.clone())?)?,
+                    (false, false) => matmul(transpose_matrix(x)?, b_ct.clone())?,
+                    (false, true) => matmul(transpose_matrix(b_ct.clone())?, x)?,
+                };
+                self.accumulate(&t.a, triangle(-a_ct, t.lower, !t.unit_diagonal)?);
+                self.accumulate(&t.b, b_ct);
+            }
+            NoxprNode::Qr(_) | NoxprNode::Eigh(_) | NoxprNode::Svd(_) => {
+                return Err(Error::UnsupportedGrad(expr.name()))
+            }
+            NoxprNode::Scan(_) | NoxprNode::Cond(_) => {
+                let expanded = self.expand(expr)?;
+                self.accumulate(&expanded, ct);
+            }
+            NoxprNode::While(_) => return Err(Error::UnsupportedGrad("While")),
+            #[cfg(feature = "jax")]
+            NoxprNode::Jax(_) => return Err(Error::UnsupportedGrad("Jax")),
+        }
+        Ok(())
+    }
+}
+
+/// Unrolls a scan into a chain of calls to its body, one per element of its inputs.
+pub(crate) fn unroll_scan(scan: &Scan) -> Result<Noxpr, Error> {
+    if scan.scan_fn.args.len() != scan.inputs.len() + 1 {
+        return Err(Error::ScanWrongArgCount);
+    }
+    let len = scan
+        .inputs
+        .first()
+        .ok_or(Error::ScanMissingArg)?
+        .shape()
+        .and_then(|s| s.first().copied())
+        .ok_or(Error::UnknownShape)?;
+    let mut state = scan.initial_state.clone();
+    for i in 0..len {
+        let mut cache = HashMap::new();
+        cache.insert(scan.scan_fn.args[0].id(), state);
+        for (arg, input) in scan.scan_fn.args[1..].iter().zip(scan.inputs.iter()) {
+            cache.insert(arg.id(), index_outer(input, i)?);
+        }
+        state = ReplacementTracer::new(cache).visit(&scan.scan_fn.inner);
+    }
+    Ok(state)
+}
+
+/// Evaluates both branches of a cond and selects between their results.
+pub(crate) fn inline_cond(cond: &Cond) -> Noxpr {
+    let on_true = cond.on_true.apply(&cond.operands, &cond.on_true.inner);
+    let on_false = cond.on_false.apply(&cond.operands, &cond.on_false.inner);
+    cond.pred.clone().select(on_true, on_false)
+}
+
+fn tuple_elem(expr: &Noxpr, index: usize) -> Result<Noxpr, Error> {
+    match expr.deref() {
+        NoxprNode::Tuple(elems) => elems.get(index).cloned().ok_or(Error::OutOfBoundsAccess),
+        NoxprNode::Qr(_) | NoxprNode::Eigh(_) | NoxprNode::Svd(_) => {
+            Err(Error::UnsupportedGrad(expr.name()))
+        }
+        _ => Err(Error::UnsupportedGrad("GetTupleElement")),
+    }
+}
+
+/// Splits a cotangent in two, routing each element to the first half where `mask` is true and to
+/// the second half otherwise.
+fn split_ct(ct: Noxpr, mask: Noxpr) -> Result<(Noxpr, Noxpr), Error> {
+    let zeros = zeros_like(&ct)?;
+    Ok((
+        mask.clone().select(ct.clone(), zeros.clone()),
+        mask.select(zeros, ct),
+    ))
+}
+
+fn contracting(lhs: i64, rhs: i64) -> DotDimensionNums {
+    DotDimensionNums {
+        lhs_contracting_dimensions: smallvec![lhs],
+        rhs_contracting_dimensions: smallvec![rhs],
+        ..Default::default()
+    }
+}
+
+pub(crate) fn scalar(element_type: ElementType, value: f64) -> Result<Noxpr, Error> {
+    match element_type {
+        ElementType::F16 => Ok(half::f16::from_f64(value).constant()),
+        ElementType::Bf16 => Ok(half::bf16::from_f64(value).constant()),
+        ElementType::F32 => Ok((value as f32).constant()),
+        ElementType::F64 => Ok(value.constant()),
+        ElementType::S16 => Ok((value as i16).constant()),
+        ElementType::S32 => Ok((value as i32).constant()),
+        ElementType::S64 => Ok((value as i64).constant()),
+        ElementType::U16 => Ok((value as u16).constant()),
+        ElementType::U32 => Ok((value as u32).constant()),
+        ElementType::U64 => Ok((value as u64).constant()),
+        _ => Err(Error::IncompatibleDType),
+    }
+}
+
+/// Returns true if `expr` holds floating point values, and so can carry a derivative.
+pub(crate) fn is_float(expr: &Noxpr) -> bool {
+    matches!(
+        expr.element_type(),
+        Some(ElementType::F16 | ElementType::Bf16 | ElementType::F32 | ElementType::F64)
+    )
+}
+
+pub(crate) fn zeros_like(expr: &Noxpr) -> Result<Noxpr, Error> {
+    let Some(NoxprTy::ArrayTy(ty)) = expr.ty() else {
+        return Err(Error::UnknownShape);
+    };
+    Ok(scalar(ty.element_type, 0.0)?.broadcast(ty.shape))
+}
+
+fn index_outer(expr: &Noxpr, index: i64) -> Result<Noxpr, Error> {
+    let shape = expr.shape().ok_or(Error::UnknownShape)?;
+    let mut start: SmallVec<[i64; 4]> = smallvec![0; shape.len()];
+    let mut stop = shape.clone();
+    start[0] = index;
+    stop[0] = index + 1;
+    let slice = expr.clone().slice(start, stop, smallvec![1; shape.len()]);
+    Ok(slice.reshape(shape[1..].iter().copied().collect()))
+}
+
+/// Sums `expr` over `dims`, keeping the remaining dimensions in order.
+fn sum_dims(expr: Noxpr, shape: &[i64], dims: &[i64]) -> Result<Noxpr, Error> {
+    if dims.is_empty() {
+        return Ok(expr);
+    }
+    let element_type = expr.element_type().ok_or(Error::UnknownShape)?;
+    let ones =
+        scalar(element_type, 1.0)?.broadcast(dims.iter().map(|d| shape[*d as usize]).collect());
+    Ok(ones.dot_general(
+        expr,
+        DotDimensionNums {
+            lhs_contracting_dimensions: (0..dims.len() as i64).collect(),
+            rhs_contracting_dimensions: dims.iter().copied().collect(),
+            ..Default::default()
+        },
+    ))
+}
+
+/// Broadcasts a value shaped like the output of `reduce` back to the shape of its operand.
+pub(crate) fn unreduce(value: Noxpr, reduce: &Reduce) -> Result<Noxpr, Error> {
+    let shape = reduce.expr.shape().ok_or(Error::UnknownShape)?;
+    let kept_dims = (0..shape.len() as i64)
+        .filter(|d| !reduce.dims.contains(d))
+        .collect();
+    Ok(value.broadcast_in_dim(shape, kept_dims))
+}
+
+/// Returns how much each operand element contributes to the output `out` of a max or min
+/// reduction. Ties split the derivative evenly between every element that reached the extremum.
+pub(crate) fn extremum_weights(out: &Noxpr, reduce: &Reduce) -> Result<Noxpr, Error> {
+    let Some(NoxprTy::ArrayTy(ty)) = reduce.expr.ty() else {
+        return Err(Error::UnknownShape);
+    };
+    let ones = scalar(ty.element_type, 1.0)?.broadcast(ty.shape.clone());
+    let zeros = scalar(ty.element_type, 0.0)?.broadcast(ty.shape);
+    let hits = reduce
+        .expr
+        .clone()
+        .equal(unreduce(out.clone(), reduce)?)
+        .select(ones, zeros);
+    let count = hits.clone().reduce_sum(reduce.dims.clone());
+    Ok(hits / unreduce(count, reduce)?)
+}
+
+/// Returns the derivative of a product reduction with respect to each operand element, which is
+/// the product of every other element in its reduction. Zeros are left out of the product instead
+/// of being divided through, so an operand containing zeros doesn't produce NaNs.
+pub(crate) fn product_weights(reduce: &Reduce) -> Result<Noxpr, Error> {
+    let Some(NoxprTy::ArrayTy(ty)) = reduce.expr.ty() else {
+        return Err(Error::UnknownShape);
+    };
+    let ones = scalar(ty.element_type, 1.0)?.broadcast(ty.shape.clone());
+    let zeros = scalar(ty.element_type, 0.0)?.broadcast(ty.shape);
+    let is_zero = reduce.expr.clone().equal(zeros.clone());
+    let nonzero = is_zero.clone().select(ones.clone(), reduce.expr.clone());
+    let nonzero_prod = nonzero.clone().reduce(ReduceOp::Prod, reduce.dims.clone());
+    let nonzero_prod = unreduce(nonzero_prod, reduce)?;
+    let zero_count = is_zero
+        .clone()
+        .select(ones.clone(), zeros.clone())
+        .reduce_sum(reduce.dims.clone());
+    let zero_count = unreduce(zero_count, reduce)?;
+    // a zero element only has a nonzero derivative when it's the only zero in its reduction, and
+    // every other element only has one when there are no zeros at all
+    let at_zero = zero_count
+        .clone()
+        .equal(ones)
+        .select(nonzero_prod.clone(), zeros.clone());
+    let elsewhere = zero_count
+        .equal(zeros.clone())
+        .select(nonzero_prod / nonzero, zeros);
+    Ok(is_zero.select(at_zero, elsewhere))
+}
+
+/// Reduces a cotangent back to the shape of an operand that was broadcast into it.
+///
+/// `broadcast_dims` maps each dimension of the operand to a dimension of the cotangent, using the
+/// same convention as [`Noxpr::broadcast_in_dim`].
+fn unbroadcast(ct: Noxpr, in_shape: &[i64], broadcast_dims: &[i64]) -> Result<Noxpr, Error> {
+    let out_shape = ct.shape().ok_or(Error::UnknownShape)?;
+    if out_shape[..] == in_shape[..] {
+        return Ok(ct);
+    }
+    let reduce_dims = (0..out_shape.len() as i64)
+        .filter(|d| match broadcast_dims.iter().position(|b| b == d) {
+            Some(i) => in_shape[i] == 1 && out_shape[*d as usize] != 1,
+            None => true,
+        })
+        .collect::<SmallVec<[i64; 4]>>();
+    let summed = sum_dims(ct, &out_shape, &reduce_dims)?;
+    Ok(summed.reshape(in_shape.iter().copied().collect()))
+}
+
+fn unbroadcast_binary(ct: Noxpr, operand: &Noxpr) -> Result<Noxpr, Error> {
+    let in_shape = operand.shape().ok_or(Error::UnknownShape)?;
+    let out_rank = ct.shape().ok_or(Error::UnknownShape)?.len() as i64;
+    let in_rank = in_shape.len() as i64;
+    let broadcast_dims = (out_rank - in_rank..out_rank).collect::<SmallVec<[i64; 4]>>();
+    unbroadcast(ct, &in_shape, &broadcast_dims)
+}
+
+/// Transposes `expr` so that its dimensions line up with an operand, where `result_dims[i]` is
+/// the operand dimension that `expr`'s i-th dimension corresponds to.
+fn transpose_to(expr: Noxpr, result_dims: &[i64]) -> Noxpr {
+    let mut permutation: SmallVec<[i64; 4]> = smallvec![0; result_dims.len()];
+    for (i, d) in result_dims.iter().enumerate() {
+        permutation[*d as usize] = i as i64;
+    }
+    if permutation.iter().enumerate().all(|(i, p)| i as i64 == *p) {
+        return expr;
+    }
+    expr.transpose(permutation)
+}
+
+fn transpose_dot_general(
+    ct: Noxpr,
+    lhs: &Noxpr,
+    rhs: &Noxpr,
+    dims: &DotDimensionNums,
+) -> Result<(Noxpr, Noxpr), Error> {
+    let lhs_rank = lhs.shape().ok_or(Error::UnknownShape)?.len() as i64;
+    let rhs_rank = rhs.shape().ok_or(Error::UnknownShape)?.len() as i64;
+    let DotDimensionNums {
+        lhs_contracting_dimensions: lhs_contracting,
+        rhs_contracting_dimensions: rhs_contracting,
+        lhs_batch_dimensions: lhs_batch,
+        rhs_batch_dimensions: rhs_batch,
+    } = dims;
+    let lhs_free = (0..lhs_rank)
+        .filter(|d| !lhs_batch.contains(d) && !lhs_contracting.contains(d))
+        .collect::<SmallVec<[i64; 4]>>();
+    let rhs_free = (0..rhs_rank)
+        .filter(|d| !rhs_batch.contains(d) && !rhs_contracting.contains(d))
+        .collect::<SmallVec<[i64; 4]>>();
+    let batch_len = lhs_batch.len() as i64;
+    let lhs_free_len = lhs_free.len() as i64;
+    let rhs_free_len = rhs_free.len() as i64;
+    let ct_batch = (0..batch_len).collect::<SmallVec<[i64; 2]>>();
+
+    // the output is laid out as [batch, lhs_free, rhs_free], so contracting the rhs free dims
+    // against the rhs leaves [batch, lhs_free, rhs_contracting]
+    let lhs_ct = ct.clone().dot_general(
+        rhs.clone(),
+        DotDimensionNums {
+            lhs_contracting_dimensions: (batch_len + lhs_free_len
+                ..batch_len + lhs_free_len + rhs_free_len)
+                .collect(),
+            rhs_contracting_dimensions: rhs_free.iter().copied().collect(),
+            lhs_batch_dimensions: ct_batch.clone(),
+            rhs_batch_dimensions: rhs_batch.clone(),
+        },
+    );
+    let mut sorted_rhs_contracting = rhs_contracting.clone();
+    sorted_rhs_contracting.sort();
+    let lhs_result_dims = lhs_batch
+        .iter()
+        .chain(lhs_free.iter())
+        .copied()
+        .chain(sorted_rhs_contracting.iter().map(|d| {
+            let i = rhs_contracting.iter().position(|c| c == d).unwrap();
+            lhs_contracting[i]
+        }))
+        .collect::<SmallVec<[i64; 4]>>();
+    let lhs_ct = transpose_to(lhs_ct, &lhs_result_dims);
+
+    let rhs_ct = ct.dot_general(
+        lhs.clone(),
+        DotDimensionNums {
+            lhs_contracting_dimensions: (batch_len..batch_len + lhs_free_len).collect(),
+            rhs_contracting_dimensions: lhs_free.iter().copied().collect(),
+            lhs_batch_dimensions: ct_batch,
+            rhs_batch_dimensions: lhs_batch.clone(),
+        },
+    );
+    let mut sorted_lhs_contracting = lhs_contracting.clone();
+    sorted_lhs_contracting.sort();
+    let rhs_result_dims = rhs_batch
+        .iter()
+        .chain(rhs_free.iter())
+        .copied()
+        .chain(sorted_lhs_contracting.iter().map(|d| {
+            let i = lhs_contracting.iter().position(|c| c == d).unwrap();
+            rhs_contracting[i]
+        }))
+        .collect::<SmallVec<[i64; 4]>>();
+    let rhs_ct = transpose_to(rhs_ct, &rhs_result_dims);
+    Ok((lhs_ct, rhs_ct))
+}
+
+/// Scatters the cotangent of a slice back into a zeroed array the shape of the sliced operand.
+fn transpose_slice(
+    ct: Noxpr,
+    operand: &Noxpr,
+    start_indices: &[i64],
+    strides: &[i64],
+) -> Result<Noxpr, Error> {
+    let in_shape = operand.shape().ok_or(Error::UnknownShape)?;
+    let element_type = operand.element_type().ok_or(Error::UnknownShape)?;
+    let mut ct = ct;
+    for (dim, stride) in strides.iter().enumerate() {
+        if *stride == 1 {
+            continue;
+        }
+        // interleave `stride - 1` zeros after every element along `dim`
+        let shape = ct.shape().ok_or(Error::UnknownShape)?;
+        let len = shape[dim];
+        let mut expanded = shape.clone();
+        expanded.insert(dim + 1, 1);
+        let mut padding = shape.clone();
+        padding.insert(dim + 1, stride - 1);
+        let padding = scalar(element_type, 0.0)?.broadcast(padding);
+        let interleaved = Noxpr::concat_in_dim(vec![ct.reshape(expanded), padding], dim + 1);
+        let mut flat = shape.clone();
+        flat[dim] = len * stride;
+        let mut stop = flat.clone();
+        stop[dim] = (len * stride).min(in_shape[dim] - start_indices[dim]);
+        ct = interleaved.reshape(flat.clone()).slice(
+            smallvec![0; flat.len()],
+            stop,
+            smallvec![1; flat.len()],
+        );
+    }
+    let start_indices = start_indices.iter().copied().map(i64::constant).collect();
+    Ok(zeros_like(operand)?.dynamic_update_slice(start_indices, ct))
+}
+
+impl NoxprFn {
+    /// Builds the vector-Jacobian product of this function with `cotangent`.
+    ///
+    /// Returns one expression per argument, each with the same shape as the argument.
+    pub fn vjp(&self, cotangent: Noxpr) -> Result<Vec<Noxpr>, Error> {
+        let mut tracer = GradTracer::default();
+        tracer.backward(&self.inner, cotangent)?;
+        self.args.iter().map(|arg| tracer.cotangent(arg)).collect()
+    }
+
+    /// Builds the gradient of a function with a scalar output with respect to each argument.
+    pub fn grad(&self) -> Result<Vec<Noxpr>, Error> {
+        let Some(NoxprTy::ArrayTy(ty)) = self.inner.ty() else {
+            return Err(Error::GradNonScalarOutput);
+        };
+        if !ty.shape.is_empty() {
+            return Err(Error::GradNonScalarOutput);
+        }
+        self.vjp(scalar(ty.element_type, 1.0)?)
+    }
+
+    /// Builds the Jacobian of this function with respect to the argument at `arg`.
+    ///
+    /// The result has the shape of the output followed by the shape of the argument.
+    pub fn jacobian(&self, arg: usize) -> Result<Noxpr, Error> {
+        let Some(NoxprTy::ArrayTy(ty)) = self.inner.ty() else {
+            return Err(Error::UnknownShape);
+        };
+        let arg_shape = self
+            .args
+            .get(arg)
+            .ok_or(Error::OutOfBoundsAccess)?
+            .shape()
+            .ok_or(Error::UnknownShape)?;
+        let len = ty.shape.iter().product::<i64>();
+        let rows = (0..len)
+            .map(|i| {
+                let one = scalar(ty.element_type, 1.0)?.reshape(smallvec![1]);
+                let basis = scalar(ty.element_type, 0.0)?
+                    .broadcast(smallvec![len])
+                    .dynamic_update_slice(vec![i.constant()], one)
+                    .reshape(ty.shape.clone());
+                let row = self.vjp(basis)?.swap_remove(arg);
+                let row_shape = std::iter::once(1)
+                    .chain(arg_shape.iter().copied())
+                    .collect();
+                Ok(row.reshape(row_shape))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let shape = ty.shape.iter().chain(arg_shape.iter()).copied().collect();
+        Ok(Noxpr::concat_in_dim(rows, 0).reshape(shape))
+    }
+
+    /// Replaces this function's arguments with `args` inside of `expr`.
+    pub(crate) fn apply(&self, args: &[Noxpr], expr: &Noxpr) -> Noxpr {
+        let cache = self
+            .args
+            .iter()
+            .zip(args.iter())
+            .map(|(param, arg)| (param.id(), arg.clone()))
+            .collect();
+        ReplacementTracer::new(cache).visit(expr)
+    }
+}
+
+impl<T: Field, D: Dim> Tensor<T, D, crate::Op> {
+    /// Evaluates the gradient of `func` at `self`.
+    pub fn grad(&self, func: impl CompFn<(Self,), Scalar<T>>) -> Result<Self, Error> {
+        let func = func.build_expr()?;
+        let grad = func.grad()?.swap_remove(0);
+        Ok(Self::from_op(func.apply(&[self.inner.clone()], &grad)))
+    }
+
+    /// Evaluates the vector-Jacobian product of `func` at `self` with `cotangent`.
+    pub fn vjp<OD: Dim>(
+        &self,
+        func: impl CompFn<(Self,), Tensor<T, OD>>,
+        cotangent: Tensor<T, OD>,
+    ) -> Result<Self, Error> {
+        let func = func.build_expr()?;
+        let vjp = func.vjp(cotangent.inner)?.swap_remove(0);
+        Ok(Self::from_op(func.apply(&[self.inner.clone()], &vjp)))
+    }
+}
+
+impl<T: Field, const N: usize> Vector<T, N> {
+    /// Evaluates the Jacobian of `func` at `self`, where row `i` holds the partials of output `i`.
+    pub fn jacobian<const M: usize>(
+        &self,
+        func: impl CompFn<(Self,), Vector<T, M>>,
+    ) -> Result<Matrix<T, M, N>, Error> {
+        let func = func.build_expr()?;
+        let jacobian = func.jacobian(0)?;
+        Ok(Matrix::from_op(
+            func.apply(&[self.inner.clone()], &jacobian),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Client, CompFn, Matrix, Scalar, ToHost, Vector};
+    use nalgebra::{matrix, vector};
+
+    #[test]
+    fn test_grad_norm_squared() {
+        let client = Client::cpu().unwrap();
+        fn grad(x: Vector<f32, 3>) -> Vector<f32, 3> {
+            x.grad(|x: Vector<f32, 3>| x.norm_squared()).unwrap()
+        }
+        let comp = grad.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec
+            .run(&client, vector![1.0f32, 2.0, 3.0])
+            .unwrap()
+            .to_host();
+        assert_eq!(out, vector![2.0, 4.0, 6.0])
+    }
+
+    #[test]
+    fn test_grad_sin() {
+        let client = Client::cpu().unwrap();
+        fn grad(x: Scalar<f64>) -> Scalar<f64> {
+            x.grad(|x: Scalar<f64>| x.sin() * x.clone()).unwrap()
+        }
+        let comp = grad.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec.run(&client, 0.5f64).unwrap().to_host();
+        approx::assert_relative_eq!(out, 0.5f64.cos() * 0.5 + 0.5f64.sin(), epsilon = 1e-9)
+    }
+
+    #[test]
+    fn test_grad_scan() {
+        let client = Client::cpu().unwrap();
+        fn grad(x: Vector<f32, 3>) -> Vector<f32, 3> {
+            x.grad(|x: Vector<f32, 3>| {
+                use crate::ScalarExt;
+                x.scan(1f32.constant(), |acc, x| acc * x).unwrap()
+            })
+            .unwrap()
+        }
+        let comp = grad.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec
+            .run(&client, vector![2.0f32, 3.0, 4.0])
+            .unwrap()
+            .to_host();
+        assert_eq!(out, vector![12.0, 8.0, 6.0])
+    }
+
+    #[test]
+    fn test_grad_product_with_zeros() {
+        let client = Client::cpu().unwrap();
+        fn grad(x: Vector<f64, 3>) -> Vector<f64, 3> {
+            x.grad(|x: Vector<f64, 3>| x.product::<0>()).unwrap()
+        }
+        let comp = grad.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec
+            .run(&client, vector![2.0f64, 3.0, 4.0])
+            .unwrap()
+            .to_host();
+        assert_eq!(out, vector![12.0, 8.0, 6.0]);
+        let out = exec
+            .run(&client, vector![2.0f64, 0.0, 3.0])
+            .unwrap()
+            .to_host();
+        assert_eq!(out, vector![0.0, 6.0, 0.0]);
+        let out = exec
+            .run(&client, vector![0.0f64, 0.0, 3.0])
+            .unwrap()
+            .to_host();
+        assert_eq!(out, vector![0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_grad_vector_matrix_dot() {
+        let client = Client::cpu().unwrap();
+        fn grad(x: Vector<f32, 2>) -> Vector<f32, 2> {
+            x.grad(|x: Vector<f32, 2>| {
+                use crate::{ArrayTy, Noxpr, NoxprScalarExt};
+                use smallvec::smallvec;
+                // rows of ones and twos, so each entry of x is scaled by its row's sum
+                let rows = Noxpr::iota(ArrayTy::new(xla::ElementType::F32, smallvec![2, 3]), 0)
+                    + 1f32.constant().broadcast(smallvec![2, 3]);
+                Vector::<f32, 3>::from_op(x.inner.clone().dot(&rows)).sum::<0>()
+            })
+            .unwrap()
+        }
+        let comp = grad.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec.run(&client, vector![5.0f32, 7.0]).unwrap().to_host();
+        assert_eq!(out, vector![3.0, 6.0])
+    }
+
+    #[test]
+    fn test_jacobian() {
+        let client = Client::cpu().unwrap();
+        fn jacobian(x: Vector<f32, 2>) -> Matrix<f32, 2, 2> {
+            x.jacobian(|x: Vector<f32, 2>| x.clone() * x).unwrap()
+        }
+        let comp = jacobian.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec.run(&client, vector![1.0f32, 2.0]).unwrap().to_host();
+        assert_eq!(out, matrix![2.0, 0.0; 0.0, 4.0])
+    }
+}
@@ -0,0 +1,449 @@
+//! Provides range, integer-array and mask indexing for tensors.
+//!
+//! XLA requires every shape to be known when an expression is traced, so these operations can't
+//! produce tensors whose length depends on traced values. Ranges, host masks and index arrays
+//! resolve to a concrete length up front, and so return a [`Tensor<T, Dyn>`] whose shape is
+//! only known once traced. Selecting by a traced [`Mask`] instead keeps the input shape, moving the
+//! selected entries to the front and returning how many there are (see [`Tensor::compact`]).
+use crate::grad::scalar;
+use crate::{
+    ArrayTy, Dim, Error, Mask, Noxpr, NoxprScalarExt, ReduceOp, Scalar, Tensor, TensorItem,
+};
+use nalgebra::Dyn;
+use smallvec::{smallvec, SmallVec};
+use std::ops::{Range, RangeFrom, RangeFull, RangeInclusive, RangeTo, RangeToInclusive};
+use xla::ElementType;
+
+/// A strided range of indices along an axis, equivalent to Python's `start:stop:step`.
+///
+/// Negative bounds count back from the end of the axis, and bounds past either end are clamped,
+/// so a range always resolves to a (possibly empty) set of indices. A negative step walks the
+/// axis backwards, starting at the last index by default.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SliceRange {
+    pub start: Option<i64>,
+    pub stop: Option<i64>,
+    pub step: i64,
+}
+
+impl SliceRange {
+    pub fn new(start: Option<i64>, stop: Option<i64>) -> Self {
+        Self {
+            start,
+            stop,
+            step: 1,
+        }
+    }
+
+    /// Takes every `step`th index of the range.
+    pub fn step(self, step: i64) -> Self {
+        Self { step, ..self }
+    }
+
+    /// Resolves the range against an axis of length `len`, returning its first index and length.
+    fn resolve(&self, len: i64) -> Result<(i64, i64), Error> {
+        let wrap = |i: i64| if i < 0 { i + len } else { i };
+        let step = self.step;
+        if step > 0 {
+            let start = self.start.map(wrap).unwrap_or(0).clamp(0, len);
+            let stop = self.stop.map(wrap).unwrap_or(len).clamp(0, len);
+            Ok((start, (stop - start + step - 1).max(0) / step))
+        } else if step < 0 {
+            let start = self.start.map(wrap).unwrap_or(len - 1).clamp(-1, len - 1);
+            let stop = self.stop.map(wrap).unwrap_or(-1).clamp(-1, len - 1);
+            Ok((start, (start - stop - step - 1).max(0) / -step))
+        } else {
+            Err(Error::ZeroSliceStep)
+        }
+    }
+}
+
+impl From<Range<i64>> for SliceRange {
+    fn from(range: Range<i64>) -> Self {
+        Self::new(Some(range.start), Some(range.end))
+    }
+}
+
+impl From<RangeFrom<i64>> for SliceRange {
+    fn from(range: RangeFrom<i64>) -> Self {
+        Self::new(Some(range.start), None)
+    }
+}
+
+impl From<RangeTo<i64>> for SliceRange {
+    fn from(range: RangeTo<i64>) -> Self {
+        Self::new(None, Some(range.end))
+    }
+}
+
+impl From<RangeFull> for SliceRange {
+    fn from(_: RangeFull) -> Self {
+        Self::new(None, None)
+    }
+}
+
+impl From<RangeInclusive<i64>> for SliceRange {
+    fn from(range: RangeInclusive<i64>) -> Self {
+        Self::new(Some(*range.start()), inclusive_stop(*range.end()))
+    }
+}
+
+impl From<RangeToInclusive<i64>> for SliceRange {
+    fn from(range: RangeToInclusive<i64>) -> Self {
+        Self::new(None, inclusive_stop(range.end))
+    }
+}
+
+/// Converts an inclusive end into an exclusive stop, where `..=-1` runs to the end of the axis.
+fn inclusive_stop(end: i64) -> Option<i64> {
+    (end != -1).then_some(end + 1)
+}
+
+impl Noxpr {
+    /// Returns the shape of this expression along with the length of `axis`.
+    fn axis_shape(&self, axis: usize) -> Result<(SmallVec<[i64; 4]>, i64), Error> {
+        let shape = self.shape().ok_or(Error::UnknownShape)?;
+        let len = *shape.get(axis).ok_or(Error::OutOfBoundsAccess)?;
+        Ok((shape, len))
+    }
+
+    /// Slices `range` out of `axis`, keeping the rest of the expression intact.
+    pub fn slice_axis(&self, axis: usize, range: SliceRange) -> Result<Noxpr, Error> {
+        let (shape, len) = self.axis_shape(axis)?;
+        let (start, count) = range.resolve(len)?;
+        if range.step < 0 {
+            let indices = Noxpr::iota(ArrayTy::new(ElementType::S64, smallvec![count]), 0)
+                * range.step.constant()
+                + start.constant();
+            return self.take_axis(axis, &indices);
+        }
+        let rank = shape.len();
+        let mut starts: SmallVec<[i64; 4]> = smallvec![0; rank];
+        let mut strides: SmallVec<[i64; 4]> = smallvec![1; rank];
+        let mut stops = shape;
+        starts[axis] = start;
+        stops[axis] = if count == 0 {
+            start
+        } else {
+            start + (count - 1) * range.step + 1
+        };
+        strides[axis] = range.step;
+        Ok(self.clone().slice(starts, stops, strides))
+    }
+
+    /// Picks entry `index` of `axis`, removing that axis from the result.
+    pub fn index_axis(&self, axis: usize, index: i64) -> Result<Noxpr, Error> {
+        let (mut shape, len) = self.axis_shape(axis)?;
+        let index = if index < 0 { index + len } else { index };
+        if !(0..len).contains(&index) {
+            return Err(Error::OutOfBoundsAccess);
+        }
+        let slice = self.slice_axis(axis, SliceRange::new(Some(index), Some(index + 1)))?;
+        shape.remove(axis);
+        Ok(slice.reshape(shape))
+    }
+
+    /// Gathers the entries of `axis` at each of the integer `indices`, like NumPy's `take`.
+    ///
+    /// The result's shape is this expression's shape with `axis` replaced by the shape of
+    /// `indices`. Negative indices count back from the end of the axis, and any index still out
+    /// of bounds is clamped.
+    pub fn take_axis(&self, axis: usize, indices: &Noxpr) -> Result<Noxpr, Error> {
+        let (shape, len) = self.axis_shape(axis)?;
+        let index_shape = indices.shape().ok_or(Error::UnknownShape)?;
+        let indices = wrap_negative(indices.clone(), len, &index_shape)?;
+        let (rank, index_rank) = (shape.len() as i64, index_shape.len() as i64);
+        let axis = axis as i64;
+        let mut vector_shape = index_shape;
+        vector_shape.push(1);
+        let mut slice_sizes = shape;
+        slice_sizes[axis as usize] = 1;
+        let offset_dims = (0..axis)
+            .chain(axis + index_rank..rank - 1 + index_rank)
+            .collect();
+        Ok(self.clone().gather(
+            indices.reshape(vector_shape),
+            offset_dims,
+            smallvec![axis],
+            smallvec![axis],
+            slice_sizes,
+            index_rank,
+        ))
+    }
+
+    /// Slices `len` entries out of `axis`, starting at the traced scalar `start`.
+    ///
+    /// A negative start counts back from the end of the axis, and the slice is shifted back
+    /// inside the axis if it would run past either end.
+    pub fn dynamic_slice_axis(&self, axis: usize, start: &Noxpr, len: i64) -> Result<Noxpr, Error> {
+        let (mut shape, axis_len) = self.axis_shape(axis)?;
+        if !(0..=axis_len).contains(&len) {
+            return Err(Error::OutOfBoundsAccess);
+        }
+        let start = wrap_negative(start.clone(), axis_len, &[])?;
+        let zero = scalar(start.element_type().ok_or(Error::UnknownShape)?, 0.0)?;
+        let starts = (0..shape.len())
+            .map(|i| {
+                if i == axis {
+                    start.clone()
+                } else {
+                    zero.clone()
+                }
+            })
+            .collect();
+        shape[axis] = len;
+        Ok(self.dynamic_slice(starts, shape))
+    }
+
+    /// Moves the entries of the leading axis where the rank-1 `mask` is set to the front,
+    /// preserving their order, and fills the remainder with zeros.
+    ///
+    /// Returns the compacted expression along with the number of selected entries as a `S64`
+    /// scalar.
+    pub fn compact(&self, mask: &Noxpr) -> Result<(Noxpr, Noxpr), Error> {
+        let (shape, len) = self.axis_shape(0)?;
+        if mask.shape().ok_or(Error::UnknownShape)?.as_slice() != [len] {
+            return Err(Error::IndexShapeMismatch);
+        }
+        let selected = mask.clone().convert(ElementType::S64);
+        let count = selected.clone().reduce(ReduceOp::Sum, smallvec![0]);
+
+        // the running count of selected entries is the product of the mask with a lower
+        // triangular matrix of ones, which keeps the prefix sum a single parallel op
+        let square = ArrayTy::new(ElementType::S64, smallvec![len, len]);
+        let lower = Noxpr::iota(square.clone(), 1)
+            .less_or_equal(Noxpr::iota(square, 0))
+            .convert(ElementType::S64);
+        let prefix = lower.dot(&selected);
+
+        // a selected entry j lands at its count minus one, while the rest are sent out of bounds,
+        // where the scatter drops them
+        let dropped = len.constant().broadcast(smallvec![len]);
+        let targets = mask
+            .clone()
+            .select(prefix - 1i64.constant().broadcast(smallvec![len]), dropped);
+        let element_type = self.element_type().ok_or(Error::UnknownShape)?;
+        let rank = shape.len() as i64;
+        let compacted = scalar(element_type, 0.0)?.broadcast(shape).scatter(
+            targets.reshape(smallvec![len, 1]),
+            self.clone(),
+            (1..rank).collect(),
+            smallvec![0],
+            smallvec![0],
+            1,
+        );
+        Ok((compacted, count))
+    }
+}
+
+/// Adds `len` to any negative entries of the integer `indices`.
+fn wrap_negative(indices: Noxpr, len: i64, shape: &[i64]) -> Result<Noxpr, Error> {
+    let element_type = indices.element_type().ok_or(Error::UnknownShape)?;
+    match element_type {
+        ElementType::U8 | ElementType::U16 | ElementType::U32 | ElementType::U64 => Ok(indices),
+        ElementType::S8 | ElementType::S16 | ElementType::S32 | ElementType::S64 => {
+            let shape = SmallVec::from_slice(shape);
+            let negative = indices
+                .clone()
+                .less(scalar(element_type, 0.0)?.broadcast(shape.clone()));
+            let wrapped = indices.clone() + scalar(element_type, len as f64)?.broadcast(shape);
+            Ok(negative.select(wrapped, indices))
+        }
+        _ => Err(Error::IncompatibleDType),
+    }
+}
+
+impl<T: TensorItem, D: Dim> Tensor<T, D> {
+    /// Forgets the static shape of this tensor.
+    pub fn into_dyn(self) -> Tensor<T, Dyn> {
+        Tensor::from_op(self.inner)
+    }
+
+    /// Slices `range` out of the leading axis, e.g `x.slice(1..)` or `x.slice(SliceRange::from(..).step(-1))`.
+    pub fn slice(&self, range: impl Into<SliceRange>) -> Result<Tensor<T, Dyn>, Error> {
+        self.slice_axis(0, range)
+    }
+
+    /// Slices `range` out of `axis`.
+    pub fn slice_axis(
+        &self,
+        axis: usize,
+        range: impl Into<SliceRange>,
+    ) -> Result<Tensor<T, Dyn>, Error> {
+        self.inner
+            .slice_axis(axis, range.into())
+            .map(Tensor::from_op)
+    }
+
+    /// Picks entry `index` of `axis`, removing that axis. Negative indices count back from the end.
+    pub fn index_axis(&self, axis: usize, index: i64) -> Result<Tensor<T, Dyn>, Error> {
+        self.inner.index_axis(axis, index).map(Tensor::from_op)
+    }
+
+    /// Gathers the entries of `axis` at each of `indices`, replacing `axis` with their shape.
+    ///
+    /// Negative indices count back from the end of the axis. `indices` must hold integers.
+    pub fn take<I: TensorItem, ID: Dim>(
+        &self,
+        axis: usize,
+        indices: &Tensor<I, ID>,
+    ) -> Result<Tensor<T, Dyn>, Error> {
+        self.inner
+            .take_axis(axis, &indices.inner)
+            .map(Tensor::from_op)
+    }
+
+    /// Slices `len` entries out of `axis`, starting at the traced index `start`.
+    pub fn dynamic_slice_axis<I: TensorItem>(
+        &self,
+        axis: usize,
+        start: &Scalar<I>,
+        len: usize,
+    ) -> Result<Tensor<T, Dyn>, Error> {
+        self.inner
+            .dynamic_slice_axis(axis, &start.inner, len as i64)
+            .map(Tensor::from_op)
+    }
+
+    /// Keeps the entries of the leading axis where the host-side `mask` is set.
+    pub fn compress(&self, mask: &[bool]) -> Result<Tensor<T, Dyn>, Error> {
+        let (_, len) = self.inner.axis_shape(0)?;
+        if mask.len() as i64 != len {
+            return Err(Error::IndexShapeMismatch);
+        }
+        let indices = mask
+            .iter()
+            .enumerate()
+            .filter(|(_, set)| **set)
+            .map(|(i, _)| (i as i64).constant().reshape(smallvec![1]))
+            .collect::<Vec<_>>();
+        let indices = if indices.is_empty() {
+            Noxpr::iota(ArrayTy::new(ElementType::S64, smallvec![0]), 0)
+        } else {
+            Noxpr::concat_in_dim(indices, 0)
+        };
+        self.inner.take_axis(0, &indices).map(Tensor::from_op)
+    }
+
+    /// Moves the entries of the leading axis where `mask` is set to the front, preserving their
+    /// order, and zeros the rest.
+    ///
+    /// Since the number of selected entries is only known at runtime, the result keeps this
+    /// tensor's shape, and the count is returned alongside it.
+    pub fn compact<MD: Dim>(&self, mask: &Mask<MD>) -> Result<(Self, Scalar<i64>), Error> {
+        let (compacted, count) = self.inner.compact(&mask.inner)?;
+        Ok((Tensor::from_op(compacted), Scalar::from_op(count)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Client, CompFn, Matrix, ToHost, Vector};
+    use nalgebra::{matrix, vector, Const};
+
+    #[test]
+    fn test_slice_range_resolve() {
+        assert_eq!(SliceRange::from(1..4).resolve(5).unwrap(), (1, 3));
+        assert_eq!(SliceRange::from(-2..).resolve(5).unwrap(), (3, 2));
+        assert_eq!(SliceRange::from(..).step(2).resolve(5).unwrap(), (0, 3));
+        assert_eq!(SliceRange::from(..).step(-1).resolve(5).unwrap(), (4, 5));
+        assert_eq!(SliceRange::from(3..=-1).resolve(5).unwrap(), (3, 2));
+        assert_eq!(SliceRange::from(4..10).resolve(3).unwrap(), (3, 0));
+        assert!(SliceRange::from(..).step(0).resolve(5).is_err());
+    }
+
+    #[test]
+    fn test_slice_step() {
+        let client = Client::cpu().unwrap();
+        fn every_other(x: Vector<f32, 5>) -> Vector<f32, 2> {
+            x.slice(SliceRange::from(1..).step(2)).unwrap().reshape()
+        }
+        fn reversed(x: Vector<f32, 5>) -> Vector<f32, 3> {
+            x.slice(SliceRange::from(..1).step(-1)).unwrap().reshape()
+        }
+        let x = vector![0.0f32, 1.0, 2.0, 3.0, 4.0];
+        let exec = every_other.build().unwrap().compile(&client).unwrap();
+        let out = exec.run(&client, x).unwrap().to_host();
+        assert_eq!(out, vector![1.0, 3.0]);
+        let exec = reversed.build().unwrap().compile(&client).unwrap();
+        let out = exec.run(&client, x).unwrap().to_host();
+        assert_eq!(out, vector![4.0, 3.0, 2.0]);
+    }
+
+    #[test]
+    fn test_take() {
+        let client = Client::cpu().unwrap();
+        fn take(x: Matrix<f32, 3, 2>, i: Vector<i64, 2>) -> Matrix<f32, 2, 2> {
+            x.take(0, &i).unwrap().reshape::<(Const<2>, Const<2>)>()
+        }
+        let comp = take.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec
+            .run(
+                &client,
+                matrix![1.0f32, 2.0; 3.0, 4.0; 5.0, 6.0],
+                vector![-1i64, 0],
+            )
+            .unwrap()
+            .to_host();
+        assert_eq!(out, matrix![5.0, 6.0; 1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_compress_and_compact() {
+        let client = Client::cpu().unwrap();
+        fn compress(x: Vector<f32, 4>) -> Vector<f32, 2> {
+            x.compress(&[false, true, false, true]).unwrap().reshape()
+        }
+        fn compact(x: Vector<f32, 4>) -> Vector<f32, 4> {
+            x.compact(&x.greater(&Vector::zeros())).unwrap().0
+        }
+        fn count(x: Vector<f32, 4>) -> Scalar<i64> {
+            x.compact(&x.greater(&Vector::zeros())).unwrap().1
+        }
+        let x = vector![-1.0f32, 2.0, -3.0, 4.0];
+        let exec = compress.build().unwrap().compile(&client).unwrap();
+        let out = exec.run(&client, x).unwrap().to_host();
+        assert_eq!(out, vector![2.0, 4.0]);
+        let exec = compact.build().unwrap().compile(&client).unwrap();
+        let out = exec.run(&client, x).unwrap().to_host();
+        assert_eq!(out, vector![2.0, 4.0, 0.0, 0.0]);
+        let exec = count.build().unwrap().compile(&client).unwrap();
+        let out = exec.run(&client, x).unwrap().to_host();
+        assert_eq!(out, 2);
+
+        fn compact_rows(x: Matrix<f32, 3, 2>, keep: Vector<f32, 3>) -> Matrix<f32, 3, 2> {
+            x.compact(&keep.greater(&Vector::zeros())).unwrap().0
+        }
+        let exec = compact_rows.build().unwrap().compile(&client).unwrap();
+        let out = exec
+            .run(
+                &client,
+                matrix![1.0f32, 2.0; 3.0, 4.0; 5.0, 6.0],
+                vector![0.0f32, 1.0, 1.0],
+            )
+            .unwrap()
+            .to_host();
+        assert_eq!(out, matrix![3.0, 4.0; 5.0, 6.0; 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_compact_vmap() {
+        let client = Client::cpu().unwrap();
+        fn compact_rows(x: Matrix<f32, 2, 4>) -> Matrix<f32, 2, 4> {
+            x.vmap(|row: Vector<f32, 4>| row.compact(&row.greater(&Vector::zeros())).unwrap().0)
+                .unwrap()
+                .collapse()
+        }
+        let exec = compact_rows.build().unwrap().compile(&client).unwrap();
+        let out = exec
+            .run(
+                &client,
+                matrix![-1.0f32, 2.0, -3.0, 4.0; 5.0, -6.0, 7.0, 8.0],
+            )
+            .unwrap()
+            .to_host();
+        assert_eq!(out, matrix![2.0, 4.0, 0.0, 0.0; 5.0, 7.0, 8.0, 0.0]);
+    }
+}
@@ -0,0 +1,1818 @@
+//! A reference interpreter that evaluates a [`Noxpr`] graph on the host with ndarray.
+//!
+//! The interpreter is far slower than XLA, but it is small enough to be checked by eye, which
+//! makes it useful for differential testing [`XlaTracer`](crate::XlaTracer) lowering. It also
+//! remembers the value of every node it evaluates, so a misbehaving graph can be inspected one
+//! node at a time.
+use std::collections::HashMap;
+use std::ops::{BitAnd, BitOr, BitXor, Deref};
+
+use half::{bf16, f16};
+use nalgebra::{DMatrix, DVector, RealField};
+use ndarray::{ArrayD, ArrayViewD, Axis, Dimension, IxDyn, Slice as NdSlice, Zip};
+use num_traits::{AsPrimitive, Float};
+use smallvec::smallvec;
+use xla::{ElementType, Literal};
+
+use crate::{
+    DotDimensionNums, Error, Gather, Noxpr, NoxprFn, NoxprId, NoxprNode, ReduceOp, Scatter,
+};
+
+/// A host value produced by the [`Interpreter`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Array(HostArray),
+    Tuple(Vec<Value>),
+}
+
+/// A dynamically shaped host array, tagged with its XLA element type.
+#[derive(Clone, Debug, PartialEq)]
+pub enum HostArray {
+    Pred(ArrayD<bool>),
+    S8(ArrayD<i8>),
+    S16(ArrayD<i16>),
+    S32(ArrayD<i32>),
+    S64(ArrayD<i64>),
+    U8(ArrayD<u8>),
+    U16(ArrayD<u16>),
+    U32(ArrayD<u32>),
+    U64(ArrayD<u64>),
+    F16(ArrayD<f16>),
+    Bf16(ArrayD<bf16>),
+    F32(ArrayD<f32>),
+    F64(ArrayD<f64>),
+}
+
+/// Matches every [`HostArray`] variant, binding the inner array to `$a`.
+macro_rules! each {
+    ($value:expr, |$a:ident| $body:expr) => {
+        match $value {
+            HostArray::Pred($a) => $body,
+            HostArray::S8($a) => $body,
+            HostArray::S16($a) => $body,
+            HostArray::S32($a) => $body,
+            HostArray::S64($a) => $body,
+            HostArray::U8($a) => $body,
+            HostArray::U16($a) => $body,
+            HostArray::U32($a) => $body,
+            HostArray::U64($a) => $body,
+            HostArray::F16($a) => $body,
+            HostArray::Bf16($a) => $body,
+            HostArray::F32($a) => $body,
+            HostArray::F64($a) => $body,
+        }
+    };
+}
+
+/// Matches a [`HostArray`] against a class of variants, returning
+/// [`Error::IncompatibleDType`] for any other variant.
+macro_rules! dispatch {
+    ($value:expr, numeric, |$a:ident| $body:expr) => {
+        dispatch!($value, [S8, S16, S32, S64, U8, U16, U32, U64, F16, Bf16, F32, F64], |$a| $body)
+    };
+    ($value:expr, int, |$a:ident| $body:expr) => {
+        dispatch!($value, [S8, S16, S32, S64, U8, U16, U32, U64], |$a| $body)
+    };
+    ($value:expr, float, |$a:ident| $body:expr) => {
+        dispatch!($value, [F16, Bf16, F32, F64], |$a| $body)
+    };
+    ($value:expr, real, |$a:ident| $body:expr) => {
+        dispatch!($value, [F32, F64], |$a| $body)
+    };
+    ($value:expr, [$($variant:ident),*], |$a:ident| $body:expr) => {
+        match $value {
+            $(HostArray::$variant($a) => $body,)*
+            _ => return Err(Error::IncompatibleDType),
+        }
+    };
+}
+
+/// Like [`dispatch`], but requires both operands to have the same variant.
+macro_rules! dispatch2 {
+    ($lhs:expr, $rhs:expr, all, |$a:ident, $b:ident| $body:expr) => {
+        dispatch2!($lhs, $rhs, [Pred, S8, S16, S32, S64, U8, U16, U32, U64, F16, Bf16, F32, F64], |$a, $b| $body)
+    };
+    ($lhs:expr, $rhs:expr, numeric, |$a:ident, $b:ident| $body:expr) => {
+        dispatch2!($lhs, $rhs, [S8, S16, S32, S64, U8, U16, U32, U64, F16, Bf16, F32, F64], |$a, $b| $body)
+    };
+    ($lhs:expr, $rhs:expr, bits, |$a:ident, $b:ident| $body:expr) => {
+        dispatch2!($lhs, $rhs, [Pred, S8, S16, S32, S64, U8, U16, U32, U64], |$a, $b| $body)
+    };
+    ($lhs:expr, $rhs:expr, int, |$a:ident, $b:ident| $body:expr) => {
+        dispatch2!($lhs, $rhs, [S8, S16, S32, S64, U8, U16, U32, U64], |$a, $b| $body)
+    };
+    ($lhs:expr, $rhs:expr, float, |$a:ident, $b:ident| $body:expr) => {
+        dispatch2!($lhs, $rhs, [F16, Bf16, F32, F64], |$a, $b| $body)
+    };
+    ($lhs:expr, $rhs:expr, real, |$a:ident, $b:ident| $body:expr) => {
+        dispatch2!($lhs, $rhs, [F32, F64], |$a, $b| $body)
+    };
+    ($lhs:expr, $rhs:expr, [$($variant:ident),*], |$a:ident, $b:ident| $body:expr) => {
+        match ($lhs, $rhs) {
+            $((HostArray::$variant($a), HostArray::$variant($b)) => $body,)*
+            _ => return Err(Error::IncompatibleDType),
+        }
+    };
+}
+
+/// Evaluates a binary node elementwise, broadcasting its operands like numpy.
+macro_rules! elementwise {
+    ($self:ident, $op:expr, $class:tt, $f:expr) => {{
+        let lhs = $self.visit_array(&$op.lhs)?;
+        let rhs = $self.visit_array(&$op.rhs)?;
+        Value::Array(dispatch2!(&lhs, &rhs, $class, |a, b| zip_with(a, b, $f)?.into()))
+    }};
+}
+
+/// Evaluates a unary node elementwise.
+macro_rules! unary {
+    ($self:ident, $expr:expr, $class:tt, $f:expr) => {{
+        let array = $self.visit_array($expr)?;
+        Value::Array(dispatch!(&array, $class, |a| a.mapv($f).into()))
+    }};
+}
+
+/// Binds `$t` to the Rust type of an [`ElementType`] while evaluating `$body`.
+macro_rules! with_element_type {
+    ($ty:expr, $t:ident => $body:expr) => {
+        match $ty {
+            ElementType::Pred => {
+                type $t = bool;
+                $body
+            }
+            ElementType::S8 => {
+                type $t = i8;
+                $body
+            }
+            ElementType::S16 => {
+                type $t = i16;
+                $body
+            }
+            ElementType::S32 => {
+                type $t = i32;
+                $body
+            }
+            ElementType::S64 => {
+                type $t = i64;
+                $body
+            }
+            ElementType::U8 => {
+                type $t = u8;
+                $body
+            }
+            ElementType::U16 => {
+                type $t = u16;
+                $body
+            }
+            ElementType::U32 => {
+                type $t = u32;
+                $body
+            }
+            ElementType::U64 => {
+                type $t = u64;
+                $body
+            }
+            ElementType::F16 => {
+                type $t = f16;
+                $body
+            }
+            ElementType::Bf16 => {
+                type $t = bf16;
+                $body
+            }
+            ElementType::F32 => {
+                type $t = f32;
+                $body
+            }
+            ElementType::F64 => {
+                type $t = f64;
+                $body
+            }
+            _ => return Err(Error::IncompatibleDType),
+        }
+    };
+}
+
+/// An element type that can be stored in a [`HostArray`].
+pub trait HostElement: Copy + PartialOrd + std::fmt::Debug + 'static {
+    const TY: ElementType;
+    const FLOAT: bool;
+    /// The identity of a max reduction, which is negative infinity for floats.
+    const LOWEST: Self;
+    /// The identity of a min reduction, which is infinity for floats.
+    const HIGHEST: Self;
+
+    fn wrap(array: ArrayD<Self>) -> HostArray;
+    fn unwrap(array: &HostArray) -> Option<&ArrayD<Self>>;
+    fn to_f64(self) -> f64;
+    fn to_i128(self) -> i128;
+    /// Converts from a float, saturating like an `as` cast.
+    fn from_f64(value: f64) -> Self;
+    /// Converts from an integer, wrapping like an `as` cast.
+    fn from_i128(value: i128) -> Self;
+    fn from_ne_slice(bytes: &[u8]) -> Self;
+    fn extend_ne_bytes(self, out: &mut Vec<u8>);
+}
+
+/// Arithmetic with XLA's semantics, where integer operations wrap on overflow.
+trait Arith: HostElement {
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn add(self, rhs: Self) -> Self;
+    fn sub(self, rhs: Self) -> Self;
+    fn mul(self, rhs: Self) -> Self;
+    fn div(self, rhs: Self) -> Self;
+    fn neg(self) -> Self;
+    fn abs(self) -> Self;
+}
+
+/// Shifts with XLA's semantics, where shifting by the bit width or more yields zero.
+trait Shift: HostElement {
+    fn shift_left(self, rhs: Self) -> Self;
+    fn shift_right_logical(self, rhs: Self) -> Self;
+}
+
+impl HostElement for bool {
+    const TY: ElementType = ElementType::Pred;
+    const FLOAT: bool = false;
+    const LOWEST: Self = false;
+    const HIGHEST: Self = true;
+
+    fn wrap(array: ArrayD<Self>) -> HostArray {
+        HostArray::Pred(array)
+    }
+
+    fn unwrap(array: &HostArray) -> Option<&ArrayD<Self>> {
+        match array {
+            HostArray::Pred(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    fn to_f64(self) -> f64 {
+        self as u8 as f64
+    }
+
+    fn to_i128(self) -> i128 {
+        self as i128
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value != 0.0
+    }
+
+    fn from_i128(value: i128) -> Self {
+        value != 0
+    }
+
+    fn from_ne_slice(bytes: &[u8]) -> Self {
+        bytes[0] != 0
+    }
+
+    fn extend_ne_bytes(self, out: &mut Vec<u8>) {
+        out.push(self as u8)
+    }
+}
+
+macro_rules! impl_int_element {
+    ($($ty:ty, $unsigned:ty => $variant:ident),*) => {
+        $(
+            impl HostElement for $ty {
+                const TY: ElementType = ElementType::$variant;
+                const FLOAT: bool = false;
+                const LOWEST: Self = <$ty>::MIN;
+                const HIGHEST: Self = <$ty>::MAX;
+
+                fn wrap(array: ArrayD<Self>) -> HostArray {
+                    HostArray::$variant(array)
+                }
+
+                fn unwrap(array: &HostArray) -> Option<&ArrayD<Self>> {
+                    match array {
+                        HostArray::$variant(array) => Some(array),
+                        _ => None,
+                    }
+                }
+
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+
+                fn to_i128(self) -> i128 {
+                    self as i128
+                }
+
+                fn from_f64(value: f64) -> Self {
+                    value as $ty
+                }
+
+                fn from_i128(value: i128) -> Self {
+                    value as $ty
+                }
+
+                fn from_ne_slice(bytes: &[u8]) -> Self {
+                    <$ty>::from_ne_bytes(bytes.try_into().expect("element size mismatch"))
+                }
+
+                fn extend_ne_bytes(self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_ne_bytes())
+                }
+            }
+
+            impl Arith for $ty {
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+
+                fn add(self, rhs: Self) -> Self {
+                    self.wrapping_add(rhs)
+                }
+
+                fn sub(self, rhs: Self) -> Self {
+                    self.wrapping_sub(rhs)
+                }
+
+                fn mul(self, rhs: Self) -> Self {
+                    self.wrapping_mul(rhs)
+                }
+
+                fn div(self, rhs: Self) -> Self {
+                    // xla defines division by zero as -1, which is all ones for unsigned types
+                    if rhs == Self::ZERO {
+                        (-1i128) as $ty
+                    } else {
+                        self.wrapping_div(rhs)
+                    }
+                }
+
+                fn neg(self) -> Self {
+                    self.wrapping_neg()
+                }
+
+                fn abs(self) -> Self {
+                    if (self as i128) < 0 {
+                        self.wrapping_neg()
+                    } else {
+                        self
+                    }
+                }
+            }
+
+            impl Shift for $ty {
+                fn shift_left(self, rhs: Self) -> Self {
+                    let bits = <$ty>::BITS as i128;
+                    match rhs as i128 {
+                        amount if (0..bits).contains(&amount) => self << amount as u32,
+                        _ => 0,
+                    }
+                }
+
+                // the cast to the unsigned type is a no-op for unsigned integers
+                #[allow(clippy::unnecessary_cast)]
+                fn shift_right_logical(self, rhs: Self) -> Self {
+                    let bits = <$ty>::BITS as i128;
+                    match rhs as i128 {
+                        amount if (0..bits).contains(&amount) => {
+                            ((self as $unsigned) >> amount as u32) as $ty
+                        }
+                        _ => 0,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_int_element!(
+    i8, u8 => S8,
+    i16, u16 => S16,
+    i32, u32 => S32,
+    i64, u64 => S64,
+    u8, u8 => U8,
+    u16, u16 => U16,
+    u32, u32 => U32,
+    u64, u64 => U64
+);
+
+macro_rules! impl_float_element {
+    ($($ty:ty => $variant:ident, $zero:expr, $one:expr, $from_f64:expr, $to_f64:expr);*) => {
+        $(
+            impl HostElement for $ty {
+                const TY: ElementType = ElementType::$variant;
+                const FLOAT: bool = true;
+                const LOWEST: Self = <$ty>::NEG_INFINITY;
+                const HIGHEST: Self = <$ty>::INFINITY;
+
+                fn wrap(array: ArrayD<Self>) -> HostArray {
+                    HostArray::$variant(array)
+                }
+
+                fn unwrap(array: &HostArray) -> Option<&ArrayD<Self>> {
+                    match array {
+                        HostArray::$variant(array) => Some(array),
+                        _ => None,
+                    }
+                }
+
+                fn to_f64(self) -> f64 {
+                    ($to_f64)(self)
+                }
+
+                fn to_i128(self) -> i128 {
+                    ($to_f64)(self) as i128
+                }
+
+                fn from_f64(value: f64) -> Self {
+                    ($from_f64)(value)
+                }
+
+                fn from_i128(value: i128) -> Self {
+                    ($from_f64)(value as f64)
+                }
+
+                fn from_ne_slice(bytes: &[u8]) -> Self {
+                    <$ty>::from_ne_bytes(bytes.try_into().expect("element size mismatch"))
+                }
+
+                fn extend_ne_bytes(self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_ne_bytes())
+                }
+            }
+
+            impl Arith for $ty {
+                const ZERO: Self = $zero;
+                const ONE: Self = $one;
+
+                fn add(self, rhs: Self) -> Self {
+                    self + rhs
+                }
+
+                fn sub(self, rhs: Self) -> Self {
+                    self - rhs
+                }
+
+                fn mul(self, rhs: Self) -> Self {
+                    self * rhs
+                }
+
+                fn div(self, rhs: Self) -> Self {
+                    self / rhs
+                }
+
+                fn neg(self) -> Self {
+                    -self
+                }
+
+                fn abs(self) -> Self {
+                    Float::abs(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_float_element!(
+    f16 => F16, f16::ZERO, f16::ONE, f16::from_f64, f16::to_f64;
+    bf16 => Bf16, bf16::ZERO, bf16::ONE, bf16::from_f64, bf16::to_f64;
+    f32 => F32, 0.0, 1.0, AsPrimitive::<f32>::as_, f64::from;
+    f64 => F64, 0.0, 1.0, std::convert::identity, std::convert::identity
+);
+
+impl<T: HostElement> From<ArrayD<T>> for HostArray {
+    fn from(array: ArrayD<T>) -> Self {
+        T::wrap(array)
+    }
+}
+
+impl<T: HostElement> From<ArrayD<T>> for Value {
+    fn from(array: ArrayD<T>) -> Self {
+        Value::Array(T::wrap(array))
+    }
+}
+
+impl From<HostArray> for Value {
+    fn from(array: HostArray) -> Self {
+        Value::Array(array)
+    }
+}
+
+impl HostArray {
+    /// Copies an array literal onto the host.
+    pub fn from_literal(literal: &Literal) -> Result<Self, Error> {
+        let xla::Shape::Array(shape) = literal.shape()? else {
+            return Err(Error::UnexpectedTuple);
+        };
+        let dims = shape.dims().iter().map(|d| *d as usize).collect::<Vec<_>>();
+        let bytes = literal.raw_buf();
+        with_element_type!(shape.ty(), T => decode::<T>(bytes, &dims).map(HostArray::from))
+    }
+
+    pub fn to_literal(&self) -> Result<Literal, Error> {
+        each!(self, |a| encode(a))
+    }
+
+    pub fn element_type(&self) -> ElementType {
+        each!(self, |a| element_type_of(a))
+    }
+
+    pub fn shape(&self) -> &[usize] {
+        each!(self, |a| a.shape())
+    }
+
+    fn reshape(&self, shape: &[usize]) -> Result<Self, Error> {
+        each!(self, |a| reshape(a, shape).map(HostArray::from))
+    }
+
+    fn index_axis0(&self, index: usize) -> Self {
+        each!(self, |a| a.index_axis(Axis(0), index).to_owned().into())
+    }
+
+    fn scalar_index(&self) -> Result<i64, Error> {
+        dispatch!(self, int, |a| match a.iter().next() {
+            Some(index) if a.len() == 1 => Ok(index.to_i128() as i64),
+            _ => Err(Error::ShapeMismatch),
+        })
+    }
+
+    fn indices(&self) -> Result<ArrayD<i64>, Error> {
+        dispatch!(self, int, |a| Ok(a.mapv(|index| index.to_i128() as i64)))
+    }
+
+    fn scalar_pred(&self) -> Result<bool, Error> {
+        dispatch!(self, [Pred], |a| match a.iter().next() {
+            Some(pred) if a.len() == 1 => Ok(*pred),
+            _ => Err(Error::ShapeMismatch),
+        })
+    }
+}
+
+impl Value {
+    /// Copies a literal onto the host, decomposing tuples into [`Value::Tuple`].
+    pub fn from_literal(mut literal: Literal) -> Result<Self, Error> {
+        match literal.shape()? {
+            xla::Shape::Array(_) => HostArray::from_literal(&literal).map(Value::Array),
+            xla::Shape::Tuple(_) => literal
+                .decompose_tuple()?
+                .into_iter()
+                .map(Value::from_literal)
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Tuple),
+        }
+    }
+
+    /// Copies an array value into a literal. Tuples aren't supported, since xla-rs can't build
+    /// tuple literals.
+    pub fn to_literal(&self) -> Result<Literal, Error> {
+        self.as_array()?.to_literal()
+    }
+
+    pub fn as_array(&self) -> Result<&HostArray, Error> {
+        match self {
+            Value::Array(array) => Ok(array),
+            Value::Tuple(_) => Err(Error::UnexpectedTuple),
+        }
+    }
+
+    pub fn into_array(self) -> Result<HostArray, Error> {
+        match self {
+            Value::Array(array) => Ok(array),
+            Value::Tuple(_) => Err(Error::UnexpectedTuple),
+        }
+    }
+}
+
+type Trace = Box<dyn FnMut(&Noxpr, &Value)>;
+
+/// Evaluates [`Noxpr`] graphs on the host.
+///
+/// Every evaluated node is cached by id, so shared sub-expressions are only evaluated once, and
+/// their values can be looked up afterwards with [`Interpreter::value`].
+#[derive(Default)]
+pub struct Interpreter {
+    cache: HashMap<NoxprId, Value>,
+    trace: Option<Trace>,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Calls `trace` with each node and its value as soon as the node is evaluated, including
+    /// the nodes inside scan, cond and while bodies.
+    pub fn with_trace(mut self, trace: impl FnMut(&Noxpr, &Value) + 'static) -> Self {
+        self.trace = Some(Box::new(trace));
+        self
+    }
+
+    /// Binds a parameter to a value. Any other node can be bound as well, which overrides its
+    /// value for the rest of the evaluation.
+    pub fn bind(&mut self, param: &Noxpr, value: impl Into<Value>) {
+        self.cache.insert(param.id(), value.into());
+    }
+
+    /// Returns the value of a node that has already been evaluated.
+    pub fn value(&self, expr: &Noxpr) -> Option<&Value> {
+        self.cache.get(&expr.id())
+    }
+
+    /// Evaluates `func` with `args` bound to its parameters, in a fresh scope.
+    pub fn call(&mut self, func: &NoxprFn, args: Vec<Value>) -> Result<Value, Error> {
+        if func.args.len() != args.len() {
+            return Err(Error::WrongArgCount(func.args.len()));
+        }
+        let mut inner = Interpreter {
+            cache: HashMap::new(),
+            trace: self.trace.take(),
+        };
+        for (param, value) in func.args.iter().zip(args) {
+            inner.bind(param, value);
+        }
+        let out = inner.visit(&func.inner);
+        self.trace = inner.trace.take();
+        out
+    }
+
+    pub fn visit(&mut self, expr: &Noxpr) -> Result<Value, Error> {
+        let id = expr.id();
+        if let Some(value) = self.cache.get(&id) {
+            return Ok(value.clone());
+        }
+
+        let value: Value = match expr.deref() {
+            NoxprNode::Param(p) => return Err(Error::UnboundParam(p.name.clone())),
+            NoxprNode::Tuple(tuple) => Value::Tuple(
+                tuple
+                    .iter()
+                    .map(|n| self.visit(n))
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            NoxprNode::GetTupleElement(g) => match self.visit(&g.expr)? {
+                Value::Tuple(mut elems) if g.index < elems.len() => elems.swap_remove(g.index),
+                Value::Tuple(_) => return Err(Error::OutOfBoundsAccess),
+                Value::Array(_) => return Err(Error::GetTupleElemWrongType),
+            },
+            NoxprNode::Constant(c) => {
+                let shape = dims(&c.ty.shape)?;
+                HostArray::from_literal(&c.data)?.reshape(&shape)?.into()
+            }
+            NoxprNode::Iota(i) => {
+                let shape = dims(&i.shape.shape)?;
+                if i.dim >= shape.len() {
+                    return Err(Error::ShapeMismatch);
+                }
+                with_element_type!(i.shape.element_type, T => {
+                    let array = ArrayD::from_shape_fn(IxDyn(&shape), |index| {
+                        T::from_i128(index[i.dim] as i128)
+                    });
+                    Value::from(array)
+                })
+            }
+            NoxprNode::Add(b) => elementwise!(self, b, numeric, Arith::add),
+            NoxprNode::Sub(b) => elementwise!(self, b, numeric, Arith::sub),
+            NoxprNode::Mul(b) => elementwise!(self, b, numeric, Arith::mul),
+            NoxprNode::Div(b) => elementwise!(self, b, numeric, Arith::div),
+            NoxprNode::And(b) => elementwise!(self, b, bits, BitAnd::bitand),
+            NoxprNode::Or(b) => elementwise!(self, b, bits, BitOr::bitor),
+            NoxprNode::Xor(b) => elementwise!(self, b, bits, BitXor::bitxor),
+            NoxprNode::ShiftLeft(b) => elementwise!(self, b, int, Shift::shift_left),
+            NoxprNode::ShiftRightLogical(b) => {
+                elementwise!(self, b, int, Shift::shift_right_logical)
+            }
+            NoxprNode::GreaterOrEqual(b) => elementwise!(self, b, all, |x, y| x >= y),
+            NoxprNode::LessOrEqual(b) => elementwise!(self, b, all, |x, y| x <= y),
+            NoxprNode::Less(b) => elementwise!(self, b, all, |x, y| x < y),
+            NoxprNode::Greater(b) => elementwise!(self, b, all, |x, y| x > y),
+            NoxprNode::Equal(b) => elementwise!(self, b, all, |x, y| x == y),
+            NoxprNode::NotEqual(b) => elementwise!(self, b, all, |x, y| x != y),
+            NoxprNode::Atan2(b) => elementwise!(self, b, float, Float::atan2),
+            NoxprNode::Pow(b) => elementwise!(self, b, float, Float::powf),
+            NoxprNode::Max(b) => elementwise!(self, b, numeric, max),
+            NoxprNode::Min(b) => elementwise!(self, b, numeric, min),
+            NoxprNode::Dot(b) => {
+                let lhs = self.visit_array(&b.lhs)?;
+                let rhs = self.visit_array(&b.rhs)?;
+                if lhs.shape().is_empty() || lhs.shape().len() > 2 || rhs.shape().len() > 2 {
+                    return Err(Error::ShapeMismatch);
+                }
+                // dot contracts the last dimension of lhs with the first dimension of rhs
+                let dimensions = DotDimensionNums {
+                    lhs_contracting_dimensions: smallvec![lhs.shape().len() as i64 - 1],
+                    rhs_contracting_dimensions: smallvec![0],
+                    ..Default::default()
+                };
+                dispatch2!(&lhs, &rhs, numeric, |a, b| {
+                    dot_general(a, b, &dimensions)?.into()
+                })
+            }
+            NoxprNode::DotGeneral(d) => {
+                let lhs = self.visit_array(&d.lhs)?;
+                let rhs = self.visit_array(&d.rhs)?;
+                dispatch2!(&lhs, &rhs, numeric, |a, b| {
+                    dot_general(a, b, &d.dimensions)?.into()
+                })
+            }
+            NoxprNode::Sqrt(e) => unary!(self, e, float, Float::sqrt),
+            NoxprNode::Log(e) => unary!(self, e, float, Float::ln),
+            NoxprNode::Sin(e) => unary!(self, e, float, Float::sin),
+            NoxprNode::Cos(e) => unary!(self, e, float, Float::cos),
+            NoxprNode::Exp(e) => unary!(self, e, float, Float::exp),
+            NoxprNode::Tanh(e) => unary!(self, e, float, Float::tanh),
+            NoxprNode::Floor(e) => unary!(self, e, float, Float::floor),
+            NoxprNode::Ceil(e) => unary!(self, e, float, Float::ceil),
+            NoxprNode::Rsqrt(e) => unary!(self, e, float, |x| Float::sqrt(x).recip()),
+            NoxprNode::Neg(e) => unary!(self, e, numeric, Arith::neg),
+            NoxprNode::Abs(e) => unary!(self, e, numeric, Arith::abs),
+            NoxprNode::Convert(c) => {
+                let array = self.visit_array(&c.expr)?;
+                with_element_type!(c.element_type, T => {
+                    Value::Array(each!(&array, |a| convert::<_, T>(a).into()))
+                })
+            }
+            NoxprNode::Select(s) => {
+                let cond = self.visit_array(&s.cond)?;
+                let on_true = self.visit_array(&s.on_true)?;
+                let on_false = self.visit_array(&s.on_false)?;
+                let HostArray::Pred(cond) = &cond else {
+                    return Err(Error::IncompatibleDType);
+                };
+                dispatch2!(&on_true, &on_false, all, |t, f| {
+                    zip3_with(cond, t, f, |c, t, f| if c { t } else { f })?.into()
+                })
+            }
+            NoxprNode::Clamp(c) => {
+                let lo = self.visit_array(&c.min)?;
+                let expr = self.visit_array(&c.expr)?;
+                let hi = self.visit_array(&c.max)?;
+                dispatch2!(&lo, &hi, numeric, |lo, hi| {
+                    let expr = same_type(lo, &expr)?;
+                    zip3_with(lo, expr, hi, |lo, x, hi| max(lo, min(x, hi)))?.into()
+                })
+            }
+            NoxprNode::Concat(concat) => {
+                let arrays = concat
+                    .nodes
+                    .iter()
+                    .map(|n| self.visit_array(n))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let first = arrays.first().ok_or(Error::ShapeMismatch)?;
+                each!(first, |a| {
+                    let views = arrays
+                        .iter()
+                        .map(|array| same_type(a, array).map(ArrayD::view))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    ndarray::concatenate(Axis(concat.dimension), &views)
+                        .map_err(|_| Error::ShapeMismatch)?
+                        .into()
+                })
+            }
+            NoxprNode::Reshape(r) => {
+                let shape = dims(&r.new_sizes)?;
+                self.visit_array(&r.expr)?.reshape(&shape)?.into()
+            }
+            NoxprNode::Broadcast(b) => {
+                let array = self.visit_array(&b.expr)?;
+                let mut shape = dims(&b.sizes)?;
+                shape.extend_from_slice(array.shape());
+                each!(&array, |a| broadcast(a, &shape)?.to_owned().into())
+            }
+            NoxprNode::BroadcastInDim(b) => {
+                let array = self.visit_array(&b.expr)?;
+                let shape = dims(&b.sizes)?;
+                let broadcast_dims = dims(&b.broadcast_dims)?;
+                if broadcast_dims.len() != array.shape().len() {
+                    return Err(Error::ShapeMismatch);
+                }
+                let mut expanded = vec![1; shape.len()];
+                for (dim, len) in broadcast_dims.iter().zip(array.shape()) {
+                    *expanded.get_mut(*dim).ok_or(Error::ShapeMismatch)? = *len;
+                }
+                let array = array.reshape(&expanded)?;
+                each!(&array, |a| broadcast(a, &shape)?.to_owned().into())
+            }
+            NoxprNode::Transpose(t) => {
+                let array = self.visit_array(&t.expr)?;
+                let permutation = dims(&t.permutation)?;
+                let mut sorted = permutation.clone();
+                sorted.sort_unstable();
+                if !sorted.iter().copied().eq(0..array.shape().len()) {
+                    return Err(Error::ShapeMismatch);
+                }
+                each!(&array, |a| a
+                    .view()
+                    .permuted_axes(IxDyn(&permutation))
+                    .to_owned()
+                    .into())
+            }
+            NoxprNode::Slice(s) => {
+                let array = self.visit_array(&s.expr)?;
+                let start = dims(&s.start_indices)?;
+                let stop = dims(&s.stop_indices)?;
+                let strides = dims(&s.strides)?;
+                each!(&array, |a| slice(a, &start, &stop, &strides)?.into())
+            }
+            NoxprNode::DynamicSlice(d) => {
+                let array = self.visit_array(&d.expr)?;
+                let sizes = dims(&d.size_indices)?;
+                let start = self.visit_indices(&d.start_indices)?;
+                let start = clamp_start(array.shape(), &start, &sizes)?;
+                let stop = start
+                    .iter()
+                    .zip(&sizes)
+                    .map(|(s, l)| s + l)
+                    .collect::<Vec<_>>();
+                let strides = vec![1; sizes.len()];
+                each!(&array, |a| slice(a, &start, &stop, &strides)?.into())
+            }
+            NoxprNode::DynamicUpdateSlice(d) => {
+                let array = self.visit_array(&d.expr)?;
+                let update = self.visit_array(&d.update)?;
+                let start = self.visit_indices(&d.start_indicies)?;
+                let start = clamp_start(array.shape(), &start, update.shape())?;
+                dispatch2!(&array, &update, all, |a, u| {
+                    let mut out = a.clone();
+                    out.slice_each_axis_mut(|axis| {
+                        let d = axis.axis.index();
+                        NdSlice::from(start[d]..start[d] + u.shape()[d])
+                    })
+                    .assign(u);
+                    out.into()
+                })
+            }
+            NoxprNode::Gather(g) => {
+                let array = self.visit_array(&g.expr)?;
+                let indices = self.visit_array(&g.indices)?.indices()?;
+                each!(&array, |a| gather(a, &indices, g)?.into())
+            }
+            NoxprNode::Scatter(s) => {
+                let array = self.visit_array(&s.expr)?;
+                let indices = self.visit_array(&s.indices)?.indices()?;
+                let updates = self.visit_array(&s.updates)?;
+                dispatch2!(&array, &updates, numeric, |a, u| {
+                    scatter_add(a, &indices, u, s)?.into()
+                })
+            }
+            NoxprNode::Reduce(r) => {
+                let array = self.visit_array(&r.expr)?;
+                let dims = dims(&r.dims)?;
+                dispatch!(&array, numeric, |a| reduce(a, r.op, &dims)?.into())
+            }
+            NoxprNode::Cholesky(e) => {
+                let array = self.visit_array(e)?;
+                dispatch!(&array, real, |a| {
+                    map_matrices(a, |m| m.cholesky().map(|c| c.l()))?.into()
+                })
+            }
+            NoxprNode::TriangularSolve(t) => {
+                let a = self.visit_array(&t.a)?;
+                let b = self.visit_array(&t.b)?;
+                dispatch2!(&a, &b, real, |a, b| {
+                    triangular_solve(a, b, t.left_side, t.lower, t.unit_diagonal, t.transpose_a)?
+                        .into()
+                })
+            }
+            NoxprNode::Qr(e) => {
+                let array = self.visit_array(e)?;
+                dispatch!(&array, real, |a| qr(a)?)
+            }
+            NoxprNode::Eigh(e) => {
+                let array = self.visit_array(e)?;
+                dispatch!(&array, real, |a| eigh(a)?)
+            }
+            NoxprNode::Svd(e) => {
+                let array = self.visit_array(e)?;
+                dispatch!(&array, real, |a| svd(a)?)
+            }
+            #[cfg(feature = "jax")]
+            NoxprNode::Jax(_) => return Err(Error::UnsupportedEval("Jax")),
+            NoxprNode::Scan(s) => {
+                let inputs = s
+                    .inputs
+                    .iter()
+                    .map(|i| self.visit_array(i))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let len = *inputs
+                    .first()
+                    .ok_or(Error::ScanMissingArg)?
+                    .shape()
+                    .first()
+                    .ok_or(Error::ScanShapeMismatch)?;
+                if inputs.iter().any(|i| i.shape().first() != Some(&len)) {
+                    return Err(Error::ScanShapeMismatch);
+                }
+                let mut state = self.visit(&s.initial_state)?;
+                for i in 0..len {
+                    let mut args = vec![state];
+                    args.extend(inputs.iter().map(|x| Value::Array(x.index_axis0(i))));
+                    state = self.call(&s.scan_fn, args)?;
+                }
+                state
+            }
+            NoxprNode::Cond(c) => {
+                let pred = self.visit_array(&c.pred)?.scalar_pred()?;
+                let operands = c
+                    .operands
+                    .iter()
+                    .map(|o| self.visit(o))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let branch = if pred { &c.on_true } else { &c.on_false };
+                self.call(branch, operands)?
+            }
+            NoxprNode::While(w) => {
+                let mut state = self.visit(&w.initial_state)?;
+                while self
+                    .call(&w.cond_fn, vec![state.clone()])?
+                    .as_array()?
+                    .scalar_pred()?
+                {
+                    state = self.call(&w.body_fn, vec![state])?;
+                }
+                state
+            }
+        };
+        if let Some(trace) = &mut self.trace {
+            trace(expr, &value);
+        }
+        self.cache.insert(id, value.clone());
+        Ok(value)
+    }
+
+    fn visit_array(&mut self, expr: &Noxpr) -> Result<HostArray, Error> {
+        self.visit(expr)?.into_array()
+    }
+
+    fn visit_indices(&mut self, exprs: &[Noxpr]) -> Result<Vec<i64>, Error> {
+        exprs
+            .iter()
+            .map(|e| self.visit_array(e)?.scalar_index())
+            .collect()
+    }
+}
+
+impl NoxprFn {
+    /// Evaluates the function on the host with the [`Interpreter`].
+    pub fn eval(&self, args: Vec<Value>) -> Result<Value, Error> {
+        Interpreter::new().call(self, args)
+    }
+}
+
+fn element_type_of<T: HostElement>(_: &ArrayD<T>) -> ElementType {
+    T::TY
+}
+
+fn decode<T: HostElement>(bytes: &[u8], shape: &[usize]) -> Result<ArrayD<T>, Error> {
+    let data = bytes
+        .chunks_exact(std::mem::size_of::<T>())
+        .map(T::from_ne_slice)
+        .collect();
+    ArrayD::from_shape_vec(IxDyn(shape), data).map_err(|_| Error::ShapeMismatch)
+}
+
+fn encode<T: HostElement>(array: &ArrayD<T>) -> Result<Literal, Error> {
+    let mut bytes = Vec::with_capacity(array.len() * std::mem::size_of::<T>());
+    array.iter().for_each(|x| x.extend_ne_bytes(&mut bytes));
+    let dims = array.shape().iter().map(|d| *d as i64).collect::<Vec<_>>();
+    Ok(Literal::create_from_raw_buf(T::TY, &dims, &bytes)?)
+}
+
+fn dims(dims: &[i64]) -> Result<Vec<usize>, Error> {
+    dims.iter()
+        .map(|d| usize::try_from(*d).map_err(|_| Error::ShapeMismatch))
+        .collect()
+}
+
+/// Returns `other` as an array of the same element type as `like`.
+fn same_type<'a, T: HostElement>(
+    _like: &ArrayD<T>,
+    other: &'a HostArray,
+) -> Result<&'a ArrayD<T>, Error> {
+    T::unwrap(other).ok_or(Error::IncompatibleDType)
+}
+
+fn reshape<T: Copy>(array: &ArrayD<T>, shape: &[usize]) -> Result<ArrayD<T>, Error> {
+    ArrayD::from_shape_vec(IxDyn(shape), array.iter().copied().collect())
+        .map_err(|_| Error::ShapeMismatch)
+}
+
+fn broadcast<'a, T>(array: &'a ArrayD<T>, shape: &[usize]) -> Result<ArrayViewD<'a, T>, Error> {
+    array.broadcast(IxDyn(shape)).ok_or(Error::ShapeMismatch)
+}
+
+/// Computes the shape two operands broadcast to, following numpy's rules.
+fn broadcast_shape(lhs: &[usize], rhs: &[usize]) -> Result<Vec<usize>, Error> {
+    let rank = lhs.len().max(rhs.len());
+    let dim =
+        |shape: &[usize], i: usize| (i + shape.len()).checked_sub(rank).map_or(1, |i| shape[i]);
+    (0..rank)
+        .map(|i| match (dim(lhs, i), dim(rhs, i)) {
+            (l, r) if l == r || r == 1 => Ok(l),
+            (1, r) => Ok(r),
+            _ => Err(Error::ShapeMismatch),
+        })
+        .collect()
+}
+
+fn zip_with<T: Copy, U>(
+    lhs: &ArrayD<T>,
+    rhs: &ArrayD<T>,
+    f: impl Fn(T, T) -> U,
+) -> Result<ArrayD<U>, Error> {
+    let shape = broadcast_shape(lhs.shape(), rhs.shape())?;
+    let (lhs, rhs) = (broadcast(lhs, &shape)?, broadcast(rhs, &shape)?);
+    Ok(Zip::from(&lhs).and(&rhs).map_collect(|l, r| f(*l, *r)))
+}
+
+fn zip3_with<A: Copy, B: Copy, C: Copy, U>(
+    a: &ArrayD<A>,
+    b: &ArrayD<B>,
+    c: &ArrayD<C>,
+    f: impl Fn(A, B, C) -> U,
+) -> Result<ArrayD<U>, Error> {
+    let shape = broadcast_shape(&broadcast_shape(a.shape(), b.shape())?, c.shape())?;
+    let (a, b, c) = (
+        broadcast(a, &shape)?,
+        broadcast(b, &shape)?,
+        broadcast(c, &shape)?,
+    );
+    Ok(Zip::from(&a)
+        .and(&b)
+        .and(&c)
+        .map_collect(|a, b, c| f(*a, *b, *c)))
+}
+
+// xla's max and min propagate NaNs, unlike `f64::max`
+fn max<T: PartialOrd>(lhs: T, rhs: T) -> T {
+    if lhs > rhs || lhs.partial_cmp(&lhs).is_none() {
+        lhs
+    } else {
+        rhs
+    }
+}
+
+fn min<T: PartialOrd>(lhs: T, rhs: T) -> T {
+    if lhs < rhs || lhs.partial_cmp(&lhs).is_none() {
+        lhs
+    } else {
+        rhs
+    }
+}
+
+/// Converts like xla: floats go through `f64` and saturate, integers go through `i128` and wrap.
+fn convert<S: HostElement, T: HostElement>(array: &ArrayD<S>) -> ArrayD<T> {
+    array.mapv(|x| {
+        if S::FLOAT {
+            T::from_f64(x.to_f64())
+        } else {
+            T::from_i128(x.to_i128())
+        }
+    })
+}
+
+fn dot_general<T: Arith>(
+    lhs: &ArrayD<T>,
+    rhs: &ArrayD<T>,
+    dimensions: &DotDimensionNums,
+) -> Result<ArrayD<T>, Error> {
+    let lhs_batch = dims(&dimensions.lhs_batch_dimensions)?;
+    let rhs_batch = dims(&dimensions.rhs_batch_dimensions)?;
+    let lhs_contract = dims(&dimensions.lhs_contracting_dimensions)?;
+    let rhs_contract = dims(&dimensions.rhs_contracting_dimensions)?;
+    let sizes_match = |l: &[usize], r: &[usize]| {
+        l.len() == r.len()
+            && l.iter().zip(r).all(|(l, r)| {
+                *l < lhs.ndim() && *r < rhs.ndim() && lhs.shape()[*l] == rhs.shape()[*r]
+            })
+    };
+    if !sizes_match(&lhs_batch, &rhs_batch) || !sizes_match(&lhs_contract, &rhs_contract) {
+        return Err(Error::ShapeMismatch);
+    }
+    let free = |rank: usize, batch: &[usize], contract: &[usize]| {
+        (0..rank)
+            .filter(|d| !batch.contains(d) && !contract.contains(d))
+            .collect::<Vec<_>>()
+    };
+    let lhs_free = free(lhs.ndim(), &lhs_batch, &lhs_contract);
+    let rhs_free = free(rhs.ndim(), &rhs_batch, &rhs_contract);
+
+    let out_shape = lhs_batch
+        .iter()
+        .chain(&lhs_free)
+        .map(|d| lhs.shape()[*d])
+        .chain(rhs_free.iter().map(|d| rhs.shape()[*d]))
+        .collect::<Vec<_>>();
+    let contract_shape = lhs_contract
+        .iter()
+        .map(|d| lhs.shape()[*d])
+        .collect::<Vec<_>>();
+    let contract_len = contract_shape.iter().product::<usize>();
+
+    Ok(ArrayD::from_shape_fn(IxDyn(&out_shape), |out| {
+        let mut li = vec![0; lhs.ndim()];
+        let mut ri = vec![0; rhs.ndim()];
+        let mut out = out.slice().iter().copied();
+        for (l, r) in lhs_batch.iter().zip(&rhs_batch) {
+            let i = out.next().unwrap_or_default();
+            li[*l] = i;
+            ri[*r] = i;
+        }
+        for l in &lhs_free {
+            li[*l] = out.next().unwrap_or_default();
+        }
+        for r in &rhs_free {
+            ri[*r] = out.next().unwrap_or_default();
+        }
+        let mut acc = T::ZERO;
+        for mut c in 0..contract_len {
+            for ((l, r), len) in lhs_contract
+                .iter()
+                .zip(&rhs_contract)
+                .zip(&contract_shape)
+                .rev()
+            {
+                li[*l] = c % len;
+                ri[*r] = c % len;
+                c /= len;
+            }
+            acc = acc.add(lhs[li.as_slice()].mul(rhs[ri.as_slice()]));
+        }
+        acc
+    }))
+}
+
+fn slice<T: Copy>(
+    array: &ArrayD<T>,
+    start: &[usize],
+    stop: &[usize],
+    strides: &[usize],
+) -> Result<ArrayD<T>, Error> {
+    let rank = array.ndim();
+    if start.len() != rank || stop.len() != rank || strides.len() != rank {
+        return Err(Error::ShapeMismatch);
+    }
+    for d in 0..rank {
+        if start[d] > stop[d] || stop[d] > array.shape()[d] || strides[d] == 0 {
+            return Err(Error::ShapeMismatch);
+        }
+    }
+    Ok(array
+        .slice_each_axis(|axis| {
+            let d = axis.axis.index();
+            NdSlice::new(
+                start[d] as isize,
+                Some(stop[d] as isize),
+                strides[d] as isize,
+            )
+        })
+        .to_owned())
+}
+
+/// Clamps dynamic slice offsets so the slice stays in bounds, like xla does.
+fn clamp_start(shape: &[usize], start: &[i64], sizes: &[usize]) -> Result<Vec<usize>, Error> {
+    if start.len() != shape.len() || sizes.len() != shape.len() {
+        return Err(Error::ShapeMismatch);
+    }
+    shape
+        .iter()
+        .zip(start)
+        .zip(sizes)
+        .map(|((dim, start), size)| {
+            let max = dim.checked_sub(*size).ok_or(Error::ShapeMismatch)?;
+            Ok((*start).clamp(0, max as i64) as usize)
+        })
+        .collect()
+}
+
+/// Moves the index vector dimension of gather and scatter indices to the end, adding it when
+/// the indices are scalars.
+fn index_vectors(indices: &ArrayD<i64>, index_vector_dim: i64) -> Result<ArrayD<i64>, Error> {
+    let dim = usize::try_from(index_vector_dim).map_err(|_| Error::ShapeMismatch)?;
+    let mut indices = indices.view();
+    if dim == indices.ndim() {
+        indices.insert_axis_inplace(Axis(dim));
+    } else if dim > indices.ndim() {
+        return Err(Error::ShapeMismatch);
+    }
+    let mut permutation = (0..indices.ndim())
+        .filter(|d| *d != dim)
+        .collect::<Vec<_>>();
+    permutation.push(dim);
+    Ok(indices.permuted_axes(IxDyn(&permutation)).to_owned())
+}
+
+fn gather<T: Copy>(
+    array: &ArrayD<T>,
+    indices: &ArrayD<i64>,
+    g: &Gather,
+) -> Result<ArrayD<T>, Error> {
+    let indices = index_vectors(indices, g.index_vector_dim)?;
+    let offset_dims = dims(&g.offset_dims)?;
+    let collapsed = dims(&g.collapsed_slice_dims)?;
+    let start_index_map = dims(&g.start_index_map)?;
+    let slice_sizes = dims(&g.slice_sizes)?;
+    let window_dims = (0..array.ndim())
+        .filter(|d| !collapsed.contains(d))
+        .collect::<Vec<_>>();
+    let (batch_shape, index_len) = indices.shape().split_at(indices.ndim() - 1);
+    if index_len != [start_index_map.len()]
+        || start_index_map.iter().any(|d| *d >= array.ndim())
+        || slice_sizes.len() != array.ndim()
+        || slice_sizes.iter().zip(array.shape()).any(|(s, d)| s > d)
+        || collapsed.iter().any(|d| slice_sizes.get(*d) != Some(&1))
+        || offset_dims.len() != window_dims.len()
+    {
+        return Err(Error::ShapeMismatch);
+    }
+
+    let out_rank = batch_shape.len() + offset_dims.len();
+    let mut batch = batch_shape.iter();
+    let mut window = window_dims.iter();
+    let out_shape = (0..out_rank)
+        .map(|d| {
+            let len = if offset_dims.contains(&d) {
+                window.next().map(|w| slice_sizes[*w])
+            } else {
+                batch.next().copied()
+            };
+            len.ok_or(Error::ShapeMismatch)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(ArrayD::from_shape_fn(IxDyn(&out_shape), |out| {
+        let out = out.slice();
+        let mut index = Vec::with_capacity(indices.ndim());
+        let mut offsets = Vec::with_capacity(window_dims.len());
+        for (d, i) in out.iter().enumerate() {
+            if offset_dims.contains(&d) {
+                offsets.push(*i);
+            } else {
+                index.push(*i);
+            }
+        }
+        index.push(0);
+        let mut operand_index = vec![0; array.ndim()];
+        for (k, operand_dim) in start_index_map.iter().enumerate() {
+            *index.last_mut().expect("index vector dim") = k;
+            let max = array.shape()[*operand_dim] - slice_sizes[*operand_dim];
+            operand_index[*operand_dim] = indices[index.as_slice()].clamp(0, max as i64) as usize;
+        }
+        for (window_dim, offset) in window_dims.iter().zip(offsets) {
+            operand_index[*window_dim] += offset;
+        }
+        array[operand_index.as_slice()]
+    }))
+}
+
+fn scatter_add<T: Arith>(
+    array: &ArrayD<T>,
+    indices: &ArrayD<i64>,
+    updates: &ArrayD<T>,
+    s: &Scatter,
+) -> Result<ArrayD<T>, Error> {
+    let indices = index_vectors(indices, s.index_vector_dim)?;
+    let update_window_dims = dims(&s.update_window_dims)?;
+    let inserted = dims(&s.inserted_window_dims)?;
+    let scatter_dims = dims(&s.scatter_dims_to_operand_dims)?;
+    let window_dims = (0..array.ndim())
+        .filter(|d| !inserted.contains(d))
+        .collect::<Vec<_>>();
+    let update_scatter_dims = (0..updates.ndim())
+        .filter(|d| !update_window_dims.contains(d))
+        .collect::<Vec<_>>();
+    if indices.shape().last() != Some(&scatter_dims.len())
+        || scatter_dims.iter().any(|d| *d >= array.ndim())
+        || window_dims.len() != update_window_dims.len()
+        || update_window_dims.iter().any(|d| *d >= updates.ndim())
+        || update_scatter_dims.len() + 1 != indices.ndim()
+    {
+        return Err(Error::ShapeMismatch);
+    }
+    let mut window_size = vec![1; array.ndim()];
+    for (window_dim, update_dim) in window_dims.iter().zip(&update_window_dims) {
+        window_size[*window_dim] = updates.shape()[*update_dim];
+    }
+
+    let mut out = array.clone();
+    let mut index = vec![0; indices.ndim()];
+    for (update_index, update) in updates.indexed_iter() {
+        let update_index = update_index.slice();
+        for (i, d) in update_scatter_dims.iter().enumerate() {
+            index[i] = update_index[*d];
+        }
+        let mut start = vec![0i64; array.ndim()];
+        for (k, operand_dim) in scatter_dims.iter().enumerate() {
+            *index.last_mut().expect("index vector dim") = k;
+            start[*operand_dim] = indices[index.as_slice()];
+        }
+        // xla skips updates whose window doesn't fit in the operand
+        let in_bounds = start
+            .iter()
+            .zip(&window_size)
+            .zip(array.shape())
+            .all(|((s, w), d)| *s >= 0 && (*s as usize) + w <= *d);
+        if !in_bounds {
+            continue;
+        }
+        let mut operand_index = start.iter().map(|s| *s as usize).collect::<Vec<_>>();
+        for (window_dim, update_dim) in window_dims.iter().zip(&update_window_dims) {
+            operand_index[*window_dim] += update_index[*update_dim];
+        }
+        let elem = &mut out[operand_index.as_slice()];
+        *elem = Arith::add(*elem, *update);
+    }
+    Ok(out)
+}
+
+fn reduce<T: Arith>(array: &ArrayD<T>, op: ReduceOp, dims: &[usize]) -> Result<ArrayD<T>, Error> {
+    let mut dims = dims.to_vec();
+    dims.sort_unstable_by(|a, b| b.cmp(a));
+    dims.dedup();
+    let (init, combine): (T, fn(T, T) -> T) = match op {
+        ReduceOp::Sum => (T::ZERO, Arith::add),
+        ReduceOp::Prod => (T::ONE, Arith::mul),
+        ReduceOp::Max => (T::LOWEST, max),
+        ReduceOp::Min => (T::HIGHEST, min),
+    };
+    let mut out = array.clone();
+    for dim in dims {
+        if dim >= out.ndim() {
+            return Err(Error::ShapeMismatch);
+        }
+        out = out.fold_axis(Axis(dim), init, |acc, x| combine(*acc, *x));
+    }
+    Ok(out)
+}
+
+/// Splits an array into its batch shape and the matrices in its two minor dimensions.
+fn matrices<T: HostElement + RealField>(
+    array: &ArrayD<T>,
+) -> Result<(&[usize], usize, usize, Vec<DMatrix<T>>), Error> {
+    let shape = array.shape();
+    if shape.len() < 2 {
+        return Err(Error::ShapeMismatch);
+    }
+    let (batch, last) = shape.split_at(shape.len() - 2);
+    let (rows, cols) = (last[0], last[1]);
+    let data = array.iter().copied().collect::<Vec<_>>();
+    let matrices = if rows * cols == 0 {
+        vec![DMatrix::zeros(rows, cols); batch.iter().product()]
+    } else {
+        data.chunks_exact(rows * cols)
+            .map(|chunk| DMatrix::from_row_slice(rows, cols, chunk))
+            .collect()
+    };
+    Ok((batch, rows, cols, matrices))
+}
+
+fn from_matrices<T: HostElement + RealField>(
+    batch: &[usize],
+    (rows, cols): (usize, usize),
+    matrices: &[DMatrix<T>],
+) -> ArrayD<T> {
+    let shape = batch
+        .iter()
+        .copied()
+        .chain([rows, cols])
+        .collect::<Vec<_>>();
+    let data = matrices
+        .iter()
+        .flat_map(|m| m.transpose().as_slice().to_vec())
+        .collect();
+    ArrayD::from_shape_vec(IxDyn(&shape), data).expect("matrix batch shape")
+}
+
+fn from_vectors<T: HostElement + RealField>(
+    batch: &[usize],
+    len: usize,
+    vectors: &[DVector<T>],
+) -> ArrayD<T> {
+    let shape = batch.iter().copied().chain([len]).collect::<Vec<_>>();
+    let data = vectors.iter().flat_map(|v| v.as_slice().to_vec()).collect();
+    ArrayD::from_shape_vec(IxDyn(&shape), data).expect("vector batch shape")
+}
+
+/// Applies `f` to every matrix in a batch, filling failed results with NaN like xla does.
+fn map_matrices<T: HostElement + RealField>(
+    array: &ArrayD<T>,
+    f: impl Fn(DMatrix<T>) -> Option<DMatrix<T>>,
+) -> Result<ArrayD<T>, Error> {
+    let (batch, rows, cols, matrices) = matrices(array)?;
+    let matrices = matrices
+        .into_iter()
+        .map(|m| f(m).unwrap_or_else(|| nan_matrix(rows, cols)))
+        .collect::<Vec<_>>();
+    Ok(from_matrices(batch, (rows, cols), &matrices))
+}
+
+fn nan_matrix<T: RealField>(rows: usize, cols: usize) -> DMatrix<T> {
+    DMatrix::from_element(rows, cols, nalgebra::convert(f64::NAN))
+}
+
+fn triangular_solve<T: HostElement + RealField>(
+    a: &ArrayD<T>,
+    b: &ArrayD<T>,
+    left_side: bool,
+    lower: bool,
+    unit_diagonal: bool,
+    transpose_a: bool,
+) -> Result<ArrayD<T>, Error> {
+    let (a_batch, n, a_cols, a) = matrices(a)?;
+    let (b_batch, rows, cols, b) = matrices(b)?;
+    let system_len = if left_side { rows } else { cols };
+    if n != a_cols || n != system_len || a_batch != b_batch {
+        return Err(Error::SolveShapeMismatch);
+    }
+    let x = a
+        .into_iter()
+        .zip(b)
+        .map(|(a, b)| {
+            // only the selected triangle of `a` is read, like xla
+            let a = DMatrix::from_fn(n, n, |i, j| match (i == j, i > j) {
+                (true, _) if unit_diagonal => T::one(),
+                (true, _) => a[(i, j)].clone(),
+                (false, below) if below == lower => a[(i, j)].clone(),
+                _ => T::zero(),
+            });
+            let a = if transpose_a { a.transpose() } else { a };
+            let x = if left_side {
+                a.lu().solve(&b)
+            } else {
+                a.transpose()
+                    .lu()
+                    .solve(&b.transpose())
+                    .map(|x| x.transpose())
+            };
+            x.unwrap_or_else(|| nan_matrix(rows, cols))
+        })
+        .collect::<Vec<_>>();
+    Ok(from_matrices(b_batch, (rows, cols), &x))
+}
+
+fn qr<T: HostElement + RealField>(array: &ArrayD<T>) -> Result<Value, Error> {
+    let (batch, rows, cols, matrices) = matrices(array)?;
+    if rows != cols {
+        return Err(Error::NonSquareMatrix);
+    }
+    let (q, r): (Vec<_>, Vec<_>) = matrices
+        .into_iter()
+        .map(|m| {
+            let qr = m.qr();
+            (qr.q(), qr.r())
+        })
+        .unzip();
+    Ok(Value::Tuple(vec![
+        from_matrices(batch, (rows, rows), &q).into(),
+        from_matrices(batch, (rows, cols), &r).into(),
+    ]))
+}
+
+fn eigh<T: HostElement + RealField>(array: &ArrayD<T>) -> Result<Value, Error> {
+    let (batch, n, cols, matrices) = matrices(array)?;
+    if n != cols {
+        return Err(Error::NonSquareMatrix);
+    }
+    let (vectors, values): (Vec<_>, Vec<_>) = matrices
+        .into_iter()
+        .map(|m| {
+            // mirror the lower triangle, since that's the only part xla reads
+            let m = DMatrix::from_fn(n, n, |i, j| m[(i.max(j), i.min(j))].clone());
+            let eigen = m.symmetric_eigen();
+            let mut order = (0..n).collect::<Vec<_>>();
+            order.sort_by(|a, b| {
+                eigen.eigenvalues[*a]
+                    .partial_cmp(&eigen.eigenvalues[*b])
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            let vectors = DMatrix::from_fn(n, n, |i, j| eigen.eigenvectors[(i, order[j])].clone());
+            let values = DVector::from_fn(n, |i, _| eigen.eigenvalues[order[i]].clone());
+            (vectors, values)
+        })
+        .unzip();
+    Ok(Value::Tuple(vec![
+        from_matrices(batch, (n, n), &vectors).into(),
+        from_vectors(batch, n, &values).into(),
+    ]))
+}
+
+fn svd<T: HostElement + RealField>(array: &ArrayD<T>) -> Result<Value, Error> {
+    let (batch, rows, cols, matrices) = matrices(array)?;
+    if rows != cols {
+        return Err(Error::NonSquareMatrix);
+    }
+    let mut u = Vec::with_capacity(matrices.len());
+    let mut s = Vec::with_capacity(matrices.len());
+    let mut vt = Vec::with_capacity(matrices.len());
+    for m in matrices {
+        // nalgebra sorts the singular values in descending order, matching xla
+        let svd = m.svd(true, true);
+        u.push(svd.u.unwrap_or_else(|| nan_matrix(rows, rows)));
+        vt.push(svd.v_t.unwrap_or_else(|| nan_matrix(cols, cols)));
+        s.push(svd.singular_values);
+    }
+    Ok(Value::Tuple(vec![
+        from_matrices(batch, (rows, rows), &u).into(),
+        from_vectors(batch, rows, &s).into(),
+        from_matrices(batch, (cols, cols), &vt).into(),
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+    use crate::{ArrayTy, Client, CompFn, Matrix, NoxprScalarExt, NoxprTy, Scalar, ToHost, Vector};
+
+    fn param(number: i64, element_type: ElementType, shape: &[i64]) -> Noxpr {
+        let ty = ArrayTy::new(element_type, shape.iter().copied().collect());
+        Noxpr::parameter(number, NoxprTy::ArrayTy(ty), format!("p{number}"))
+    }
+
+    fn arange(shape: &[usize]) -> HostArray {
+        let len = shape.iter().product::<usize>();
+        let values = (0..len)
+            .map(|i| ((i * 7) % 11) as f64 * 0.5 - 2.0)
+            .collect();
+        ArrayD::from_shape_vec(IxDyn(shape), values).unwrap().into()
+    }
+
+    fn assert_close(actual: &Value, expected: &Value) {
+        match (actual, expected) {
+            (Value::Tuple(actual), Value::Tuple(expected)) => {
+                assert_eq!(actual.len(), expected.len());
+                for (actual, expected) in actual.iter().zip(expected) {
+                    assert_close(actual, expected);
+                }
+            }
+            (Value::Array(HostArray::F64(actual)), Value::Array(HostArray::F64(expected))) => {
+                assert_eq!(actual.shape(), expected.shape());
+                for (actual, expected) in actual.iter().zip(expected) {
+                    approx::assert_relative_eq!(*actual, *expected, epsilon = 1e-9);
+                }
+            }
+            (actual, expected) => assert_eq!(actual, expected),
+        }
+    }
+
+    /// Runs `func` through both the interpreter and XLA, and checks that they agree.
+    fn assert_matches_xla(func: NoxprFn, args: Vec<HostArray>) {
+        let client = Client::cpu().unwrap();
+        let comp = func.build("differential").unwrap().build().unwrap();
+        let exec = client.compile(&comp).unwrap();
+        let buffers = args
+            .iter()
+            .map(|arg| client.copy_literal(&arg.to_literal().unwrap()).unwrap())
+            .collect::<Vec<_>>();
+        let mut refs = xla::BufferArgsRef::default();
+        for buffer in &buffers {
+            refs.push(buffer);
+        }
+        let out = exec
+            .execute_buffers(refs.untuple_result(true))
+            .unwrap()
+            .iter()
+            .map(|buffer| Value::from_literal(buffer.to_literal_sync().unwrap()).unwrap())
+            .collect::<Vec<_>>();
+        let actual = func
+            .eval(args.into_iter().map(Value::Array).collect())
+            .unwrap();
+        let expected = match actual {
+            Value::Tuple(_) => Value::Tuple(out),
+            Value::Array(_) => out.into_iter().next().unwrap(),
+        };
+        assert_close(&actual, &expected);
+    }
+
+    #[test]
+    fn test_eval_matches_xla() {
+        let client = Client::cpu().unwrap();
+        fn f(a: Matrix<f32, 2, 3>, b: Vector<f32, 3>) -> Vector<f32, 2> {
+            let c = a.dot(&b);
+            (c.clone() + c.exp()).sqrt()
+        }
+        let exec = f.build().unwrap().compile(&client).unwrap();
+        let expected = exec
+            .run(
+                &client,
+                nalgebra::matrix![1.0f32, 0.5, 0.25; 0.0, 1.0, 2.0],
+                nalgebra::vector![1.0f32, 2.0, 3.0],
+            )
+            .unwrap()
+            .to_host();
+
+        let out = f
+            .build_expr()
+            .unwrap()
+            .eval(vec![
+                array![[1.0f32, 0.5, 0.25], [0.0, 1.0, 2.0]]
+                    .into_dyn()
+                    .into(),
+                array![1.0f32, 2.0, 3.0].into_dyn().into(),
+            ])
+            .unwrap();
+        let Value::Array(HostArray::F32(out)) = out else {
+            panic!("expected an f32 array, found {:?}", out);
+        };
+        assert_eq!(out.shape(), &[2]);
+        for (out, expected) in out.iter().zip(expected.iter()) {
+            approx::assert_relative_eq!(*out, *expected, epsilon = 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_eval_scan() {
+        fn f(mat: Matrix<f32, 3, 2>) -> Vector<f32, 2> {
+            mat.scan(Vector::<f32, 2>::zeros(), |acc, x| acc + x)
+                .unwrap()
+        }
+        let out = f
+            .build_expr()
+            .unwrap()
+            .eval(vec![array![[1.0f32, 2.0], [3.0, 5.0], [6.0, 7.0]]
+                .into_dyn()
+                .into()])
+            .unwrap();
+        assert_eq!(out, Value::from(array![10.0f32, 14.0].into_dyn()));
+    }
+
+    #[test]
+    fn test_literal_round_trip() {
+        let array = HostArray::from(array![[1i64, -2], [3, 4]].into_dyn());
+        let literal = array.to_literal().unwrap();
+        assert_eq!(literal.typed_buf::<i64>().unwrap(), &[1, -2, 3, 4]);
+        assert_eq!(HostArray::from_literal(&literal).unwrap(), array);
+    }
+
+    #[test]
+    fn test_trace_and_step() {
+        use std::sync::{Arc, Mutex};
+
+        fn f(a: Scalar<f64>, b: Scalar<f64>) -> Scalar<f64> {
+            (a.clone() * b).log() + a
+        }
+        let func = f.build_expr().unwrap();
+        let visited = Arc::new(Mutex::new(0));
+        let mut interpreter = Interpreter::new().with_trace({
+            let visited = visited.clone();
+            move |_, _| *visited.lock().unwrap() += 1
+        });
+        for (arg, value) in func.args.iter().zip([2.0f64, 3.0]) {
+            interpreter.bind(arg, ndarray::arr0(value).into_dyn());
+        }
+        let out = interpreter.visit(&func.inner).unwrap();
+        assert_eq!(out, Value::from(ndarray::arr0(6f64.ln() + 2.0).into_dyn()));
+        // the params are bound rather than evaluated, leaving the mul, log and add
+        assert_eq!(*visited.lock().unwrap(), 3);
+        let NoxprNode::Add(add) = func.inner.deref() else {
+            panic!("expected an add node");
+        };
+        assert_eq!(
+            interpreter.value(&add.lhs),
+            Some(&Value::from(ndarray::arr0(6f64.ln()).into_dyn()))
+        );
+    }
+
+    #[test]
+    fn test_gather_scatter_match_xla() {
+        let x = param(0, ElementType::F64, &[4, 3]);
+        let indices = param(1, ElementType::S64, &[3, 1]);
+        let updates = param(2, ElementType::F64, &[3, 3]);
+        let gathered = x.clone().gather(
+            indices.clone(),
+            smallvec![1],
+            smallvec![0],
+            smallvec![0],
+            smallvec![1, 3],
+            1,
+        );
+        // scatter adds duplicate indices together and drops out of bounds windows
+        let scattered = x.clone().scatter(
+            indices.clone(),
+            updates.clone(),
+            smallvec![1],
+            smallvec![0],
+            smallvec![0],
+            1,
+        );
+        let func = NoxprFn::new(
+            vec![x, indices, updates],
+            Noxpr::tuple(vec![gathered, scattered]),
+        );
+        assert_matches_xla(
+            func,
+            vec![
+                arange(&[4, 3]),
+                array![[1i64], [1], [7]].into_dyn().into(),
+                arange(&[3, 3]),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_dot_general_match_xla() {
+        let lhs = param(0, ElementType::F64, &[2, 3, 4]);
+        let rhs = param(1, ElementType::F64, &[2, 4, 5]);
+        let out = lhs.clone().dot_general(
+            rhs.clone(),
+            DotDimensionNums {
+                lhs_contracting_dimensions: smallvec![2],
+                rhs_contracting_dimensions: smallvec![1],
+                lhs_batch_dimensions: smallvec![0],
+                rhs_batch_dimensions: smallvec![0],
+            },
+        );
+        let func = NoxprFn::new(vec![lhs, rhs], out);
+        assert_matches_xla(func, vec![arange(&[2, 3, 4]), arange(&[2, 4, 5])]);
+    }
+
+    #[test]
+    fn test_dynamic_slice_match_xla() {
+        let x = param(0, ElementType::F64, &[4, 5]);
+        let i = param(1, ElementType::S64, &[]);
+        let j = param(2, ElementType::S64, &[]);
+        let update = param(3, ElementType::F64, &[2, 2]);
+        // both ops clamp their start indices so the window stays in bounds
+        let sliced = x.dynamic_slice(vec![i.clone(), j.clone()], smallvec![2, 3]);
+        let updated = x.dynamic_update_slice(vec![j.clone(), i.clone()], update.clone());
+        let func = NoxprFn::new(vec![x, i, j, update], Noxpr::tuple(vec![sliced, updated]));
+        assert_matches_xla(
+            func,
+            vec![
+                arange(&[4, 5]),
+                ndarray::arr0(3i64).into_dyn().into(),
+                ndarray::arr0(-1i64).into_dyn().into(),
+                arange(&[2, 2]),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_reduce_transpose_broadcast_match_xla() {
+        let x = param(0, ElementType::F64, &[2, 3, 4]);
+        let v = param(1, ElementType::F64, &[3]);
+        let out = Noxpr::tuple(vec![
+            x.clone().reduce(ReduceOp::Sum, smallvec![0, 2]),
+            x.clone().reduce(ReduceOp::Prod, smallvec![1]),
+            x.clone().reduce(ReduceOp::Max, smallvec![2]),
+            x.clone().reduce(ReduceOp::Min, smallvec![0]),
+            x.clone().transpose(smallvec![2, 0, 1]),
+            v.clone().broadcast_in_dim(smallvec![2, 3, 4], smallvec![1]),
+        ]);
+        let func = NoxprFn::new(vec![x, v], out);
+        assert_matches_xla(func, vec![arange(&[2, 3, 4]), arange(&[3])]);
+    }
+
+    #[test]
+    fn test_control_flow_match_xla() {
+        let x = param(0, ElementType::F64, &[]);
+        let pred = param(1, ElementType::Pred, &[]);
+        let state = param(0, ElementType::F64, &[]);
+        let cond_fn = NoxprFn::new(vec![state.clone()], state.less(100f64.constant()));
+        let state = param(0, ElementType::F64, &[]);
+        let body_fn = NoxprFn::new(
+            vec![state.clone()],
+            state * 2f64.constant() + 1f64.constant(),
+        );
+        let looped = Noxpr::while_loop(x.clone(), cond_fn, body_fn);
+        let operand = param(0, ElementType::F64, &[]);
+        let on_true = NoxprFn::new(vec![operand.clone()], operand * 3f64.constant());
+        let operand = param(0, ElementType::F64, &[]);
+        let on_false = NoxprFn::new(vec![operand.clone()], operand - 1f64.constant());
+        let branched = Noxpr::cond(pred.clone(), vec![x.clone()], on_true, on_false);
+        let func = NoxprFn::new(vec![x, pred], Noxpr::tuple(vec![looped, branched]));
+        for pred in [true, false] {
+            assert_matches_xla(
+                func.clone(),
+                vec![
+                    ndarray::arr0(1.5f64).into_dyn().into(),
+                    ndarray::arr0(pred).into_dyn().into(),
+                ],
+            );
+        }
+    }
+
+    #[test]
+    fn test_decompositions_match_xla() {
+        let a = param(0, ElementType::F64, &[3, 3]);
+        let qr = a.clone().qr();
+        let eigh = a.clone().eigh();
+        let svd = a.clone().svd();
+        // the columns of q, u and v and the eigenvectors are only unique up to sign
+        let out = Noxpr::tuple(vec![
+            a.clone().cholesky(),
+            qr.get_tuple_element(0).abs(),
+            qr.get_tuple_element(1).abs(),
+            eigh.get_tuple_element(0).abs(),
+            eigh.get_tuple_element(1),
+            svd.get_tuple_element(0).abs(),
+            svd.get_tuple_element(1),
+            svd.get_tuple_element(2).abs(),
+        ]);
+        let func = NoxprFn::new(vec![a], out);
+        assert_matches_xla(
+            func,
+            vec![array![[4.0f64, 1.0, 0.5], [1.0, 3.0, 0.2], [0.5, 0.2, 2.0]]
+                .into_dyn()
+                .into()],
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use pyo3::{
     exceptions::PyValueError,
     types::{PyDict, PyTuple},
-    PyObject, PyResult, Python,
+    IntoPy, Py, PyObject, PyResult, Python,
 };
 use smallvec::SmallVec;
 use std::{collections::HashMap, ops::Deref};
@@ -52,19 +52,27 @@ impl JaxTracer {
                 xla::ElementType::U32 => literal_to_arr::<u32>(&c.data, &c.ty.shape, &self.jnp)?,
                 xla::ElementType::U64 => literal_to_arr::<u64>(&c.data, &c.ty.shape, &self.jnp)?,
                 xla::ElementType::F32 => literal_to_arr::<f32>(&c.data, &c.ty.shape, &self.jnp)?,
-                // xla::ElementType::F16 => literal_to_arr::<F16>(&c.data, &c.ty.shape, &self.jnp)?,
-                // xla::ElementType::Bf16 => literal_to_arr::<Bf16>(&c.data, &c.ty.shape, &self.jnp)?,
+                xla::ElementType::F16 => {
+                    literal_to_arr::<half::f16>(&c.data, &c.ty.shape, &self.jnp)?
+                }
+                xla::ElementType::Bf16 => {
+                    literal_to_arr::<half::bf16>(&c.data, &c.ty.shape, &self.jnp)?
+                }
                 xla::ElementType::F64 => literal_to_arr::<f64>(&c.data, &c.ty.shape, &self.jnp)?,
                 xla::ElementType::Pred => {
                     todo!()
                 }
                 xla::ElementType::C64 => todo!(),
                 xla::ElementType::C128 => todo!(),
-                xla::ElementType::F16 => todo!(),
-                xla::ElementType::Bf16 => todo!(),
             },
             NoxprNode::Param(_) => unimplemented!(),
-            NoxprNode::Tuple(_) => todo!(),
+            NoxprNode::Tuple(elems) => {
+                let elems = elems
+                    .iter()
+                    .map(|x| self.visit(x))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Python::with_gil(|py| PyTuple::new(py, elems).into_py(py))
+            }
             NoxprNode::Iota(i) => Python::with_gil(|py| {
                 let size = i.shape.shape[i.dim];
                 let dtype = dtype(&i.shape.element_type)?;
@@ -78,10 +86,20 @@ impl JaxTracer {
             NoxprNode::Div(op) => self.visit_binary_lax(op, "div")?,
             NoxprNode::And(op) => self.visit_binary_lax(op, "bitwise_and")?,
             NoxprNode::Or(op) => self.visit_binary_lax(op, "bitwise_or")?,
+            NoxprNode::Xor(op) => self.visit_binary_lax(op, "bitwise_xor")?,
+            NoxprNode::ShiftLeft(op) => self.visit_binary_lax(op, "shift_left")?,
+            NoxprNode::ShiftRightLogical(op) => self.visit_binary_lax(op, "shift_right_logical")?,
             NoxprNode::Dot(op) => self.visit_binary_lax(op, "dot")?,
             NoxprNode::GreaterOrEqual(op) => self.visit_binary_lax(op, "ge")?,
             NoxprNode::LessOrEqual(op) => self.visit_binary_lax(op, "le")?,
             NoxprNode::Less(op) => self.visit_binary_lax(op, "lt")?,
+            NoxprNode::Greater(op) => self.visit_binary_lax(op, "gt")?,
+            NoxprNode::Equal(op) => self.visit_binary_lax(op, "eq")?,
+            NoxprNode::NotEqual(op) => self.visit_binary_lax(op, "ne")?,
+            NoxprNode::Atan2(op) => self.visit_binary_lax(op, "atan2")?,
+            NoxprNode::Pow(op) => self.visit_binary_lax(op, "pow")?,
+            NoxprNode::Max(op) => self.visit_binary_lax(op, "max")?,
+            NoxprNode::Min(op) => self.visit_binary_lax(op, "min")?,
             NoxprNode::DotGeneral(d) => {
                 let lhs = self.visit(&d.lhs)?;
                 let rhs = self.visit(&d.rhs)?;
@@ -101,6 +119,27 @@ impl JaxTracer {
             NoxprNode::Log(op) => self.visit_unary_lax(op, "log")?,
             NoxprNode::Sin(op) => self.visit_unary_lax(op, "sin")?,
             NoxprNode::Cos(op) => self.visit_unary_lax(op, "cos")?,
+            NoxprNode::Exp(op) => self.visit_unary_lax(op, "exp")?,
+            NoxprNode::Tanh(op) => self.visit_unary_lax(op, "tanh")?,
+            NoxprNode::Abs(op) => self.visit_unary_lax(op, "abs")?,
+            NoxprNode::Floor(op) => self.visit_unary_lax(op, "floor")?,
+            NoxprNode::Ceil(op) => self.visit_unary_lax(op, "ceil")?,
+            NoxprNode::Rsqrt(op) => self.visit_unary_lax(op, "rsqrt")?,
+            NoxprNode::Select(s) => {
+                let cond = self.visit(&s.cond)?;
+                let on_true = self.visit(&s.on_true)?;
+                let on_false = self.visit(&s.on_false)?;
+                Python::with_gil(|py| {
+                    self.lax
+                        .call_method1(py, "select", (cond, on_true, on_false))
+                })?
+            }
+            NoxprNode::Clamp(c) => {
+                let min = self.visit(&c.min)?;
+                let expr = self.visit(&c.expr)?;
+                let max = self.visit(&c.max)?;
+                Python::with_gil(|py| self.lax.call_method1(py, "clamp", (min, expr, max)))?
+            }
             NoxprNode::Concat(c) => {
                 let nodes = c
                     .nodes
@@ -112,6 +151,14 @@ impl JaxTracer {
                         .call_method1(py, "concatenate", (nodes, c.dimension))
                 })?
             }
+            NoxprNode::Convert(c) => {
+                let expr = self.visit(&c.expr)?;
+                let dtype = dtype(&c.element_type)?;
+                Python::with_gil(|py| {
+                    self.lax
+                        .call_method1(py, "convert_element_type", (expr, dtype))
+                })?
+            }
             NoxprNode::Reshape(r) => {
                 let expr = self.visit(&r.expr)?;
                 let sizes = r.new_sizes.to_vec();
@@ -206,12 +253,37 @@ impl JaxTracer {
                         .map_err(Error::PyO3)
                 })?
             }
+            NoxprNode::Scatter(s) => {
+                let expr = self.visit(&s.expr)?;
+                let indices = self.visit(&s.indices)?;
+                let updates = self.visit(&s.updates)?;
+                let scatter_dims = Python::with_gil(|py| {
+                    self.lax.call_method1(
+                        py,
+                        "ScatterDimensionNumbers",
+                        (
+                            s.update_window_dims.to_vec(),
+                            s.inserted_window_dims.to_vec(),
+                            s.scatter_dims_to_operand_dims.to_vec(),
+                        ),
+                    )
+                })?;
+                Python::with_gil(|py| {
+                    self.lax
+                        .call_method1(py, "scatter_add", (expr, indices, updates, scatter_dims))
+                        .map_err(Error::PyO3)
+                })?
+            }
             NoxprNode::GetTupleElement(g) => match g.expr.deref() {
                 NoxprNode::Tuple(elems) => {
                     let elem = elems.get(g.index).ok_or(Error::OutOfBoundsAccess)?;
                     self.visit(elem)?
                 }
-                _ => return Err(Error::GetTupleElemWrongType),
+                // params, loops, conds, and decompositions are all lowered to python tuples
+                _ => {
+                    let tuple = self.visit(&g.expr)?;
+                    Python::with_gil(|py| tuple.call_method1(py, "__getitem__", (g.index,)))?
+                }
             },
             NoxprNode::Scan(s) => {
                 let initial_state = self.visit(&s.initial_state)?;
@@ -227,6 +299,64 @@ impl JaxTracer {
                         .map_err(Error::PyO3)
                 })?
             }
+            NoxprNode::Reduce(r) => {
+                let expr = self.visit(&r.expr)?;
+                let method = format!("reduce_{}", r.op.name());
+                let dims = r.dims.to_vec();
+                Python::with_gil(|py| self.lax.call_method1(py, method.as_str(), (expr, dims)))?
+            }
+            NoxprNode::Cholesky(e) => self.visit_linalg(e, "cholesky")?,
+            NoxprNode::TriangularSolve(t) => {
+                let a = self.visit(&t.a)?;
+                let b = self.visit(&t.b)?;
+                Python::with_gil(|py| {
+                    let kwargs = PyDict::new(py);
+                    kwargs.set_item("left_side", t.left_side)?;
+                    kwargs.set_item("lower", t.lower)?;
+                    kwargs.set_item("unit_diagonal", t.unit_diagonal)?;
+                    kwargs.set_item("transpose_a", t.transpose_a)?;
+                    self.lax.getattr(py, "linalg")?.call_method(
+                        py,
+                        "triangular_solve",
+                        (a, b),
+                        Some(kwargs),
+                    )
+                })?
+            }
+            NoxprNode::Qr(e) => self.visit_linalg(e, "qr")?,
+            NoxprNode::Eigh(e) => self.visit_linalg(e, "eigh")?,
+            NoxprNode::Svd(e) => self.visit_linalg(e, "svd")?,
+            NoxprNode::Cond(c) => {
+                let pred = self.visit(&c.pred)?;
+                let operands = c
+                    .operands
+                    .iter()
+                    .map(|x| self.visit(x))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let on_true = self.visit_fn(&c.on_true);
+                let on_false = self.visit_fn(&c.on_false);
+                Python::with_gil(|py| {
+                    let mut args = vec![
+                        pred,
+                        Py::new(py, on_true)?.into_py(py),
+                        Py::new(py, on_false)?.into_py(py),
+                    ];
+                    args.extend(operands);
+                    self.lax
+                        .call_method1(py, "cond", PyTuple::new(py, args))
+                        .map_err(Error::PyO3)
+                })?
+            }
+            NoxprNode::While(w) => {
+                let initial_state = self.visit(&w.initial_state)?;
+                let cond_fn = self.visit_fn(&w.cond_fn);
+                let body_fn = self.visit_fn(&w.body_fn);
+                Python::with_gil(|py| {
+                    self.lax
+                        .call_method1(py, "while_loop", (cond_fn, body_fn, initial_state))
+                        .map_err(Error::PyO3)
+                })?
+            }
             NoxprNode::Jax(o) => o.clone(),
         };
         self.cache.insert(id, op.clone());
@@ -250,6 +380,17 @@ impl JaxTracer {
         Python::with_gil(|py| self.lax.call_method1(py, method, (inner,))).map_err(Error::PyO3)
     }
 
+    #[inline]
+    fn visit_linalg(&mut self, op: &Noxpr, method: &str) -> Result<PyObject, Error> {
+        let inner = self.visit(op)?;
+        Python::with_gil(|py| {
+            self.lax
+                .getattr(py, "linalg")?
+                .call_method1(py, method, (inner,))
+        })
+        .map_err(Error::PyO3)
+    }
+
     fn visit_fn(&mut self, op: &NoxprFn) -> JaxNoxprFn {
         JaxNoxprFn {
             tracer: self.clone(),
@@ -323,8 +464,8 @@ pub fn dtype(elem: &ElementType) -> Result<&'static str, Error> {
         ElementType::Pred => Ok("bool"),
         ElementType::C64 => todo!(),
         ElementType::C128 => todo!(),
-        ElementType::F16 => todo!(),
-        ElementType::Bf16 => todo!(),
+        ElementType::F16 => Ok("float16"),
+        ElementType::Bf16 => Ok("bfloat16"),
     }
 }
 
@@ -345,7 +486,7 @@ pub fn call_comp_fn<T, R: IntoOp>(
 mod tests {
     use numpy::PyArrayLike0;
 
-    use crate::ConstantExt;
+    use crate::{ArrayTy, ConstantExt, NoxprTy};
 
     use super::*;
 
@@ -362,4 +503,57 @@ mod tests {
             assert_eq!(arr.as_slice().unwrap(), &[3.0]);
         })
     }
+
+    #[test]
+    fn test_tuple_while_loop() {
+        pyo3::prepare_freethreaded_python();
+        let scalar_ty = |element_type| {
+            NoxprTy::ArrayTy(ArrayTy {
+                element_type,
+                shape: smallvec::smallvec![],
+            })
+        };
+        let state_ty = NoxprTy::Tuple(vec![
+            scalar_ty(ElementType::F32),
+            scalar_ty(ElementType::S32),
+        ]);
+        let state = Noxpr::parameter(0, state_ty.clone(), "state".to_string());
+        let cond_fn = NoxprFn::new(
+            vec![state.clone()],
+            state
+                .get_tuple_element(1)
+                .less(crate::NoxprScalarExt::constant(3i32)),
+        );
+        let state = Noxpr::parameter(0, state_ty, "state".to_string());
+        let body_fn = NoxprFn::new(
+            vec![state.clone()],
+            Noxpr::tuple(vec![
+                state.get_tuple_element(0) + crate::NoxprScalarExt::constant(0.5f32),
+                state.get_tuple_element(1) + crate::NoxprScalarExt::constant(1i32),
+            ]),
+        );
+        let init = Noxpr::tuple(vec![
+            crate::NoxprScalarExt::constant(1.0f32),
+            crate::NoxprScalarExt::constant(0i32),
+        ]);
+        let out = Noxpr::while_loop(init, cond_fn, body_fn).get_tuple_element(0);
+        let o = out.to_jax().unwrap();
+        Python::with_gil(|py| {
+            let arr = o.extract::<PyArrayLike0<f32>>(py).unwrap();
+            assert_eq!(arr.as_slice().unwrap(), &[2.5]);
+        })
+    }
+
+    #[test]
+    fn test_f16_constant() {
+        pyo3::prepare_freethreaded_python();
+        let a = half::f16::from_f32(1.5).constant();
+        let b = half::f16::from_f32(0.25).constant();
+        let c = a + b;
+        let o = c.inner.to_jax().unwrap();
+        Python::with_gil(|py| {
+            let arr = o.extract::<PyArrayLike0<half::f16>>(py).unwrap();
+            assert_eq!(arr.as_slice().unwrap(), &[half::f16::from_f32(1.75)]);
+        })
+    }
 }
@@ -0,0 +1,511 @@
+//! Forward-mode automatic differentiation over [`Noxpr`] graphs.
+use std::{collections::HashMap, ops::Deref};
+
+use smallvec::{smallvec, SmallVec};
+
+use crate::grad::{
+    extremum_weights, inline_cond, is_float, product_weights, scalar, unreduce, unroll_scan,
+    zeros_like,
+};
+use crate::linalg::{half_lower, matmul, transpose_matrix, triangle};
+use crate::{
+    CompFn, Dim, Error, Field, Noxpr, NoxprFn, NoxprId, NoxprNode, NoxprScalarExt, NoxprTy,
+    ReduceOp, Tensor, While,
+};
+
+/// Pushes tangents forwards through a [`Noxpr`] graph.
+///
+/// Tangents are seeded on any node, and every node that depends on a seeded node gets a tangent
+/// built from the tangents of its inputs. Nodes that don't depend on a seed have a zero tangent,
+/// which is tracked symbolically so constant subgraphs don't generate any extra work.
+#[derive(Default)]
+pub struct JvpTracer {
+    tangents: HashMap<NoxprId, Option<Noxpr>>,
+}
+
+impl JvpTracer {
+    /// Sets the tangent of `expr`, which must be called before any node depending on it is visited.
+    pub fn seed(&mut self, expr: &Noxpr, tangent: Noxpr) {
+        self.tangents.insert(expr.id(), Some(tangent));
+    }
+
+    /// Returns the tangent of `expr`, or zeros if it doesn't depend on any seeded node.
+    pub fn tangent(&mut self, expr: &Noxpr) -> Result<Noxpr, Error> {
+        match self.visit(expr)? {
+            Some(tangent) => Ok(tangent),
+            None => zeros_like(expr),
+        }
+    }
+
+    fn visit(&mut self, expr: &Noxpr) -> Result<Option<Noxpr>, Error> {
+        if let Some(tangent) = self.tangents.get(&expr.id()) {
+            return Ok(tangent.clone());
+        }
+        let tangent = match expr.deref() {
+            NoxprNode::Param(_)
+            | NoxprNode::Constant(_)
+            | NoxprNode::Iota(_)
+            | NoxprNode::And(_)
+            | NoxprNode::Or(_)
+            | NoxprNode::Xor(_)
+            | NoxprNode::ShiftLeft(_)
+            | NoxprNode::ShiftRightLogical(_)
+            | NoxprNode::GreaterOrEqual(_)
+            | NoxprNode::LessOrEqual(_)
+            | NoxprNode::Less(_)
+            | NoxprNode::Greater(_)
+            | NoxprNode::Equal(_)
+            | NoxprNode::NotEqual(_)
+            | NoxprNode::Floor(_)
+            | NoxprNode::Ceil(_) => None,
+            NoxprNode::Tuple(elems) => match self.visit_all(elems)? {
+                Some(tangents) => Some(Noxpr::tuple(zip_zeros(tangents, elems)?)),
+                None => None,
+            },
+            NoxprNode::GetTupleElement(g) => match self.visit(&g.expr)? {
+                Some(tangent) => match tangent.deref() {
+                    NoxprNode::Tuple(elems) => Some(
+                        elems
+                            .get(g.index)
+                            .cloned()
+                            .ok_or(Error::OutOfBoundsAccess)?,
+                    ),
+                    _ => Some(tangent.get_tuple_element(g.index)),
+                },
+                None => None,
+            },
+            NoxprNode::Add(b) => match (self.visit(&b.lhs)?, self.visit(&b.rhs)?) {
+                (Some(lhs), Some(rhs)) => Some(lhs + rhs),
+                (Some(t), None) | (None, Some(t)) => Some(broadcast_to(t, expr)?),
+                (None, None) => None,
+            },
+            NoxprNode::Sub(b) => match (self.visit(&b.lhs)?, self.visit(&b.rhs)?) {
+                (Some(lhs), Some(rhs)) => Some(lhs - rhs),
+                (Some(lhs), None) => Some(broadcast_to(lhs, expr)?),
+                (None, Some(rhs)) => Some(broadcast_to(-rhs, expr)?),
+                (None, None) => None,
+            },
+            NoxprNode::Mul(b) => {
+                let lhs = self.visit(&b.lhs)?.map(|t| t * b.rhs.clone());
+                let rhs = self.visit(&b.rhs)?.map(|t| b.lhs.clone() * t);
+                add(lhs, rhs)
+            }
+            NoxprNode::Div(b) => {
+                let lhs = self.visit(&b.lhs)?.map(|t| t / b.rhs.clone());
+                let rhs = self
+                    .visit(&b.rhs)?
+                    .map(|t| -(b.lhs.clone() * t) / (b.rhs.clone() * b.rhs.clone()));
+                add(lhs, rhs)
+            }
+            NoxprNode::Dot(b) => {
+                let lhs = self.visit(&b.lhs)?.map(|t| t.dot(&b.rhs));
+                let rhs = self.visit(&b.rhs)?.map(|t| b.lhs.clone().dot(&t));
+                add(lhs, rhs)
+            }
+            NoxprNode::DotGeneral(d) => {
+                let lhs = self
+                    .visit(&d.lhs)?
+                    .map(|t| t.dot_general(d.rhs.clone(), d.dimensions.clone()));
+                let rhs = self
+                    .visit(&d.rhs)?
+                    .map(|t| d.lhs.clone().dot_general(t, d.dimensions.clone()));
+                add(lhs, rhs)
+            }
+            NoxprNode::Sqrt(e) => match self.visit(e)? {
+                Some(t) => {
+                    let two = scalar(e.element_type().ok_or(Error::UnknownShape)?, 2.0)?;
+                    Some(t / (expr.clone() * two))
+                }
+                None => None,
+            },
+            NoxprNode::Neg(e) => self.visit(e)?.map(|t| -t),
+            NoxprNode::Log(e) => self.visit(e)?.map(|t| t / e.clone()),
+            NoxprNode::Sin(e) => self.visit(e)?.map(|t| t * e.clone().cos()),
+            NoxprNode::Cos(e) => self.visit(e)?.map(|t| -(t * e.clone().sin())),
+            NoxprNode::Exp(e) => self.visit(e)?.map(|t| t * expr.clone()),
+            NoxprNode::Tanh(e) => match self.visit(e)? {
+                Some(t) => {
+                    let one = scalar(e.element_type().ok_or(Error::UnknownShape)?, 1.0)?;
+                    Some(t * (one - expr.clone() * expr.clone()))
+                }
+                None => None,
+            },
+            NoxprNode::Abs(e) => match self.visit(e)? {
+                Some(t) => {
+                    let zero = scalar(e.element_type().ok_or(Error::UnknownShape)?, 0.0)?;
+                    let sign = e.clone().greater_or_equal(zero);
+                    Some(sign.select(t.clone(), -t))
+                }
+                None => None,
+            },
+            NoxprNode::Rsqrt(e) => match self.visit(e)? {
+                Some(t) => {
+                    let half = scalar(e.element_type().ok_or(Error::UnknownShape)?, -0.5)?;
+                    Some(t * (expr.clone() * expr.clone() * expr.clone()) * half)
+                }
+                None => None,
+            },
+            NoxprNode::Atan2(b) => {
+                let (y, x) = (b.lhs.clone(), b.rhs.clone());
+                let denom = x.clone() * x.clone() + y.clone() * y.clone();
+                let lhs = self.visit(&b.lhs)?.map(|t| t * x.clone() / denom.clone());
+                let rhs = self.visit(&b.rhs)?.map(|t| -(t * y) / denom);
+                add(lhs, rhs)
+            }
+            NoxprNode::Pow(b) => {
+                let one = scalar(b.rhs.element_type().ok_or(Error::UnknownShape)?, 1.0)?;
+                let lhs = self
+                    .visit(&b.lhs)?
+                    .map(|t| t * b.rhs.clone() * b.lhs.clone().pow(b.rhs.clone() - one));
+                let rhs = self
+                    .visit(&b.rhs)?
+                    .map(|t| t * expr.clone() * b.lhs.clone().log());
+                add(lhs, rhs)
+            }
+            NoxprNode::Max(b) => {
+                let mask = b.lhs.clone().greater_or_equal(b.rhs.clone());
+                self.visit_masked(expr, mask, &b.lhs, &b.rhs)?
+            }
+            NoxprNode::Min(b) => {
+                let mask = b.lhs.clone().less_or_equal(b.rhs.clone());
+                self.visit_masked(expr, mask, &b.lhs, &b.rhs)?
+            }
+            NoxprNode::Select(s) => {
+                self.visit_masked(expr, s.cond.clone(), &s.on_true, &s.on_false)?
+            }
+            NoxprNode::Clamp(c) => {
+                let inner = self.visit_masked(
+                    expr,
+                    c.expr.clone().greater(c.max.clone()),
+                    &c.max,
+                    &c.expr,
+                )?;
+                match (self.visit(&c.min)?, inner) {
+                    (None, None) => None,
+                    (min_t, inner) => {
+                        let min_t = broadcast_to(or_zeros(min_t, &c.min)?, expr)?;
+                        let inner = or_zeros(inner, expr)?;
+                        Some(c.expr.clone().less(c.min.clone()).select(min_t, inner))
+                    }
+                }
+            }
+            NoxprNode::Concat(c) => match self.visit_all(&c.nodes)? {
+                Some(tangents) => Some(Noxpr::concat_in_dim(
+                    zip_zeros(tangents, &c.nodes)?,
+                    c.dimension,
+                )),
+                None => None,
+            },
+            NoxprNode::Convert(c) => match self.visit(&c.expr)? {
+                Some(t) if is_float(expr) => Some(t.convert(c.element_type)),
+                _ => None,
+            },
+            NoxprNode::Reshape(r) => self.visit(&r.expr)?.map(|t| t.reshape(r.new_sizes.clone())),
+            NoxprNode::Broadcast(b) => self.visit(&b.expr)?.map(|t| t.broadcast(b.sizes.clone())),
+            NoxprNode::BroadcastInDim(b) => self
+                .visit(&b.expr)?
+                .map(|t| t.broadcast_in_dim(b.sizes.clone(), b.broadcast_dims.clone())),
+            NoxprNode::Transpose(t) => self
+                .visit(&t.expr)?
+                .map(|tangent| tangent.transpose(t.permutation.clone())),
+            NoxprNode::Gather(g) => self.visit(&g.expr)?.map(|t| {
+                t.gather(
+                    g.indices.clone(),
+                    g.offset_dims.clone(),
+                    g.collapsed_slice_dims.clone(),
+                    g.start_index_map.clone(),
+                    g.slice_sizes.clone(),
+                    g.index_vector_dim,
+                )
+            }),
+            NoxprNode::Slice(s) => self.visit(&s.expr)?.map(|t| {
+                t.slice(
+                    s.start_indices.clone(),
+                    s.stop_indices.clone(),
+                    s.strides.clone(),
+                )
+            }),
+            NoxprNode::DynamicSlice(d) => self
+                .visit(&d.expr)?
+                .map(|t| t.dynamic_slice(d.start_indices.clone(), d.size_indices.clone())),
+            NoxprNode::DynamicUpdateSlice(d) => {
+                match (self.visit(&d.expr)?, self.visit(&d.update)?) {
+                    (None, None) => None,
+                    (expr_t, update_t) => Some(or_zeros(expr_t, &d.expr)?.dynamic_update_slice(
+                        d.start_indicies.clone(),
+                        or_zeros(update_t, &d.update)?,
+                    )),
+                }
+            }
+            NoxprNode::Scatter(s) => match (self.visit(&s.expr)?, self.visit(&s.updates)?) {
+                (None, None) => None,
+                (expr_t, updates_t) => Some(or_zeros(expr_t, &s.expr)?.scatter(
+                    s.indices.clone(),
+                    or_zeros(updates_t, &s.updates)?,
+                    s.update_window_dims.clone(),
+                    s.inserted_window_dims.clone(),
+                    s.scatter_dims_to_operand_dims.clone(),
+                    s.index_vector_dim,
+                )),
+            },
+            NoxprNode::Reduce(r) => match self.visit(&r.expr)? {
+                None => None,
+                Some(t) => Some(match r.op {
+                    ReduceOp::Sum => t.reduce_sum(r.dims.clone()),
+                    ReduceOp::Prod => (t * product_weights(r)?).reduce_sum(r.dims.clone()),
+                    ReduceOp::Max | ReduceOp::Min => {
+                        (t * extremum_weights(expr, r)?).reduce_sum(r.dims.clone())
+                    }
+                }),
+            },
+            NoxprNode::Cholesky(e) => match self.visit(e)? {
+                Some(t) => {
+                    let half = scalar(e.element_type().ok_or(Error::UnknownShape)?, 0.5)?;
+                    let sym = (t.clone() + transpose_matrix(t)?) * half;
+                    // l_dot = l * phi(l^-1 * sym * l^-T)
+                    let l = expr.clone();
+                    let y = l.clone().triangular_solve(sym, true, true, false, false);
+                    let z = l.clone().triangular_solve(y, false, true, false, true);
+                    Some(matmul(l, half_lower(z)?)?)
+                }
+                None => None,
+            },
+            NoxprNode::TriangularSolve(t) => {
+                // x_dot = op(a)^-1 * (b_dot - op(a_dot) * x), or the mirror image for the right side
+                let a_term = match self.visit(&t.a)? {
+                    Some(a_dot) => {
+                        let a_dot = triangle(a_dot, t.lower, !t.unit_diagonal)?;
+                        let a_dot = match t.transpose_a {
+                            true => transpose_matrix(a_dot)?,
+                            false => a_dot,
+                        };
+                        Some(match t.left_side {
+                            true => matmul(a_dot, expr.clone())?,
+                            false => matmul(expr.clone(), a_dot)?,
+                        })
+                    }
+                    None => None,
+                };
+                let rhs = match (self.visit(&t.b)?, a_term) {
+                    (Some(b_dot), Some(a_term)) => Some(b_dot - a_term),
+                    (Some(b_dot), None) => Some(b_dot),
+                    (None, Some(a_term)) => Some(-a_term),
+                    (None, None) => None,
+                };
+                rhs.map(|rhs| {
+                    t.a.clone().triangular_solve(
+                        rhs,
+                        t.left_side,
+                        t.lower,
+                        t.unit_diagonal,
+                        t.transpose_a,
+                    )
+                })
+            }
+            NoxprNode::Qr(e) | NoxprNode::Eigh(e) | NoxprNode::Svd(e) => match self.visit(e)? {
+                Some(_) => return Err(Error::UnsupportedJvp(expr.name())),
+                None => None,
+            },
+            NoxprNode::Scan(s) => {
+                let unrolled = unroll_scan(s)?;
+                self.visit(&unrolled)?
+            }
+            NoxprNode::Cond(c) => self.visit(&inline_cond(c))?,
+            NoxprNode::While(w) => match self.visit(&w.initial_state)? {
+                Some(tangent) => Some(self.visit_while(w, tangent)?),
+                None => None,
+            },
+            #[cfg(feature = "jax")]
+            NoxprNode::Jax(_) => return Err(Error::UnsupportedJvp("Jax")),
+        };
+        self.tangents.insert(expr.id(), tangent.clone());
+        Ok(tangent)
+    }
+
+    /// Runs a second while loop that carries the tangent of the state alongside the state itself.
+    fn visit_while(&mut self, w: &While, tangent: Noxpr) -> Result<Noxpr, Error> {
+        let state_ty = w.initial_state.ty().ok_or(Error::UnknownShape)?;
+        let param = Noxpr::parameter(
+            0,
+            NoxprTy::Tuple(vec![state_ty.clone(), state_ty]),
+            "while_state".to_string(),
+        );
+        let state = param.get_tuple_element(0);
+        let body = w.body_fn.apply(&[state.clone()], &w.body_fn.inner);
+        let mut body_tracer = JvpTracer::default();
+        body_tracer.seed(&state, param.get_tuple_element(1));
+        let body_tangent = body_tracer.tangent(&body)?;
+        let cond = w.cond_fn.apply(&[state], &w.cond_fn.inner);
+        let out = Noxpr::while_loop(
+            Noxpr::tuple(vec![w.initial_state.clone(), tangent]),
+            NoxprFn::new(vec![param.clone()], cond),
+            NoxprFn::new(vec![param], Noxpr::tuple(vec![body, body_tangent])),
+        );
+        Ok(out.get_tuple_element(1))
+    }
+
+    /// Picks the tangent of `on_true` where `mask` is true and the tangent of `on_false` otherwise,
+    /// broadcasting both up to the shape of `expr`.
+    fn visit_masked(
+        &mut self,
+        expr: &Noxpr,
+        mask: Noxpr,
+        on_true: &Noxpr,
+        on_false: &Noxpr,
+    ) -> Result<Option<Noxpr>, Error> {
+        match (self.visit(on_true)?, self.visit(on_false)?) {
+            (None, None) => Ok(None),
+            (true_t, false_t) => {
+                let true_t = broadcast_to(or_zeros(true_t, on_true)?, expr)?;
+                let false_t = broadcast_to(or_zeros(false_t, on_false)?, expr)?;
+                Ok(Some(mask.select(true_t, false_t)))
+            }
+        }
+    }
+
+    /// Visits every expression in `exprs`, returning `None` if none of them have a tangent.
+    fn visit_all(&mut self, exprs: &[Noxpr]) -> Result<Option<Vec<Option<Noxpr>>>, Error> {
+        let tangents = exprs
+            .iter()
+            .map(|expr| self.visit(expr))
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(tangents.iter().any(Option::is_some).then_some(tangents))
+    }
+}
+
+fn add(lhs: Option<Noxpr>, rhs: Option<Noxpr>) -> Option<Noxpr> {
+    match (lhs, rhs) {
+        (Some(lhs), Some(rhs)) => Some(lhs + rhs),
+        (lhs, rhs) => lhs.or(rhs),
+    }
+}
+
+fn or_zeros(tangent: Option<Noxpr>, expr: &Noxpr) -> Result<Noxpr, Error> {
+    match tangent {
+        Some(tangent) => Ok(tangent),
+        None => zeros_like(expr),
+    }
+}
+
+fn zip_zeros(tangents: Vec<Option<Noxpr>>, exprs: &[Noxpr]) -> Result<Vec<Noxpr>, Error> {
+    tangents
+        .into_iter()
+        .zip(exprs.iter())
+        .map(|(tangent, expr)| or_zeros(tangent, expr))
+        .collect()
+}
+
+/// Broadcasts the tangent of one side of a binary op up to the shape of its output.
+fn broadcast_to(tangent: Noxpr, out: &Noxpr) -> Result<Noxpr, Error> {
+    let in_shape = tangent.shape().ok_or(Error::UnknownShape)?;
+    let out_shape = out.shape().ok_or(Error::UnknownShape)?;
+    if in_shape == out_shape {
+        return Ok(tangent);
+    }
+    let out_rank = out_shape.len() as i64;
+    let in_rank = in_shape.len() as i64;
+    let broadcast_dims = (out_rank - in_rank..out_rank).collect();
+    Ok(tangent.broadcast_in_dim(out_shape, broadcast_dims))
+}
+
+impl Noxpr {
+    /// Builds the Jacobian-vector product of `self`, where each of `primals` moves along the
+    /// matching entry in `tangents`.
+    pub fn jvp(&self, primals: &[Noxpr], tangents: &[Noxpr]) -> Result<Noxpr, Error> {
+        let mut tracer = JvpTracer::default();
+        for (primal, tangent) in primals.iter().zip(tangents.iter()) {
+            tracer.seed(primal, tangent.clone());
+        }
+        tracer.tangent(self)
+    }
+
+    /// Builds the Jacobian of `self` with respect to `wrt` using one forward pass per column.
+    ///
+    /// Both `self` and each expression in `wrt` are flattened, and `wrt` is concatenated, so the
+    /// result is always a matrix with a row per output element and a column per input element.
+    pub fn jacobian_fwd(&self, wrt: &[Noxpr]) -> Result<Noxpr, Error> {
+        let Some(NoxprTy::ArrayTy(ty)) = self.ty() else {
+            return Err(Error::UnknownShape);
+        };
+        let out_len = ty.shape.iter().product::<i64>();
+        let mut columns = vec![];
+        for primal in wrt {
+            let Some(NoxprTy::ArrayTy(primal_ty)) = primal.ty() else {
+                return Err(Error::UnknownShape);
+            };
+            let len = primal_ty.shape.iter().product::<i64>();
+            for i in 0..len {
+                let one = scalar(primal_ty.element_type, 1.0)?.reshape(smallvec![1]);
+                let basis = scalar(primal_ty.element_type, 0.0)?
+                    .broadcast(smallvec![len])
+                    .dynamic_update_slice(vec![i.constant()], one)
+                    .reshape(primal_ty.shape.clone());
+                let column = self.jvp(std::slice::from_ref(primal), &[basis])?;
+                columns.push(column.reshape(smallvec![out_len, 1]));
+            }
+        }
+        if columns.is_empty() {
+            let shape: SmallVec<[i64; 4]> = smallvec![out_len, 0];
+            return Ok(scalar(ty.element_type, 0.0)?.broadcast(shape));
+        }
+        Ok(Noxpr::concat_in_dim(columns, 1))
+    }
+}
+
+impl NoxprFn {
+    /// Builds the Jacobian-vector product of this function, with one tangent per argument.
+    pub fn jvp(&self, tangents: &[Noxpr]) -> Result<Noxpr, Error> {
+        if tangents.len() != self.args.len() {
+            return Err(Error::OutOfBoundsAccess);
+        }
+        self.inner.jvp(&self.args, tangents)
+    }
+}
+
+impl<T: Field, D: Dim> Tensor<T, D, crate::Op> {
+    /// Evaluates the Jacobian-vector product of `func` at `self` along `tangent`.
+    pub fn jvp<OD: Dim>(
+        &self,
+        func: impl CompFn<(Self,), Tensor<T, OD>>,
+        tangent: Self,
+    ) -> Result<Tensor<T, OD>, Error> {
+        let func = func.build_expr()?;
+        let jvp = func.jvp(&[tangent.inner])?;
+        Ok(Tensor::from_op(func.apply(&[self.inner.clone()], &jvp)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Client, CompFn, Matrix, ToHost, Vector};
+    use nalgebra::{matrix, vector};
+
+    #[test]
+    fn test_jvp_mul() {
+        let client = Client::cpu().unwrap();
+        fn jvp(x: Vector<f32, 3>) -> Vector<f32, 3> {
+            x.jvp(|x: Vector<f32, 3>| x.clone() * x, x.clone()).unwrap()
+        }
+        let comp = jvp.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec
+            .run(&client, vector![1.0f32, 2.0, 3.0])
+            .unwrap()
+            .to_host();
+        assert_eq!(out, vector![2.0, 8.0, 18.0])
+    }
+
+    #[test]
+    fn test_jacobian_fwd() {
+        let client = Client::cpu().unwrap();
+        fn jacobian(x: Vector<f32, 2>) -> Matrix<f32, 2, 2> {
+            let out = (x.clone() * x.sin()).inner;
+            Matrix::from_op(out.jacobian_fwd(&[x.inner]).unwrap())
+        }
+        let comp = jacobian.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec.run(&client, vector![0.5f32, 1.0]).unwrap().to_host();
+        let d = |x: f32| x.sin() + x * x.cos();
+        approx::assert_relative_eq!(out, matrix![d(0.5), 0.0; 0.0, d(1.0)], epsilon = 1e-6);
+    }
+}
@@ -2,19 +2,33 @@
 #![allow(clippy::arc_with_non_send_sync)]
 
 mod builder;
+mod check;
 mod client;
 mod comp;
 mod comp_fn;
 mod constant;
 mod error;
 mod exec;
+mod export;
 mod fields;
+mod grad;
+mod index;
+mod interpreter;
+mod jvp;
+mod linalg;
 mod local_backend;
+mod mask;
 mod matrix;
 mod noxpr;
+mod optimize;
 mod param;
+mod quantity;
 mod quaternion;
+mod random;
+mod reduce;
 mod scalar;
+mod serialize;
+mod sparse;
 mod spatial;
 mod tensor;
 mod transfer;
@@ -24,19 +38,33 @@ mod vector;
 pub mod jax;
 
 pub use builder::*;
+pub use check::*;
 pub use client::*;
 pub use comp::*;
 pub use comp_fn::*;
 pub use constant::*;
 pub use error::*;
 pub use exec::*;
+pub use export::*;
 pub use fields::*;
+pub use grad::*;
+pub use index::*;
+pub use interpreter::*;
+pub use jvp::*;
+pub use linalg::*;
 pub use local_backend::*;
+pub use mask::*;
 pub use matrix::*;
 pub use noxpr::*;
+pub use optimize::*;
 pub use param::*;
+pub use quantity::*;
 pub use quaternion::*;
+pub use random::*;
+pub use reduce::*;
 pub use scalar::*;
+pub use serialize::*;
+pub use sparse::*;
 pub use spatial::*;
 pub use tensor::*;
 pub use transfer::*;
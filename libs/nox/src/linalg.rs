@@ -0,0 +1,453 @@
+//! Provides dense linear algebra over square matrices, such as solves, inverses and decompositions.
+use crate::grad::scalar;
+use crate::local_backend::{ArrayBufUnit, ArrayDim};
+use crate::{
+    ArrayTy, Dim, DotDim, DotDimensionNums, Error, Field, Matrix, Noxpr, NoxprScalarExt, NoxprTy,
+    ReduceOp, Repr, Scalar, Tensor, Vector,
+};
+use core::mem::MaybeUninit;
+use nalgebra::{constraint::ShapeConstraint, Const, RealField};
+use smallvec::{smallvec, SmallVec};
+use std::marker::PhantomData;
+use xla::ElementType;
+
+/// The dimensions of an `N` by `N` matrix.
+pub type SquareDim<const N: usize> = (Const<N>, Const<N>);
+
+/// The LU decomposition of a square matrix with partial pivoting, such that `p * a = l * u`.
+pub struct Lu {
+    /// A unit lower triangular matrix.
+    pub l: Noxpr,
+    /// An upper triangular matrix.
+    pub u: Noxpr,
+    /// The permutation matrix of the row swaps.
+    pub p: Noxpr,
+    /// The determinant of `p`, either `1` or `-1`.
+    pub sign: Noxpr,
+}
+
+impl Noxpr {
+    /// Decomposes a square matrix using Doolittle's algorithm with partial pivoting.
+    ///
+    /// The elimination is unrolled into plain ops, so the result can be differentiated and batched
+    /// like any other expression.
+    pub fn lu(&self) -> Result<Lu, Error> {
+        let (element_type, n) = square_matrix(self)?;
+        Ok(self.lu_in_shape(element_type, n))
+    }
+
+    pub(crate) fn lu_in_shape(&self, element_type: ElementType, n: i64) -> Lu {
+        let shape: SmallVec<[i64; 4]> = smallvec![n, n];
+        let index = |dim| Noxpr::iota(ArrayTy::new(ElementType::S64, shape.clone()), dim);
+        let (rows, cols) = (index(0), index(1));
+        let row_index = Noxpr::iota(ArrayTy::new(ElementType::S64, smallvec![n]), 0);
+        let zeros = constant(element_type, 0.0).broadcast(shape.clone());
+        let ones = constant(element_type, 1.0).broadcast(shape.clone());
+        let eye = rows
+            .clone()
+            .equal(cols.clone())
+            .select(ones.clone(), zeros.clone());
+
+        let mut u = self.clone();
+        let mut l = zeros.clone();
+        let mut p = eye.clone();
+        let mut sign = constant(element_type, 1.0);
+        for k in 0..n {
+            let k_vec = k.constant().broadcast(smallvec![n]);
+            let k_mat = k.constant().broadcast(shape.clone());
+
+            // pick the largest remaining element of column k as the pivot, and swap it into row k
+            let not_pivot = constant(element_type, -1.0).broadcast(smallvec![n]);
+            let pivot = row_index
+                .clone()
+                .greater_or_equal(k_vec.clone())
+                .select(column(&u, k, n).abs(), not_pivot)
+                .argmax_in_shape(0, smallvec![n]);
+            let pivot_mat = pivot.clone().broadcast(shape.clone());
+            let k_row = rows.clone().equal(k_mat.clone());
+            let pivot_row = rows.clone().equal(pivot_mat.clone());
+            let unswapped = rows
+                .clone()
+                .equal(cols.clone())
+                .and(rows.clone().not_equal(k_mat.clone()))
+                .and(rows.clone().not_equal(pivot_mat.clone()));
+            let swap = unswapped
+                .or(k_row.and(cols.clone().equal(pivot_mat)))
+                .or(pivot_row.and(cols.clone().equal(k_mat.clone())))
+                .select(ones.clone(), zeros.clone());
+            u = swap.clone().dot(&u);
+            l = swap.clone().dot(&l);
+            p = swap.dot(&p);
+            sign = pivot.equal(k.constant()).select(sign.clone(), -sign);
+
+            // eliminate the elements below the pivot
+            let pivot_value = u
+                .clone()
+                .slice(smallvec![k, k], smallvec![k + 1, k + 1], smallvec![1, 1])
+                .reshape(smallvec![]);
+            let factors = row_index.clone().greater(k_vec).select(
+                column(&u, k, n) / pivot_value,
+                constant(element_type, 0.0).broadcast(smallvec![n]),
+            );
+            let factors = factors.broadcast_in_dim(shape.clone(), smallvec![0]);
+            let pivot_row = u
+                .clone()
+                .slice(smallvec![k, 0], smallvec![k + 1, n], smallvec![1, 1])
+                .reshape(smallvec![n])
+                .broadcast_in_dim(shape.clone(), smallvec![1]);
+            u = u - factors.clone() * pivot_row;
+            l = l + cols.clone().equal(k_mat).select(factors, zeros.clone());
+        }
+        let upper = rows.less_or_equal(cols);
+        Lu {
+            l: l + eye,
+            u: upper.select(u, zeros),
+            p,
+            sign,
+        }
+    }
+
+    /// Solves `self * x = b` for `x`, where `b` is either a vector or a matrix.
+    pub fn solve(&self, b: &Noxpr) -> Result<Noxpr, Error> {
+        let (element_type, n) = square_matrix(self)?;
+        let b_shape = b.shape().ok_or(Error::UnknownShape)?;
+        match b_shape[..] {
+            [rows] | [rows, _] if rows == n => {}
+            _ => return Err(Error::SolveShapeMismatch),
+        }
+        Ok(self.solve_in_shape(b, element_type, n, b_shape))
+    }
+
+    /// Solves `self * x = b` for an `n` by `n` matrix, where `b` has `n` rows.
+    pub(crate) fn solve_in_shape(
+        &self,
+        b: &Noxpr,
+        element_type: ElementType,
+        n: i64,
+        b_shape: SmallVec<[i64; 4]>,
+    ) -> Noxpr {
+        let rhs = match b_shape.len() {
+            1 => b.clone().reshape(smallvec![n, 1]),
+            _ => b.clone(),
+        };
+        let lu = self.lu_in_shape(element_type, n);
+        let y =
+            lu.l.triangular_solve(lu.p.dot(&rhs), true, true, true, false);
+        let x = lu.u.triangular_solve(y, true, false, false, false);
+        x.reshape(b_shape)
+    }
+
+    /// Returns the inverse of a square matrix.
+    pub fn inverse(&self) -> Result<Noxpr, Error> {
+        let (element_type, n) = square_matrix(self)?;
+        Ok(self.inverse_in_shape(element_type, n))
+    }
+
+    pub(crate) fn inverse_in_shape(&self, element_type: ElementType, n: i64) -> Noxpr {
+        self.solve_in_shape(&eye(element_type, n), element_type, n, smallvec![n, n])
+    }
+
+    /// Returns the determinant of a square matrix, computed from its LU decomposition.
+    pub fn determinant(&self) -> Result<Noxpr, Error> {
+        let (element_type, n) = square_matrix(self)?;
+        Ok(self.determinant_in_shape(element_type, n))
+    }
+
+    pub(crate) fn determinant_in_shape(&self, element_type: ElementType, n: i64) -> Noxpr {
+        let lu = self.lu_in_shape(element_type, n);
+        let diagonal = (lu.u * eye(element_type, n)).reduce_sum(smallvec![1]);
+        lu.sign * diagonal.reduce(ReduceOp::Prod, smallvec![0])
+    }
+}
+
+fn square_matrix(expr: &Noxpr) -> Result<(ElementType, i64), Error> {
+    let Some(NoxprTy::ArrayTy(ty)) = expr.ty() else {
+        return Err(Error::UnknownShape);
+    };
+    match ty.shape[..] {
+        [rows, cols] if rows == cols => Ok((ty.element_type, rows)),
+        _ => Err(Error::NonSquareMatrix),
+    }
+}
+
+/// Returns `value` as a scalar of `element_type`, which unlike `scalar` works for any numeric
+/// element type.
+fn constant(element_type: ElementType, value: f64) -> Noxpr {
+    value.constant().convert(element_type)
+}
+
+fn column(expr: &Noxpr, index: i64, n: i64) -> Noxpr {
+    expr.clone()
+        .slice(
+            smallvec![0, index],
+            smallvec![n, index + 1],
+            smallvec![1, 1],
+        )
+        .reshape(smallvec![n])
+}
+
+fn eye(element_type: ElementType, n: i64) -> Noxpr {
+    let shape: SmallVec<[i64; 4]> = smallvec![n, n];
+    let rows = Noxpr::iota(ArrayTy::new(ElementType::S64, shape.clone()), 0);
+    let cols = Noxpr::iota(ArrayTy::new(ElementType::S64, shape.clone()), 1);
+    rows.equal(cols).select(
+        constant(element_type, 1.0).broadcast(shape.clone()),
+        constant(element_type, 0.0).broadcast(shape),
+    )
+}
+
+/// Swaps the last two dimensions of a stack of matrices.
+pub(crate) fn transpose_matrix(expr: Noxpr) -> Result<Noxpr, Error> {
+    let rank = expr.shape().ok_or(Error::UnknownShape)?.len();
+    let mut permutation = (0..rank as i64).collect::<SmallVec<[i64; 4]>>();
+    permutation.swap(rank - 2, rank - 1);
+    Ok(expr.transpose(permutation))
+}
+
+/// Multiplies two stacks of matrices, treating every dimension but the last two as a batch dimension.
+pub(crate) fn matmul(lhs: Noxpr, rhs: Noxpr) -> Result<Noxpr, Error> {
+    let rank = lhs.shape().ok_or(Error::UnknownShape)?.len() as i64;
+    let batch = (0..rank - 2).collect::<SmallVec<[i64; 4]>>();
+    Ok(lhs.dot_general(
+        rhs,
+        DotDimensionNums {
+            lhs_contracting_dimensions: smallvec![rank - 1],
+            rhs_contracting_dimensions: smallvec![rank - 2],
+            lhs_batch_dimensions: batch.clone(),
+            rhs_batch_dimensions: batch,
+        },
+    ))
+}
+
+/// Zeroes every element of a stack of matrices outside of its lower (or upper) triangle. The
+/// diagonal is kept when `diagonal` is true.
+pub(crate) fn triangle(expr: Noxpr, lower: bool, diagonal: bool) -> Result<Noxpr, Error> {
+    let Some(NoxprTy::ArrayTy(ty)) = expr.ty() else {
+        return Err(Error::UnknownShape);
+    };
+    let rank = ty.shape.len();
+    let index = |dim| Noxpr::iota(ArrayTy::new(ElementType::S64, ty.shape.clone()), dim);
+    let (rows, cols) = match lower {
+        true => (index(rank - 2), index(rank - 1)),
+        false => (index(rank - 1), index(rank - 2)),
+    };
+    let mask = match diagonal {
+        true => rows.greater_or_equal(cols),
+        false => rows.greater(cols),
+    };
+    let zeros = scalar(ty.element_type, 0.0)?.broadcast(ty.shape);
+    Ok(mask.select(expr, zeros))
+}
+
+/// Takes the lower triangle of a stack of matrices, halving the diagonal.
+pub(crate) fn half_lower(expr: Noxpr) -> Result<Noxpr, Error> {
+    let element_type = expr.element_type().ok_or(Error::UnknownShape)?;
+    let strict = triangle(expr.clone(), true, false)?;
+    let with_diagonal = triangle(expr, true, true)?;
+    let half = scalar(element_type, 0.5)?;
+    Ok((strict + with_diagonal) * half)
+}
+
+impl<T: Field + RealField, const N: usize, R: Repr> Matrix<T, N, N, R> {
+    /// Returns the lower triangular Cholesky factor `l` of a symmetric positive definite matrix,
+    /// such that `self = l * l^T`.
+    pub fn cholesky(&self) -> Self {
+        Tensor {
+            inner: R::cholesky::<T, N>(&self.inner),
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn inverse(&self) -> Self {
+        Tensor {
+            inner: R::inverse::<T, N>(&self.inner),
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn determinant(&self) -> Scalar<T, R> {
+        Tensor {
+            inner: R::determinant::<T, N>(&self.inner),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Solves `self * x = b` for `x`, where `b` is either a vector or a matrix with `N` rows.
+    pub fn solve<D: Dim>(&self, b: &Tensor<T, D, R>) -> Tensor<T, D, R>
+    where
+        ShapeConstraint: DotDim<SquareDim<N>, D, Output = D>,
+        <D as ArrayDim>::Buf<MaybeUninit<T>>: ArrayBufUnit<T, Init = <D as ArrayDim>::Buf<T>>,
+    {
+        Tensor {
+            inner: R::solve::<T, D, N>(&self.inner, &b.inner),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the `(q, r)` decomposition of the matrix, where `q` is orthogonal and `r` is upper
+    /// triangular.
+    pub fn qr(&self) -> (Self, Self) {
+        let (q, r) = R::qr::<T, N>(&self.inner);
+        (
+            Tensor {
+                inner: q,
+                phantom: PhantomData,
+            },
+            Tensor {
+                inner: r,
+                phantom: PhantomData,
+            },
+        )
+    }
+
+    /// Returns the eigenvalues of a symmetric matrix in ascending order, along with a matrix whose
+    /// columns are the matching eigenvectors.
+    pub fn eigh(&self) -> (Vector<T, N, R>, Self) {
+        let (values, vectors) = R::eigh::<T, N>(&self.inner);
+        (
+            Tensor {
+                inner: values,
+                phantom: PhantomData,
+            },
+            Tensor {
+                inner: vectors,
+                phantom: PhantomData,
+            },
+        )
+    }
+
+    /// Returns the `(u, s, vt)` singular value decomposition of the matrix, such that
+    /// `self = u * diag(s) * vt`, with the singular values in descending order.
+    pub fn svd(&self) -> (Self, Vector<T, N, R>, Self) {
+        let (u, s, vt) = R::svd::<T, N>(&self.inner);
+        (
+            Tensor {
+                inner: u,
+                phantom: PhantomData,
+            },
+            Tensor {
+                inner: s,
+                phantom: PhantomData,
+            },
+            Tensor {
+                inner: vt,
+                phantom: PhantomData,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Client, CompFn, Matrix, Scalar, ToHost, Vector};
+    use nalgebra::{matrix, vector, Matrix3};
+    use smallvec::smallvec;
+
+    #[test]
+    fn test_solve_inverse_determinant() {
+        let client = Client::cpu().unwrap();
+        fn solve(a: Matrix<f64, 3, 3>, b: Vector<f64, 3>) -> Vector<f64, 3> {
+            a.solve(&b)
+        }
+        let comp = solve.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let a = matrix![0.0, 2.0, 1.0; 1.0, 1.0, 0.0; 3.0, 0.0, 1.0];
+        let out = exec
+            .run(&client, a, vector![5.0, 3.0, 6.0])
+            .unwrap()
+            .to_host();
+        approx::assert_relative_eq!(out, vector![1.0, 2.0, 1.0], epsilon = 1e-9);
+
+        fn inverse(a: Matrix<f64, 3, 3>) -> Matrix<f64, 3, 3> {
+            a.inverse()
+        }
+        let comp = inverse.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec.run(&client, a).unwrap().to_host();
+        approx::assert_relative_eq!(out, a.try_inverse().unwrap(), epsilon = 1e-9);
+
+        fn determinant(a: Matrix<f64, 3, 3>) -> Scalar<f64> {
+            a.determinant()
+        }
+        let comp = determinant.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec.run(&client, a).unwrap().to_host();
+        approx::assert_relative_eq!(out, a.determinant(), epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_qr_svd() {
+        let client = Client::cpu().unwrap();
+        let a = matrix![0.0, 2.0, 1.0; 1.0, 1.0, 0.0; 3.0, 0.0, 1.0];
+
+        fn qr_product(a: Matrix<f64, 3, 3>) -> Matrix<f64, 3, 3> {
+            let (q, r) = a.qr();
+            q.dot(&r)
+        }
+        let comp = qr_product.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec.run(&client, a).unwrap().to_host();
+        approx::assert_relative_eq!(out, a, epsilon = 1e-9);
+
+        fn q_gram(a: Matrix<f64, 3, 3>) -> Matrix<f64, 3, 3> {
+            let (q, _) = a.qr();
+            q.transpose().dot(&q)
+        }
+        let comp = q_gram.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec.run(&client, a).unwrap().to_host();
+        approx::assert_relative_eq!(out, Matrix3::identity(), epsilon = 1e-9);
+
+        fn r_factor(a: Matrix<f64, 3, 3>) -> Matrix<f64, 3, 3> {
+            a.qr().1
+        }
+        let comp = r_factor.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let r = exec.run(&client, a).unwrap().to_host();
+        approx::assert_relative_eq!(r, r.upper_triangle(), epsilon = 1e-9);
+
+        fn svd_product(a: Matrix<f64, 3, 3>) -> Matrix<f64, 3, 3> {
+            let (u, s, vt) = a.svd();
+            // scaling the columns of u by s is the same as multiplying by diag(s)
+            let s = s.inner.broadcast_in_dim(smallvec![3, 3], smallvec![1]);
+            Matrix::<f64, 3, 3>::from_op(u.inner * s).dot(&vt)
+        }
+        let comp = svd_product.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec.run(&client, a).unwrap().to_host();
+        approx::assert_relative_eq!(out, a, epsilon = 1e-9);
+
+        fn singular_values(a: Matrix<f64, 3, 3>) -> Vector<f64, 3> {
+            a.svd().1
+        }
+        let comp = singular_values.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let s = exec.run(&client, a).unwrap().to_host();
+        assert!(s[0] >= s[1] && s[1] >= s[2]);
+        approx::assert_relative_eq!(s.product(), a.determinant().abs(), epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_cholesky_eigh() {
+        let client = Client::cpu().unwrap();
+        fn cholesky(a: Matrix<f64, 2, 2>) -> Matrix<f64, 2, 2> {
+            a.cholesky()
+        }
+        let comp = cholesky.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let a = matrix![4.0, 2.0; 2.0, 3.0];
+        let out = exec.run(&client, a).unwrap().to_host();
+        approx::assert_relative_eq!(out, matrix![2.0, 0.0; 1.0, 2.0f64.sqrt()], epsilon = 1e-9);
+
+        fn eigenvalues(a: Matrix<f64, 2, 2>) -> Vector<f64, 2> {
+            a.eigh().0
+        }
+        let comp = eigenvalues.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec
+            .run(&client, matrix![2.0, 1.0; 1.0, 2.0])
+            .unwrap()
+            .to_host();
+        approx::assert_relative_eq!(out, vector![1.0, 3.0], epsilon = 1e-9);
+    }
+}
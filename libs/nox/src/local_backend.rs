@@ -1,20 +1,39 @@
-use nalgebra::{constraint::ShapeConstraint, Const, Dyn};
+use nalgebra::{constraint::ShapeConstraint, Const, DMatrix, Dyn, RealField};
 use smallvec::SmallVec;
 use std::{
+    cmp::Ordering,
     marker::PhantomData,
     mem::MaybeUninit,
-    ops::{Add, Div, Mul, Sub},
+    ops::{Add, Div, Mul, Neg, Sub},
 };
 
 use crate::{
-    AddDim, BroadcastDim, BroadcastedDim, DefaultMap, DefaultMappedDim, Dim, DottedDim, Field,
-    MapDim, ReplaceMappedDim, Repr, ScalarDim, TensorDim, XlaDim,
+    AddDim, BroadcastDim, BroadcastedDim, DefaultMap, DefaultMappedDim, Dim, DimReduce, DottedDim,
+    Error, Field, MapDim, Matrix, ReduceOp, ReducedDim, ReplaceMappedDim, Repr, Scalar, ScalarDim,
+    SquareDim, Tensor, TensorDim, TensorItem, Vector, XlaDim,
 };
 
 pub struct Array<T: Copy, D: ArrayDim> {
     buf: D::Buf<T>,
 }
 
+impl<T: Copy, D: ArrayDim> Clone for Array<T, D> {
+    fn clone(&self) -> Self {
+        Array {
+            buf: self.buf.clone(),
+        }
+    }
+}
+
+impl<T: Copy, D: ArrayDim> std::fmt::Debug for Array<T, D>
+where
+    D::Buf<T>: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.buf.fmt(f)
+    }
+}
+
 pub trait ArrayDim: TensorDim {
     type Buf<T>: ArrayBuf<T>
     where
@@ -304,7 +323,7 @@ macro_rules! impl_op {
                     }
                     std::cmp::Ordering::Greater => {
                         let mut out: Array<MaybeUninit<T1>, BroadcastedDim<D1, D2>> =
-                            Array::uninit(d2.as_ref());
+                            Array::uninit(d1.as_ref());
                         let mut broadcast_dims = d1.clone();
                         if !cobroadcast_dims(broadcast_dims.as_mut(), d2.as_ref()) {
                             todo!("handle unbroadcastble dims");
@@ -391,7 +410,11 @@ impl<T1: Copy, D1: ArrayDim + TensorDim + XlaDim> Array<T1, D1> {
         let dim_left = D1::dim(&left.buf);
         let stride_left = D1::strides(&left.buf);
         let dim_right = D2::dim(&right.buf);
-        let m = dim_left.as_ref().first().copied().unwrap_or(0);
+        // a vector on the left is a single row
+        let m = match dim_left.as_ref() {
+            [m, _] => *m,
+            _ => 1,
+        };
         let k = dim_right.as_ref().first().copied().unwrap_or(0);
         let n = dim_right.as_ref().get(1).copied().unwrap_or(1);
         let stride_right = D2::strides(&right.buf);
@@ -510,6 +533,307 @@ impl<T1: Copy, D1: ArrayDim + TensorDim + XlaDim> Array<T1, D1> {
         }
         unsafe { out.assume_init() }
     }
+    pub fn reduce<const AXIS: usize>(&self, op: ReduceOp) -> Array<T1, ReducedDim<D1, AXIS>>
+    where
+        T1: Field + PartialOrd,
+        ShapeConstraint: DimReduce<D1, AXIS>,
+        <ReducedDim<D1, AXIS> as ArrayDim>::Buf<MaybeUninit<T1>>:
+            ArrayBufUnit<T1, Init = <ReducedDim<D1, AXIS> as ArrayDim>::Buf<T1>>,
+    {
+        let dims = D1::dim(&self.buf);
+        let dims = dims.as_ref();
+        let len = dims[AXIS];
+        let inner_len: usize = dims[AXIS + 1..].iter().product();
+        let out_dims = dims
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != AXIS)
+            .map(|(_, dim)| *dim)
+            .collect::<SmallVec<[usize; 4]>>();
+        let mut out: Array<MaybeUninit<T1>, ReducedDim<D1, AXIS>> = Array::uninit(&out_dims);
+        let buf = self.buf.as_buf();
+        for (i, out) in out.buf.as_mut_buf().iter_mut().enumerate() {
+            let (outer, inner) = (i / inner_len, i % inner_len);
+            let elems = (0..len).map(|j| buf[(outer * len + j) * inner_len + inner]);
+            // each reduction starts from its identity, which is what an empty axis reduces to
+            let value = match op {
+                ReduceOp::Sum => elems.fold(T1::zero_prim(), |acc, x| acc + x),
+                ReduceOp::Prod => elems.fold(T1::one_prim(), |acc, x| acc * x),
+                ReduceOp::Max => elems.fold(T1::lowest_prim(), |acc, x| {
+                    extremum(acc, x, Ordering::Greater)
+                }),
+                ReduceOp::Min => elems.fold(T1::highest_prim(), |acc, x| {
+                    extremum(acc, x, Ordering::Less)
+                }),
+            };
+            out.write(value);
+        }
+        unsafe { out.assume_init() }
+    }
+}
+
+impl<T1: Copy, D1: Dim> Array<T1, D1> {
+    pub fn map<T2: Copy>(&self, f: impl Fn(T1) -> T2) -> Array<T2, D1>
+    where
+        <D1 as ArrayDim>::Buf<MaybeUninit<T2>>: ArrayBufUnit<T2, Init = <D1 as ArrayDim>::Buf<T2>>,
+    {
+        let mut out: Array<MaybeUninit<T2>, D1> = Array::uninit(D1::dim(&self.buf).as_ref());
+        for (a, out) in self
+            .buf
+            .as_buf()
+            .iter()
+            .zip(out.buf.as_mut_buf().iter_mut())
+        {
+            out.write(f(*a));
+        }
+        unsafe { out.assume_init() }
+    }
+
+    pub fn zip_map(&self, other: &Self, f: impl Fn(T1, T1) -> T1) -> Self
+    where
+        <D1 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D1 as ArrayDim>::Buf<T1>>,
+    {
+        let mut out: Array<MaybeUninit<T1>, D1> = Array::uninit(D1::dim(&self.buf).as_ref());
+        for ((a, b), out) in self
+            .buf
+            .as_buf()
+            .iter()
+            .zip(other.buf.as_buf().iter())
+            .zip(out.buf.as_mut_buf().iter_mut())
+        {
+            out.write(f(*a, *b));
+        }
+        unsafe { out.assume_init() }
+    }
+
+    /// Reinterprets the elements, in row-major order, as an array of shape `D2`.
+    pub fn reshape<D2: Dim>(&self) -> Result<Array<T1, D2>, Error>
+    where
+        <D2 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D2 as ArrayDim>::Buf<T1>>,
+    {
+        let dims = const_dims::<D2>();
+        check_reshape(D1::dim(&self.buf).as_ref(), &dims)?;
+        let buf = self.buf.as_buf();
+        let mut out: Array<MaybeUninit<T1>, D2> = Array::uninit(&dims);
+        for (a, out) in buf.iter().zip(out.buf.as_mut_buf().iter_mut()) {
+            out.write(*a);
+        }
+        Ok(unsafe { out.assume_init() })
+    }
+
+    /// Broadcasts the array to shape `D2`, aligning the trailing dimensions like numpy does.
+    pub fn broadcast<D2: Dim>(&self) -> Result<Array<T1, D2>, Error>
+    where
+        <D2 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D2 as ArrayDim>::Buf<T1>>,
+    {
+        let dims = const_dims::<D2>();
+        let in_dims = D1::dim(&self.buf);
+        let in_strides = D1::strides(&self.buf);
+        let (in_dims, in_strides) = (in_dims.as_ref(), in_strides.as_ref());
+        let leading = check_broadcast(in_dims, &dims)?;
+        let strides = dims
+            .iter()
+            .enumerate()
+            .map(|(i, dim)| match i.checked_sub(leading) {
+                Some(j) if in_dims[j] == *dim => in_strides[j],
+                _ => 0,
+            })
+            .collect::<SmallVec<[usize; 4]>>();
+        Ok(self.strided_copy(&dims, &strides, 0))
+    }
+
+    /// Copies out the block of shape `D2` that starts at `offsets`.
+    pub fn fixed_slice<D2: Dim>(&self, offsets: &[usize]) -> Result<Array<T1, D2>, Error>
+    where
+        <D2 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D2 as ArrayDim>::Buf<T1>>,
+    {
+        let dims = const_dims::<D2>();
+        let strides = D1::strides(&self.buf);
+        check_fixed_slice(D1::dim(&self.buf).as_ref(), &dims, offsets)?;
+        let start = offsets
+            .iter()
+            .zip(strides.as_ref())
+            .map(|(offset, stride)| offset * stride)
+            .sum();
+        Ok(self.strided_copy(&dims, strides.as_ref(), start))
+    }
+
+    /// Gathers an array of shape `dims`, where the element at index `i` is read from
+    /// `start + i · strides`.
+    fn strided_copy<D2: Dim>(
+        &self,
+        dims: &[usize],
+        strides: &[usize],
+        start: usize,
+    ) -> Array<T1, D2>
+    where
+        <D2 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D2 as ArrayDim>::Buf<T1>>,
+    {
+        let buf = self.buf.as_buf();
+        let mut out: Array<MaybeUninit<T1>, D2> = Array::uninit(dims);
+        for (i, out) in out.buf.as_mut_buf().iter_mut().enumerate() {
+            let mut index = i;
+            let mut offset = start;
+            for (dim, stride) in dims.iter().zip(strides).rev() {
+                offset += index % dim * stride;
+                index /= dim;
+            }
+            out.write(buf[offset]);
+        }
+        unsafe { out.assume_init() }
+    }
+}
+
+impl<T: Copy, const R: usize, const C: usize> Array<T, (Const<R>, Const<C>)> {
+    pub fn transpose(&self) -> Array<T, (Const<C>, Const<R>)> {
+        Array {
+            buf: std::array::from_fn(|i| std::array::from_fn(|j| self.buf[j][i])),
+        }
+    }
+}
+
+pub(crate) fn const_dims<D: XlaDim>() -> SmallVec<[usize; 4]> {
+    D::shape().iter().map(|dim| *dim as usize).collect()
+}
+
+/// Checks that an array of shape `from` holds as many elements as one of shape `to`.
+pub(crate) fn check_reshape(from: &[usize], to: &[usize]) -> Result<(), Error> {
+    if from.iter().product::<usize>() != to.iter().product::<usize>() {
+        return Err(Error::ShapeMismatch);
+    }
+    Ok(())
+}
+
+/// Checks that shape `from` broadcasts to `to` when their trailing dimensions are aligned, and
+/// returns the number of leading dimensions the broadcast adds.
+pub(crate) fn check_broadcast(from: &[usize], to: &[usize]) -> Result<usize, Error> {
+    let leading = to
+        .len()
+        .checked_sub(from.len())
+        .ok_or(Error::ShapeMismatch)?;
+    if from
+        .iter()
+        .zip(&to[leading..])
+        .any(|(from, to)| from != to && *from != 1)
+    {
+        return Err(Error::ShapeMismatch);
+    }
+    Ok(leading)
+}
+
+/// Checks that a block of shape `to` starting at `offsets` lies inside shape `from`.
+pub(crate) fn check_fixed_slice(
+    from: &[usize],
+    to: &[usize],
+    offsets: &[usize],
+) -> Result<(), Error> {
+    if offsets.len() != from.len() || to.len() != from.len() {
+        return Err(Error::ShapeMismatch);
+    }
+    if offsets
+        .iter()
+        .zip(to)
+        .zip(from)
+        .any(|((offset, to), from)| offset + to > *from)
+    {
+        return Err(Error::OutOfBoundsAccess);
+    }
+    Ok(())
+}
+
+impl<T: Field + RealField, const N: usize> Array<T, SquareDim<N>> {
+    fn to_dmatrix(&self) -> DMatrix<T> {
+        DMatrix::from_fn(N, N, |i, j| self.buf[i][j])
+    }
+
+    fn from_dmatrix(matrix: &DMatrix<T>) -> Self {
+        Array {
+            buf: std::array::from_fn(|i| std::array::from_fn(|j| matrix[(i, j)])),
+        }
+    }
+
+    /// Returns a matrix filled with NaNs, which stands in for the result of a failed decomposition.
+    fn nan() -> Self {
+        Array {
+            buf: [[nalgebra::convert(f64::NAN); N]; N],
+        }
+    }
+
+    pub fn cholesky(&self) -> Self {
+        match self.to_dmatrix().cholesky() {
+            Some(cholesky) => Self::from_dmatrix(&cholesky.l()),
+            None => Self::nan(),
+        }
+    }
+
+    pub fn inverse(&self) -> Self {
+        match self.to_dmatrix().try_inverse() {
+            Some(inverse) => Self::from_dmatrix(&inverse),
+            None => Self::nan(),
+        }
+    }
+
+    pub fn determinant(&self) -> Array<T, ScalarDim> {
+        Array {
+            buf: self.to_dmatrix().determinant(),
+        }
+    }
+
+    pub fn solve<D: Dim>(&self, b: &Array<T, D>) -> Array<T, D>
+    where
+        <D as ArrayDim>::Buf<MaybeUninit<T>>: ArrayBufUnit<T, Init = <D as ArrayDim>::Buf<T>>,
+    {
+        let rhs = b.buf.as_buf();
+        let cols = rhs.len() / N;
+        let rhs = DMatrix::from_row_slice(N, cols, rhs);
+        let x = self
+            .to_dmatrix()
+            .lu()
+            .solve(&rhs)
+            .unwrap_or_else(|| DMatrix::from_element(N, cols, nalgebra::convert(f64::NAN)));
+        let mut out: Array<MaybeUninit<T>, D> = Array::uninit(D::dim(&b.buf).as_ref());
+        for (i, out) in out.buf.as_mut_buf().iter_mut().enumerate() {
+            out.write(x[(i / cols, i % cols)]);
+        }
+        unsafe { out.assume_init() }
+    }
+
+    pub fn qr(&self) -> (Self, Self) {
+        let qr = self.to_dmatrix().qr();
+        (Self::from_dmatrix(&qr.q()), Self::from_dmatrix(&qr.r()))
+    }
+
+    /// Returns the eigenvalues in ascending order, along with the matching eigenvectors as columns.
+    pub fn eigh(&self) -> (Array<T, Const<N>>, Self) {
+        let eigen = self.to_dmatrix().symmetric_eigen();
+        let mut order = (0..N).collect::<SmallVec<[usize; 4]>>();
+        order.sort_by(|a, b| {
+            eigen.eigenvalues[*a]
+                .partial_cmp(&eigen.eigenvalues[*b])
+                .unwrap_or(Ordering::Equal)
+        });
+        let values = Array {
+            buf: std::array::from_fn(|i| eigen.eigenvalues[order[i]]),
+        };
+        let vectors = Array {
+            buf: std::array::from_fn(|i| {
+                std::array::from_fn(|j| eigen.eigenvectors[(i, order[j])])
+            }),
+        };
+        (values, vectors)
+    }
+
+    /// Returns the `(u, s, vt)` singular value decomposition, with the singular values in
+    /// descending order.
+    pub fn svd(&self) -> (Self, Array<T, Const<N>>, Self) {
+        let svd = self.to_dmatrix().svd(true, true);
+        let u = svd.u.as_ref().map_or_else(Self::nan, Self::from_dmatrix);
+        let vt = svd.v_t.as_ref().map_or_else(Self::nan, Self::from_dmatrix);
+        let s = Array {
+            buf: std::array::from_fn(|i| svd.singular_values[i]),
+        };
+        (u, s, vt)
+    }
 }
 
 type ConcatDim<D1, D2> = ReplaceMappedDim<
@@ -602,7 +926,10 @@ impl<'a, T, S: AsRef<[usize]>, I: AsMut<[usize]>, D: AsRef<[usize]>> Iterator
 pub struct LocalBackend;
 
 impl Repr for LocalBackend {
-    type Inner<T, D: Dim> = Array<T, D> where T: Copy;
+    type Inner<T, D: Dim>
+        = Array<T, D>
+    where
+        T: Copy;
 
     fn add<T, D1: ArrayDim, D2: ArrayDim>(
         left: &Self::Inner<T, D1>,
@@ -712,6 +1039,326 @@ impl Repr for LocalBackend {
     {
         arg.get(index)
     }
+    fn reduce<T1: Field + PartialOrd, D1: Dim, const AXIS: usize>(
+        arg: &Self::Inner<T1, D1>,
+        op: ReduceOp,
+    ) -> Self::Inner<T1, ReducedDim<D1, AXIS>>
+    where
+        ShapeConstraint: DimReduce<D1, AXIS>,
+        <ReducedDim<D1, AXIS> as ArrayDim>::Buf<MaybeUninit<T1>>:
+            ArrayBufUnit<T1, Init = <ReducedDim<D1, AXIS> as ArrayDim>::Buf<T1>>,
+    {
+        arg.reduce::<AXIS>(op)
+    }
+
+    fn cholesky<T1: Field + RealField, const N: usize>(
+        arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> Self::Inner<T1, SquareDim<N>> {
+        arg.cholesky()
+    }
+
+    fn inverse<T1: Field + RealField, const N: usize>(
+        arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> Self::Inner<T1, SquareDim<N>> {
+        arg.inverse()
+    }
+
+    fn determinant<T1: Field + RealField, const N: usize>(
+        arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> Self::Inner<T1, ScalarDim> {
+        arg.determinant()
+    }
+
+    fn solve<T1: Field + RealField, D1: Dim, const N: usize>(
+        a: &Self::Inner<T1, SquareDim<N>>,
+        b: &Self::Inner<T1, D1>,
+    ) -> Self::Inner<T1, D1>
+    where
+        <D1 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D1 as ArrayDim>::Buf<T1>>,
+    {
+        a.solve(b)
+    }
+
+    fn qr<T1: Field + RealField, const N: usize>(
+        arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> (Self::Inner<T1, SquareDim<N>>, Self::Inner<T1, SquareDim<N>>) {
+        arg.qr()
+    }
+
+    fn eigh<T1: Field + RealField, const N: usize>(
+        arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> (Self::Inner<T1, Const<N>>, Self::Inner<T1, SquareDim<N>>) {
+        arg.eigh()
+    }
+
+    fn svd<T1: Field + RealField, const N: usize>(
+        arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> (
+        Self::Inner<T1, SquareDim<N>>,
+        Self::Inner<T1, Const<N>>,
+        Self::Inner<T1, SquareDim<N>>,
+    ) {
+        arg.svd()
+    }
+
+    fn reshape<T1: Field, D1: Dim, D2: Dim>(
+        arg: &Self::Inner<T1, D1>,
+    ) -> Result<Self::Inner<T1, D2>, Error>
+    where
+        <D2 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D2 as ArrayDim>::Buf<T1>>,
+    {
+        arg.reshape()
+    }
+
+    fn broadcast<T1: Field, D1: Dim, D2: Dim>(
+        arg: &Self::Inner<T1, D1>,
+    ) -> Result<Self::Inner<T1, D2>, Error>
+    where
+        <D2 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D2 as ArrayDim>::Buf<T1>>,
+    {
+        arg.broadcast()
+    }
+
+    fn fixed_slice<T1: Field, D1: Dim, D2: Dim>(
+        arg: &Self::Inner<T1, D1>,
+        offsets: &[usize],
+    ) -> Result<Self::Inner<T1, D2>, Error>
+    where
+        <D2 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D2 as ArrayDim>::Buf<T1>>,
+    {
+        arg.fixed_slice(offsets)
+    }
+}
+
+impl<T: TensorItem, D: Dim> Clone for Tensor<T, D, LocalBackend> {
+    fn clone(&self) -> Self {
+        Tensor {
+            inner: self.inner.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: TensorItem, D: Dim> Tensor<T, D, LocalBackend> {
+    /// Wraps a host buffer, e.g `[[f64; 3]; 2]` for a `Matrix<f64, 2, 3, LocalBackend>`.
+    pub fn from_buf(buf: <D as ArrayDim>::Buf<T::Elem>) -> Self {
+        Tensor {
+            inner: Array { buf },
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn buf(&self) -> &<D as ArrayDim>::Buf<T::Elem> {
+        &self.inner.buf
+    }
+
+    pub fn into_buf(self) -> <D as ArrayDim>::Buf<T::Elem> {
+        self.inner.buf
+    }
+}
+
+impl<T: TensorItem, const R: usize, const C: usize> Matrix<T, R, C, LocalBackend> {
+    pub fn transpose(&self) -> Matrix<T, C, R, LocalBackend> {
+        Tensor {
+            inner: self.inner.transpose(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: Field + RealField, D: Dim> Tensor<T, D, LocalBackend>
+where
+    <D as ArrayDim>::Buf<MaybeUninit<T>>: ArrayBufUnit<T, Init = <D as ArrayDim>::Buf<T>>,
+{
+    fn map_elems(&self, f: impl Fn(T) -> T) -> Self {
+        Tensor {
+            inner: self.inner.map(f),
+            phantom: PhantomData,
+        }
+    }
+
+    fn zip_elems(&self, other: &Self, f: impl Fn(T, T) -> T) -> Self {
+        Tensor {
+            inner: self.inner.zip_map(&other.inner, f),
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn zeros() -> Self {
+        let mut out: Array<MaybeUninit<T>, D> = Array::uninit(&const_dims::<D>());
+        for x in out.buf.as_mut_buf() {
+            x.write(T::zero_prim());
+        }
+        Tensor {
+            inner: unsafe { out.assume_init() },
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn sqrt(&self) -> Self {
+        self.map_elems(|x| x.sqrt())
+    }
+
+    pub fn log(&self) -> Self {
+        self.map_elems(|x| x.ln())
+    }
+
+    pub fn sin(&self) -> Self {
+        self.map_elems(|x| x.sin())
+    }
+
+    pub fn cos(&self) -> Self {
+        self.map_elems(|x| x.cos())
+    }
+
+    pub fn exp(&self) -> Self {
+        self.map_elems(|x| x.exp())
+    }
+
+    pub fn tanh(&self) -> Self {
+        self.map_elems(|x| x.tanh())
+    }
+
+    pub fn abs(&self) -> Self {
+        self.map_elems(|x| x.abs())
+    }
+
+    pub fn floor(&self) -> Self {
+        self.map_elems(|x| x.floor())
+    }
+
+    pub fn ceil(&self) -> Self {
+        self.map_elems(|x| x.ceil())
+    }
+
+    pub fn rsqrt(&self) -> Self {
+        self.map_elems(|x| T::one_prim() / x.sqrt())
+    }
+
+    /// Computes the four-quadrant arctangent of `self / other`, element-wise.
+    pub fn atan2(&self, other: &Self) -> Self {
+        self.zip_elems(other, |y, x| y.atan2(x))
+    }
+
+    pub fn pow(&self, exp: &Self) -> Self {
+        self.zip_elems(exp, |x, exp| x.powf(exp))
+    }
+
+    /// Computes the element-wise maximum, which is NaN where either element is, like XLA's.
+    pub fn max(&self, other: &Self) -> Self {
+        self.zip_elems(other, max_nan)
+    }
+
+    /// Computes the element-wise minimum, which is NaN where either element is, like XLA's.
+    pub fn min(&self, other: &Self) -> Self {
+        self.zip_elems(other, min_nan)
+    }
+
+    /// Clamps every element to the range `[min, max]`.
+    pub fn clamp(&self, min: &Scalar<T, LocalBackend>, max: &Scalar<T, LocalBackend>) -> Self {
+        let (min, max) = (min.inner.buf, max.inner.buf);
+        self.map_elems(|x| min_nan(max_nan(x, min), max))
+    }
+}
+
+/// Keeps `x` over `acc` if it compares as `keep`, propagating NaNs like XLA's reductions.
+fn extremum<T: PartialOrd>(acc: T, x: T, keep: Ordering) -> T {
+    match x.partial_cmp(&acc) {
+        Some(ord) if ord == keep => x,
+        Some(_) => acc,
+        None if acc.partial_cmp(&acc).is_none() => acc,
+        None => x,
+    }
+}
+
+fn max_nan<T: RealField + Copy>(a: T, b: T) -> T {
+    match a.partial_cmp(&b) {
+        Some(Ordering::Less) => b,
+        Some(_) => a,
+        // one of them is NaN, which the sum propagates
+        None => a + b,
+    }
+}
+
+fn min_nan<T: RealField + Copy>(a: T, b: T) -> T {
+    match a.partial_cmp(&b) {
+        Some(Ordering::Greater) => b,
+        Some(_) => a,
+        None => a + b,
+    }
+}
+
+impl<T: Field + RealField, D: Dim> Neg for Tensor<T, D, LocalBackend>
+where
+    <D as ArrayDim>::Buf<MaybeUninit<T>>: ArrayBufUnit<T, Init = <D as ArrayDim>::Buf<T>>,
+{
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        self.map_elems(|x| -x)
+    }
+}
+
+impl<'a, T: Field + RealField, D: Dim> Neg for &'a Tensor<T, D, LocalBackend>
+where
+    <D as ArrayDim>::Buf<MaybeUninit<T>>: ArrayBufUnit<T, Init = <D as ArrayDim>::Buf<T>>,
+{
+    type Output = Tensor<T, D, LocalBackend>;
+
+    fn neg(self) -> Self::Output {
+        self.map_elems(|x| -x)
+    }
+}
+
+impl<T: Field + RealField, D: Dim> Mul<T> for Tensor<T, D, LocalBackend>
+where
+    <D as ArrayDim>::Buf<MaybeUninit<T>>: ArrayBufUnit<T, Init = <D as ArrayDim>::Buf<T>>,
+{
+    type Output = Self;
+
+    fn mul(self, rhs: T) -> Self::Output {
+        self.map_elems(|x| x * rhs)
+    }
+}
+
+impl<'a, T: Field + RealField, D: Dim> Mul<T> for &'a Tensor<T, D, LocalBackend>
+where
+    <D as ArrayDim>::Buf<MaybeUninit<T>>: ArrayBufUnit<T, Init = <D as ArrayDim>::Buf<T>>,
+{
+    type Output = Tensor<T, D, LocalBackend>;
+
+    fn mul(self, rhs: T) -> Self::Output {
+        self.map_elems(|x| x * rhs)
+    }
+}
+
+impl<T: Field> From<T> for Scalar<T, LocalBackend> {
+    fn from(val: T) -> Self {
+        Scalar::from_buf(val)
+    }
+}
+
+impl<T, const N: usize, S> From<nalgebra::Vector<T, Const<N>, S>> for Vector<T, N, LocalBackend>
+where
+    T: Field + nalgebra::Scalar,
+    S: nalgebra::Storage<T, Const<N>, Const<1>>,
+{
+    fn from(val: nalgebra::Vector<T, Const<N>, S>) -> Self {
+        Vector::from_buf(std::array::from_fn(|i| val[i]))
+    }
+}
+
+impl<T, const R: usize, const C: usize, S> From<nalgebra::Matrix<T, Const<R>, Const<C>, S>>
+    for Matrix<T, R, C, LocalBackend>
+where
+    T: Field + nalgebra::Scalar,
+    S: nalgebra::Storage<T, Const<R>, Const<C>>,
+{
+    fn from(val: nalgebra::Matrix<T, Const<R>, Const<C>, S>) -> Self {
+        Matrix::from_buf(std::array::from_fn(|i| {
+            std::array::from_fn(|j| val[(i, j)])
+        }))
+    }
 }
 
 fn matmul_dims(a: &'_ [usize], b: &'_ [usize]) -> Option<([usize; 2], usize)> {
@@ -817,4 +1464,166 @@ mod tests {
         let d: Array<f32, Const<3>> = Array::concat_many([&a, &b, &c]);
         assert_eq!(d.buf, [1.0, 2.0, 3.0]);
     }
+
+    #[test]
+    fn test_reduce() {
+        let a: Array<f32, (Const<2>, Const<3>)> = Array {
+            buf: [[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]],
+        };
+        let b: Array<f32, Const<3>> = a.reduce::<0>(ReduceOp::Sum);
+        assert_eq!(b.buf, [5.0, 7.0, 9.0]);
+        let b: Array<f32, Const<2>> = a.reduce::<1>(ReduceOp::Max);
+        assert_eq!(b.buf, [3.0, 6.0]);
+        let b: Array<f32, Const<2>> = a.reduce::<1>(ReduceOp::Prod);
+        assert_eq!(b.buf, [6.0, 120.0]);
+
+        let a: Array<f32, Const<3>> = Array {
+            buf: [2.0, -1.0, 4.0],
+        };
+        let b: Array<f32, ()> = a.reduce::<0>(ReduceOp::Min);
+        assert_eq!(b.buf, -1.0);
+
+        let a: Array<f32, Const<3>> = Array {
+            buf: [2.0, f32::NAN, 4.0],
+        };
+        let b: Array<f32, ()> = a.reduce::<0>(ReduceOp::Max);
+        assert!(b.buf.is_nan());
+
+        let a: Array<f32, (Const<2>, Const<0>)> = Array { buf: [[], []] };
+        let b: Array<f32, Const<2>> = a.reduce::<1>(ReduceOp::Max);
+        assert_eq!(b.buf, [f32::NEG_INFINITY; 2]);
+        let b: Array<f32, Const<2>> = a.reduce::<1>(ReduceOp::Min);
+        assert_eq!(b.buf, [f32::INFINITY; 2]);
+        let b: Array<f32, Const<2>> = a.reduce::<1>(ReduceOp::Sum);
+        assert_eq!(b.buf, [0.0; 2]);
+    }
+
+    #[test]
+    fn test_linalg() {
+        let a: Array<f64, (Const<2>, Const<2>)> = Array {
+            buf: [[4.0, 2.0], [2.0, 3.0]],
+        };
+        assert!((a.determinant().buf - 8.0).abs() < 1e-9);
+        let l = a.cholesky();
+        let sqrt_2 = 2.0f64.sqrt();
+        for (l, e) in l.buf.iter().flatten().zip([2.0, 0.0, 1.0, sqrt_2]) {
+            assert!((l - e).abs() < 1e-9, "{l} != {e}");
+        }
+        let b: Array<f64, Const<2>> = Array { buf: [8.0, 7.0] };
+        let x = a.solve(&b);
+        for (x, e) in x.buf.iter().zip([1.25, 1.5]) {
+            assert!((x - e).abs() < 1e-9, "{x} != {e}");
+        }
+        let (values, _) = a.eigh();
+        assert!(values.buf[0] <= values.buf[1]);
+        assert!((values.buf[0] * values.buf[1] - 8.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_qr_svd() {
+        let a: Array<f64, (Const<3>, Const<3>)> = Array {
+            buf: [[0.0, 2.0, 1.0], [1.0, 1.0, 0.0], [3.0, 0.0, 1.0]],
+        };
+        let matrix =
+            |a: &Array<f64, (Const<3>, Const<3>)>| DMatrix::from_fn(3, 3, |i, j| a.buf[i][j]);
+        let expected = matrix(&a);
+
+        let (q, r) = a.qr();
+        let (q, r) = (matrix(&q), matrix(&r));
+        approx::assert_relative_eq!(&q * &r, expected, epsilon = 1e-9);
+        approx::assert_relative_eq!(q.transpose() * &q, DMatrix::identity(3, 3), epsilon = 1e-9);
+        for i in 0..3 {
+            for j in 0..i {
+                assert!(r[(i, j)].abs() < 1e-9);
+            }
+        }
+
+        let (u, s, vt) = a.svd();
+        assert!(s.buf[0] >= s.buf[1] && s.buf[1] >= s.buf[2]);
+        let sigma = DMatrix::from_fn(3, 3, |i, j| if i == j { s.buf[i] } else { 0.0 });
+        approx::assert_relative_eq!(matrix(&u) * sigma * matrix(&vt), expected, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_shape_ops() {
+        let a: Array<f32, (Const<2>, Const<3>)> = Array {
+            buf: [[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]],
+        };
+        let b: Array<f32, (Const<3>, Const<2>)> = a.transpose();
+        assert_eq!(b.buf, [[1.0, 4.0], [2.0, 5.0], [3.0, 6.0]]);
+        let b: Array<f32, (Const<3>, Const<2>)> = a.reshape().unwrap();
+        assert_eq!(b.buf, [[1.0, 2.0], [3.0, 4.0], [5.0, 6.0]]);
+        let b: Array<f32, (Const<2>, Const<2>)> = a.fixed_slice(&[0, 1]).unwrap();
+        assert_eq!(b.buf, [[2.0, 3.0], [5.0, 6.0]]);
+        assert!(a.reshape::<(Const<2>, Const<2>)>().is_err());
+        assert!(a.fixed_slice::<(Const<2>, Const<2>)>(&[0, 2]).is_err());
+        assert!(a.fixed_slice::<Const<2>>(&[0]).is_err());
+        assert!(a.broadcast::<(Const<2>, Const<2>)>().is_err());
+
+        let a: Array<f32, Const<3>> = Array {
+            buf: [1.0, 2.0, 3.0],
+        };
+        let b: Array<f32, (Const<2>, Const<3>)> = a.broadcast().unwrap();
+        assert_eq!(b.buf, [[1.0, 2.0, 3.0], [1.0, 2.0, 3.0]]);
+        let a: Array<f32, ()> = Array { buf: 2.0 };
+        let b: Array<f32, Const<2>> = a.broadcast().unwrap();
+        assert_eq!(b.buf, [2.0, 2.0]);
+    }
+
+    #[test]
+    fn test_shape_parity() {
+        use crate::{Client, CompFn, ToHost};
+
+        fn shape_ops<R: Repr>(m: &Matrix<f32, 2, 3, R>) -> Result<Matrix<f32, 2, 2, R>, Error> {
+            let row: Vector<f32, 3, R> = m
+                .try_fixed_slice::<(Const<1>, Const<3>)>(&[1, 0])?
+                .try_reshape()?;
+            let rows: Matrix<f32, 2, 3, R> = row.try_broadcast()?;
+            rows.try_fixed_slice(&[0, 1])
+        }
+        fn bad_shapes<R: Repr>(m: &Matrix<f32, 2, 3, R>) {
+            assert!(m.try_reshape::<Const<4>>().is_err());
+            assert!(m.try_broadcast::<(Const<2>, Const<2>)>().is_err());
+            assert!(m.try_fixed_slice::<(Const<2>, Const<2>)>(&[0, 2]).is_err());
+            assert!(m.try_fixed_slice::<Const<2>>(&[0]).is_err());
+        }
+
+        let m = Matrix::<f32, 2, 3, LocalBackend>::from_buf([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        assert_eq!(shape_ops(&m).unwrap().into_buf(), [[5.0, 6.0], [5.0, 6.0]]);
+        bad_shapes(&m);
+
+        let client = Client::cpu().unwrap();
+        let op = |m: Matrix<f32, 2, 3>| -> Matrix<f32, 2, 2> {
+            bad_shapes(&m);
+            shape_ops(&m).unwrap()
+        };
+        let exec = op.build().unwrap().compile(&client).unwrap();
+        let out = exec
+            .run(&client, nalgebra::matrix![1.0f32, 2.0, 3.0; 4.0, 5.0, 6.0])
+            .unwrap()
+            .to_host();
+        assert_eq!(out, nalgebra::matrix![5.0, 6.0; 5.0, 6.0]);
+    }
+
+    #[test]
+    fn test_tensor_math() {
+        let a = Vector::<f64, 3, LocalBackend>::from_buf([0.0, 4.0, -9.0]);
+        assert_eq!(a.abs().sqrt().into_buf(), [0.0, 2.0, 3.0]);
+        assert_eq!((-a.clone() * 2.0).into_buf(), [0.0, -8.0, 18.0]);
+        let b = Vector::<f64, 3, LocalBackend>::from_buf([1.0, 1.0, 1.0]);
+        assert_eq!(a.max(&b).into_buf(), [1.0, 4.0, 1.0]);
+        let nan = Vector::<f64, 3, LocalBackend>::from_buf([f64::NAN, 0.0, 0.0]);
+        assert!(nan.max(&b).into_buf()[0].is_nan());
+        assert!(b.min(&nan).into_buf()[0].is_nan());
+        assert_eq!((&a + &b).into_buf(), [1.0, 5.0, -8.0]);
+        let x = Vector::<f64, 3, LocalBackend>::from_buf([1.0, 0.0, 0.0]);
+        let y = Vector::<f64, 3, LocalBackend>::from_buf([0.0, 1.0, 0.0]);
+        assert_eq!(x.cross(&y).into_buf(), [0.0, 0.0, 1.0]);
+        assert_eq!(a.norm_squared().into_buf(), 97.0);
+        let m = Matrix::<f64, 2, 3, LocalBackend>::from(nalgebra::matrix![
+            1.0, 2.0, 3.0;
+            4.0, 5.0, 6.0
+        ]);
+        assert_eq!(m.into_buf(), [[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+    }
 }
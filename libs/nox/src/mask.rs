@@ -0,0 +1,113 @@
+//! Provides boolean masks produced by comparing tensors.
+use crate::{Dim, FromOp, IntoOp, Noxpr, Tensor, TensorItem};
+use std::marker::PhantomData;
+
+/// An element-wise boolean mask with dimensionality `D`.
+///
+/// Masks are produced by the comparison methods on [`Tensor`], and can be combined with each
+/// other or used to pick between the elements of two tensors.
+pub struct Mask<D: Dim> {
+    pub(crate) inner: Noxpr,
+    pub(crate) phantom: PhantomData<D>,
+}
+
+impl<D: Dim> Clone for Mask<D> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<D: Dim> std::fmt::Debug for Mask<D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Mask").field("inner", &self.inner).finish()
+    }
+}
+
+impl<D: Dim> FromOp for Mask<D> {
+    fn from_op(inner: Noxpr) -> Self {
+        Self {
+            inner,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<D: Dim> IntoOp for Mask<D> {
+    fn into_op(self) -> Noxpr {
+        self.inner
+    }
+}
+
+impl<D: Dim> Mask<D> {
+    pub fn and(&self, other: &Self) -> Self {
+        Self::from_op(self.inner.clone().and(other.inner.clone()))
+    }
+
+    pub fn or(&self, other: &Self) -> Self {
+        Self::from_op(self.inner.clone().or(other.inner.clone()))
+    }
+
+    /// Picks each element from `on_true` where the mask is set, and from `on_false` otherwise.
+    pub fn select<T: TensorItem>(
+        &self,
+        on_true: &Tensor<T, D>,
+        on_false: &Tensor<T, D>,
+    ) -> Tensor<T, D> {
+        Tensor::from_op(
+            self.inner
+                .clone()
+                .select(on_true.inner.clone(), on_false.inner.clone()),
+        )
+    }
+}
+
+impl<T: TensorItem, D: Dim> Tensor<T, D> {
+    pub fn equal(&self, other: &Self) -> Mask<D> {
+        Mask::from_op(self.inner.clone().equal(other.inner.clone()))
+    }
+
+    pub fn not_equal(&self, other: &Self) -> Mask<D> {
+        Mask::from_op(self.inner.clone().not_equal(other.inner.clone()))
+    }
+
+    pub fn greater(&self, other: &Self) -> Mask<D> {
+        Mask::from_op(self.inner.clone().greater(other.inner.clone()))
+    }
+
+    pub fn greater_or_equal(&self, other: &Self) -> Mask<D> {
+        Mask::from_op(self.inner.clone().greater_or_equal(other.inner.clone()))
+    }
+
+    pub fn less(&self, other: &Self) -> Mask<D> {
+        Mask::from_op(self.inner.clone().less(other.inner.clone()))
+    }
+
+    pub fn less_or_equal(&self, other: &Self) -> Mask<D> {
+        Mask::from_op(self.inner.clone().less_or_equal(other.inner.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Client, CompFn, ToHost, Vector};
+    use nalgebra::vector;
+
+    #[test]
+    fn test_select() {
+        let client = Client::cpu().unwrap();
+        fn relu(x: Vector<f32, 3>) -> Vector<f32, 3> {
+            let zeros = Vector::<f32, 3>::zeros();
+            x.greater(&zeros).select(&x, &zeros)
+        }
+        let comp = relu.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec
+            .run(&client, vector![-1.0f32, 0.5, 2.0])
+            .unwrap()
+            .to_host();
+        assert_eq!(out, vector![0.0, 0.5, 2.0])
+    }
+}
@@ -1,5 +1,6 @@
 use crate::{
-    ArrayTy, Buffer, BufferArg, Client, FromHost, Literal, MaybeOwned, Noxpr, Op, Tensor, ToHost,
+    ArrayTy, Buffer, BufferArg, Client, FromHost, Literal, MaybeOwned, Noxpr, Op, Tensor,
+    TensorItem, ToHost,
 };
 use nalgebra::{ArrayStorage, Const, IsContiguous, Scalar as NalgebraScalar, Storage};
 use num_traits::Zero;
@@ -53,6 +54,15 @@ impl<T: ArrayElement + NativeType, const R: usize, const C: usize> Matrix<T, R,
     }
 }
 
+impl<T: TensorItem, const R: usize, const C: usize> Matrix<T, R, C, Op> {
+    pub fn transpose(&self) -> Matrix<T, C, R, Op> {
+        Matrix {
+            inner: self.inner.clone().transpose(smallvec![1, 0]),
+            phantom: PhantomData,
+        }
+    }
+}
+
 pub trait MatrixExt<T: ArrayElement + NativeType, const R: usize, const C: usize> {
     fn constant(&self) -> Matrix<T, R, C, Op>;
     fn literal(&self) -> Matrix<T, R, C, Literal>;
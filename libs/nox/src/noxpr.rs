@@ -7,12 +7,14 @@ use std::{
     sync::Arc,
 };
 
+use half::{bf16, f16};
 use itertools::Itertools;
 use smallvec::{smallvec, SmallVec};
 use xla::{ArrayElement, ElementType, NativeType, XlaBuilder, XlaOp, XlaOpRef};
 
 use crate::{
-    CompFn, DefaultMap, DefaultMappedDim, Dim, Error, FromOp, IntoOp, MapDim, Tensor, TensorItem,
+    CompFn, DefaultMap, DefaultMappedDim, Dim, Error, FromOp, IntoOp, MapDim, Mask, ScalarDim,
+    Tensor, TensorItem,
 };
 
 #[derive(Debug)]
@@ -35,9 +37,19 @@ pub enum NoxprNode {
     Div(BinaryOp),
     And(BinaryOp),
     Or(BinaryOp),
+    Xor(BinaryOp),
+    ShiftLeft(BinaryOp),
+    ShiftRightLogical(BinaryOp),
     GreaterOrEqual(BinaryOp),
     LessOrEqual(BinaryOp),
     Less(BinaryOp),
+    Greater(BinaryOp),
+    Equal(BinaryOp),
+    NotEqual(BinaryOp),
+    Atan2(BinaryOp),
+    Pow(BinaryOp),
+    Max(BinaryOp),
+    Min(BinaryOp),
 
     // Matrix Multiplication
     Dot(BinaryOp),
@@ -49,6 +61,19 @@ pub enum NoxprNode {
     Log(Noxpr),
     Sin(Noxpr),
     Cos(Noxpr),
+    Exp(Noxpr),
+    Tanh(Noxpr),
+    Abs(Noxpr),
+    Floor(Noxpr),
+    Ceil(Noxpr),
+    Rsqrt(Noxpr),
+
+    // Type Conversion
+    Convert(Convert),
+
+    // Ternary Ops
+    Select(Select),
+    Clamp(Clamp),
 
     // Nary ops
     Concat(Concat),
@@ -64,9 +89,22 @@ pub enum NoxprNode {
     Slice(Slice),
     DynamicSlice(DynamicSlice),
     DynamicUpdateSlice(DynamicUpdateSlice),
+    Scatter(Scatter),
+
+    // Reductions
+    Reduce(Reduce),
+
+    // Linear Algebra
+    Cholesky(Noxpr),
+    TriangularSolve(TriangularSolve),
+    Qr(Noxpr),
+    Eigh(Noxpr),
+    Svd(Noxpr),
 
     // Control Flow
     Scan(Scan),
+    Cond(Cond),
+    While(While),
 
     #[cfg(feature = "jax")]
     Jax(pyo3::PyObject),
@@ -92,7 +130,7 @@ pub enum NoxprTy {
 }
 
 impl NoxprTy {
-    fn pretty_print(&self, writer: &mut impl std::fmt::Write) -> std::fmt::Result {
+    pub(crate) fn pretty_print(&self, writer: &mut impl std::fmt::Write) -> std::fmt::Result {
         match self {
             NoxprTy::ArrayTy(a) => a.pretty_print(writer),
             NoxprTy::Tuple(t) => {
@@ -210,7 +248,7 @@ pub struct DotGeneral {
 impl DotGeneral {
     fn shape(&self) -> Option<SmallVec<[i64; 4]>> {
         let lhs = self.lhs.shape()?;
-        let rhs = self.lhs.shape()?;
+        let rhs = self.rhs.shape()?;
         let DotDimensionNums {
             lhs_contracting_dimensions: lhs_contracting,
             rhs_contracting_dimensions: rhs_contracting,
@@ -362,6 +400,12 @@ pub struct DynamicSlice {
     pub size_indices: SmallVec<[i64; 4]>,
 }
 
+#[derive(Debug)]
+pub struct Convert {
+    pub expr: Noxpr,
+    pub element_type: ElementType,
+}
+
 #[derive(Debug)]
 pub struct Reshape {
     pub expr: Noxpr,
@@ -405,6 +449,37 @@ pub struct DynamicUpdateSlice {
     pub update: Noxpr,
 }
 
+/// Scatter-add `updates` into `expr` at the positions described by `indices`.
+///
+/// The dimension numbers mirror XLA's `ScatterDimensionNumbers`, which makes this the
+/// transpose of [`Gather`] when the same dimensions are used.
+#[derive(Debug)]
+pub struct Scatter {
+    pub expr: Noxpr,
+    pub indices: Noxpr,
+    pub updates: Noxpr,
+    pub update_window_dims: SmallVec<[i64; 4]>,
+    pub inserted_window_dims: SmallVec<[i64; 4]>,
+    pub scatter_dims_to_operand_dims: SmallVec<[i64; 4]>,
+    pub index_vector_dim: i64,
+}
+
+/// Picks elements from `on_true` where `cond` is true, and from `on_false` otherwise.
+#[derive(Debug)]
+pub struct Select {
+    pub cond: Noxpr,
+    pub on_true: Noxpr,
+    pub on_false: Noxpr,
+}
+
+/// Clamps every element of `expr` to the range `[min, max]`.
+#[derive(Debug)]
+pub struct Clamp {
+    pub min: Noxpr,
+    pub expr: Noxpr,
+    pub max: Noxpr,
+}
+
 #[derive(Debug)]
 pub struct GetTupleElement {
     pub expr: Noxpr,
@@ -425,6 +500,99 @@ pub struct Scan {
     pub scan_fn: NoxprFn,
 }
 
+/// Solves `op(a) * x = b` for `x`, or `x * op(a) = b` when `left_side` is false, where `a` is a
+/// triangular matrix and `op(a)` is either `a` or its transpose.
+#[derive(Debug)]
+pub struct TriangularSolve {
+    pub a: Noxpr,
+    pub b: Noxpr,
+    pub left_side: bool,
+    pub lower: bool,
+    pub unit_diagonal: bool,
+    pub transpose_a: bool,
+}
+
+/// Reduces `expr` along `dims` with `op`, removing those dimensions from the output.
+#[derive(Debug)]
+pub struct Reduce {
+    pub expr: Noxpr,
+    pub op: ReduceOp,
+    pub dims: SmallVec<[i64; 4]>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReduceOp {
+    Sum,
+    Prod,
+    Max,
+    Min,
+}
+
+impl ReduceOp {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReduceOp::Sum => "sum",
+            ReduceOp::Prod => "prod",
+            ReduceOp::Max => "max",
+            ReduceOp::Min => "min",
+        }
+    }
+
+    /// Returns the identity of this reduction as a scalar constant.
+    pub fn init(&self, element_type: ElementType) -> Result<Noxpr, Error> {
+        let init = match element_type {
+            ElementType::F16 => {
+                self.init_value(f16::ZERO, f16::ONE, f16::NEG_INFINITY, f16::INFINITY)
+            }
+            ElementType::Bf16 => {
+                self.init_value(bf16::ZERO, bf16::ONE, bf16::NEG_INFINITY, bf16::INFINITY)
+            }
+            ElementType::F32 => self.init_value(0.0f32, 1.0, f32::NEG_INFINITY, f32::INFINITY),
+            ElementType::F64 => self.init_value(0.0f64, 1.0, f64::NEG_INFINITY, f64::INFINITY),
+            ElementType::S16 => self.init_value(0i16, 1, i16::MIN, i16::MAX),
+            ElementType::S32 => self.init_value(0i32, 1, i32::MIN, i32::MAX),
+            ElementType::S64 => self.init_value(0i64, 1, i64::MIN, i64::MAX),
+            ElementType::U16 => self.init_value(0u16, 1, u16::MIN, u16::MAX),
+            ElementType::U32 => self.init_value(0u32, 1, u32::MIN, u32::MAX),
+            ElementType::U64 => self.init_value(0u64, 1, u64::MIN, u64::MAX),
+            _ => return Err(Error::IncompatibleDType),
+        };
+        Ok(init)
+    }
+
+    fn init_value<T: NativeType + ArrayElement>(
+        &self,
+        zero: T,
+        one: T,
+        lowest: T,
+        highest: T,
+    ) -> Noxpr {
+        match self {
+            ReduceOp::Sum => zero.constant(),
+            ReduceOp::Prod => one.constant(),
+            ReduceOp::Max => lowest.constant(),
+            ReduceOp::Min => highest.constant(),
+        }
+    }
+}
+
+/// Calls `on_true` or `on_false` with `operands`, depending on the scalar predicate `pred`.
+#[derive(Debug, Clone)]
+pub struct Cond {
+    pub pred: Noxpr,
+    pub operands: Vec<Noxpr>,
+    pub on_true: NoxprFn,
+    pub on_false: NoxprFn,
+}
+
+/// Repeatedly applies `body_fn` to the state for as long as `cond_fn` returns true.
+#[derive(Debug, Clone)]
+pub struct While {
+    pub initial_state: Noxpr,
+    pub cond_fn: NoxprFn,
+    pub body_fn: NoxprFn,
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub struct NoxprId(usize);
 
@@ -455,7 +623,7 @@ impl Noxpr {
         }))
     }
 
-    fn dot_general(self, rhs: Noxpr, dimensions: DotDimensionNums) -> Self {
+    pub(crate) fn dot_general(self, rhs: Noxpr, dimensions: DotDimensionNums) -> Self {
         Self::new(NoxprNode::DotGeneral(DotGeneral {
             lhs: self,
             rhs,
@@ -479,6 +647,62 @@ impl Noxpr {
         Self::new(NoxprNode::Cos(self))
     }
 
+    pub fn exp(self) -> Self {
+        Self::new(NoxprNode::Exp(self))
+    }
+
+    pub fn tanh(self) -> Self {
+        Self::new(NoxprNode::Tanh(self))
+    }
+
+    pub fn abs(self) -> Self {
+        Self::new(NoxprNode::Abs(self))
+    }
+
+    pub fn floor(self) -> Self {
+        Self::new(NoxprNode::Floor(self))
+    }
+
+    pub fn ceil(self) -> Self {
+        Self::new(NoxprNode::Ceil(self))
+    }
+
+    pub fn rsqrt(self) -> Self {
+        Self::new(NoxprNode::Rsqrt(self))
+    }
+
+    pub fn atan2(self, rhs: Noxpr) -> Self {
+        Self::new(NoxprNode::Atan2(BinaryOp { lhs: self, rhs }))
+    }
+
+    pub fn pow(self, rhs: Noxpr) -> Self {
+        Self::new(NoxprNode::Pow(BinaryOp { lhs: self, rhs }))
+    }
+
+    pub fn max(self, rhs: Noxpr) -> Self {
+        Self::new(NoxprNode::Max(BinaryOp { lhs: self, rhs }))
+    }
+
+    pub fn min(self, rhs: Noxpr) -> Self {
+        Self::new(NoxprNode::Min(BinaryOp { lhs: self, rhs }))
+    }
+
+    pub fn select(self, on_true: Noxpr, on_false: Noxpr) -> Self {
+        Self::new(NoxprNode::Select(Select {
+            cond: self,
+            on_true,
+            on_false,
+        }))
+    }
+
+    pub fn clamp(self, min: Noxpr, max: Noxpr) -> Self {
+        Self::new(NoxprNode::Clamp(Clamp {
+            min,
+            expr: self,
+            max,
+        }))
+    }
+
     pub fn constant(data: xla::Literal, ty: ArrayTy) -> Self {
         Self::new(NoxprNode::Constant(Constant { data, ty }))
     }
@@ -541,6 +765,18 @@ impl Noxpr {
         Self::new(NoxprNode::And(BinaryOp { lhs: self, rhs }))
     }
 
+    pub fn xor(self, rhs: Noxpr) -> Self {
+        Self::new(NoxprNode::Xor(BinaryOp { lhs: self, rhs }))
+    }
+
+    pub fn shift_left(self, rhs: Noxpr) -> Self {
+        Self::new(NoxprNode::ShiftLeft(BinaryOp { lhs: self, rhs }))
+    }
+
+    pub fn shift_right_logical(self, rhs: Noxpr) -> Self {
+        Self::new(NoxprNode::ShiftRightLogical(BinaryOp { lhs: self, rhs }))
+    }
+
     pub fn greater_or_equal(self, rhs: Noxpr) -> Self {
         Self::new(NoxprNode::GreaterOrEqual(BinaryOp { lhs: self, rhs }))
     }
@@ -553,6 +789,25 @@ impl Noxpr {
         Self::new(NoxprNode::Less(BinaryOp { lhs: self, rhs }))
     }
 
+    pub fn greater(self, rhs: Noxpr) -> Self {
+        Self::new(NoxprNode::Greater(BinaryOp { lhs: self, rhs }))
+    }
+
+    pub fn equal(self, rhs: Noxpr) -> Self {
+        Self::new(NoxprNode::Equal(BinaryOp { lhs: self, rhs }))
+    }
+
+    pub fn not_equal(self, rhs: Noxpr) -> Self {
+        Self::new(NoxprNode::NotEqual(BinaryOp { lhs: self, rhs }))
+    }
+
+    pub fn convert(self, element_type: ElementType) -> Self {
+        Self::new(NoxprNode::Convert(Convert {
+            expr: self,
+            element_type,
+        }))
+    }
+
     pub fn reshape(self, new_sizes: SmallVec<[i64; 4]>) -> Self {
         Self::new(NoxprNode::Reshape(Reshape {
             expr: self,
@@ -580,6 +835,29 @@ impl Noxpr {
         }))
     }
 
+    /// Scatter-adds `updates` into `self`, see [`Scatter`]. Updates landing on the same index are
+    /// summed, and windows that fall out of bounds are dropped, matching XLA's scatter with an
+    /// add combiner.
+    pub fn scatter(
+        self,
+        indices: Noxpr,
+        updates: Noxpr,
+        update_window_dims: SmallVec<[i64; 4]>,
+        inserted_window_dims: SmallVec<[i64; 4]>,
+        scatter_dims_to_operand_dims: SmallVec<[i64; 4]>,
+        index_vector_dim: i64,
+    ) -> Self {
+        Self::new(NoxprNode::Scatter(Scatter {
+            expr: self,
+            indices,
+            updates,
+            update_window_dims,
+            inserted_window_dims,
+            scatter_dims_to_operand_dims,
+            index_vector_dim,
+        }))
+    }
+
     pub fn iota(shape: ArrayTy, dim: usize) -> Self {
         Self::new(NoxprNode::Iota(Iota { shape, dim }))
     }
@@ -599,6 +877,102 @@ impl Noxpr {
         }))
     }
 
+    /// Returns the lower triangular Cholesky factor of a symmetric positive definite matrix.
+    pub fn cholesky(self) -> Self {
+        Self::new(NoxprNode::Cholesky(self))
+    }
+
+    pub fn triangular_solve(
+        self,
+        b: Noxpr,
+        left_side: bool,
+        lower: bool,
+        unit_diagonal: bool,
+        transpose_a: bool,
+    ) -> Self {
+        Self::new(NoxprNode::TriangularSolve(TriangularSolve {
+            a: self,
+            b,
+            left_side,
+            lower,
+            unit_diagonal,
+            transpose_a,
+        }))
+    }
+
+    /// Returns a `(q, r)` tuple holding the full QR decomposition.
+    pub fn qr(self) -> Self {
+        Self::new(NoxprNode::Qr(self))
+    }
+
+    /// Returns an `(eigenvectors, eigenvalues)` tuple for a symmetric matrix, using its lower
+    /// triangle. The eigenvalues are sorted in ascending order.
+    pub fn eigh(self) -> Self {
+        Self::new(NoxprNode::Eigh(self))
+    }
+
+    /// Returns a `(u, s, vt)` tuple holding the singular value decomposition, with the singular
+    /// values sorted in descending order.
+    pub fn svd(self) -> Self {
+        Self::new(NoxprNode::Svd(self))
+    }
+
+    pub fn reduce(self, op: ReduceOp, dims: SmallVec<[i64; 4]>) -> Self {
+        Self::new(NoxprNode::Reduce(Reduce {
+            expr: self,
+            op,
+            dims,
+        }))
+    }
+
+    pub fn reduce_sum(self, dims: SmallVec<[i64; 4]>) -> Self {
+        self.reduce(ReduceOp::Sum, dims)
+    }
+
+    pub fn reduce_max(self, dims: SmallVec<[i64; 4]>) -> Self {
+        self.reduce(ReduceOp::Max, dims)
+    }
+
+    /// Returns the index of the first maximum element along `dim`, as a `S64` tensor.
+    pub fn argmax(self, dim: i64) -> Result<Self, Error> {
+        let shape = self.shape().ok_or(Error::UnknownShape)?;
+        if dim as usize >= shape.len() {
+            return Err(Error::OutOfBoundsAccess);
+        }
+        Ok(self.argmax_in_shape(dim, shape))
+    }
+
+    pub(crate) fn argmax_in_shape(self, dim: i64, shape: SmallVec<[i64; 4]>) -> Self {
+        let len = shape[dim as usize];
+        let kept_dims = (0..shape.len() as i64).filter(|d| *d != dim).collect();
+        let max = self
+            .clone()
+            .reduce_max(smallvec![dim])
+            .broadcast_in_dim(shape.clone(), kept_dims);
+        let indices = Noxpr::iota(ArrayTy::new(ElementType::S64, shape.clone()), dim as usize);
+        let not_max = len.constant().broadcast(shape);
+        self.equal(max)
+            .select(indices, not_max)
+            .reduce(ReduceOp::Min, smallvec![dim])
+    }
+
+    pub fn cond(pred: Noxpr, operands: Vec<Noxpr>, on_true: NoxprFn, on_false: NoxprFn) -> Self {
+        Self::new(NoxprNode::Cond(Cond {
+            pred,
+            operands,
+            on_true,
+            on_false,
+        }))
+    }
+
+    pub fn while_loop(initial_state: Noxpr, cond_fn: NoxprFn, body_fn: NoxprFn) -> Self {
+        Self::new(NoxprNode::While(While {
+            initial_state,
+            cond_fn,
+            body_fn,
+        }))
+    }
+
     pub fn ty(&self) -> Option<NoxprTy> {
         match self.deref() {
             NoxprNode::Constant(c) => Some(NoxprTy::ArrayTy(ArrayTy {
@@ -612,9 +986,19 @@ impl Noxpr {
             | NoxprNode::Mul(ref b)
             | NoxprNode::And(ref b)
             | NoxprNode::Or(ref b)
+            | NoxprNode::Xor(ref b)
+            | NoxprNode::ShiftLeft(ref b)
+            | NoxprNode::ShiftRightLogical(ref b)
             | NoxprNode::GreaterOrEqual(ref b)
             | NoxprNode::LessOrEqual(ref b)
-            | NoxprNode::Less(ref b) => b.ty(),
+            | NoxprNode::Less(ref b)
+            | NoxprNode::Greater(ref b)
+            | NoxprNode::Equal(ref b)
+            | NoxprNode::NotEqual(ref b)
+            | NoxprNode::Atan2(ref b)
+            | NoxprNode::Pow(ref b)
+            | NoxprNode::Max(ref b)
+            | NoxprNode::Min(ref b) => b.ty(),
 
             NoxprNode::Dot(b) => {
                 let NoxprTy::ArrayTy(lhs_ty) = b.lhs.ty()? else {
@@ -641,7 +1025,24 @@ impl Noxpr {
             NoxprNode::Sqrt(expr)
             | NoxprNode::Neg(expr)
             | NoxprNode::Sin(expr)
-            | NoxprNode::Cos(expr) => expr.ty(),
+            | NoxprNode::Cos(expr)
+            | NoxprNode::Exp(expr)
+            | NoxprNode::Tanh(expr)
+            | NoxprNode::Abs(expr)
+            | NoxprNode::Floor(expr)
+            | NoxprNode::Ceil(expr)
+            | NoxprNode::Rsqrt(expr) => expr.ty(),
+            NoxprNode::Convert(c) => {
+                let NoxprTy::ArrayTy(ty) = c.expr.ty()? else {
+                    return None;
+                };
+                Some(NoxprTy::ArrayTy(ArrayTy {
+                    element_type: c.element_type,
+                    shape: ty.shape,
+                }))
+            }
+            NoxprNode::Select(s) => s.on_true.ty(),
+            NoxprNode::Clamp(c) => c.expr.ty(),
 
             NoxprNode::Concat(concat) => {
                 let tys = concat
@@ -791,13 +1192,47 @@ impl Noxpr {
             }
             NoxprNode::Iota(i) => Some(NoxprTy::ArrayTy(i.shape.clone())),
             NoxprNode::DynamicUpdateSlice(d) => d.expr.ty(),
+            NoxprNode::Scatter(s) => s.expr.ty(),
             NoxprNode::GetTupleElement(g) => {
                 let NoxprTy::Tuple(ty) = g.expr.ty()? else {
                     return None;
                 };
                 ty.get(g.index).cloned()
             }
+            NoxprNode::Reduce(r) => Some(NoxprTy::ArrayTy(ArrayTy {
+                element_type: r.expr.element_type()?,
+                shape: self.shape()?,
+            })),
+            NoxprNode::Cholesky(e) => e.ty(),
+            NoxprNode::TriangularSolve(t) => t.b.ty(),
+            NoxprNode::Qr(e) | NoxprNode::Eigh(e) | NoxprNode::Svd(e) => {
+                let NoxprTy::ArrayTy(ty) = e.ty()? else {
+                    return None;
+                };
+                let rank = ty.shape.len();
+                if rank < 2 {
+                    return None;
+                }
+                let (batch, m, n) = (
+                    &ty.shape[..rank - 2],
+                    ty.shape[rank - 2],
+                    ty.shape[rank - 1],
+                );
+                let array = |dims: &[i64]| {
+                    let mut shape = SmallVec::from_slice(batch);
+                    shape.extend_from_slice(dims);
+                    NoxprTy::ArrayTy(ArrayTy::new(ty.element_type, shape))
+                };
+                let tys = match self.deref() {
+                    NoxprNode::Qr(_) => vec![array(&[m, m]), array(&[m, n])],
+                    NoxprNode::Eigh(_) => vec![array(&[n, n]), array(&[n])],
+                    _ => vec![array(&[m, m]), array(&[m.min(n)]), array(&[n, n])],
+                };
+                Some(NoxprTy::Tuple(tys))
+            }
             NoxprNode::Scan(s) => s.initial_state.ty(),
+            NoxprNode::Cond(c) => c.on_true.inner.ty(),
+            NoxprNode::While(w) => w.initial_state.ty(),
             #[cfg(feature = "jax")]
             NoxprNode::Jax(o) => pyo3::Python::with_gil(|py| {
                 let shape = o.getattr(py, "shape").ok()?.extract::<Vec<i64>>(py).ok()?;
@@ -828,17 +1263,36 @@ impl Noxpr {
             | NoxprNode::Div(ref b)
             | NoxprNode::Mul(ref b)
             | NoxprNode::And(ref b)
-            | NoxprNode::Or(ref b) => b.rhs.element_type(),
-            NoxprNode::GreaterOrEqual(_) | NoxprNode::LessOrEqual(_) | NoxprNode::Less(_) => {
-                Some(ElementType::Pred)
-            }
+            | NoxprNode::Or(ref b)
+            | NoxprNode::Xor(ref b)
+            | NoxprNode::ShiftLeft(ref b)
+            | NoxprNode::ShiftRightLogical(ref b)
+            | NoxprNode::Atan2(ref b)
+            | NoxprNode::Pow(ref b)
+            | NoxprNode::Max(ref b)
+            | NoxprNode::Min(ref b) => b.rhs.element_type(),
+            NoxprNode::GreaterOrEqual(_)
+            | NoxprNode::LessOrEqual(_)
+            | NoxprNode::Less(_)
+            | NoxprNode::Greater(_)
+            | NoxprNode::Equal(_)
+            | NoxprNode::NotEqual(_) => Some(ElementType::Pred),
             NoxprNode::Dot(b) => b.rhs.element_type(),
             NoxprNode::DotGeneral(s) => s.rhs.element_type(),
             NoxprNode::Sqrt(expr)
             | NoxprNode::Neg(expr)
             | NoxprNode::Log(expr)
             | NoxprNode::Sin(expr)
-            | NoxprNode::Cos(expr) => expr.element_type(),
+            | NoxprNode::Cos(expr)
+            | NoxprNode::Exp(expr)
+            | NoxprNode::Tanh(expr)
+            | NoxprNode::Abs(expr)
+            | NoxprNode::Floor(expr)
+            | NoxprNode::Ceil(expr)
+            | NoxprNode::Rsqrt(expr) => expr.element_type(),
+            NoxprNode::Convert(c) => Some(c.element_type),
+            NoxprNode::Select(s) => s.on_true.element_type(),
+            NoxprNode::Clamp(c) => c.expr.element_type(),
             NoxprNode::Concat(concat) => concat.nodes.first()?.element_type(),
             NoxprNode::Slice(slice) => slice.expr.element_type(),
             NoxprNode::DynamicSlice(dynamic_slice) => dynamic_slice.expr.element_type(),
@@ -850,6 +1304,7 @@ impl Noxpr {
             NoxprNode::Gather(gather) => gather.expr.element_type(),
             NoxprNode::Iota(i) => Some(i.shape.element_type),
             NoxprNode::DynamicUpdateSlice(d) => d.expr.element_type(),
+            NoxprNode::Scatter(s) => s.expr.element_type(),
             NoxprNode::GetTupleElement(g) => match g.expr.deref() {
                 NoxprNode::Tuple(elems) => elems.get(g.index)?.element_type(),
                 NoxprNode::Param(p) => {
@@ -861,9 +1316,21 @@ impl Noxpr {
                     }
                     None
                 }
-                _ => None,
+                _ => match g.expr.ty()? {
+                    NoxprTy::Tuple(elems) => match elems.get(g.index)? {
+                        NoxprTy::ArrayTy(a) => Some(a.element_type),
+                        _ => None,
+                    },
+                    _ => None,
+                },
             },
+            NoxprNode::Reduce(r) => r.expr.element_type(),
+            NoxprNode::Cholesky(e) => e.element_type(),
+            NoxprNode::TriangularSolve(t) => t.b.element_type(),
+            NoxprNode::Qr(_) | NoxprNode::Eigh(_) | NoxprNode::Svd(_) => None,
             NoxprNode::Scan(s) => s.initial_state.element_type(),
+            NoxprNode::Cond(c) => c.on_true.inner.element_type(),
+            NoxprNode::While(w) => w.initial_state.element_type(),
             #[cfg(feature = "jax")]
             NoxprNode::Jax(o) => pyo3::Python::with_gil(|py| {
                 let element_type = o
@@ -889,9 +1356,19 @@ impl Noxpr {
             | NoxprNode::Mul(ref b)
             | NoxprNode::And(ref b)
             | NoxprNode::Or(ref b)
+            | NoxprNode::Xor(ref b)
+            | NoxprNode::ShiftLeft(ref b)
+            | NoxprNode::ShiftRightLogical(ref b)
             | NoxprNode::GreaterOrEqual(ref b)
             | NoxprNode::LessOrEqual(ref b)
-            | NoxprNode::Less(ref b) => b.shape(),
+            | NoxprNode::Less(ref b)
+            | NoxprNode::Greater(ref b)
+            | NoxprNode::Equal(ref b)
+            | NoxprNode::NotEqual(ref b)
+            | NoxprNode::Atan2(ref b)
+            | NoxprNode::Pow(ref b)
+            | NoxprNode::Max(ref b)
+            | NoxprNode::Min(ref b) => b.shape(),
 
             NoxprNode::Dot(b) => {
                 let lhs_shape = b.lhs.shape()?;
@@ -907,7 +1384,16 @@ impl Noxpr {
             NoxprNode::Sqrt(expr)
             | NoxprNode::Neg(expr)
             | NoxprNode::Sin(expr)
-            | NoxprNode::Cos(expr) => expr.shape(),
+            | NoxprNode::Cos(expr)
+            | NoxprNode::Exp(expr)
+            | NoxprNode::Tanh(expr)
+            | NoxprNode::Abs(expr)
+            | NoxprNode::Floor(expr)
+            | NoxprNode::Ceil(expr)
+            | NoxprNode::Rsqrt(expr) => expr.shape(),
+            NoxprNode::Convert(c) => c.expr.shape(),
+            NoxprNode::Select(s) => s.on_true.shape(),
+            NoxprNode::Clamp(c) => c.expr.shape(),
 
             NoxprNode::Concat(concat) => {
                 let shapes = concat
@@ -996,6 +1482,7 @@ impl Noxpr {
             }
             NoxprNode::Iota(i) => Some(i.shape.shape.clone()),
             NoxprNode::DynamicUpdateSlice(d) => d.expr.shape(),
+            NoxprNode::Scatter(s) => s.expr.shape(),
             NoxprNode::GetTupleElement(g) => match g.expr.deref() {
                 NoxprNode::Tuple(elems) => elems.get(g.index)?.shape(),
                 NoxprNode::Param(p) => {
@@ -1007,9 +1494,31 @@ impl Noxpr {
                     }
                     None
                 }
-                _ => None,
+                _ => match g.expr.ty()? {
+                    NoxprTy::Tuple(elems) => match elems.get(g.index)? {
+                        NoxprTy::ArrayTy(a) => Some(a.shape.clone()),
+                        _ => None,
+                    },
+                    _ => None,
+                },
             },
+            NoxprNode::Reduce(r) => {
+                let shape = r.expr.shape()?;
+                Some(
+                    shape
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, _)| !r.dims.contains(&(*i as i64)))
+                        .map(|(_, len)| *len)
+                        .collect(),
+                )
+            }
+            NoxprNode::Cholesky(e) => e.shape(),
+            NoxprNode::TriangularSolve(t) => t.b.shape(),
+            NoxprNode::Qr(_) | NoxprNode::Eigh(_) | NoxprNode::Svd(_) => None,
             NoxprNode::Scan(s) => s.initial_state.shape(),
+            NoxprNode::Cond(c) => c.on_true.inner.shape(),
+            NoxprNode::While(w) => w.initial_state.shape(),
             #[cfg(feature = "jax")]
             NoxprNode::Jax(o) => pyo3::Python::with_gil(|py| {
                 let shape = o.getattr(py, "shape").ok()?.extract::<Vec<i64>>(py).ok()?;
@@ -1047,22 +1556,212 @@ impl Noxpr {
         self.id
     }
 
+    /// Rebuilds this node with each of its inputs replaced by `f(input)`.
+    ///
+    /// Nested functions, like the body of a scan, are kept as is.
+    pub fn map_inputs(&self, mut f: impl FnMut(&Noxpr) -> Noxpr) -> Noxpr {
+        fn map_binary(f: &mut impl FnMut(&Noxpr) -> Noxpr, op: &BinaryOp) -> BinaryOp {
+            BinaryOp {
+                lhs: f(&op.lhs),
+                rhs: f(&op.rhs),
+            }
+        }
+        match self.deref() {
+            NoxprNode::Param(_) | NoxprNode::Constant(_) | NoxprNode::Iota(_) => self.clone(),
+            NoxprNode::Tuple(t) => Noxpr::tuple(t.iter().map(&mut f).collect()),
+            NoxprNode::GetTupleElement(g) => {
+                Noxpr::new(NoxprNode::GetTupleElement(GetTupleElement {
+                    expr: f(&g.expr),
+                    index: g.index,
+                }))
+            }
+            NoxprNode::Add(a) => Noxpr::new(NoxprNode::Add(map_binary(&mut f, a))),
+            NoxprNode::Sub(s) => Noxpr::new(NoxprNode::Sub(map_binary(&mut f, s))),
+            NoxprNode::Mul(x) => Noxpr::new(NoxprNode::Mul(map_binary(&mut f, x))),
+            NoxprNode::Div(x) => Noxpr::new(NoxprNode::Div(map_binary(&mut f, x))),
+            NoxprNode::And(x) => Noxpr::new(NoxprNode::And(map_binary(&mut f, x))),
+            NoxprNode::GreaterOrEqual(x) => {
+                Noxpr::new(NoxprNode::GreaterOrEqual(map_binary(&mut f, x)))
+            }
+            NoxprNode::LessOrEqual(x) => Noxpr::new(NoxprNode::LessOrEqual(map_binary(&mut f, x))),
+            NoxprNode::Less(x) => Noxpr::new(NoxprNode::Less(map_binary(&mut f, x))),
+            NoxprNode::Greater(x) => Noxpr::new(NoxprNode::Greater(map_binary(&mut f, x))),
+            NoxprNode::Equal(x) => Noxpr::new(NoxprNode::Equal(map_binary(&mut f, x))),
+            NoxprNode::NotEqual(x) => Noxpr::new(NoxprNode::NotEqual(map_binary(&mut f, x))),
+            NoxprNode::Atan2(x) => Noxpr::new(NoxprNode::Atan2(map_binary(&mut f, x))),
+            NoxprNode::Pow(x) => Noxpr::new(NoxprNode::Pow(map_binary(&mut f, x))),
+            NoxprNode::Max(x) => Noxpr::new(NoxprNode::Max(map_binary(&mut f, x))),
+            NoxprNode::Min(x) => Noxpr::new(NoxprNode::Min(map_binary(&mut f, x))),
+            NoxprNode::Or(x) => Noxpr::new(NoxprNode::Or(map_binary(&mut f, x))),
+            NoxprNode::Xor(x) => Noxpr::new(NoxprNode::Xor(map_binary(&mut f, x))),
+            NoxprNode::ShiftLeft(x) => Noxpr::new(NoxprNode::ShiftLeft(map_binary(&mut f, x))),
+            NoxprNode::ShiftRightLogical(x) => {
+                Noxpr::new(NoxprNode::ShiftRightLogical(map_binary(&mut f, x)))
+            }
+            NoxprNode::Dot(x) => Noxpr::new(NoxprNode::Dot(map_binary(&mut f, x))),
+            NoxprNode::DotGeneral(d) => Noxpr::new(NoxprNode::DotGeneral(DotGeneral {
+                lhs: f(&d.lhs),
+                rhs: f(&d.rhs),
+                dimensions: d.dimensions.clone(),
+            })),
+            NoxprNode::Sqrt(s) => Noxpr::new(NoxprNode::Sqrt(f(s))),
+            NoxprNode::Neg(n) => Noxpr::new(NoxprNode::Neg(f(n))),
+            NoxprNode::Log(l) => Noxpr::new(NoxprNode::Log(f(l))),
+            NoxprNode::Sin(s) => Noxpr::new(NoxprNode::Sin(f(s))),
+            NoxprNode::Cos(c) => Noxpr::new(NoxprNode::Cos(f(c))),
+            NoxprNode::Exp(e) => Noxpr::new(NoxprNode::Exp(f(e))),
+            NoxprNode::Tanh(t) => Noxpr::new(NoxprNode::Tanh(f(t))),
+            NoxprNode::Abs(a) => Noxpr::new(NoxprNode::Abs(f(a))),
+            NoxprNode::Floor(f) => Noxpr::new(NoxprNode::Floor(f(f))),
+            NoxprNode::Ceil(c) => Noxpr::new(NoxprNode::Ceil(f(c))),
+            NoxprNode::Rsqrt(r) => Noxpr::new(NoxprNode::Rsqrt(f(r))),
+            NoxprNode::Convert(c) => Noxpr::new(NoxprNode::Convert(Convert {
+                expr: f(&c.expr),
+                element_type: c.element_type,
+            })),
+            NoxprNode::Select(s) => Noxpr::new(NoxprNode::Select(Select {
+                cond: f(&s.cond),
+                on_true: f(&s.on_true),
+                on_false: f(&s.on_false),
+            })),
+            NoxprNode::Clamp(c) => Noxpr::new(NoxprNode::Clamp(Clamp {
+                min: f(&c.min),
+                expr: f(&c.expr),
+                max: f(&c.max),
+            })),
+            NoxprNode::Concat(c) => Noxpr::new(NoxprNode::Concat(Concat {
+                nodes: c.nodes.iter().map(&mut f).collect(),
+                dimension: c.dimension,
+            })),
+            NoxprNode::Reshape(r) => Noxpr::new(NoxprNode::Reshape(Reshape {
+                expr: f(&r.expr),
+                new_sizes: r.new_sizes.clone(),
+            })),
+            NoxprNode::Broadcast(b) => Noxpr::new(NoxprNode::Broadcast(Broadcast {
+                expr: f(&b.expr),
+                sizes: b.sizes.clone(),
+            })),
+            NoxprNode::BroadcastInDim(b) => Noxpr::new(NoxprNode::BroadcastInDim(BroadcastInDim {
+                expr: f(&b.expr),
+                sizes: b.sizes.clone(),
+                broadcast_dims: b.broadcast_dims.clone(),
+            })),
+            NoxprNode::Transpose(t) => Noxpr::new(NoxprNode::Transpose(Transpose {
+                expr: f(&t.expr),
+                permutation: t.permutation.clone(),
+            })),
+            NoxprNode::Gather(g) => Noxpr::new(NoxprNode::Gather(Gather {
+                expr: f(&g.expr),
+                indices: f(&g.indices),
+                offset_dims: g.offset_dims.clone(),
+                collapsed_slice_dims: g.collapsed_slice_dims.clone(),
+                start_index_map: g.start_index_map.clone(),
+                slice_sizes: g.slice_sizes.clone(),
+                index_vector_dim: g.index_vector_dim,
+            })),
+            NoxprNode::Slice(s) => Noxpr::new(NoxprNode::Slice(Slice {
+                expr: f(&s.expr),
+                start_indices: s.start_indices.clone(),
+                stop_indices: s.stop_indices.clone(),
+                strides: s.strides.clone(),
+            })),
+            NoxprNode::DynamicSlice(d) => Noxpr::new(NoxprNode::DynamicSlice(DynamicSlice {
+                expr: f(&d.expr),
+                start_indices: d.start_indices.iter().map(&mut f).collect(),
+                size_indices: d.size_indices.clone(),
+            })),
+            NoxprNode::DynamicUpdateSlice(d) => {
+                Noxpr::new(NoxprNode::DynamicUpdateSlice(DynamicUpdateSlice {
+                    expr: f(&d.expr),
+                    start_indicies: d.start_indicies.iter().map(&mut f).collect(),
+                    update: f(&d.update),
+                }))
+            }
+            NoxprNode::Scatter(s) => Noxpr::new(NoxprNode::Scatter(Scatter {
+                expr: f(&s.expr),
+                indices: f(&s.indices),
+                updates: f(&s.updates),
+                update_window_dims: s.update_window_dims.clone(),
+                inserted_window_dims: s.inserted_window_dims.clone(),
+                scatter_dims_to_operand_dims: s.scatter_dims_to_operand_dims.clone(),
+                index_vector_dim: s.index_vector_dim,
+            })),
+            NoxprNode::Scan(s) => Noxpr::new(NoxprNode::Scan(Scan {
+                inputs: s.inputs.iter().map(&mut f).collect(),
+                initial_state: f(&s.initial_state),
+                scan_fn: s.scan_fn.clone(),
+            })),
+            NoxprNode::Reduce(r) => Noxpr::new(NoxprNode::Reduce(Reduce {
+                expr: f(&r.expr),
+                op: r.op,
+                dims: r.dims.clone(),
+            })),
+            NoxprNode::Cholesky(e) => Noxpr::new(NoxprNode::Cholesky(f(e))),
+            NoxprNode::TriangularSolve(t) => {
+                Noxpr::new(NoxprNode::TriangularSolve(TriangularSolve {
+                    a: f(&t.a),
+                    b: f(&t.b),
+                    left_side: t.left_side,
+                    lower: t.lower,
+                    unit_diagonal: t.unit_diagonal,
+                    transpose_a: t.transpose_a,
+                }))
+            }
+            NoxprNode::Qr(e) => Noxpr::new(NoxprNode::Qr(f(e))),
+            NoxprNode::Eigh(e) => Noxpr::new(NoxprNode::Eigh(f(e))),
+            NoxprNode::Svd(e) => Noxpr::new(NoxprNode::Svd(f(e))),
+            NoxprNode::Cond(c) => Noxpr::new(NoxprNode::Cond(Cond {
+                pred: f(&c.pred),
+                operands: c.operands.iter().map(&mut f).collect(),
+                on_true: c.on_true.clone(),
+                on_false: c.on_false.clone(),
+            })),
+            NoxprNode::While(w) => Noxpr::new(NoxprNode::While(While {
+                initial_state: f(&w.initial_state),
+                cond_fn: w.cond_fn.clone(),
+                body_fn: w.body_fn.clone(),
+            })),
+            #[cfg(feature = "jax")]
+            NoxprNode::Jax(_) => self.clone(),
+        }
+    }
+
+    /// Returns the inputs of this node, not including the inputs of nested functions.
+    pub fn inputs(&self) -> Vec<Noxpr> {
+        let mut inputs = vec![];
+        self.map_inputs(|input| {
+            inputs.push(input.clone());
+            input.clone()
+        });
+        inputs
+    }
+
     pub fn name(&self) -> &'static str {
         match self.deref() {
             NoxprNode::Param(_) => "Param",
             NoxprNode::Tuple(_) => "Tuple",
             NoxprNode::GetTupleElement(_) => "GetTupleElement",
             NoxprNode::Constant(_) => "Constant",
-            NoxprNode::Iota(_) => todo!(),
+            NoxprNode::Iota(_) => "Iota",
             NoxprNode::Add(_) => "Add",
             NoxprNode::Sub(_) => "Sub",
             NoxprNode::Mul(_) => "Mul",
             NoxprNode::Div(_) => "Div",
             NoxprNode::And(_) => "And",
             NoxprNode::Or(_) => "Or",
+            NoxprNode::Xor(_) => "Xor",
+            NoxprNode::ShiftLeft(_) => "ShiftLeft",
+            NoxprNode::ShiftRightLogical(_) => "ShiftRightLogical",
             NoxprNode::GreaterOrEqual(_) => "GreaterOrEqual",
             NoxprNode::LessOrEqual(_) => "LessOrEqual",
             NoxprNode::Less(_) => "Less",
+            NoxprNode::Greater(_) => "Greater",
+            NoxprNode::Equal(_) => "Equal",
+            NoxprNode::NotEqual(_) => "NotEqual",
+            NoxprNode::Atan2(_) => "Atan2",
+            NoxprNode::Pow(_) => "Pow",
+            NoxprNode::Max(_) => "Max",
+            NoxprNode::Min(_) => "Min",
             NoxprNode::Dot(_) => "Dot",
             NoxprNode::DotGeneral(_) => "DotGeneral",
             NoxprNode::Sqrt(_) => "Sqrt",
@@ -1077,10 +1776,28 @@ impl Noxpr {
             NoxprNode::Slice(_) => "Slice",
             NoxprNode::DynamicSlice(_) => "DynamicSlice",
             NoxprNode::DynamicUpdateSlice(_) => "DynamicUpdateSlice",
+            NoxprNode::Scatter(_) => "Scatter",
+            NoxprNode::Reduce(_) => "Reduce",
+            NoxprNode::Cholesky(_) => "Cholesky",
+            NoxprNode::TriangularSolve(_) => "TriangularSolve",
+            NoxprNode::Qr(_) => "Qr",
+            NoxprNode::Eigh(_) => "Eigh",
+            NoxprNode::Svd(_) => "Svd",
             NoxprNode::Scan(_) => "Scan",
+            NoxprNode::Cond(_) => "Cond",
+            NoxprNode::While(_) => "While",
             NoxprNode::Jax(_) => "Jax",
             NoxprNode::Sin(_) => "Sin",
             NoxprNode::Cos(_) => "Cos",
+            NoxprNode::Exp(_) => "Exp",
+            NoxprNode::Tanh(_) => "Tanh",
+            NoxprNode::Abs(_) => "Abs",
+            NoxprNode::Floor(_) => "Floor",
+            NoxprNode::Ceil(_) => "Ceil",
+            NoxprNode::Rsqrt(_) => "Rsqrt",
+            NoxprNode::Convert(_) => "Convert",
+            NoxprNode::Select(_) => "Select",
+            NoxprNode::Clamp(_) => "Clamp",
         }
     }
 
@@ -1207,6 +1924,18 @@ impl XlaTracer {
                 let (lhs, rhs) = self.visit_binary_op(b)?;
                 lhs.or(&rhs)
             }
+            NoxprNode::Xor(b) => {
+                let (lhs, rhs) = self.visit_binary_op(b)?;
+                lhs.xor(&rhs)
+            }
+            NoxprNode::ShiftLeft(b) => {
+                let (lhs, rhs) = self.visit_binary_op(b)?;
+                lhs.shift_left(&rhs)
+            }
+            NoxprNode::ShiftRightLogical(b) => {
+                let (lhs, rhs) = self.visit_binary_op(b)?;
+                lhs.shift_right_logical(&rhs)
+            }
             NoxprNode::GreaterOrEqual(b) => {
                 let (lhs, rhs) = self.visit_binary_op(b)?;
                 lhs.ge(&rhs)
@@ -1219,6 +1948,34 @@ impl XlaTracer {
                 let (lhs, rhs) = self.visit_binary_op(b)?;
                 lhs.lt(&rhs)
             }
+            NoxprNode::Greater(b) => {
+                let (lhs, rhs) = self.visit_binary_op(b)?;
+                lhs.gt(&rhs)
+            }
+            NoxprNode::Equal(b) => {
+                let (lhs, rhs) = self.visit_binary_op(b)?;
+                lhs.eq(&rhs)
+            }
+            NoxprNode::NotEqual(b) => {
+                let (lhs, rhs) = self.visit_binary_op(b)?;
+                lhs.ne(&rhs)
+            }
+            NoxprNode::Atan2(b) => {
+                let (lhs, rhs) = self.visit_binary_op(b)?;
+                lhs.atan2(&rhs)
+            }
+            NoxprNode::Pow(b) => {
+                let (lhs, rhs) = self.visit_binary_op(b)?;
+                lhs.pow(&rhs)
+            }
+            NoxprNode::Max(b) => {
+                let (lhs, rhs) = self.visit_binary_op(b)?;
+                lhs.max(&rhs)
+            }
+            NoxprNode::Min(b) => {
+                let (lhs, rhs) = self.visit_binary_op(b)?;
+                lhs.min(&rhs)
+            }
             NoxprNode::Sqrt(expr) => {
                 let expr = self.visit(expr)?;
                 expr.sqrt()
@@ -1239,7 +1996,48 @@ impl XlaTracer {
                 let expr = self.visit(expr)?;
                 expr.cos()
             }
-            NoxprNode::Concat(concat) => {
+            NoxprNode::Exp(expr) => {
+                let expr = self.visit(expr)?;
+                expr.exp()
+            }
+            NoxprNode::Tanh(expr) => {
+                let expr = self.visit(expr)?;
+                expr.tanh()
+            }
+            NoxprNode::Abs(expr) => {
+                let expr = self.visit(expr)?;
+                expr.abs()
+            }
+            NoxprNode::Floor(expr) => {
+                let expr = self.visit(expr)?;
+                expr.floor()
+            }
+            NoxprNode::Ceil(expr) => {
+                let expr = self.visit(expr)?;
+                expr.ceil()
+            }
+            NoxprNode::Rsqrt(expr) => {
+                let expr = self.visit(expr)?;
+                expr.rsqrt()
+            }
+            NoxprNode::Convert(c) => {
+                let expr = self.visit(&c.expr)?;
+                expr.convert_element_type(c.element_type.primitive_type())
+            }
+            NoxprNode::Select(s) => {
+                let cond = self.visit(&s.cond)?;
+                let on_true = self.visit(&s.on_true)?;
+                let on_false = self.visit(&s.on_false)?;
+                cond.select(&on_true, &on_false)
+            }
+            NoxprNode::Clamp(c) => {
+                let min = self.visit(&c.min)?;
+                let expr = self.visit(&c.expr)?;
+                let max = self.visit(&c.max)?;
+                // NOTE: xla's clamp takes the lower bound first, so it is the receiver here
+                min.clamp(&expr, &max)
+            }
+            NoxprNode::Concat(concat) => {
                 let ops = concat
                     .nodes
                     .iter()
@@ -1320,6 +2118,80 @@ impl XlaTracer {
                     .collect::<SmallVec<[XlaOpRef<'_>; 4]>>();
                 inner.dynamic_update_slice(&update, &start)
             }
+            NoxprNode::Scatter(s) => {
+                let op = self.visit(&s.expr)?;
+                let indices = self.visit(&s.indices)?;
+                let updates = self.visit(&s.updates)?;
+                let element_type = s.expr.element_type().ok_or(Error::IncompatibleDType)?;
+                let update_comp = {
+                    let builder = XlaBuilder::new("scatter_add");
+                    let scalar_ty = NoxprTy::ArrayTy(ArrayTy::new(element_type, smallvec![]));
+                    let lhs = builder.parameter(0, scalar_ty.clone().into(), "lhs")?;
+                    let rhs = builder.parameter(1, scalar_ty.into(), "rhs")?;
+                    (lhs + rhs).build()?
+                };
+                let mut dims = xla::ScatterDimensionNumbers::new();
+                s.update_window_dims
+                    .iter()
+                    .for_each(|d| dims.add_window_dim(*d));
+                s.inserted_window_dims
+                    .iter()
+                    .for_each(|d| dims.add_inserted_window_dim(*d));
+                s.scatter_dims_to_operand_dims
+                    .iter()
+                    .for_each(|d| dims.add_scatter_dims_to_operand_dims(*d));
+                dims.set_index_vector_dim(s.index_vector_dim);
+                op.scatter(
+                    &[op.as_ref()],
+                    &indices,
+                    &[updates.as_ref()],
+                    &update_comp,
+                    &dims,
+                    false,
+                    false,
+                )
+            }
+            NoxprNode::Reduce(r) => {
+                let expr = self.visit(&r.expr)?;
+                let element_type = r.expr.element_type().ok_or(Error::IncompatibleDType)?;
+                let init = self.visit(&r.op.init(element_type)?)?;
+                let comp = {
+                    let builder = XlaBuilder::new(&format!("reduce_{}", r.op.name()));
+                    let scalar_ty = NoxprTy::ArrayTy(ArrayTy::new(element_type, smallvec![]));
+                    let lhs = builder.parameter(0, scalar_ty.clone().into(), "lhs")?;
+                    let rhs = builder.parameter(1, scalar_ty.into(), "rhs")?;
+                    let out = match r.op {
+                        ReduceOp::Sum => lhs + rhs,
+                        ReduceOp::Prod => lhs * rhs,
+                        ReduceOp::Max => lhs.max(&rhs),
+                        ReduceOp::Min => lhs.min(&rhs),
+                    };
+                    out.build()?
+                };
+                expr.reduce(&init, &comp, &r.dims)
+            }
+            NoxprNode::Cholesky(e) => {
+                let expr = self.visit(e)?;
+                // xla leaves the upper triangle of the factor unspecified
+                expr.cholesky(true).lower_triangle()
+            }
+            NoxprNode::TriangularSolve(t) => {
+                let a = self.visit(&t.a)?;
+                let b = self.visit(&t.b)?;
+                a.triangular_solve(&b, t.left_side, t.lower, t.unit_diagonal, t.transpose_a)
+            }
+            NoxprNode::Qr(e) => {
+                let expr = self.visit(e)?;
+                expr.qr()
+            }
+            NoxprNode::Eigh(e) => {
+                let expr = self.visit(e)?;
+                expr.eigh(true)
+            }
+            NoxprNode::Svd(e) => {
+                let expr = self.visit(e)?;
+                expr.svd()
+            }
             NoxprNode::Jax(_) => {
                 unimplemented!()
             }
@@ -1410,6 +2282,36 @@ impl XlaTracer {
                 let out = cond.stmt_while(&scan_fn, &initial_state);
                 out.get_tuple_element(last_elem as i64)
             }
+            NoxprNode::Cond(c) => {
+                let pred = self.visit(&c.pred)?;
+                let operands = self.visit(&Noxpr::tuple(c.operands.clone()))?;
+                let on_true = c
+                    .on_true
+                    .collapse_params(vec![])?
+                    .build("cond_true")?
+                    .build()?;
+                let on_false = c
+                    .on_false
+                    .collapse_params(vec![])?
+                    .build("cond_false")?
+                    .build()?;
+                pred.conditional(&operands, &on_true, &operands, &on_false)
+            }
+            NoxprNode::While(w) => {
+                // the state is wrapped in a single element tuple, since xla requires the while
+                // body's parameter and result to have the same shape
+                let cond = w
+                    .cond_fn
+                    .collapse_params(vec![])?
+                    .build("while_cond")?
+                    .build()?;
+                let mut body_fn = w.body_fn.collapse_params(vec![])?;
+                body_fn.inner = Noxpr::tuple(vec![body_fn.inner]);
+                let body_fn = body_fn.build("while_body")?.build()?;
+                let initial_state = self.visit(&Noxpr::tuple(vec![w.initial_state.clone()]))?;
+                let out = cond.stmt_while(&body_fn, &initial_state);
+                out.get_tuple_element(0)
+            }
         };
         self.cache.insert(id, op.clone());
         Ok(op)
@@ -1500,19 +2402,23 @@ pub struct ReplacementTracer {
 }
 
 impl ReplacementTracer {
+    pub(crate) fn new(cache: HashMap<NoxprId, Noxpr>) -> Self {
+        Self { cache }
+    }
+
     fn visit_fn(&mut self, func: &NoxprFn) -> NoxprFn {
         let args = func.args.iter().map(|a| self.visit(a)).collect::<Vec<_>>();
         let inner = self.visit(&func.inner);
         NoxprFn::new(args, inner)
     }
 
-    fn visit(&mut self, expr: &Noxpr) -> Noxpr {
+    pub(crate) fn visit(&mut self, expr: &Noxpr) -> Noxpr {
         let id = expr.id();
         if let Some(expr) = self.cache.get(&id) {
             return expr.clone();
         }
         let expr = match expr.deref() {
-            NoxprNode::Param(p) => Noxpr::new(NoxprNode::Param(p.clone())),
+            NoxprNode::Param(_) => expr.clone(),
             NoxprNode::Tuple(t) => Noxpr::tuple(t.iter().map(|e| self.visit(e)).collect()),
             NoxprNode::GetTupleElement(g) => {
                 Noxpr::new(NoxprNode::GetTupleElement(GetTupleElement {
@@ -1534,7 +2440,19 @@ impl ReplacementTracer {
                 Noxpr::new(NoxprNode::LessOrEqual(self.visit_binary_op(x)))
             }
             NoxprNode::Less(x) => Noxpr::new(NoxprNode::Less(self.visit_binary_op(x))),
+            NoxprNode::Greater(x) => Noxpr::new(NoxprNode::Greater(self.visit_binary_op(x))),
+            NoxprNode::Equal(x) => Noxpr::new(NoxprNode::Equal(self.visit_binary_op(x))),
+            NoxprNode::NotEqual(x) => Noxpr::new(NoxprNode::NotEqual(self.visit_binary_op(x))),
+            NoxprNode::Atan2(x) => Noxpr::new(NoxprNode::Atan2(self.visit_binary_op(x))),
+            NoxprNode::Pow(x) => Noxpr::new(NoxprNode::Pow(self.visit_binary_op(x))),
+            NoxprNode::Max(x) => Noxpr::new(NoxprNode::Max(self.visit_binary_op(x))),
+            NoxprNode::Min(x) => Noxpr::new(NoxprNode::Min(self.visit_binary_op(x))),
             NoxprNode::Or(x) => Noxpr::new(NoxprNode::Or(self.visit_binary_op(x))),
+            NoxprNode::Xor(x) => Noxpr::new(NoxprNode::Xor(self.visit_binary_op(x))),
+            NoxprNode::ShiftLeft(x) => Noxpr::new(NoxprNode::ShiftLeft(self.visit_binary_op(x))),
+            NoxprNode::ShiftRightLogical(x) => {
+                Noxpr::new(NoxprNode::ShiftRightLogical(self.visit_binary_op(x)))
+            }
             NoxprNode::Dot(x) => Noxpr::new(NoxprNode::Dot(self.visit_binary_op(x))),
             NoxprNode::DotGeneral(d) => Noxpr::new(NoxprNode::DotGeneral(DotGeneral {
                 lhs: self.visit(&d.lhs),
@@ -1546,6 +2464,26 @@ impl ReplacementTracer {
             NoxprNode::Log(l) => Noxpr::new(NoxprNode::Log(self.visit(l))),
             NoxprNode::Sin(s) => Noxpr::new(NoxprNode::Sin(self.visit(s))),
             NoxprNode::Cos(c) => Noxpr::new(NoxprNode::Cos(self.visit(c))),
+            NoxprNode::Exp(e) => Noxpr::new(NoxprNode::Exp(self.visit(e))),
+            NoxprNode::Tanh(t) => Noxpr::new(NoxprNode::Tanh(self.visit(t))),
+            NoxprNode::Abs(a) => Noxpr::new(NoxprNode::Abs(self.visit(a))),
+            NoxprNode::Floor(f) => Noxpr::new(NoxprNode::Floor(self.visit(f))),
+            NoxprNode::Ceil(c) => Noxpr::new(NoxprNode::Ceil(self.visit(c))),
+            NoxprNode::Rsqrt(r) => Noxpr::new(NoxprNode::Rsqrt(self.visit(r))),
+            NoxprNode::Convert(c) => Noxpr::new(NoxprNode::Convert(Convert {
+                expr: self.visit(&c.expr),
+                element_type: c.element_type,
+            })),
+            NoxprNode::Select(s) => Noxpr::new(NoxprNode::Select(Select {
+                cond: self.visit(&s.cond),
+                on_true: self.visit(&s.on_true),
+                on_false: self.visit(&s.on_false),
+            })),
+            NoxprNode::Clamp(c) => Noxpr::new(NoxprNode::Clamp(Clamp {
+                min: self.visit(&c.min),
+                expr: self.visit(&c.expr),
+                max: self.visit(&c.max),
+            })),
             NoxprNode::Concat(c) => Noxpr::new(NoxprNode::Concat(Concat {
                 nodes: c.nodes.iter().map(|n| self.visit(n)).collect(),
                 dimension: c.dimension,
@@ -1594,11 +2532,50 @@ impl ReplacementTracer {
                     update: self.visit(&d.update),
                 }))
             }
+            NoxprNode::Scatter(s) => Noxpr::new(NoxprNode::Scatter(Scatter {
+                expr: self.visit(&s.expr),
+                indices: self.visit(&s.indices),
+                updates: self.visit(&s.updates),
+                update_window_dims: s.update_window_dims.clone(),
+                inserted_window_dims: s.inserted_window_dims.clone(),
+                scatter_dims_to_operand_dims: s.scatter_dims_to_operand_dims.clone(),
+                index_vector_dim: s.index_vector_dim,
+            })),
             NoxprNode::Scan(s) => Noxpr::new(NoxprNode::Scan(Scan {
                 inputs: s.inputs.iter().map(|e| self.visit(e)).collect(),
                 initial_state: self.visit(&s.initial_state),
                 scan_fn: s.scan_fn.clone(),
             })),
+            NoxprNode::Reduce(r) => Noxpr::new(NoxprNode::Reduce(Reduce {
+                expr: self.visit(&r.expr),
+                op: r.op,
+                dims: r.dims.clone(),
+            })),
+            NoxprNode::Cholesky(e) => Noxpr::new(NoxprNode::Cholesky(self.visit(e))),
+            NoxprNode::TriangularSolve(t) => {
+                Noxpr::new(NoxprNode::TriangularSolve(TriangularSolve {
+                    a: self.visit(&t.a),
+                    b: self.visit(&t.b),
+                    left_side: t.left_side,
+                    lower: t.lower,
+                    unit_diagonal: t.unit_diagonal,
+                    transpose_a: t.transpose_a,
+                }))
+            }
+            NoxprNode::Qr(e) => Noxpr::new(NoxprNode::Qr(self.visit(e))),
+            NoxprNode::Eigh(e) => Noxpr::new(NoxprNode::Eigh(self.visit(e))),
+            NoxprNode::Svd(e) => Noxpr::new(NoxprNode::Svd(self.visit(e))),
+            NoxprNode::Cond(c) => Noxpr::new(NoxprNode::Cond(Cond {
+                pred: self.visit(&c.pred),
+                operands: c.operands.iter().map(|e| self.visit(e)).collect(),
+                on_true: c.on_true.clone(),
+                on_false: c.on_false.clone(),
+            })),
+            NoxprNode::While(w) => Noxpr::new(NoxprNode::While(While {
+                initial_state: self.visit(&w.initial_state),
+                cond_fn: w.cond_fn.clone(),
+                body_fn: w.body_fn.clone(),
+            })),
             NoxprNode::Jax(j) => Noxpr::new(NoxprNode::Jax(j.clone())),
         };
         self.cache.insert(id, expr.clone());
@@ -1689,6 +2666,34 @@ impl<T: TensorItem, D: Dim + DefaultMap> Tensor<T, D, crate::Op> {
     }
 }
 
+impl<T: TensorItem, D: Dim> Tensor<T, D, crate::Op> {
+    /// Applies `on_true` to `self` if `pred` is set, and `on_false` otherwise.
+    pub fn cond<O: FromOp + IntoOp>(
+        &self,
+        pred: &Mask<ScalarDim>,
+        on_true: impl CompFn<(Self,), O>,
+        on_false: impl CompFn<(Self,), O>,
+    ) -> Result<O, Error> {
+        let res = Noxpr::cond(
+            pred.inner.clone(),
+            vec![self.inner.clone()],
+            on_true.build_expr()?,
+            on_false.build_expr()?,
+        );
+        Ok(O::from_op(res))
+    }
+
+    /// Repeatedly applies `body` to `self` for as long as `cond` returns true.
+    pub fn while_loop(
+        &self,
+        cond: impl CompFn<(Self,), Mask<ScalarDim>>,
+        body: impl CompFn<(Self,), Self>,
+    ) -> Result<Self, Error> {
+        let res = Noxpr::while_loop(self.inner.clone(), cond.build_expr()?, body.build_expr()?);
+        Ok(Self::from_op(res))
+    }
+}
+
 #[derive(Clone)]
 pub struct BatchTracer {
     cache: HashMap<NoxprId, BatchedExpr>,
@@ -1788,14 +2793,52 @@ impl BatchTracer {
             NoxprNode::Div(b) => self.visit_binary_op(b, Noxpr::div)?,
             NoxprNode::And(b) => self.visit_binary_op(b, Noxpr::and)?,
             NoxprNode::Or(b) => self.visit_binary_op(b, Noxpr::or)?,
+            NoxprNode::Xor(b) => self.visit_binary_op(b, Noxpr::xor)?,
+            NoxprNode::ShiftLeft(b) => self.visit_binary_op(b, Noxpr::shift_left)?,
+            NoxprNode::ShiftRightLogical(b) => {
+                self.visit_binary_op(b, Noxpr::shift_right_logical)?
+            }
             NoxprNode::GreaterOrEqual(b) => self.visit_binary_op(b, Noxpr::greater_or_equal)?,
             NoxprNode::LessOrEqual(b) => self.visit_binary_op(b, Noxpr::less_or_equal)?,
             NoxprNode::Less(b) => self.visit_binary_op(b, Noxpr::less)?,
+            NoxprNode::Greater(b) => self.visit_binary_op(b, Noxpr::greater)?,
+            NoxprNode::Equal(b) => self.visit_binary_op(b, Noxpr::equal)?,
+            NoxprNode::NotEqual(b) => self.visit_binary_op(b, Noxpr::not_equal)?,
+            NoxprNode::Atan2(b) => self.visit_binary_op(b, Noxpr::atan2)?,
+            NoxprNode::Pow(b) => self.visit_binary_op(b, Noxpr::pow)?,
+            NoxprNode::Max(b) => self.visit_binary_op(b, Noxpr::max)?,
+            NoxprNode::Min(b) => self.visit_binary_op(b, Noxpr::min)?,
             NoxprNode::Sqrt(e) => self.visit_unary_op(e, Noxpr::sqrt)?,
             NoxprNode::Neg(e) => self.visit_unary_op(e, Noxpr::neg)?,
             NoxprNode::Log(e) => self.visit_unary_op(e, Noxpr::log)?,
             NoxprNode::Sin(e) => self.visit_unary_op(e, Noxpr::sin)?,
             NoxprNode::Cos(e) => self.visit_unary_op(e, Noxpr::cos)?,
+            NoxprNode::Exp(e) => self.visit_unary_op(e, Noxpr::exp)?,
+            NoxprNode::Tanh(e) => self.visit_unary_op(e, Noxpr::tanh)?,
+            NoxprNode::Abs(e) => self.visit_unary_op(e, Noxpr::abs)?,
+            NoxprNode::Floor(e) => self.visit_unary_op(e, Noxpr::floor)?,
+            NoxprNode::Ceil(e) => self.visit_unary_op(e, Noxpr::ceil)?,
+            NoxprNode::Rsqrt(e) => self.visit_unary_op(e, Noxpr::rsqrt)?,
+            NoxprNode::Convert(c) => {
+                let element_type = c.element_type;
+                self.visit_unary_op(&c.expr, |e| e.convert(element_type))?
+            }
+            NoxprNode::Select(s) => {
+                // a scalar predicate can be batched while the operands aren't, so it is
+                // broadcast up front to keep all three operands the same rank
+                let cond = match (s.cond.shape(), s.on_true.shape()) {
+                    (Some(cond_shape), Some(shape))
+                        if cond_shape.is_empty() && !shape.is_empty() =>
+                    {
+                        s.cond.clone().broadcast(shape)
+                    }
+                    _ => s.cond.clone(),
+                };
+                self.visit_ternary_op([&cond, &s.on_true, &s.on_false], |[c, t, f]| c.select(t, f))?
+            }
+            NoxprNode::Clamp(c) => {
+                self.visit_ternary_op([&c.min, &c.expr, &c.max], |[min, e, max]| e.clamp(min, max))?
+            }
             NoxprNode::Concat(c) => {
                 let nodes = c
                     .nodes
@@ -2092,16 +3135,21 @@ impl BatchTracer {
                 // TODO: dynamic update slice is a special case of scatter, add this when we add scatter
                 todo!()
             }
+            NoxprNode::Scatter(s) => self.visit_scatter(s)?,
             NoxprNode::Jax(_) => {
                 unimplemented!()
             }
-            NoxprNode::GetTupleElement(g) => {
-                let NoxprNode::Tuple(elems) = g.expr.deref() else {
-                    return Err(Error::UnbatchableArgument);
-                };
-                let expr = elems.get(g.index).ok_or(Error::UnbatchableArgument)?;
-                self.visit(expr)?
-            }
+            NoxprNode::GetTupleElement(g) => match g.expr.deref() {
+                NoxprNode::Tuple(elems) => {
+                    let expr = elems.get(g.index).ok_or(Error::UnbatchableArgument)?;
+                    self.visit(expr)?
+                }
+                // every element of a decomposition shares the batch axis of its operand
+                NoxprNode::Qr(_) | NoxprNode::Eigh(_) | NoxprNode::Svd(_) => self
+                    .visit(&g.expr)?
+                    .map_expr(|expr| expr.get_tuple_element(g.index)),
+                _ => return Err(Error::UnbatchableArgument),
+            },
             NoxprNode::Scan(s) => {
                 let mut inputs: Vec<_> = s
                     .inputs
@@ -2171,6 +3219,161 @@ impl BatchTracer {
                     }
                 }
             }
+            NoxprNode::Reduce(r) => {
+                let expr = self.visit(&r.expr)?;
+                match expr.batch_axis {
+                    BatchAxis::NotMapped => BatchedExpr {
+                        inner: expr.inner.reduce(r.op, r.dims.clone()),
+                        batch_axis: BatchAxis::NotMapped,
+                    },
+                    BatchAxis::Mapped { index, size } => {
+                        let dims = r
+                            .dims
+                            .iter()
+                            .map(|d| d + (*d >= index as i64) as i64)
+                            .collect();
+                        let removed_before = r.dims.iter().filter(|d| **d < index as i64).count();
+                        BatchedExpr {
+                            inner: expr.inner.reduce(r.op, dims),
+                            batch_axis: BatchAxis::Mapped {
+                                index: index - removed_before,
+                                size,
+                            },
+                        }
+                    }
+                }
+            }
+            NoxprNode::Cholesky(e) => self.visit_matrix_op(e, Noxpr::cholesky)?,
+            NoxprNode::TriangularSolve(t) => {
+                let a = self.visit(&t.a)?;
+                let b = self.visit(&t.b)?;
+                match (&a.batch_axis, &b.batch_axis) {
+                    (BatchAxis::NotMapped, BatchAxis::NotMapped) => BatchedExpr {
+                        inner: a.inner.triangular_solve(
+                            b.inner,
+                            t.left_side,
+                            t.lower,
+                            t.unit_diagonal,
+                            t.transpose_a,
+                        ),
+                        batch_axis: BatchAxis::NotMapped,
+                    },
+                    (BatchAxis::Mapped { size, .. }, _) | (_, BatchAxis::Mapped { size, .. }) => {
+                        let batch_axis = BatchAxis::Mapped {
+                            index: 0,
+                            size: *size,
+                        };
+                        let a = a
+                            .move_batch_axis(batch_axis.clone())
+                            .ok_or(Error::UnbatchableArgument)?;
+                        let b = b
+                            .move_batch_axis(batch_axis.clone())
+                            .ok_or(Error::UnbatchableArgument)?;
+                        BatchedExpr {
+                            inner: a.inner.triangular_solve(
+                                b.inner,
+                                t.left_side,
+                                t.lower,
+                                t.unit_diagonal,
+                                t.transpose_a,
+                            ),
+                            batch_axis,
+                        }
+                    }
+                }
+            }
+            NoxprNode::Qr(e) => self.visit_matrix_op(e, Noxpr::qr)?,
+            NoxprNode::Eigh(e) => self.visit_matrix_op(e, Noxpr::eigh)?,
+            NoxprNode::Svd(e) => self.visit_matrix_op(e, Noxpr::svd)?,
+            NoxprNode::Cond(c) => {
+                let pred = self.visit(&c.pred)?;
+                let operands = c
+                    .operands
+                    .iter()
+                    .map(|o| self.visit(o))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                if pred.batch_axis == BatchAxis::NotMapped
+                    && operands
+                        .iter()
+                        .all(|o| o.batch_axis == BatchAxis::NotMapped)
+                {
+                    BatchedExpr {
+                        inner: Noxpr::cond(
+                            pred.inner,
+                            operands.into_iter().map(|o| o.inner).collect(),
+                            c.on_true.clone(),
+                            c.on_false.clone(),
+                        ),
+                        batch_axis: BatchAxis::NotMapped,
+                    }
+                    .move_batch_axis(self.out_axis.clone())
+                    .ok_or(Error::UnbatchableArgument)?
+                } else {
+                    // each element of the batch can take a different branch, so both branches
+                    // are evaluated and the results are selected between
+                    self.visit(&crate::grad::inline_cond(c))?
+                }
+            }
+            NoxprNode::While(w) => {
+                let state = self.visit(&w.initial_state)?;
+                match state.batch_axis {
+                    BatchAxis::NotMapped => BatchedExpr {
+                        inner: Noxpr::while_loop(state.inner, w.cond_fn.clone(), w.body_fn.clone()),
+                        batch_axis: BatchAxis::NotMapped,
+                    }
+                    .move_batch_axis(self.out_axis.clone())
+                    .ok_or(Error::UnbatchableArgument)?,
+                    BatchAxis::Mapped { size, .. } => {
+                        let batch_axis = BatchAxis::Mapped { index: 0, size };
+                        let state = state
+                            .move_batch_axis(batch_axis.clone())
+                            .ok_or(Error::UnbatchableArgument)?;
+                        let Some(NoxprTy::ArrayTy(ty)) = state.inner.ty() else {
+                            return Err(Error::UnbatchableArgument);
+                        };
+                        let param = Noxpr::parameter(
+                            0,
+                            NoxprTy::ArrayTy(ty.clone()),
+                            "while_state".to_string(),
+                        );
+                        let batched_param = BatchedExpr {
+                            inner: param.clone(),
+                            batch_axis: batch_axis.clone(),
+                        };
+                        let pred = self.visit_fn(&w.cond_fn, batched_param.clone())?;
+                        let body = self
+                            .visit_fn(&w.body_fn, batched_param)?
+                            .move_batch_axis(batch_axis.clone())
+                            .ok_or(Error::UnbatchableArgument)?;
+                        let (cond, body) = match pred.batch_axis {
+                            BatchAxis::NotMapped => (pred.inner, body.inner),
+                            BatchAxis::Mapped { .. } => {
+                                // the loop runs until every element is done, and elements
+                                // that finish early keep their state from then on
+                                let pred = pred
+                                    .move_batch_axis(batch_axis.clone())
+                                    .ok_or(Error::UnbatchableArgument)?
+                                    .inner;
+                                let any = pred
+                                    .clone()
+                                    .convert(ElementType::S32)
+                                    .reduce_max(smallvec![0])
+                                    .greater(0i32.constant());
+                                let mask = pred.broadcast_in_dim(ty.shape.clone(), smallvec![0]);
+                                (any, mask.select(body.inner, param.clone()))
+                            }
+                        };
+                        BatchedExpr {
+                            inner: Noxpr::while_loop(
+                                state.inner,
+                                NoxprFn::new(vec![param.clone()], cond),
+                                NoxprFn::new(vec![param], body),
+                            ),
+                            batch_axis,
+                        }
+                    }
+                }
+            }
         };
         self.cache.insert(id, op.clone());
         Ok(op)
@@ -2336,6 +3539,76 @@ impl BatchTracer {
         }
     }
 
+    fn visit_scatter(&mut self, s: &Scatter) -> Result<BatchedExpr, Error> {
+        let expr = self.visit(&s.expr)?;
+        let indices = self.visit(&s.indices)?;
+        let updates = self.visit(&s.updates)?;
+        let size = [&expr, &indices, &updates]
+            .iter()
+            .find_map(|e| match e.batch_axis {
+                BatchAxis::NotMapped => None,
+                BatchAxis::Mapped { size, .. } => Some(size),
+            });
+        let Some(size) = size else {
+            let inner = expr.inner.scatter(
+                indices.inner,
+                updates.inner,
+                s.update_window_dims.clone(),
+                s.inserted_window_dims.clone(),
+                s.scatter_dims_to_operand_dims.clone(),
+                s.index_vector_dim,
+            );
+            return BatchedExpr {
+                inner,
+                batch_axis: BatchAxis::NotMapped,
+            }
+            .move_batch_axis(self.out_axis.clone())
+            .ok_or(Error::UnbatchableArgument);
+        };
+        let batch_axis = BatchAxis::Mapped { index: 0, size };
+        let leading = |e: BatchedExpr| {
+            e.move_batch_axis(batch_axis.clone())
+                .map(|e| e.inner)
+                .ok_or(Error::UnbatchableArgument)
+        };
+        let expr = leading(expr)?;
+        let mut indices = leading(indices)?;
+        let updates = leading(updates)?;
+
+        // every batch element scatters into its own slice of the operand, so its position in the
+        // batch is prepended to each index vector, and the batch becomes an inserted window dim
+        let index_vector_dim = s.index_vector_dim as usize + 1;
+        let mut index_shape = indices.shape().ok_or(Error::UnbatchableArgument)?;
+        if index_vector_dim == index_shape.len() {
+            index_shape.push(1);
+            indices = indices.reshape(index_shape.clone());
+        }
+        index_shape[index_vector_dim] = 1;
+        let element_type = indices.element_type().ok_or(Error::UnbatchableArgument)?;
+        let counts = Noxpr::iota(
+            ArrayTy {
+                element_type,
+                shape: index_shape,
+            },
+            0,
+        );
+        let indices = Noxpr::concat_in_dim(vec![counts, indices], index_vector_dim);
+        let prepend_batch = |dims: &[i64]| {
+            std::iter::once(0)
+                .chain(dims.iter().map(|d| d + 1))
+                .collect::<SmallVec<[i64; 4]>>()
+        };
+        let inner = expr.scatter(
+            indices,
+            updates,
+            s.update_window_dims.iter().map(|d| d + 1).collect(),
+            prepend_batch(&s.inserted_window_dims),
+            prepend_batch(&s.scatter_dims_to_operand_dims),
+            index_vector_dim as i64,
+        );
+        Ok(BatchedExpr { inner, batch_axis })
+    }
+
     fn visit_binary_op(
         &mut self,
         op: &BinaryOp,
@@ -2415,6 +3688,69 @@ impl BatchTracer {
             BatchAxis::Mapped { .. } => Ok(expr.map_expr(func)),
         }
     }
+
+    /// Batches an op that treats every dimension but the last two as a batch dimension.
+    fn visit_matrix_op(
+        &mut self,
+        expr: &Noxpr,
+        func: impl Fn(Noxpr) -> Noxpr,
+    ) -> Result<BatchedExpr, Error> {
+        let expr = self.visit(expr)?;
+        match expr.batch_axis {
+            BatchAxis::NotMapped => Ok(expr.map_expr(func)),
+            BatchAxis::Mapped { size, .. } => Ok(expr
+                .move_batch_axis(BatchAxis::Mapped { index: 0, size })
+                .ok_or(Error::UnbatchableArgument)?
+                .map_expr(func)),
+        }
+    }
+
+    /// Batches the body of a single argument function, where the argument is `arg`.
+    fn visit_fn(&self, func: &NoxprFn, arg: BatchedExpr) -> Result<BatchedExpr, Error> {
+        let mut tracer = self.clone();
+        let param = func.args.first().ok_or(Error::UnbatchableArgument)?;
+        tracer.cache.insert(param.id(), arg);
+        tracer.visit(&func.inner)
+    }
+
+    fn visit_ternary_op(
+        &mut self,
+        exprs: [&Noxpr; 3],
+        func: impl Fn([Noxpr; 3]) -> Noxpr,
+    ) -> Result<BatchedExpr, Error> {
+        let [a, b, c] = exprs;
+        let exprs = [self.visit(a)?, self.visit(b)?, self.visit(c)?];
+        let Some(batch_axis) = exprs
+            .iter()
+            .map(|e| e.batch_axis.clone())
+            .find(|axis| *axis != BatchAxis::NotMapped)
+        else {
+            return BatchedExpr {
+                inner: func(exprs.map(|e| e.inner)),
+                batch_axis: BatchAxis::NotMapped,
+            }
+            .move_batch_axis(self.out_axis.clone())
+            .ok_or(Error::UnbatchableArgument);
+        };
+        // unmapped scalars are left alone, since xla broadcasts scalar operands of select and clamp
+        let [a, b, c] = exprs.map(|e| {
+            let is_scalar = e.inner.shape().map(|s| s.is_empty()).unwrap_or(false);
+            if e.batch_axis == BatchAxis::NotMapped && is_scalar {
+                Some(e.inner)
+            } else {
+                e.move_batch_axis(batch_axis.clone()).map(|e| e.inner)
+            }
+        });
+        let exprs = [
+            a.ok_or(Error::UnbatchableArgument)?,
+            b.ok_or(Error::UnbatchableArgument)?,
+            c.ok_or(Error::UnbatchableArgument)?,
+        ];
+        Ok(BatchedExpr {
+            inner: func(exprs),
+            batch_axis,
+        })
+    }
 }
 
 pub trait NoxprScalarExt {
@@ -2512,9 +3848,19 @@ impl PrettyPrintTracer {
             NoxprNode::Div(d) => self.visit_binary_op(id, d, "/", writer),
             NoxprNode::And(a) => self.visit_binary_op(id, a, "&&", writer),
             NoxprNode::Or(o) => self.visit_binary_op(id, o, "||", writer),
+            NoxprNode::Xor(x) => self.visit_binary_op(id, x, "^", writer),
+            NoxprNode::ShiftLeft(x) => self.visit_binary_op(id, x, "<<", writer),
+            NoxprNode::ShiftRightLogical(x) => self.visit_binary_op(id, x, ">>", writer),
             NoxprNode::GreaterOrEqual(g) => self.visit_binary_op(id, g, ">=", writer),
             NoxprNode::LessOrEqual(le) => self.visit_binary_op(id, le, "<=", writer),
             NoxprNode::Less(l) => self.visit_binary_op(id, l, "<", writer),
+            NoxprNode::Greater(g) => self.visit_binary_op(id, g, ">", writer),
+            NoxprNode::Equal(e) => self.visit_binary_op(id, e, "==", writer),
+            NoxprNode::NotEqual(n) => self.visit_binary_op(id, n, "!=", writer),
+            NoxprNode::Atan2(a) => self.visit_binary_fn(id, a, "atan2", writer),
+            NoxprNode::Pow(p) => self.visit_binary_fn(id, p, "pow", writer),
+            NoxprNode::Max(m) => self.visit_binary_fn(id, m, "max", writer),
+            NoxprNode::Min(m) => self.visit_binary_fn(id, m, "min", writer),
             NoxprNode::Dot(d) => self.visit_binary_op(id, d, ".", writer),
             NoxprNode::DotGeneral(d) => {
                 let lhs = self.visit(&d.lhs, writer)?;
@@ -2557,6 +3903,38 @@ impl PrettyPrintTracer {
                 write!(writer, "cos(var_{})", arg)?;
                 Ok(num)
             }
+            NoxprNode::Exp(e) => self.visit_unary_fn(id, e, "exp", writer),
+            NoxprNode::Tanh(t) => self.visit_unary_fn(id, t, "tanh", writer),
+            NoxprNode::Abs(a) => self.visit_unary_fn(id, a, "abs", writer),
+            NoxprNode::Floor(f) => self.visit_unary_fn(id, f, "floor", writer),
+            NoxprNode::Ceil(c) => self.visit_unary_fn(id, c, "ceil", writer),
+            NoxprNode::Rsqrt(r) => self.visit_unary_fn(id, r, "rsqrt", writer),
+            NoxprNode::Convert(c) => {
+                let expr = self.visit(&c.expr, writer)?;
+                let num = self.print_var(id, writer)?;
+                write!(writer, "convert(var_{}, {:?})", expr, c.element_type)?;
+                Ok(num)
+            }
+            NoxprNode::Select(s) => {
+                let cond = self.visit(&s.cond, writer)?;
+                let on_true = self.visit(&s.on_true, writer)?;
+                let on_false = self.visit(&s.on_false, writer)?;
+                let num = self.print_var(id, writer)?;
+                write!(
+                    writer,
+                    "select(var_{}, var_{}, var_{})",
+                    cond, on_true, on_false
+                )?;
+                Ok(num)
+            }
+            NoxprNode::Clamp(c) => {
+                let min = self.visit(&c.min, writer)?;
+                let expr = self.visit(&c.expr, writer)?;
+                let max = self.visit(&c.max, writer)?;
+                let num = self.print_var(id, writer)?;
+                write!(writer, "clamp(var_{}, var_{}, var_{})", min, expr, max)?;
+                Ok(num)
+            }
 
             NoxprNode::Concat(c) => {
                 let nums: Vec<_> = c
@@ -2660,6 +4038,18 @@ impl PrettyPrintTracer {
                 write!(writer, "])")?;
                 Ok(num)
             }
+            NoxprNode::Scatter(s) => {
+                let expr = self.visit(&s.expr, writer)?;
+                let indices = self.visit(&s.indices, writer)?;
+                let updates = self.visit(&s.updates, writer)?;
+                let num = self.print_var(id, writer)?;
+                write!(
+                    writer,
+                    "scatter_add(expr = var_{}, indices = var_{}, updates = var_{}, update_window_dims = {:?}, inserted_window_dims = {:?}, scatter_dims_to_operand_dims = {:?}, index_vector_dim = {})",
+                    expr, indices, updates, s.update_window_dims, s.inserted_window_dims, s.scatter_dims_to_operand_dims, s.index_vector_dim
+                )?;
+                Ok(num)
+            }
             NoxprNode::Scan(s) => {
                 let inputs = s
                     .inputs
@@ -2677,6 +4067,62 @@ impl PrettyPrintTracer {
                 write!(writer, ")")?;
                 Ok(num)
             }
+            NoxprNode::Cholesky(e) => self.visit_unary_fn(id, e, "cholesky", writer),
+            NoxprNode::TriangularSolve(t) => {
+                let a = self.visit(&t.a, writer)?;
+                let b = self.visit(&t.b, writer)?;
+                let num = self.print_var(id, writer)?;
+                write!(
+                    writer,
+                    "triangular_solve(var_{}, var_{}, left_side = {}, lower = {}, unit_diagonal = {}, transpose_a = {})",
+                    a, b, t.left_side, t.lower, t.unit_diagonal, t.transpose_a
+                )?;
+                Ok(num)
+            }
+            NoxprNode::Qr(e) => self.visit_unary_fn(id, e, "qr", writer),
+            NoxprNode::Eigh(e) => self.visit_unary_fn(id, e, "eigh", writer),
+            NoxprNode::Svd(e) => self.visit_unary_fn(id, e, "svd", writer),
+            NoxprNode::Reduce(r) => {
+                let expr = self.visit(&r.expr, writer)?;
+                let num = self.print_var(id, writer)?;
+                write!(
+                    writer,
+                    "reduce_{}(var_{}, dims = {:?})",
+                    r.op.name(),
+                    expr,
+                    r.dims
+                )?;
+                Ok(num)
+            }
+            NoxprNode::Cond(c) => {
+                let pred = self.visit(&c.pred, writer)?;
+                let operands = c
+                    .operands
+                    .iter()
+                    .map(|e| self.visit(e, writer).map(|n| format!("var_{}", n)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let num = self.print_var(id, writer)?;
+                write!(
+                    writer,
+                    "cond(var_{}, operands = {:?}, on_true = ",
+                    pred, &operands
+                )?;
+                c.on_true.pretty_print(self, writer)?;
+                write!(writer, ", on_false = ")?;
+                c.on_false.pretty_print(self, writer)?;
+                write!(writer, ")")?;
+                Ok(num)
+            }
+            NoxprNode::While(w) => {
+                let init = self.visit(&w.initial_state, writer)?;
+                let num = self.print_var(id, writer)?;
+                write!(writer, "while(init = var_{}, cond = ", init)?;
+                w.cond_fn.pretty_print(self, writer)?;
+                write!(writer, ", body = ")?;
+                w.body_fn.pretty_print(self, writer)?;
+                write!(writer, ")")?;
+                Ok(num)
+            }
             NoxprNode::Jax(j) => {
                 let num = self.print_var(id, writer)?;
                 write!(writer, "jax({:?})", j)?;
@@ -2700,6 +4146,33 @@ impl PrettyPrintTracer {
         write!(writer, "{} {} {}", lhs, op_label, rhs)?;
         Ok(num)
     }
+
+    fn visit_binary_fn(
+        &mut self,
+        id: NoxprId,
+        op: &BinaryOp,
+        fn_name: &str,
+        writer: &mut impl std::fmt::Write,
+    ) -> Result<usize, std::fmt::Error> {
+        let lhs = self.visit(&op.lhs, writer)?;
+        let rhs = self.visit(&op.rhs, writer)?;
+        let num = self.print_var(id, writer)?;
+        write!(writer, "{}(var_{}, var_{})", fn_name, lhs, rhs)?;
+        Ok(num)
+    }
+
+    fn visit_unary_fn(
+        &mut self,
+        id: NoxprId,
+        expr: &Noxpr,
+        fn_name: &str,
+        writer: &mut impl std::fmt::Write,
+    ) -> Result<usize, std::fmt::Error> {
+        let arg = self.visit(expr, writer)?;
+        let num = self.print_var(id, writer)?;
+        write!(writer, "{}(var_{})", fn_name, arg)?;
+        Ok(num)
+    }
 }
 
 #[cfg(test)]
@@ -2732,6 +4205,138 @@ mod tests {
         assert_eq!(out, vector![2.0, 3.0, 4.0, 6.0, 7.0])
     }
 
+    #[test]
+    fn test_while_loop() {
+        let client = Client::cpu().unwrap();
+        fn double_until(x: Scalar<f64>) -> Scalar<f64> {
+            use crate::ScalarExt;
+            x.while_loop(
+                |x: Scalar<f64>| x.less(&100.0.constant()),
+                |x: Scalar<f64>| x.clone() + x,
+            )
+            .unwrap()
+        }
+        let comp = double_until.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec.run(&client, 3.0).unwrap().to_host();
+        assert_eq!(out, 192.0)
+    }
+
+    #[test]
+    fn test_cond() {
+        let client = Client::cpu().unwrap();
+        fn abs_square(x: Scalar<f64>) -> Scalar<f64> {
+            use crate::ScalarExt;
+            x.cond(
+                &x.greater(&0.0.constant()),
+                |x: Scalar<f64>| x.clone() * x,
+                |x: Scalar<f64>| -x,
+            )
+            .unwrap()
+        }
+        let comp = abs_square.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        assert_eq!(exec.run(&client, 3.0).unwrap().to_host(), 9.0);
+        assert_eq!(exec.run(&client, -2.0).unwrap().to_host(), 2.0);
+    }
+
+    #[test]
+    fn test_while_loop_vmap() {
+        let client = Client::cpu().unwrap();
+        fn double_until(xs: Vector<f32, 3>) -> Vector<f32, 3> {
+            use crate::ScalarExt;
+            xs.vmap(|x: Scalar<f32>| {
+                x.while_loop(
+                    |x: Scalar<f32>| x.less(&100.0.constant()),
+                    |x: Scalar<f32>| x.clone() + x,
+                )
+                .unwrap()
+            })
+            .unwrap()
+            .collapse()
+        }
+        let comp = double_until.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec
+            .run(&client, vector![1.0f32, 10.0, 50.0])
+            .unwrap()
+            .to_host();
+        assert_eq!(out, vector![128.0, 160.0, 100.0])
+    }
+
+    #[test]
+    fn test_cond_vmap() {
+        let client = Client::cpu().unwrap();
+        fn abs_square(xs: Vector<f32, 3>) -> Vector<f32, 3> {
+            use crate::ScalarExt;
+            xs.vmap(|x: Scalar<f32>| {
+                x.cond(
+                    &x.greater(&0.0.constant()),
+                    |x: Scalar<f32>| x.clone() * x,
+                    |x: Scalar<f32>| -x,
+                )
+                .unwrap()
+            })
+            .unwrap()
+            .collapse()
+        }
+        let comp = abs_square.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec
+            .run(&client, vector![-2.0f32, 3.0, 0.0])
+            .unwrap()
+            .to_host();
+        assert_eq!(out, vector![2.0, 9.0, 0.0])
+    }
+
+    #[test]
+    fn test_scatter_vmap() {
+        let client = Client::cpu().unwrap();
+        fn scatter_rows(mat: Matrix<f32, 2, 3>) -> Matrix<f32, 2, 3> {
+            use crate::{ArrayTy, Noxpr, NoxprScalarExt};
+            mat.vmap(|x: Vector<f32, 3>| {
+                let indices = Noxpr::iota(ArrayTy::new(xla::ElementType::S64, smallvec![3]), 0)
+                    .min(1i64.constant().broadcast(smallvec![3]))
+                    .reshape(smallvec![3, 1]);
+                let zeros = 0f32.constant().broadcast(smallvec![3]);
+                Vector::<f32, 3>::from_op(zeros.scatter(
+                    indices,
+                    x.inner,
+                    smallvec![],
+                    smallvec![0],
+                    smallvec![0],
+                    1,
+                ))
+            })
+            .unwrap()
+            .collapse()
+        }
+        let comp = scatter_rows.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec
+            .run(&client, matrix![1.0f32, 2.0, 3.0; 4.0, 5.0, 6.0])
+            .unwrap()
+            .to_host();
+        assert_eq!(out, matrix![1.0, 5.0, 0.0; 4.0, 11.0, 0.0])
+    }
+
+    #[test]
+    fn test_reduce_vmap() {
+        let client = Client::cpu().unwrap();
+        fn row_sums(mat: Matrix<f32, 2, 3>) -> Vector<f32, 2> {
+            mat.vmap(|x: Vector<f32, 3>| x.sum::<0>())
+                .unwrap()
+                .collapse()
+        }
+        let comp = row_sums.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec
+            .run(&client, matrix![1.0f32, 2.0, 3.0; 4.0, 5.0, 6.0])
+            .unwrap()
+            .to_host();
+        assert_eq!(out, vector![6.0, 15.0])
+    }
+
     #[test]
     fn test_unary_vmap() {
         let client = Client::cpu().unwrap();
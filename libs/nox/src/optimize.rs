@@ -0,0 +1,513 @@
+//! Provides an optimization pass over [`Noxpr`] graphs, run before they are lowered to XLA.
+use crate::{ArrayTy, BinaryOp, Cond, Noxpr, NoxprFn, NoxprId, NoxprNode, Scan, While};
+use smallvec::SmallVec;
+use std::collections::{HashMap, HashSet};
+use std::ops::Deref;
+use xla::{ArrayElement, ElementType, NativeType};
+
+/// The largest number of elements a folded constant may hold, so folding never bloats a graph.
+const MAX_FOLD_LEN: usize = 1024;
+
+/// The number of distinct nodes in a function before and after it was optimized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OptimizeReport {
+    pub nodes_before: usize,
+    pub nodes_after: usize,
+}
+
+impl std::fmt::Display for OptimizeReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} -> {} nodes", self.nodes_before, self.nodes_after)
+    }
+}
+
+impl NoxprFn {
+    /// Optimizes the function, returning it alongside a report of the node counts.
+    pub fn optimize(&self) -> (NoxprFn, OptimizeReport) {
+        let optimized = Optimizer::default().visit_fn(self);
+        let report = OptimizeReport {
+            nodes_before: self.node_count(),
+            nodes_after: optimized.node_count(),
+        };
+        (optimized, report)
+    }
+
+    /// Counts the distinct nodes reachable from the function, not including nested functions.
+    pub fn node_count(&self) -> usize {
+        let mut seen = HashSet::new();
+        let mut stack = self.args.clone();
+        stack.push(self.inner.clone());
+        while let Some(expr) = stack.pop() {
+            if seen.insert(expr.id()) {
+                stack.extend(expr.inputs());
+            }
+        }
+        seen.len()
+    }
+}
+
+/// Rebuilds a graph from the bottom up, simplifying each node as it goes.
+///
+/// Structurally identical nodes are shared, arithmetic on constants and iotas is folded, and
+/// reshapes, broadcasts, transposes and conversions that leave their input unchanged are removed.
+#[derive(Default)]
+pub struct Optimizer {
+    cache: HashMap<NoxprId, Noxpr>,
+    interned: HashMap<NodeKey, Noxpr>,
+}
+
+/// Identifies a node by its kind, its inputs and its attributes.
+#[derive(PartialEq, Eq, Hash)]
+struct NodeKey {
+    name: &'static str,
+    inputs: Vec<NoxprId>,
+    attrs: Vec<u8>,
+}
+
+impl Optimizer {
+    pub fn visit_fn(&mut self, func: &NoxprFn) -> NoxprFn {
+        let args = func.args.iter().map(|a| self.visit(a)).collect();
+        let inner = self.visit(&func.inner);
+        NoxprFn::new(args, inner)
+    }
+
+    pub fn visit(&mut self, expr: &Noxpr) -> Noxpr {
+        if let Some(expr) = self.cache.get(&expr.id()) {
+            return expr.clone();
+        }
+        let rebuilt = optimize_nested(expr.map_inputs(|input| self.visit(input)));
+        let optimized = self.intern(simplify(rebuilt));
+        self.cache.insert(expr.id(), optimized.clone());
+        optimized
+    }
+
+    fn intern(&mut self, expr: Noxpr) -> Noxpr {
+        let Some(attrs) = attrs(&expr) else {
+            return expr;
+        };
+        let key = NodeKey {
+            name: expr.name(),
+            inputs: expr.inputs().iter().map(Noxpr::id).collect(),
+            attrs,
+        };
+        self.interned.entry(key).or_insert(expr).clone()
+    }
+}
+
+/// Optimizes the functions nested in a scan, cond or while node.
+///
+/// Each function is lowered on its own, so it's optimized by its own [`Optimizer`].
+fn optimize_nested(expr: Noxpr) -> Noxpr {
+    let optimize = |func: &NoxprFn| Optimizer::default().visit_fn(func);
+    match expr.deref() {
+        NoxprNode::Scan(s) => Noxpr::new(NoxprNode::Scan(Scan {
+            inputs: s.inputs.clone(),
+            initial_state: s.initial_state.clone(),
+            scan_fn: optimize(&s.scan_fn),
+        })),
+        NoxprNode::Cond(c) => Noxpr::new(NoxprNode::Cond(Cond {
+            pred: c.pred.clone(),
+            operands: c.operands.clone(),
+            on_true: optimize(&c.on_true),
+            on_false: optimize(&c.on_false),
+        })),
+        NoxprNode::While(w) => Noxpr::new(NoxprNode::While(While {
+            initial_state: w.initial_state.clone(),
+            cond_fn: optimize(&w.cond_fn),
+            body_fn: optimize(&w.body_fn),
+        })),
+        _ => expr,
+    }
+}
+
+/// Returns the attributes that, along with its kind and inputs, identify a node.
+///
+/// Nodes holding nested functions or python objects can't be compared, and return `None`. Params
+/// are only identified by the node itself, since distinct params can share a number and name.
+fn attrs(expr: &Noxpr) -> Option<Vec<u8>> {
+    let attrs = match expr.deref() {
+        NoxprNode::Param(_) => return None,
+        NoxprNode::Constant(c) => {
+            let mut attrs = format!("{:?}", c.ty).into_bytes();
+            attrs.extend_from_slice(c.data.raw_buf());
+            return Some(attrs);
+        }
+        NoxprNode::Iota(i) => format!("{:?} {}", i.shape, i.dim),
+        NoxprNode::GetTupleElement(g) => g.index.to_string(),
+        NoxprNode::DotGeneral(d) => format!("{:?}", d.dimensions),
+        NoxprNode::Convert(c) => format!("{:?}", c.element_type),
+        NoxprNode::Concat(c) => c.dimension.to_string(),
+        NoxprNode::Reshape(r) => format!("{:?}", r.new_sizes),
+        NoxprNode::Broadcast(b) => format!("{:?}", b.sizes),
+        NoxprNode::BroadcastInDim(b) => format!("{:?} {:?}", b.sizes, b.broadcast_dims),
+        NoxprNode::Transpose(t) => format!("{:?}", t.permutation),
+        NoxprNode::Gather(g) => format!(
+            "{:?} {:?} {:?} {:?} {}",
+            g.offset_dims,
+            g.collapsed_slice_dims,
+            g.start_index_map,
+            g.slice_sizes,
+            g.index_vector_dim
+        ),
+        NoxprNode::Slice(s) => {
+            format!("{:?} {:?} {:?}", s.start_indices, s.stop_indices, s.strides)
+        }
+        NoxprNode::DynamicSlice(d) => format!("{:?}", d.size_indices),
+        NoxprNode::Scatter(s) => format!(
+            "{:?} {:?} {:?} {}",
+            s.update_window_dims,
+            s.inserted_window_dims,
+            s.scatter_dims_to_operand_dims,
+            s.index_vector_dim
+        ),
+        NoxprNode::Reduce(r) => format!("{:?} {:?}", r.op, r.dims),
+        NoxprNode::TriangularSolve(t) => format!(
+            "{} {} {} {}",
+            t.left_side, t.lower, t.unit_diagonal, t.transpose_a
+        ),
+        NoxprNode::Tuple(_)
+        | NoxprNode::Add(_)
+        | NoxprNode::Sub(_)
+        | NoxprNode::Mul(_)
+        | NoxprNode::Div(_)
+        | NoxprNode::And(_)
+        | NoxprNode::Or(_)
+        | NoxprNode::Xor(_)
+        | NoxprNode::ShiftLeft(_)
+        | NoxprNode::ShiftRightLogical(_)
+        | NoxprNode::GreaterOrEqual(_)
+        | NoxprNode::LessOrEqual(_)
+        | NoxprNode::Less(_)
+        | NoxprNode::Greater(_)
+        | NoxprNode::Equal(_)
+        | NoxprNode::NotEqual(_)
+        | NoxprNode::Atan2(_)
+        | NoxprNode::Pow(_)
+        | NoxprNode::Max(_)
+        | NoxprNode::Min(_)
+        | NoxprNode::Dot(_)
+        | NoxprNode::Sqrt(_)
+        | NoxprNode::Neg(_)
+        | NoxprNode::Log(_)
+        | NoxprNode::Sin(_)
+        | NoxprNode::Cos(_)
+        | NoxprNode::Exp(_)
+        | NoxprNode::Tanh(_)
+        | NoxprNode::Abs(_)
+        | NoxprNode::Floor(_)
+        | NoxprNode::Ceil(_)
+        | NoxprNode::Rsqrt(_)
+        | NoxprNode::Select(_)
+        | NoxprNode::Clamp(_)
+        | NoxprNode::DynamicUpdateSlice(_)
+        | NoxprNode::Cholesky(_)
+        | NoxprNode::Qr(_)
+        | NoxprNode::Eigh(_)
+        | NoxprNode::Svd(_) => String::new(),
+        NoxprNode::Scan(_) | NoxprNode::Cond(_) | NoxprNode::While(_) => return None,
+        #[cfg(feature = "jax")]
+        NoxprNode::Jax(_) => return None,
+    };
+    Some(attrs.into_bytes())
+}
+
+/// Removes a node that leaves its input unchanged, or folds it into a constant.
+fn simplify(expr: Noxpr) -> Noxpr {
+    let simplified = match expr.deref() {
+        NoxprNode::Reshape(r) => {
+            let input = match r.expr.deref() {
+                NoxprNode::Reshape(inner) => &inner.expr,
+                _ => &r.expr,
+            };
+            if input.shape().as_ref() == Some(&r.new_sizes) {
+                Some(input.clone())
+            } else if let NoxprNode::Constant(c) = input.deref() {
+                c.data.reshape(&r.new_sizes).ok().map(|data| {
+                    Noxpr::constant(data, ArrayTy::new(c.ty.element_type, r.new_sizes.clone()))
+                })
+            } else if input.id() != r.expr.id() {
+                Some(input.clone().reshape(r.new_sizes.clone()))
+            } else {
+                None
+            }
+        }
+        NoxprNode::Broadcast(b) if b.sizes.is_empty() => Some(b.expr.clone()),
+        NoxprNode::BroadcastInDim(b) => {
+            let identity = b.broadcast_dims.iter().copied().eq(0..b.sizes.len() as i64);
+            (identity && b.expr.shape().as_ref() == Some(&b.sizes)).then(|| b.expr.clone())
+        }
+        NoxprNode::Transpose(t) => {
+            let identity = t
+                .permutation
+                .iter()
+                .copied()
+                .eq(0..t.permutation.len() as i64);
+            identity.then(|| t.expr.clone())
+        }
+        NoxprNode::Convert(c) => {
+            (c.expr.element_type() == Some(c.element_type)).then(|| c.expr.clone())
+        }
+        NoxprNode::Add(b) => fold_binary(ArithOp::Add, b),
+        NoxprNode::Sub(b) => fold_binary(ArithOp::Sub, b),
+        NoxprNode::Mul(b) => fold_binary(ArithOp::Mul, b),
+        NoxprNode::Div(b) => fold_binary(ArithOp::Div, b),
+        NoxprNode::Max(b) => fold_binary(ArithOp::Max, b),
+        NoxprNode::Min(b) => fold_binary(ArithOp::Min, b),
+        NoxprNode::Neg(e) => fold_neg(e),
+        _ => None,
+    };
+    simplified.unwrap_or(expr)
+}
+
+#[derive(Clone, Copy)]
+enum ArithOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Max,
+    Min,
+}
+
+fn fold_binary(op: ArithOp, b: &BinaryOp) -> Option<Noxpr> {
+    let lhs = foldable_ty(&b.lhs)?;
+    let rhs = foldable_ty(&b.rhs)?;
+    if lhs.element_type != rhs.element_type {
+        return None;
+    }
+    // only scalars are broadcast, which covers the constants built by the typed api
+    let shape = if rhs.shape.is_empty() || lhs.shape == rhs.shape {
+        lhs.shape
+    } else if lhs.shape.is_empty() {
+        rhs.shape
+    } else {
+        return None;
+    };
+    macro_rules! fold_as {
+        ($ty:ty) => {{
+            let lhs = host_values::<$ty>(&b.lhs)?;
+            let rhs = host_values::<$ty>(&b.rhs)?;
+            let len = shape.iter().product::<i64>() as usize;
+            let at = |values: &[$ty], i: usize| values[if values.len() == 1 { 0 } else { i }];
+            let out = (0..len)
+                .map(|i| <$ty>::apply(op, at(&lhs, i), at(&rhs, i)))
+                .collect::<Option<Vec<$ty>>>()?;
+            host_constant(&out, shape)
+        }};
+    }
+    match lhs.element_type {
+        ElementType::F32 => fold_as!(f32),
+        ElementType::F64 => fold_as!(f64),
+        ElementType::S32 => fold_as!(i32),
+        ElementType::S64 => fold_as!(i64),
+        ElementType::U32 => fold_as!(u32),
+        ElementType::U64 => fold_as!(u64),
+        _ => None,
+    }
+}
+
+fn fold_neg(expr: &Noxpr) -> Option<Noxpr> {
+    let ty = foldable_ty(expr)?;
+    macro_rules! fold_as {
+        ($ty:ty) => {{
+            let values = host_values::<$ty>(expr)?;
+            let out = values.into_iter().map(FoldElem::neg).collect::<Vec<_>>();
+            host_constant(&out, ty.shape)
+        }};
+    }
+    match ty.element_type {
+        ElementType::F32 => fold_as!(f32),
+        ElementType::F64 => fold_as!(f64),
+        ElementType::S32 => fold_as!(i32),
+        ElementType::S64 => fold_as!(i64),
+        ElementType::U32 => fold_as!(u32),
+        ElementType::U64 => fold_as!(u64),
+        _ => None,
+    }
+}
+
+/// Returns the type of a small constant or iota, whose values are known on the host.
+fn foldable_ty(expr: &Noxpr) -> Option<ArrayTy> {
+    let ty = match expr.deref() {
+        NoxprNode::Constant(c) => c.ty.clone(),
+        NoxprNode::Iota(i) => i.shape.clone(),
+        _ => return None,
+    };
+    let len = ty.shape.iter().product::<i64>() as usize;
+    (len <= MAX_FOLD_LEN).then_some(ty)
+}
+
+fn host_values<T: FoldElem>(expr: &Noxpr) -> Option<Vec<T>> {
+    match expr.deref() {
+        NoxprNode::Constant(c) => Some(
+            c.data
+                .raw_buf()
+                .chunks_exact(T::ELEMENT_SIZE_IN_BYTES)
+                .map(T::from_bytes)
+                .collect(),
+        ),
+        NoxprNode::Iota(i) => {
+            let shape = &i.shape.shape;
+            let len = shape.iter().product::<i64>() as usize;
+            let stride = shape[i.dim + 1..].iter().product::<i64>() as usize;
+            let size = shape[i.dim] as usize;
+            Some(
+                (0..len)
+                    .map(|index| T::from_index(index / stride % size))
+                    .collect(),
+            )
+        }
+        _ => None,
+    }
+}
+
+fn host_constant<T: FoldElem>(values: &[T], shape: SmallVec<[i64; 4]>) -> Option<Noxpr> {
+    let data = T::create_r1(values).reshape(&shape).ok()?;
+    Some(Noxpr::constant(data, ArrayTy::new(T::TY, shape)))
+}
+
+/// An element type whose arithmetic can be evaluated on the host, matching XLA's semantics.
+trait FoldElem: NativeType + ArrayElement {
+    fn from_bytes(bytes: &[u8]) -> Self;
+    fn from_index(index: usize) -> Self;
+    fn apply(op: ArithOp, lhs: Self, rhs: Self) -> Option<Self>;
+    fn neg(self) -> Self;
+}
+
+macro_rules! impl_fold_float {
+    ($($ty:ty),*) => {
+        $(
+            impl FoldElem for $ty {
+                fn from_bytes(bytes: &[u8]) -> Self {
+                    <$ty>::from_ne_bytes(bytes.try_into().expect("chunk matches the element size"))
+                }
+
+                fn from_index(index: usize) -> Self {
+                    index as $ty
+                }
+
+                fn apply(op: ArithOp, lhs: Self, rhs: Self) -> Option<Self> {
+                    // XLA propagates NaNs through max and min, while `f32::max` drops them
+                    Some(match op {
+                        ArithOp::Add => lhs + rhs,
+                        ArithOp::Sub => lhs - rhs,
+                        ArithOp::Mul => lhs * rhs,
+                        ArithOp::Div => lhs / rhs,
+                        ArithOp::Max if lhs.is_nan() || rhs.is_nan() => <$ty>::NAN,
+                        ArithOp::Max => lhs.max(rhs),
+                        ArithOp::Min if lhs.is_nan() || rhs.is_nan() => <$ty>::NAN,
+                        ArithOp::Min => lhs.min(rhs),
+                    })
+                }
+
+                fn neg(self) -> Self {
+                    -self
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_fold_int {
+    ($($ty:ty),*) => {
+        $(
+            impl FoldElem for $ty {
+                fn from_bytes(bytes: &[u8]) -> Self {
+                    <$ty>::from_ne_bytes(bytes.try_into().expect("chunk matches the element size"))
+                }
+
+                fn from_index(index: usize) -> Self {
+                    index as $ty
+                }
+
+                fn apply(op: ArithOp, lhs: Self, rhs: Self) -> Option<Self> {
+                    // division by zero and overflowing division are left for XLA to evaluate
+                    Some(match op {
+                        ArithOp::Add => lhs.wrapping_add(rhs),
+                        ArithOp::Sub => lhs.wrapping_sub(rhs),
+                        ArithOp::Mul => lhs.wrapping_mul(rhs),
+                        ArithOp::Div => lhs.checked_div(rhs)?,
+                        ArithOp::Max => lhs.max(rhs),
+                        ArithOp::Min => lhs.min(rhs),
+                    })
+                }
+
+                fn neg(self) -> Self {
+                    self.wrapping_neg()
+                }
+            }
+        )*
+    };
+}
+
+impl_fold_float!(f32, f64);
+impl_fold_int!(i32, i64, u32, u64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Client, CompFn, FromOp, NoxprScalarExt, NoxprTy, ToHost, Vector};
+    use nalgebra::vector;
+    use smallvec::smallvec;
+
+    fn param(shape: SmallVec<[i64; 4]>) -> Noxpr {
+        let ty = NoxprTy::ArrayTy(ArrayTy::new(ElementType::F64, shape));
+        Noxpr::parameter(0, ty, "x".to_string())
+    }
+
+    #[test]
+    fn test_shares_identical_nodes() {
+        let x = param(smallvec![]);
+        let branch = || x.clone().sin() + 1.0f64.constant();
+        let func = NoxprFn::new(vec![x.clone()], branch() * branch());
+        let (_, report) = func.optimize();
+        assert_eq!(
+            report,
+            OptimizeReport {
+                nodes_before: 8,
+                nodes_after: 5
+            }
+        );
+    }
+
+    #[test]
+    fn test_keeps_distinct_params() {
+        let (x, y) = (param(smallvec![]), param(smallvec![]));
+        let func = NoxprFn::new(vec![x.clone(), y.clone()], x - y);
+        let (func, _) = func.optimize();
+        let NoxprNode::Sub(b) = func.inner.deref() else {
+            panic!("expected the difference to remain");
+        };
+        assert_ne!(b.lhs.id(), b.rhs.id());
+    }
+
+    #[test]
+    fn test_folds_constants() {
+        let x = param(smallvec![3]);
+        let iota = Noxpr::iota(ArrayTy::new(ElementType::F64, smallvec![3]), 0);
+        let offset = (iota * 2.0f64.constant()).reshape(smallvec![3]);
+        let func = NoxprFn::new(vec![x.clone()], x + offset);
+        let (func, report) = func.optimize();
+        assert_eq!(report.nodes_after, 3);
+        let NoxprNode::Add(b) = func.inner.deref() else {
+            panic!("expected the sum to remain");
+        };
+        assert_eq!(host_values::<f64>(&b.rhs), Some(vec![0.0, 2.0, 4.0]));
+    }
+
+    #[test]
+    fn test_optimized_comp_runs() {
+        let client = Client::cpu().unwrap();
+        fn shifted(x: Vector<f64, 3>) -> Vector<f64, 3> {
+            let offset = -(2.0f64.constant() * 3.0f64.constant());
+            let offset = Vector::<f64, 3>::from_op(offset.broadcast(smallvec![3]));
+            let a = x.clone() + offset.clone();
+            let b = x + offset;
+            a * b
+        }
+        let comp = shifted.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec.run(&client, vector![1.0, 6.0, 8.0]).unwrap().to_host();
+        assert_eq!(out, vector![25.0, 0.0, 4.0]);
+    }
+}
@@ -4,13 +4,16 @@ use std::{
     ops::{Add, Div, Mul, Sub},
 };
 
-use nalgebra::{constraint::ShapeConstraint, Const};
-use smallvec::SmallVec;
+use nalgebra::{constraint::ShapeConstraint, Const, RealField};
+use smallvec::{smallvec, SmallVec};
 
 use crate::{
-    local_backend::{ArrayBufUnit, ArrayDim},
-    BroadcastDim, BroadcastedDim, ConcatManyDim, DefaultMap, DefaultMappedDim, DimGet, DotDim,
-    DottedDim, Field, GetDim, MapDim, MulDim, Noxpr, TensorDim, XlaDim,
+    local_backend::{
+        check_broadcast, check_fixed_slice, check_reshape, const_dims, ArrayBufUnit, ArrayDim,
+    },
+    BroadcastDim, BroadcastedDim, ConcatManyDim, DefaultMap, DefaultMappedDim, DimGet, DimReduce,
+    DotDim, DottedDim, Error, Field, GetDim, MapDim, MulDim, Noxpr, ReduceOp, ReducedDim,
+    ScalarDim, SquareDim, TensorDim, XlaDim,
 };
 
 pub struct Op;
@@ -111,6 +114,69 @@ pub trait Repr {
         ShapeConstraint: DimGet<D1>,
         <GetDim<D1> as ArrayDim>::Buf<MaybeUninit<T1>>:
             ArrayBufUnit<T1, Init = <GetDim<D1> as ArrayDim>::Buf<T1>>;
+
+    fn reduce<T1: Field + PartialOrd, D1: Dim, const AXIS: usize>(
+        arg: &Self::Inner<T1, D1>,
+        op: ReduceOp,
+    ) -> Self::Inner<T1, ReducedDim<D1, AXIS>>
+    where
+        ShapeConstraint: DimReduce<D1, AXIS>,
+        <ReducedDim<D1, AXIS> as ArrayDim>::Buf<MaybeUninit<T1>>:
+            ArrayBufUnit<T1, Init = <ReducedDim<D1, AXIS> as ArrayDim>::Buf<T1>>;
+
+    fn cholesky<T1: Field + RealField, const N: usize>(
+        arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> Self::Inner<T1, SquareDim<N>>;
+
+    fn inverse<T1: Field + RealField, const N: usize>(
+        arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> Self::Inner<T1, SquareDim<N>>;
+
+    fn determinant<T1: Field + RealField, const N: usize>(
+        arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> Self::Inner<T1, ScalarDim>;
+
+    fn solve<T1: Field + RealField, D1: Dim, const N: usize>(
+        a: &Self::Inner<T1, SquareDim<N>>,
+        b: &Self::Inner<T1, D1>,
+    ) -> Self::Inner<T1, D1>
+    where
+        <D1 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D1 as ArrayDim>::Buf<T1>>;
+
+    fn qr<T1: Field + RealField, const N: usize>(
+        arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> (Self::Inner<T1, SquareDim<N>>, Self::Inner<T1, SquareDim<N>>);
+
+    fn eigh<T1: Field + RealField, const N: usize>(
+        arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> (Self::Inner<T1, Const<N>>, Self::Inner<T1, SquareDim<N>>);
+
+    fn svd<T1: Field + RealField, const N: usize>(
+        arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> (
+        Self::Inner<T1, SquareDim<N>>,
+        Self::Inner<T1, Const<N>>,
+        Self::Inner<T1, SquareDim<N>>,
+    );
+
+    fn reshape<T1: Field, D1: Dim, D2: Dim>(
+        arg: &Self::Inner<T1, D1>,
+    ) -> Result<Self::Inner<T1, D2>, Error>
+    where
+        <D2 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D2 as ArrayDim>::Buf<T1>>;
+
+    fn broadcast<T1: Field, D1: Dim, D2: Dim>(
+        arg: &Self::Inner<T1, D1>,
+    ) -> Result<Self::Inner<T1, D2>, Error>
+    where
+        <D2 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D2 as ArrayDim>::Buf<T1>>;
+
+    fn fixed_slice<T1: Field, D1: Dim, D2: Dim>(
+        arg: &Self::Inner<T1, D1>,
+        offsets: &[usize],
+    ) -> Result<Self::Inner<T1, D2>, Error>
+    where
+        <D2 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D2 as ArrayDim>::Buf<T1>>;
 }
 
 impl Repr for Literal {
@@ -224,6 +290,96 @@ impl Repr for Literal {
     {
         todo!()
     }
+
+    fn reduce<T1: Field + PartialOrd, D1: Dim, const AXIS: usize>(
+        _arg: &Self::Inner<T1, D1>,
+        _op: ReduceOp,
+    ) -> Self::Inner<T1, ReducedDim<D1, AXIS>>
+    where
+        ShapeConstraint: DimReduce<D1, AXIS>,
+        <ReducedDim<D1, AXIS> as ArrayDim>::Buf<MaybeUninit<T1>>:
+            ArrayBufUnit<T1, Init = <ReducedDim<D1, AXIS> as ArrayDim>::Buf<T1>>,
+    {
+        todo!()
+    }
+
+    fn cholesky<T1: Field + RealField, const N: usize>(
+        _arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> Self::Inner<T1, SquareDim<N>> {
+        todo!()
+    }
+
+    fn inverse<T1: Field + RealField, const N: usize>(
+        _arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> Self::Inner<T1, SquareDim<N>> {
+        todo!()
+    }
+
+    fn determinant<T1: Field + RealField, const N: usize>(
+        _arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> Self::Inner<T1, ScalarDim> {
+        todo!()
+    }
+
+    fn solve<T1: Field + RealField, D1: Dim, const N: usize>(
+        _a: &Self::Inner<T1, SquareDim<N>>,
+        _b: &Self::Inner<T1, D1>,
+    ) -> Self::Inner<T1, D1>
+    where
+        <D1 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D1 as ArrayDim>::Buf<T1>>,
+    {
+        todo!()
+    }
+
+    fn qr<T1: Field + RealField, const N: usize>(
+        _arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> (Self::Inner<T1, SquareDim<N>>, Self::Inner<T1, SquareDim<N>>) {
+        todo!()
+    }
+
+    fn eigh<T1: Field + RealField, const N: usize>(
+        _arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> (Self::Inner<T1, Const<N>>, Self::Inner<T1, SquareDim<N>>) {
+        todo!()
+    }
+
+    fn svd<T1: Field + RealField, const N: usize>(
+        _arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> (
+        Self::Inner<T1, SquareDim<N>>,
+        Self::Inner<T1, Const<N>>,
+        Self::Inner<T1, SquareDim<N>>,
+    ) {
+        todo!()
+    }
+
+    fn reshape<T1: Field, D1: Dim, D2: Dim>(
+        _arg: &Self::Inner<T1, D1>,
+    ) -> Result<Self::Inner<T1, D2>, Error>
+    where
+        <D2 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D2 as ArrayDim>::Buf<T1>>,
+    {
+        todo!()
+    }
+
+    fn broadcast<T1: Field, D1: Dim, D2: Dim>(
+        _arg: &Self::Inner<T1, D1>,
+    ) -> Result<Self::Inner<T1, D2>, Error>
+    where
+        <D2 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D2 as ArrayDim>::Buf<T1>>,
+    {
+        todo!()
+    }
+
+    fn fixed_slice<T1: Field, D1: Dim, D2: Dim>(
+        _arg: &Self::Inner<T1, D1>,
+        _offsets: &[usize],
+    ) -> Result<Self::Inner<T1, D2>, Error>
+    where
+        <D2 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D2 as ArrayDim>::Buf<T1>>,
+    {
+        todo!()
+    }
 }
 
 impl Repr for Buffer {
@@ -337,6 +493,96 @@ impl Repr for Buffer {
     {
         todo!()
     }
+
+    fn reduce<T1: Field + PartialOrd, D1: Dim, const AXIS: usize>(
+        _arg: &Self::Inner<T1, D1>,
+        _op: ReduceOp,
+    ) -> Self::Inner<T1, ReducedDim<D1, AXIS>>
+    where
+        ShapeConstraint: DimReduce<D1, AXIS>,
+        <ReducedDim<D1, AXIS> as ArrayDim>::Buf<MaybeUninit<T1>>:
+            ArrayBufUnit<T1, Init = <ReducedDim<D1, AXIS> as ArrayDim>::Buf<T1>>,
+    {
+        todo!()
+    }
+
+    fn cholesky<T1: Field + RealField, const N: usize>(
+        _arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> Self::Inner<T1, SquareDim<N>> {
+        todo!()
+    }
+
+    fn inverse<T1: Field + RealField, const N: usize>(
+        _arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> Self::Inner<T1, SquareDim<N>> {
+        todo!()
+    }
+
+    fn determinant<T1: Field + RealField, const N: usize>(
+        _arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> Self::Inner<T1, ScalarDim> {
+        todo!()
+    }
+
+    fn solve<T1: Field + RealField, D1: Dim, const N: usize>(
+        _a: &Self::Inner<T1, SquareDim<N>>,
+        _b: &Self::Inner<T1, D1>,
+    ) -> Self::Inner<T1, D1>
+    where
+        <D1 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D1 as ArrayDim>::Buf<T1>>,
+    {
+        todo!()
+    }
+
+    fn qr<T1: Field + RealField, const N: usize>(
+        _arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> (Self::Inner<T1, SquareDim<N>>, Self::Inner<T1, SquareDim<N>>) {
+        todo!()
+    }
+
+    fn eigh<T1: Field + RealField, const N: usize>(
+        _arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> (Self::Inner<T1, Const<N>>, Self::Inner<T1, SquareDim<N>>) {
+        todo!()
+    }
+
+    fn svd<T1: Field + RealField, const N: usize>(
+        _arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> (
+        Self::Inner<T1, SquareDim<N>>,
+        Self::Inner<T1, Const<N>>,
+        Self::Inner<T1, SquareDim<N>>,
+    ) {
+        todo!()
+    }
+
+    fn reshape<T1: Field, D1: Dim, D2: Dim>(
+        _arg: &Self::Inner<T1, D1>,
+    ) -> Result<Self::Inner<T1, D2>, Error>
+    where
+        <D2 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D2 as ArrayDim>::Buf<T1>>,
+    {
+        todo!()
+    }
+
+    fn broadcast<T1: Field, D1: Dim, D2: Dim>(
+        _arg: &Self::Inner<T1, D1>,
+    ) -> Result<Self::Inner<T1, D2>, Error>
+    where
+        <D2 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D2 as ArrayDim>::Buf<T1>>,
+    {
+        todo!()
+    }
+
+    fn fixed_slice<T1: Field, D1: Dim, D2: Dim>(
+        _arg: &Self::Inner<T1, D1>,
+        _offsets: &[usize],
+    ) -> Result<Self::Inner<T1, D2>, Error>
+    where
+        <D2 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D2 as ArrayDim>::Buf<T1>>,
+    {
+        todo!()
+    }
 }
 
 impl Repr for Op {
@@ -468,4 +714,109 @@ impl Repr for Op {
             .collect::<SmallVec<[i64; 4]>>();
         arg.clone().slice(offsets, new_offsets, strides)
     }
+
+    fn reduce<T1: Field + PartialOrd, D1: Dim, const AXIS: usize>(
+        arg: &Self::Inner<T1, D1>,
+        op: ReduceOp,
+    ) -> Self::Inner<T1, ReducedDim<D1, AXIS>>
+    where
+        ShapeConstraint: DimReduce<D1, AXIS>,
+        <ReducedDim<D1, AXIS> as ArrayDim>::Buf<MaybeUninit<T1>>:
+            ArrayBufUnit<T1, Init = <ReducedDim<D1, AXIS> as ArrayDim>::Buf<T1>>,
+    {
+        arg.clone().reduce(op, smallvec![AXIS as i64])
+    }
+
+    fn cholesky<T1: Field + RealField, const N: usize>(
+        arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> Self::Inner<T1, SquareDim<N>> {
+        arg.clone().cholesky()
+    }
+
+    fn inverse<T1: Field + RealField, const N: usize>(
+        arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> Self::Inner<T1, SquareDim<N>> {
+        arg.inverse_in_shape(T1::ELEM, N as i64)
+    }
+
+    fn determinant<T1: Field + RealField, const N: usize>(
+        arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> Self::Inner<T1, ScalarDim> {
+        arg.determinant_in_shape(T1::ELEM, N as i64)
+    }
+
+    fn solve<T1: Field + RealField, D1: Dim, const N: usize>(
+        a: &Self::Inner<T1, SquareDim<N>>,
+        b: &Self::Inner<T1, D1>,
+    ) -> Self::Inner<T1, D1>
+    where
+        <D1 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D1 as ArrayDim>::Buf<T1>>,
+    {
+        a.solve_in_shape(b, T1::ELEM, N as i64, D1::shape())
+    }
+
+    fn qr<T1: Field + RealField, const N: usize>(
+        arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> (Self::Inner<T1, SquareDim<N>>, Self::Inner<T1, SquareDim<N>>) {
+        let qr = arg.clone().qr();
+        (qr.get_tuple_element(0), qr.get_tuple_element(1))
+    }
+
+    fn eigh<T1: Field + RealField, const N: usize>(
+        arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> (Self::Inner<T1, Const<N>>, Self::Inner<T1, SquareDim<N>>) {
+        let eigh = arg.clone().eigh();
+        (eigh.get_tuple_element(1), eigh.get_tuple_element(0))
+    }
+
+    fn svd<T1: Field + RealField, const N: usize>(
+        arg: &Self::Inner<T1, SquareDim<N>>,
+    ) -> (
+        Self::Inner<T1, SquareDim<N>>,
+        Self::Inner<T1, Const<N>>,
+        Self::Inner<T1, SquareDim<N>>,
+    ) {
+        let svd = arg.clone().svd();
+        (
+            svd.get_tuple_element(0),
+            svd.get_tuple_element(1),
+            svd.get_tuple_element(2),
+        )
+    }
+
+    fn reshape<T1: Field, D1: Dim, D2: Dim>(
+        arg: &Self::Inner<T1, D1>,
+    ) -> Result<Self::Inner<T1, D2>, Error>
+    where
+        <D2 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D2 as ArrayDim>::Buf<T1>>,
+    {
+        check_reshape(&const_dims::<D1>(), &const_dims::<D2>())?;
+        Ok(arg.clone().reshape(D2::shape()))
+    }
+
+    fn broadcast<T1: Field, D1: Dim, D2: Dim>(
+        arg: &Self::Inner<T1, D1>,
+    ) -> Result<Self::Inner<T1, D2>, Error>
+    where
+        <D2 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D2 as ArrayDim>::Buf<T1>>,
+    {
+        let shape = D2::shape();
+        let leading = check_broadcast(&const_dims::<D1>(), &const_dims::<D2>())?;
+        let dims = (leading as i64..shape.len() as i64).collect();
+        Ok(arg.clone().broadcast_in_dim(shape, dims))
+    }
+
+    fn fixed_slice<T1: Field, D1: Dim, D2: Dim>(
+        arg: &Self::Inner<T1, D1>,
+        offsets: &[usize],
+    ) -> Result<Self::Inner<T1, D2>, Error>
+    where
+        <D2 as ArrayDim>::Buf<MaybeUninit<T1>>: ArrayBufUnit<T1, Init = <D2 as ArrayDim>::Buf<T1>>,
+    {
+        check_fixed_slice(&const_dims::<D1>(), &const_dims::<D2>(), offsets)?;
+        let starts: SmallVec<[i64; 4]> = offsets.iter().map(|o| *o as i64).collect();
+        let stops = starts.iter().zip(D2::shape()).map(|(a, b)| a + b).collect();
+        let strides = smallvec![1; starts.len()];
+        Ok(arg.clone().slice(starts, stops, strides))
+    }
 }
@@ -0,0 +1,278 @@
+//! Provides tensors tagged with a unit of measure, checked at compile time.
+use crate::{Builder, FromBuilder, FromOp, IntoOp, Noxpr};
+use std::marker::PhantomData;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use typenum::{Diff, Integer, Sum, N1, N2, P1, P2, Z0};
+
+/// A unit of measure, tracked in the type of a [`Quantity`].
+pub trait Unit {
+    /// Returns the unit's symbol in SI base units, like `kg m s^-2` for a newton.
+    fn symbol() -> String;
+}
+
+/// A unit made of SI base units, raised to the exponents `L` (meters), `M` (kilograms),
+/// `T` (seconds) and `A` (radians).
+///
+/// Angles are tracked as their own dimension, so that an angular velocity can't be added to a
+/// frequency.
+pub struct Dimension<L, M, T, A>(PhantomData<(L, M, T, A)>);
+
+impl<L: Integer, M: Integer, T: Integer, A: Integer> Unit for Dimension<L, M, T, A> {
+    fn symbol() -> String {
+        [
+            ("kg", M::to_i32()),
+            ("m", L::to_i32()),
+            ("s", T::to_i32()),
+            ("rad", A::to_i32()),
+        ]
+        .into_iter()
+        .filter(|(_, exp)| *exp != 0)
+        .map(|(symbol, exp)| match exp {
+            1 => symbol.to_string(),
+            exp => format!("{}^{}", symbol, exp),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+    }
+}
+
+/// The unit of the product of quantities in units `Self` and `Rhs`.
+pub trait UnitMul<Rhs> {
+    type Output: Unit;
+}
+
+/// The unit of the quotient of quantities in units `Self` and `Rhs`.
+pub trait UnitDiv<Rhs> {
+    type Output: Unit;
+}
+
+impl<L1, M1, T1, A1, L2, M2, T2, A2> UnitMul<Dimension<L2, M2, T2, A2>>
+    for Dimension<L1, M1, T1, A1>
+where
+    L1: Add<L2>,
+    M1: Add<M2>,
+    T1: Add<T2>,
+    A1: Add<A2>,
+    Sum<L1, L2>: Integer,
+    Sum<M1, M2>: Integer,
+    Sum<T1, T2>: Integer,
+    Sum<A1, A2>: Integer,
+{
+    type Output = Dimension<Sum<L1, L2>, Sum<M1, M2>, Sum<T1, T2>, Sum<A1, A2>>;
+}
+
+impl<L1, M1, T1, A1, L2, M2, T2, A2> UnitDiv<Dimension<L2, M2, T2, A2>>
+    for Dimension<L1, M1, T1, A1>
+where
+    L1: Sub<L2>,
+    M1: Sub<M2>,
+    T1: Sub<T2>,
+    A1: Sub<A2>,
+    Diff<L1, L2>: Integer,
+    Diff<M1, M2>: Integer,
+    Diff<T1, T2>: Integer,
+    Diff<A1, A2>: Integer,
+{
+    type Output = Dimension<Diff<L1, L2>, Diff<M1, M2>, Diff<T1, T2>, Diff<A1, A2>>;
+}
+
+pub type Dimensionless = Dimension<Z0, Z0, Z0, Z0>;
+pub type Meters = Dimension<P1, Z0, Z0, Z0>;
+pub type Kilograms = Dimension<Z0, P1, Z0, Z0>;
+pub type Seconds = Dimension<Z0, Z0, P1, Z0>;
+pub type Radians = Dimension<Z0, Z0, Z0, P1>;
+pub type MetersPerSecond = Dimension<P1, Z0, N1, Z0>;
+pub type MetersPerSecondSquared = Dimension<P1, Z0, N2, Z0>;
+pub type RadiansPerSecond = Dimension<Z0, Z0, N1, P1>;
+pub type RadiansPerSecondSquared = Dimension<Z0, Z0, N2, P1>;
+pub type Newtons = Dimension<P1, P1, N2, Z0>;
+pub type NewtonMeters = Dimension<P2, P1, N2, Z0>;
+pub type KilogramMetersSquared = Dimension<P2, P1, Z0, Z0>;
+
+/// A value, usually a [`Tensor`](crate::Tensor), measured in the unit `U`.
+///
+/// Quantities can only be added to or subtracted from quantities in the same unit, and
+/// multiplying or dividing them produces a quantity in the combined unit:
+///
+/// ```compile_fail
+/// use nox::{Meters, MetersPerSecond, Quantity, Vector};
+///
+/// let pos = Quantity::<_, Meters>::new(Vector::<f64, 3>::zeros());
+/// let vel = Quantity::<_, MetersPerSecond>::new(Vector::<f64, 3>::zeros());
+/// let _ = pos + vel;
+/// ```
+#[repr(transparent)]
+pub struct Quantity<T, U: Unit> {
+    value: T,
+    phantom: PhantomData<U>,
+}
+
+impl<T, U: Unit> Quantity<T, U> {
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Discards the unit, returning the underlying value.
+    pub fn into_value(self) -> T {
+        self.value
+    }
+
+    /// Applies `f` to the underlying value, keeping the unit.
+    pub fn map<O>(self, f: impl FnOnce(T) -> O) -> Quantity<O, U> {
+        Quantity::new(f(self.value))
+    }
+
+    /// Returns the unit's symbol, like `m s^-1`.
+    pub fn unit() -> String {
+        U::symbol()
+    }
+}
+
+impl<T: Clone, U: Unit> Clone for Quantity<T, U> {
+    fn clone(&self) -> Self {
+        Self::new(self.value.clone())
+    }
+}
+
+impl<T: std::fmt::Debug, U: Unit> std::fmt::Debug for Quantity<T, U> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Quantity")
+            .field("value", &self.value)
+            .field("unit", &U::symbol())
+            .finish()
+    }
+}
+
+impl<T: FromOp, U: Unit> FromOp for Quantity<T, U> {
+    fn from_op(noxpr: Noxpr) -> Self {
+        Self::new(T::from_op(noxpr))
+    }
+}
+
+impl<T: IntoOp, U: Unit> IntoOp for Quantity<T, U> {
+    fn into_op(self) -> Noxpr {
+        self.value.into_op()
+    }
+}
+
+impl<T: FromBuilder, U: Unit> FromBuilder for Quantity<T, U> {
+    type Item<'a> = Quantity<T::Item<'a>, U>;
+
+    fn from_builder(builder: &Builder) -> Self::Item<'_> {
+        Quantity::new(T::from_builder(builder))
+    }
+
+    fn is_mut_borrowed() -> bool {
+        T::is_mut_borrowed()
+    }
+}
+
+impl<A: Add<B>, B, U: Unit> Add<Quantity<B, U>> for Quantity<A, U> {
+    type Output = Quantity<A::Output, U>;
+
+    fn add(self, rhs: Quantity<B, U>) -> Self::Output {
+        Quantity::new(self.value + rhs.value)
+    }
+}
+
+impl<A: Sub<B>, B, U: Unit> Sub<Quantity<B, U>> for Quantity<A, U> {
+    type Output = Quantity<A::Output, U>;
+
+    fn sub(self, rhs: Quantity<B, U>) -> Self::Output {
+        Quantity::new(self.value - rhs.value)
+    }
+}
+
+impl<A: Mul<B>, B, U1: Unit + UnitMul<U2>, U2: Unit> Mul<Quantity<B, U2>> for Quantity<A, U1> {
+    type Output = Quantity<A::Output, U1::Output>;
+
+    fn mul(self, rhs: Quantity<B, U2>) -> Self::Output {
+        Quantity::new(self.value * rhs.value)
+    }
+}
+
+impl<A: Div<B>, B, U1: Unit + UnitDiv<U2>, U2: Unit> Div<Quantity<B, U2>> for Quantity<A, U1> {
+    type Output = Quantity<A::Output, U1::Output>;
+
+    fn div(self, rhs: Quantity<B, U2>) -> Self::Output {
+        Quantity::new(self.value / rhs.value)
+    }
+}
+
+impl<T: Neg, U: Unit> Neg for Quantity<T, U> {
+    type Output = Quantity<T::Output, U>;
+
+    fn neg(self) -> Self::Output {
+        Quantity::new(-self.value)
+    }
+}
+
+macro_rules! impl_scale {
+    ($($ty:ty),*) => {
+        $(
+            impl<T: Mul<$ty>, U: Unit> Mul<$ty> for Quantity<T, U> {
+                type Output = Quantity<T::Output, U>;
+
+                fn mul(self, rhs: $ty) -> Self::Output {
+                    Quantity::new(self.value * rhs)
+                }
+            }
+        )*
+    };
+}
+
+impl_scale!(f32, f64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Client, CompFn, ToHost, Vector};
+    use nalgebra::vector;
+
+    #[test]
+    fn test_symbol() {
+        assert_eq!(Meters::symbol(), "m");
+        assert_eq!(MetersPerSecond::symbol(), "m s^-1");
+        assert_eq!(Newtons::symbol(), "kg m s^-2");
+        assert_eq!(Dimensionless::symbol(), "");
+        assert_eq!(
+            <<Newtons as UnitMul<Meters>>::Output as Unit>::symbol(),
+            NewtonMeters::symbol()
+        );
+        assert_eq!(
+            <<Meters as UnitDiv<Seconds>>::Output as Unit>::symbol(),
+            MetersPerSecond::symbol()
+        );
+    }
+
+    #[test]
+    fn test_integrate_position() {
+        let client = Client::cpu().unwrap();
+        fn step(pos: Vector<f64, 3>, vel: Vector<f64, 3>, dt: Vector<f64, 3>) -> Vector<f64, 3> {
+            let pos = Quantity::<_, Meters>::new(pos);
+            let vel = Quantity::<_, MetersPerSecond>::new(vel);
+            let dt = Quantity::<_, Seconds>::new(dt);
+            let pos: Quantity<Vector<f64, 3>, Meters> = pos + vel * dt;
+            pos.into_value()
+        }
+        let comp = step.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec
+            .run(
+                &client,
+                vector![1.0, 2.0, 3.0],
+                vector![1.0, -1.0, 0.5],
+                vector![2.0, 2.0, 2.0],
+            )
+            .unwrap()
+            .to_host();
+        assert_eq!(out, vector![3.0, 0.0, 4.0]);
+    }
+}
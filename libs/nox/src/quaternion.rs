@@ -1,15 +1,20 @@
-use std::ops::{Add, Mul};
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::str::FromStr;
 
 use nalgebra::{Const, RealField, Scalar as NalgebraScalar};
 use num_traits::Zero;
 use xla::{ArrayElement, NativeType};
 
+use crate::grad::scalar;
 use crate::{
-    AsBuffer, Buffer, BufferArg, BufferForm, Builder, Client, Field, FixedSliceExt, FromBuilder,
-    FromHost, FromOp, FromPjrtBuffer, IntoOp, MaybeOwned, Noxpr, Op, Repr, Scalar, TensorItem,
-    ToHost, Vector,
+    AsBuffer, Buffer, BufferArg, BufferForm, Builder, Client, Error, Field, FixedSliceExt,
+    FromBuilder, FromHost, FromOp, FromPjrtBuffer, IntoOp, LocalBackend, Mask, Matrix, MaybeOwned,
+    Noxpr, Op, Repr, Scalar, ScalarDim, TensorItem, ToHost, Vector,
 };
 
+/// Below this angle, the small angle approximations are used instead of dividing by the angle.
+const SMALL_ANGLE: f64 = 1e-6;
+
 /// Quaternion is representation of spatial orientation or rotation in 3D space.
 pub struct Quaternion<T: TensorItem, P: Repr = Op>(pub Vector<T, 4, P>);
 
@@ -94,18 +99,568 @@ impl<T: Field> Quaternion<T> {
     }
 }
 
-impl<T: Field> Mul for Quaternion<T> {
+/// The scalar math that [`Quaternion`]'s conversions and interpolations are written in, so they
+/// can be traced into XLA as well as evaluated on the host.
+pub trait QuaternionScalar:
+    Sized
+    + Clone
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    type Elem: Field;
+    type Repr: Repr;
+    /// The result of a comparison, which [`QuaternionScalar::select`] picks scalars with.
+    type Mask;
+
+    fn from_f64(value: f64) -> Self;
+    fn sqrt(&self) -> Self;
+    fn sin(&self) -> Self;
+    fn cos(&self) -> Self;
+    fn abs(&self) -> Self;
+    fn atan2(&self, other: &Self) -> Self;
+    fn max(&self, other: &Self) -> Self;
+    fn less(&self, other: &Self) -> Self::Mask;
+    fn less_or_equal(&self, other: &Self) -> Self::Mask;
+    fn select(mask: &Self::Mask, on_true: &Self, on_false: &Self) -> Self;
+    fn from_rows(rows: [[Self; 3]; 3]) -> Matrix<Self::Elem, 3, 3, Self::Repr>;
+    fn rows(m: &Matrix<Self::Elem, 3, 3, Self::Repr>) -> [[Self; 3]; 3];
+}
+
+impl<T: Field> QuaternionScalar for Scalar<T> {
+    type Elem = T;
+    type Repr = Op;
+    type Mask = Mask<ScalarDim>;
+
+    fn from_f64(value: f64) -> Self {
+        Scalar::from_op(scalar(T::ELEM, value).expect("quaternions have a numeric element type"))
+    }
+
+    fn sqrt(&self) -> Self {
+        self.sqrt()
+    }
+
+    fn sin(&self) -> Self {
+        self.sin()
+    }
+
+    fn cos(&self) -> Self {
+        self.cos()
+    }
+
+    fn abs(&self) -> Self {
+        self.abs()
+    }
+
+    fn atan2(&self, other: &Self) -> Self {
+        self.atan2(other)
+    }
+
+    fn max(&self, other: &Self) -> Self {
+        self.max(other)
+    }
+
+    fn less(&self, other: &Self) -> Self::Mask {
+        self.less(other)
+    }
+
+    fn less_or_equal(&self, other: &Self) -> Self::Mask {
+        self.less_or_equal(other)
+    }
+
+    fn select(mask: &Self::Mask, on_true: &Self, on_false: &Self) -> Self {
+        mask.select(on_true, on_false)
+    }
+
+    fn from_rows(rows: [[Self; 3]; 3]) -> Matrix<T, 3, 3> {
+        let [[m00, m01, m02], [m10, m11, m12], [m20, m21, m22]] = &rows;
+        Vector::<T, 9>::from_arr([m00, m01, m02, m10, m11, m12, m20, m21, m22]).reshape()
+    }
+
+    fn rows(m: &Matrix<T, 3, 3>) -> [[Self; 3]; 3] {
+        let [m00, m01, m02, m10, m11, m12, m20, m21, m22] = m.clone().reshape::<Const<9>>().parts();
+        [[m00, m01, m02], [m10, m11, m12], [m20, m21, m22]]
+    }
+}
+
+impl<T: Field + RealField> QuaternionScalar for Scalar<T, LocalBackend> {
+    type Elem = T;
+    type Repr = LocalBackend;
+    type Mask = bool;
+
+    fn from_f64(value: f64) -> Self {
+        Scalar::from_buf(nalgebra::convert(value))
+    }
+
+    fn sqrt(&self) -> Self {
+        self.sqrt()
+    }
+
+    fn sin(&self) -> Self {
+        self.sin()
+    }
+
+    fn cos(&self) -> Self {
+        self.cos()
+    }
+
+    fn abs(&self) -> Self {
+        self.abs()
+    }
+
+    fn atan2(&self, other: &Self) -> Self {
+        self.atan2(other)
+    }
+
+    fn max(&self, other: &Self) -> Self {
+        self.max(other)
+    }
+
+    fn less(&self, other: &Self) -> bool {
+        self.buf() < other.buf()
+    }
+
+    fn less_or_equal(&self, other: &Self) -> bool {
+        self.buf() <= other.buf()
+    }
+
+    fn select(mask: &bool, on_true: &Self, on_false: &Self) -> Self {
+        if *mask {
+            on_true.clone()
+        } else {
+            on_false.clone()
+        }
+    }
+
+    fn from_rows(rows: [[Self; 3]; 3]) -> Matrix<T, 3, 3, LocalBackend> {
+        Matrix::from_buf(rows.map(|row| row.map(|x| x.into_buf())))
+    }
+
+    fn rows(m: &Matrix<T, 3, 3, LocalBackend>) -> [[Self; 3]; 3] {
+        m.buf().map(|row| row.map(Scalar::from_buf))
+    }
+}
+
+impl<T, R> Quaternion<T, R>
+where
+    T: Field + RealField,
+    R: Repr,
+    Scalar<T, R>: QuaternionScalar<Elem = T, Repr = R>,
+{
+    /// Create a quaternion from a rotation matrix, such that `m * v == q * v`.
+    ///
+    /// The transpose of `m` is the direction cosine matrix that takes world vectors into the
+    /// rotated frame.
+    pub fn from_rotation_matrix(m: impl Into<Matrix<T, 3, 3, R>>) -> Self {
+        from_coords(from_rotation_matrix(Scalar::<T, R>::rows(&m.into())))
+    }
+
+    /// Returns the rotation matrix of the quaternion, such that `m * v == q * v`.
+    ///
+    /// The quaternion doesn't need to be normalized.
+    pub fn to_rotation_matrix(&self) -> Matrix<T, 3, 3, R> {
+        Scalar::<T, R>::from_rows(to_rotation_matrix(self.0.parts()))
+    }
+
+    /// Create a quaternion from a set of Euler angles, given in the order of `seq`.
+    pub fn from_euler(seq: EulerSeq, angles: impl Into<Vector<T, 3, R>>) -> Self {
+        let [a, b, c] = angles.into().parts();
+        let [axis_a, axis_b, axis_c] = seq.axes;
+        let (q_a, q_b, q_c) = (
+            elemental(axis_a, a),
+            elemental(axis_b, b),
+            elemental(axis_c, c),
+        );
+        if seq.intrinsic {
+            from_coords(hamilton(hamilton(q_a, q_b), q_c))
+        } else {
+            from_coords(hamilton(hamilton(q_c, q_b), q_a))
+        }
+    }
+
+    /// Returns the Euler angles of the quaternion, in the order of `seq`.
+    ///
+    /// Every angle is in `[-π, π]`, except that the second angle of a Tait-Bryan sequence is in
+    /// `[-π/2, π/2]`, and the second angle of a proper Euler sequence is in `[0, π]`. At gimbal
+    /// lock only the sum or difference of the first and third angles is observable, so the angle
+    /// of the last extrinsic rotation is set to zero.
+    pub fn to_euler(&self, seq: EulerSeq) -> Vector<T, 3, R> {
+        let [a, b, c] = &to_euler(self.0.parts(), seq);
+        Vector::from_arr([a, b, c])
+    }
+
+    /// Maps a rotation vector, the rotation axis scaled by the rotation angle, to a unit
+    /// quaternion.
+    pub fn exp(rotation: impl Into<Vector<T, 3, R>>) -> Self {
+        from_coords(exp(rotation.into().parts()))
+    }
+
+    /// Maps a unit quaternion to the rotation vector of its shortest rotation, which has an angle
+    /// in `[0, π]`. This is the inverse of [`Quaternion::exp`].
+    pub fn log(&self) -> Vector<T, 3, R> {
+        let [x, y, z] = &log(self.0.parts());
+        Vector::from_arr([x, y, z])
+    }
+
+    /// Returns the angle of the shortest rotation that takes `self` to `other`.
+    pub fn angular_distance(&self, other: &Self) -> Scalar<T, R> {
+        // the conjugate only differs from the inverse by a positive scale, which doesn't change
+        // the angle
+        let [x, y, z, w] = hamilton(conjugate(self.0.parts()), other.0.parts());
+        constant::<Scalar<T, R>>(2.0) * norm(&[x, y, z]).atan2(&w.abs())
+    }
+
+    /// Interpolates between `self` at `t = 0` and `other` at `t = 1` along the shortest arc, at a
+    /// constant angular rate.
+    pub fn slerp(&self, other: &Self, t: impl Into<Scalar<T, R>>) -> Self {
+        from_coords(slerp(self.0.parts(), other.0.parts(), t.into()))
+    }
+
+    /// Interpolates linearly between `self` at `t = 0` and `other` at `t = 1` along the shortest
+    /// arc, and normalizes the result. It's cheaper than [`Quaternion::slerp`], but the angular
+    /// rate isn't constant.
+    pub fn nlerp(&self, other: &Self, t: impl Into<Scalar<T, R>>) -> Self {
+        let (a, b) = (self.0.parts(), other.0.parts());
+        let (b, _) = shortest_path(&a, b);
+        let t = t.into();
+        let s = constant::<Scalar<T, R>>(1.0) - t.clone();
+        from_coords(normalize(lerp(a, b, s, t)))
+    }
+}
+
+fn from_coords<T, R>(coords: [Scalar<T, R>; 4]) -> Quaternion<T, R>
+where
+    T: Field,
+    R: Repr,
+{
+    let [x, y, z, w] = &coords;
+    Quaternion(Vector::from_arr([x, y, z, w]))
+}
+
+fn constant<S: QuaternionScalar>(value: f64) -> S {
+    S::from_f64(value)
+}
+
+/// Returns `-1` where `x` is negative, and `1` elsewhere.
+fn sign<S: QuaternionScalar>(x: &S) -> S {
+    S::select(&x.less(&constant(0.0)), &constant(-1.0), &constant(1.0))
+}
+
+fn dot<S: QuaternionScalar, const N: usize>(a: &[S; N], b: &[S; N]) -> S {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| a.clone() * b.clone())
+        .reduce(|acc, x| acc + x)
+        .expect("quaternion math only takes dot products of non-empty arrays")
+}
+
+fn norm<S: QuaternionScalar, const N: usize>(v: &[S; N]) -> S {
+    dot(v, v).sqrt()
+}
+
+fn normalize<S: QuaternionScalar>(q: [S; 4]) -> [S; 4] {
+    let norm = norm(&q);
+    q.map(|x| x / norm.clone())
+}
+
+fn conjugate<S: QuaternionScalar>([x, y, z, w]: [S; 4]) -> [S; 4] {
+    [-x, -y, -z, w]
+}
+
+/// Returns `a * w_a + b * w_b`.
+fn lerp<S: QuaternionScalar>(a: [S; 4], b: [S; 4], w_a: S, w_b: S) -> [S; 4] {
+    std::array::from_fn(|i| a[i].clone() * w_a.clone() + b[i].clone() * w_b.clone())
+}
+
+/// The Hamilton product of two quaternions.
+fn hamilton<S: QuaternionScalar>(l: [S; 4], r: [S; 4]) -> [S; 4] {
+    let [l_i, l_j, l_k, l_w] = &l;
+    let [r_i, r_j, r_k, r_w] = &r;
+    let m = |a: &S, b: &S| a.clone() * b.clone();
+    let i = m(l_w, r_i) + m(l_i, r_w) + m(l_j, r_k) - m(l_k, r_j);
+    let j = m(l_w, r_j) - m(l_i, r_k) + m(l_j, r_w) + m(l_k, r_i);
+    let k = m(l_w, r_k) + m(l_i, r_j) - m(l_j, r_i) + m(l_k, r_w);
+    let w = m(l_w, r_w) - m(l_i, r_i) - m(l_j, r_j) - m(l_k, r_k);
+    [i, j, k, w]
+}
+
+/// Returns the quaternion that rotates by `angle` about a single coordinate axis.
+fn elemental<S: QuaternionScalar>(axis: Axis, angle: S) -> [S; 4] {
+    let half_angle = angle * constant(0.5);
+    let mut q = [
+        constant(0.0),
+        constant(0.0),
+        constant(0.0),
+        half_angle.cos(),
+    ];
+    q[axis as usize] = half_angle.sin();
+    q
+}
+
+fn from_rotation_matrix<S: QuaternionScalar>(m: [[S; 3]; 3]) -> [S; 4] {
+    let [[m00, m01, m02], [m10, m11, m12], [m20, m21, m22]] = m;
+    let root = |d: S| (constant::<S>(1.0) + d).max(&constant(1e-12)).sqrt() * constant(2.0);
+    // Shepperd's method: each candidate divides by the largest of the four components, so the
+    // one whose component is the largest is picked
+    let trace = m00.clone() + m11.clone() + m22.clone();
+    let s_w = root(trace.clone());
+    let s_x = root(m00.clone() - m11.clone() - m22.clone());
+    let s_y = root(m11.clone() - m00.clone() - m22.clone());
+    let s_z = root(m22.clone() - m00.clone() - m11.clone());
+    let (a, b, c) = (
+        m21.clone() - m12.clone(),
+        m02.clone() - m20.clone(),
+        m10.clone() - m01.clone(),
+    );
+    let (d, e, f) = (m01 + m10, m02 + m20, m12 + m21);
+    let div = |n: &S, s: &S| n.clone() / s.clone();
+    let quarter = |s: &S| s.clone() * constant(0.25);
+    let q_w = [div(&a, &s_w), div(&b, &s_w), div(&c, &s_w), quarter(&s_w)];
+    let q_x = [quarter(&s_x), div(&d, &s_x), div(&e, &s_x), div(&a, &s_x)];
+    let q_y = [div(&d, &s_y), quarter(&s_y), div(&f, &s_y), div(&b, &s_y)];
+    let q_z = [div(&e, &s_z), div(&f, &s_z), quarter(&s_z), div(&c, &s_z)];
+    let use_w = constant::<S>(0.0).less(&trace);
+    let use_x = m11.max(&m22).less_or_equal(&m00);
+    let use_y = m22.less_or_equal(&m11);
+    [0, 1, 2, 3].map(|i| {
+        let q_yz = S::select(&use_y, &q_y[i], &q_z[i]);
+        let q_xyz = S::select(&use_x, &q_x[i], &q_yz);
+        S::select(&use_w, &q_w[i], &q_xyz)
+    })
+}
+
+fn to_rotation_matrix<S: QuaternionScalar>(q: [S; 4]) -> [[S; 3]; 3] {
+    let s = constant::<S>(2.0) / dot(&q, &q);
+    let [x, y, z, w] = &q;
+    let term = |a: &S, b: &S| a.clone() * b.clone() * s.clone();
+    let (xx, yy, zz) = (term(x, x), term(y, y), term(z, z));
+    let (xy, xz, yz) = (term(x, y), term(x, z), term(y, z));
+    let (xw, yw, zw) = (term(x, w), term(y, w), term(z, w));
+    let one = || constant::<S>(1.0);
+    [
+        [
+            one() - (yy.clone() + zz.clone()),
+            xy.clone() - zw.clone(),
+            xz.clone() + yw.clone(),
+        ],
+        [xy + zw, one() - (xx.clone() + zz), yz.clone() - xw.clone()],
+        [xz - yw, yz + xw, one() - (xx + yy)],
+    ]
+}
+
+fn to_euler<S: QuaternionScalar>(q: [S; 4], seq: EulerSeq) -> [S; 3] {
+    // uses the method from "Quaternion to Euler angles conversion: A direct, general and
+    // computationally efficient method" by Bernardes and Viollet, which works on the
+    // equivalent extrinsic sequence
+    let [i, j, k] = seq.extrinsic_axes().map(|axis| axis as usize);
+    let symmetric = i == k;
+    let k = if symmetric { 3 - i - j } else { k };
+    let (i_, j_, k_) = (i as i64, j as i64, k as i64);
+    let sign = constant::<S>(((i_ - j_) * (j_ - k_) * (k_ - i_) / 2) as f64);
+
+    let (a, b, c, d) = if symmetric {
+        let d = q[k].clone() * sign.clone();
+        (q[3].clone(), q[i].clone(), q[j].clone(), d)
+    } else {
+        let k_sign = q[k].clone() * sign.clone();
+        (
+            q[3].clone() - q[j].clone(),
+            q[i].clone() + k_sign.clone(),
+            q[j].clone() + q[3].clone(),
+            k_sign - q[i].clone(),
+        )
+    };
+
+    let two = || constant::<S>(2.0);
+    let pi = || constant::<S>(std::f64::consts::PI);
+    let eps = constant::<S>(1e-7);
+    let hypot = |x: &S, y: &S| (x.clone() * x.clone() + y.clone() * y.clone()).sqrt();
+    let second = two() * hypot(&c, &d).atan2(&hypot(&a, &b));
+    let half_sum = b.atan2(&a);
+    let half_diff = d.atan2(&c);
+
+    let near_zero = second.abs().less_or_equal(&eps);
+    let near_pi = (second.clone() - pi()).abs().less_or_equal(&eps);
+    let locked = |at_zero: S, at_pi: S, unlocked: S| {
+        let at_pi = S::select(&near_pi, &at_pi, &unlocked);
+        S::select(&near_zero, &at_zero, &at_pi)
+    };
+    let first = half_sum.clone() - half_diff.clone();
+    let third = half_sum.clone() + half_diff.clone();
+    let extrinsic = !seq.intrinsic;
+    let (first, third) = if extrinsic {
+        let first = locked(two() * half_sum, -(two() * half_diff), first);
+        (first, locked(constant(0.0), constant(0.0), third))
+    } else {
+        let third = locked(two() * half_sum, two() * half_diff, third);
+        (locked(constant(0.0), constant(0.0), first), third)
+    };
+
+    let (second, third) = if symmetric {
+        (second, third)
+    } else {
+        (second - pi() / two(), third * sign)
+    };
+    let wrap = |angle: S| angle.sin().atan2(&angle.cos());
+    let [first, second, third] = [first, second, third].map(wrap);
+    if extrinsic {
+        [first, second, third]
+    } else {
+        [third, second, first]
+    }
+}
+
+fn exp<S: QuaternionScalar>(rotation: [S; 3]) -> [S; 4] {
+    let angle = norm(&rotation);
+    let half_angle = angle.clone() * constant(0.5);
+    // sin(θ / 2) / θ, which uses its Taylor series near zero
+    let small = angle.less(&constant(SMALL_ANGLE));
+    let series = constant::<S>(0.5) - angle.clone() * angle.clone() / constant(48.0);
+    let exact = half_angle.sin() / angle.max(&constant(SMALL_ANGLE));
+    let scale = S::select(&small, &series, &exact);
+    let [x, y, z] = rotation.map(|x| x * scale.clone());
+    [x, y, z, half_angle.cos()]
+}
+
+fn log<S: QuaternionScalar>([x, y, z, w]: [S; 4]) -> [S; 3] {
+    // `q` and `-q` are the same rotation, and the one with a positive real part is the shorter
+    let sign = sign(&w);
+    let v = [x, y, z].map(|x| x * sign.clone());
+    let w = w * sign;
+    let norm = norm(&v);
+    let angle = constant::<S>(2.0) * norm.atan2(&w);
+    let small = norm.less(&constant(SMALL_ANGLE));
+    let series = constant::<S>(2.0) / w;
+    let exact = angle / norm.max(&constant(SMALL_ANGLE));
+    let scale = S::select(&small, &series, &exact);
+    v.map(|x| x * scale.clone())
+}
+
+fn slerp<S: QuaternionScalar>(a: [S; 4], b: [S; 4], t: S) -> [S; 4] {
+    let (b, cos) = shortest_path(&a, b);
+    let one = || constant::<S>(1.0);
+    let sin = (one() - cos.clone() * cos.clone())
+        .max(&constant(0.0))
+        .sqrt();
+    let angle = sin.atan2(&cos);
+    // falls back to linear interpolation when the quaternions are too close to divide by sin
+    let small = sin.less(&constant(SMALL_ANGLE));
+    let inv_sin = one() / sin.max(&constant(SMALL_ANGLE));
+    let s = one() - t.clone();
+    let w_a = (s.clone() * angle.clone()).sin() * inv_sin.clone();
+    let w_b = (t.clone() * angle).sin() * inv_sin;
+    let w_a = S::select(&small, &s, &w_a);
+    let w_b = S::select(&small, &t, &w_b);
+    normalize(lerp(a, b, w_a, w_b))
+}
+
+/// Returns `b` or `-b`, whichever is closer to `a`, along with its dot product with `a`.
+fn shortest_path<S: QuaternionScalar>(a: &[S; 4], b: [S; 4]) -> ([S; 4], S) {
+    let cos = dot(a, &b);
+    let sign = sign(&cos);
+    (b.map(|x| x * sign.clone()), cos * sign)
+}
+
+/// One of the three coordinate axes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+/// A sequence of three rotations about coordinate axes, which gives the order of a set of Euler
+/// angles.
+///
+/// Intrinsic sequences rotate about the axes of the rotating frame, while extrinsic sequences
+/// rotate about the fixed world axes. An intrinsic sequence is the same as the reversed extrinsic
+/// sequence. Both Tait-Bryan sequences such as `ZYX` and proper Euler sequences such as `ZXZ` are
+/// supported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EulerSeq {
+    axes: [Axis; 3],
+    intrinsic: bool,
+}
+
+impl EulerSeq {
+    /// The aerospace yaw, pitch and roll sequence, which is intrinsic `ZYX`.
+    pub const YAW_PITCH_ROLL: EulerSeq = EulerSeq {
+        axes: [Axis::Z, Axis::Y, Axis::X],
+        intrinsic: true,
+    };
+
+    pub fn intrinsic(axes: [Axis; 3]) -> Result<Self, Error> {
+        Self::new(axes, true)
+    }
+
+    pub fn extrinsic(axes: [Axis; 3]) -> Result<Self, Error> {
+        Self::new(axes, false)
+    }
+
+    fn new(axes: [Axis; 3], intrinsic: bool) -> Result<Self, Error> {
+        if axes[0] == axes[1] || axes[1] == axes[2] {
+            return Err(Error::InvalidEulerSeq(format!("{:?}", axes)));
+        }
+        Ok(EulerSeq { axes, intrinsic })
+    }
+
+    pub fn axes(&self) -> [Axis; 3] {
+        self.axes
+    }
+
+    pub fn is_intrinsic(&self) -> bool {
+        self.intrinsic
+    }
+
+    fn extrinsic_axes(&self) -> [Axis; 3] {
+        let [a, b, c] = self.axes;
+        if self.intrinsic {
+            [c, b, a]
+        } else {
+            [a, b, c]
+        }
+    }
+}
+
+/// Parses a sequence such as `"ZYX"` the way SciPy does, where upper case axes are an intrinsic
+/// sequence and lower case axes are an extrinsic one.
+impl FromStr for EulerSeq {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || Error::InvalidEulerSeq(s.to_string());
+        let &[a, b, c] = s.as_bytes() else {
+            return Err(err());
+        };
+        let axis = |byte: u8| match byte.to_ascii_lowercase() {
+            b'x' => Ok(Axis::X),
+            b'y' => Ok(Axis::Y),
+            b'z' => Ok(Axis::Z),
+            _ => Err(err()),
+        };
+        let axes = [axis(a)?, axis(b)?, axis(c)?];
+        if s.bytes().all(|byte| byte.is_ascii_uppercase()) {
+            Self::intrinsic(axes)
+        } else if s.bytes().all(|byte| byte.is_ascii_lowercase()) {
+            Self::extrinsic(axes)
+        } else {
+            Err(err())
+        }
+    }
+}
+
+impl<T, R> Mul for Quaternion<T, R>
+where
+    T: Field,
+    R: Repr,
+    Scalar<T, R>: QuaternionScalar<Elem = T, Repr = R>,
+{
     type Output = Self;
 
     fn mul(self, rhs: Self) -> Self::Output {
-        let [l_i, l_j, l_k, l_w] = &self.parts();
-        let [r_i, r_j, r_k, r_w] = &rhs.parts();
-        let i = l_w * r_i + l_i * r_w + l_j * r_k - l_k * r_j;
-        let j = l_w * r_j - l_i * r_k + l_j * r_w + l_k * r_i;
-        let k = l_w * r_k + l_i * r_j - l_j * r_i + l_k * r_w;
-        let w = l_w * r_w - l_i * r_i - l_j * r_j - l_k * r_k;
-
-        Quaternion(Vector::from_arr([&i, &j, &k, &w]))
+        from_coords(hamilton(self.0.parts(), rhs.0.parts()))
     }
 }
 
@@ -207,6 +762,85 @@ where
     }
 }
 
+impl<T: TensorItem> Clone for Quaternion<T, LocalBackend> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+/// Evaluates the quaternion math on the host, so it can be used without an XLA client.
+impl<T: Field + RealField> Quaternion<T, LocalBackend> {
+    pub fn new(
+        w: impl Into<Scalar<T, LocalBackend>>,
+        x: impl Into<Scalar<T, LocalBackend>>,
+        y: impl Into<Scalar<T, LocalBackend>>,
+        z: impl Into<Scalar<T, LocalBackend>>,
+    ) -> Self {
+        let [w, x, y, z]: [Scalar<T, LocalBackend>; 4] = [w.into(), x.into(), y.into(), z.into()];
+        Quaternion(Vector::from_buf([x, y, z, w].map(|s| s.into_buf())))
+    }
+
+    /// Create a unit quaternion with no rotation.
+    pub fn identity() -> Self {
+        let zero = T::zero_prim();
+        Self::new(T::one_prim(), zero, zero, zero)
+    }
+
+    /// Create a quaternion from an axis and an angle.
+    pub fn from_axis_angle(
+        axis: impl Into<Vector<T, 3, LocalBackend>>,
+        angle: impl Into<Scalar<T, LocalBackend>>,
+    ) -> Self {
+        let [x, y, z] = axis.into().normalize().into_buf();
+        let half_angle = angle.into().into_buf() * nalgebra::convert::<f64, T>(0.5);
+        let (sin, cos) = half_angle.sin_cos();
+        Self::new(cos, x * sin, y * sin, z * sin)
+    }
+
+    pub fn conjugate(&self) -> Self {
+        let [x, y, z, w] = *self.0.buf();
+        Self::new(w, -x, -y, -z)
+    }
+
+    /// Compute the inverse of the quaternion.
+    pub fn inverse(&self) -> Self {
+        Quaternion(self.conjugate().0 / self.0.norm_squared())
+    }
+
+    /// Normalize to a unit quaternion.
+    pub fn normalize(&self) -> Self {
+        Quaternion(self.0.normalize())
+    }
+}
+
+impl<T: Field + RealField> Mul<Vector<T, 3, LocalBackend>> for Quaternion<T, LocalBackend> {
+    type Output = Vector<T, 3, LocalBackend>;
+
+    fn mul(self, rhs: Vector<T, 3, LocalBackend>) -> Self::Output {
+        let [x, y, z] = rhs.into_buf();
+        let v = Quaternion(Vector::from_buf([x, y, z, T::zero_prim()]));
+        let inv = self.inverse();
+        let [x, y, z, _] = (self * v * inv).0.into_buf();
+        Vector::from_buf([x, y, z])
+    }
+}
+
+impl<T: Field + RealField> Add for Quaternion<T, LocalBackend> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Quaternion(self.0 + rhs.0)
+    }
+}
+
+impl<T: Field + RealField + NalgebraScalar> From<nalgebra::Quaternion<T>>
+    for Quaternion<T, LocalBackend>
+{
+    fn from(val: nalgebra::Quaternion<T>) -> Self {
+        Quaternion(val.coords.into())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::Client;
@@ -297,4 +931,160 @@ mod tests {
             .to_host();
         assert_eq!(nalgebra::Quaternion::new(0.0, 0.0, 0.0, 1.0), out);
     }
+
+    #[test]
+    fn test_quat_rotation_matrix() {
+        let client = Client::cpu().unwrap();
+        let q = UnitQuaternion::from_euler_angles(0.3, -1.2, 2.9);
+        let comp = (|q: Quaternion<f64>| -> Matrix<f64, 3, 3> { q.to_rotation_matrix() })
+            .build()
+            .unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec.run(&client, q.into_inner()).unwrap().to_host();
+        approx::assert_relative_eq!(out, q.to_rotation_matrix().into_inner(), epsilon = 1e-9);
+
+        let comp =
+            (|m: Matrix<f64, 3, 3>| -> Quaternion<f64> { Quaternion::from_rotation_matrix(m) })
+                .build()
+                .unwrap();
+        let exec = comp.compile(&client).unwrap();
+        // a half turn has a negative trace, so this takes a different branch than the first one
+        for q in [q, UnitQuaternion::from_axis_angle(&Vector3::y_axis(), 3.0)] {
+            let m = q.to_rotation_matrix().into_inner();
+            let out = exec.run(&client, m).unwrap().to_host();
+            let out = UnitQuaternion::from_quaternion(out);
+            approx::assert_relative_eq!(out.angle_to(&q), 0.0, epsilon = 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_quat_euler() {
+        let client = Client::cpu().unwrap();
+        let (roll, pitch, yaw) = (0.3, -1.2, 2.9);
+        let comp = (|| -> Quaternion<f64> {
+            Quaternion::from_euler(EulerSeq::YAW_PITCH_ROLL, vector![yaw, pitch, roll])
+        })
+        .build()
+        .unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec.run(&client).unwrap().to_host();
+        let q = UnitQuaternion::from_euler_angles(roll, pitch, yaw);
+        approx::assert_relative_eq!(out, q.into_inner(), epsilon = 1e-9);
+
+        for seq in ["ZYX", "xyz", "ZXZ", "yxy"] {
+            let seq: EulerSeq = seq.parse().unwrap();
+            let comp = (move |q: Quaternion<f64>| -> Vector<f64, 3> { q.to_euler(seq) })
+                .build()
+                .unwrap();
+            let exec = comp.compile(&client).unwrap();
+            let angles = exec.run(&client, q.into_inner()).unwrap().to_host();
+            let comp =
+                (move |a: Vector<f64, 3>| -> Quaternion<f64> { Quaternion::from_euler(seq, a) })
+                    .build()
+                    .unwrap();
+            let exec = comp.compile(&client).unwrap();
+            let out = exec.run(&client, angles).unwrap().to_host();
+            let out = UnitQuaternion::from_quaternion(out);
+            approx::assert_relative_eq!(out.angle_to(&q), 0.0, epsilon = 1e-9);
+        }
+        assert!("ZZY".parse::<EulerSeq>().is_err());
+        assert!("Zyx".parse::<EulerSeq>().is_err());
+    }
+
+    #[test]
+    fn test_quat_exp_log() {
+        let client = Client::cpu().unwrap();
+        let comp = (|v: Vector<f64, 3>| -> Vector<f64, 3> { Quaternion::<f64, Op>::exp(v).log() })
+            .build()
+            .unwrap();
+        let exec = comp.compile(&client).unwrap();
+        for v in [vector![0.4, -1.1, 2.0], vector![1e-9, 0.0, -2e-9]] {
+            let out = exec.run(&client, v).unwrap().to_host();
+            approx::assert_relative_eq!(out, v, epsilon = 1e-12);
+        }
+
+        let comp = (|v: Vector<f64, 3>| -> Quaternion<f64> { Quaternion::exp(v) })
+            .build()
+            .unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let v = vector![0.4, -1.1, 2.0];
+        let out = exec.run(&client, v).unwrap().to_host();
+        let q = UnitQuaternion::from_scaled_axis(v);
+        approx::assert_relative_eq!(out, q.into_inner(), epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_quat_slerp() {
+        let client = Client::cpu().unwrap();
+        let a = UnitQuaternion::from_euler_angles(0.3, -1.2, 2.9);
+        let b = UnitQuaternion::from_euler_angles(-0.5, 0.2, 1.0);
+        let comp = (|a: Quaternion<f64>, b: Quaternion<f64>| -> Quaternion<f64> {
+            a.slerp(&b, constant::<Scalar<f64>>(0.25))
+        })
+        .build()
+        .unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec
+            .run(&client, a.into_inner(), b.into_inner())
+            .unwrap()
+            .to_host();
+        approx::assert_relative_eq!(out, a.slerp(&b, 0.25).into_inner(), epsilon = 1e-9);
+
+        let comp =
+            (|a: Quaternion<f64>, b: Quaternion<f64>| -> Scalar<f64> { a.angular_distance(&b) })
+                .build()
+                .unwrap();
+        let exec = comp.compile(&client).unwrap();
+        // `-b` is the same rotation as `b`
+        let out = exec
+            .run(&client, a.into_inner(), -b.into_inner())
+            .unwrap()
+            .to_host();
+        approx::assert_relative_eq!(out, a.angle_to(&b), epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_local_quat() {
+        let a = UnitQuaternion::from_euler_angles(0.3, -1.2, 2.9);
+        let b = UnitQuaternion::from_euler_angles(-0.5, 0.2, 1.0);
+        let (qa, qb) = (
+            Quaternion::<f64, LocalBackend>::from(a.into_inner()),
+            Quaternion::<f64, LocalBackend>::from(b.into_inner()),
+        );
+        let v = Vector3::new(1.0, -2.0, 0.5);
+        let rotated = qa.clone() * Vector::<f64, 3, LocalBackend>::from(v);
+        approx::assert_relative_eq!(Vector3::from(rotated.into_buf()), a * v, epsilon = 1e-9);
+        let product = (qa.clone() * qb.clone()).0.into_buf();
+        approx::assert_relative_eq!(
+            nalgebra::Vector4::from(product),
+            (a * b).into_inner().coords,
+            epsilon = 1e-9
+        );
+        let slerp = qa.slerp(&qb, 0.25).0.into_buf();
+        approx::assert_relative_eq!(
+            nalgebra::Vector4::from(slerp),
+            a.slerp(&b, 0.25).into_inner().coords,
+            epsilon = 1e-9
+        );
+        let v = vector![0.4, -1.1, 2.0];
+        let log = Quaternion::<f64, LocalBackend>::exp(v).log().into_buf();
+        approx::assert_relative_eq!(Vector3::from(log), v, epsilon = 1e-12);
+        approx::assert_relative_eq!(
+            qa.angular_distance(&qb).into_buf(),
+            a.angle_to(&b),
+            epsilon = 1e-9
+        );
+
+        let unit = |q: Quaternion<f64, LocalBackend>| {
+            UnitQuaternion::from_quaternion(nalgebra::Vector4::from(q.0.into_buf()).into())
+        };
+        let m = Matrix::<f64, 3, 3, LocalBackend>::from(a.to_rotation_matrix().into_inner());
+        let out = unit(Quaternion::from_rotation_matrix(m));
+        approx::assert_relative_eq!(out.angle_to(&a), 0.0, epsilon = 1e-9);
+        for seq in ["ZYX", "xyz", "ZXZ", "yxy"] {
+            let seq: EulerSeq = seq.parse().unwrap();
+            let out = unit(Quaternion::from_euler(seq, qa.to_euler(seq)));
+            approx::assert_relative_eq!(out.angle_to(&a), 0.0, epsilon = 1e-9);
+        }
+    }
 }
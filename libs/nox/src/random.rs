@@ -0,0 +1,324 @@
+//! Provides a counter-based pseudo random number generator, built on the threefry-2x32 hash.
+use crate::grad::scalar;
+use crate::{
+    ArrayTy, Builder, Dim, Error, Field, FromBuilder, FromOp, IntoOp, Noxpr, NoxprScalarExt,
+    Scalar, Tensor, Vector,
+};
+use nalgebra::RealField;
+use smallvec::{smallvec, SmallVec};
+use std::marker::PhantomData;
+use xla::{ElementType, NativeType};
+
+/// The rotation constants of threefry-2x32, alternating between each block of four rounds.
+const ROTATIONS: [[u32; 4]; 2] = [[13, 15, 26, 6], [17, 29, 16, 24]];
+
+/// The parity constant used to derive the third word of the key schedule.
+const KEY_PARITY: u32 = 0x1BD11BDA;
+
+/// Hashes `count` with `key` using 20 rounds of the threefry-2x32 block cipher.
+///
+/// `key` must be a `u32` array of shape `[2]`, and `count` a `u32` array of any shape. Like
+/// `jax.random.threefry_2x32`, the first half of the flattened counts are paired with the second
+/// half, and an odd count is padded with a zero.
+pub fn threefry_2x32(key: &Noxpr, count: &Noxpr) -> Result<Noxpr, Error> {
+    if key.element_type() != Some(ElementType::U32)
+        || count.element_type() != Some(ElementType::U32)
+    {
+        return Err(Error::IncompatibleDType);
+    }
+    let shape = count.shape().ok_or(Error::UnknownShape)?;
+    let len = shape.iter().product::<i64>();
+    let half = (len + 1) / 2;
+    let mut flat = count.clone().reshape(smallvec![len]);
+    if len % 2 == 1 {
+        flat = Noxpr::concat_in_dim(vec![flat, 0u32.constant()], 0);
+    }
+    let x0 = flat
+        .clone()
+        .slice(smallvec![0], smallvec![half], smallvec![1]);
+    let x1 = flat.slice(smallvec![half], smallvec![2 * half], smallvec![1]);
+    let (x0, x1) = threefry_rounds(key, x0, x1);
+    Ok(Noxpr::concat_in_dim(vec![x0, x1], 0)
+        .slice(smallvec![0], smallvec![len], smallvec![1])
+        .reshape(shape))
+}
+
+fn threefry_rounds(key: &Noxpr, mut x0: Noxpr, mut x1: Noxpr) -> (Noxpr, Noxpr) {
+    let word = |i: i64| {
+        key.clone()
+            .slice(smallvec![i], smallvec![i + 1], smallvec![1])
+            .reshape(smallvec![])
+    };
+    let (k0, k1) = (word(0), word(1));
+    let k2 = k0.clone().xor(k1.clone()).xor(KEY_PARITY.constant());
+    let schedule = [k0, k1, k2];
+    x0 = x0 + schedule[0].clone();
+    x1 = x1 + schedule[1].clone();
+    for block in 0..5 {
+        for rotation in ROTATIONS[block % 2] {
+            x0 = x0 + x1.clone();
+            x1 = rotate_left(x1, rotation).xor(x0.clone());
+        }
+        x0 = x0 + schedule[(block + 1) % 3].clone();
+        x1 = x1 + schedule[(block + 2) % 3].clone() + (block as u32 + 1).constant();
+    }
+    (x0, x1)
+}
+
+fn rotate_left(x: Noxpr, rotation: u32) -> Noxpr {
+    x.clone()
+        .shift_left(rotation.constant())
+        .or(x.shift_right_logical((32 - rotation).constant()))
+}
+
+impl Noxpr {
+    /// Generates random `u32` words of the given shape from a threefry key.
+    pub fn random_bits(&self, shape: SmallVec<[i64; 4]>) -> Result<Noxpr, Error> {
+        let len = shape.iter().product::<i64>();
+        let count = Noxpr::iota(ArrayTy::new(ElementType::U32, smallvec![len]), 0);
+        Ok(threefry_2x32(self, &count)?.reshape(shape))
+    }
+
+    /// Derives `n` new keys from a threefry key, returned as a `[n, 2]` array.
+    pub fn random_split(&self, n: usize) -> Result<Noxpr, Error> {
+        self.random_bits(smallvec![n as i64, 2])
+    }
+
+    /// Derives a new threefry key by hashing `data`, a `u32` scalar, into the key.
+    pub fn random_fold_in(&self, data: Noxpr) -> Result<Noxpr, Error> {
+        threefry_2x32(self, &Noxpr::concat_in_dim(vec![0u32.constant(), data], 0))
+    }
+
+    /// Generates floats uniformly distributed over `[0, 1)` from a threefry key.
+    ///
+    /// Every float has the full precision of its mantissa, so `f64` draws use two words each.
+    pub fn random_uniform(&self, ty: ArrayTy) -> Result<Noxpr, Error> {
+        let len = ty.shape.iter().product::<i64>();
+        let uniform = match ty.element_type {
+            ElementType::F32 => {
+                let bits = self.random_bits(ty.shape)?;
+                bits.shift_right_logical(8u32.constant())
+                    .convert(ElementType::F32)
+                    * 2f32.powi(-24).constant()
+            }
+            ElementType::F64 => {
+                let bits = self.random_bits(smallvec![2 * len])?;
+                let word = |i: i64, shift: u32| {
+                    bits.clone()
+                        .slice(smallvec![i * len], smallvec![(i + 1) * len], smallvec![1])
+                        .shift_right_logical(shift.constant())
+                        .convert(ElementType::F64)
+                };
+                let mantissa = word(0, 5) * 2f64.powi(26).constant() + word(1, 6);
+                (mantissa * 2f64.powi(-53).constant()).reshape(ty.shape)
+            }
+            ElementType::F16 | ElementType::Bf16 => {
+                // keeps only as many bits as the mantissa holds, so no draw can round up to 1
+                let precision: u32 = if ty.element_type == ElementType::F16 {
+                    11
+                } else {
+                    8
+                };
+                let bits = self.random_bits(ty.shape)?;
+                (bits
+                    .shift_right_logical((32 - precision).constant())
+                    .convert(ElementType::F32)
+                    * 2f32.powi(-(precision as i32)).constant())
+                .convert(ty.element_type)
+            }
+            _ => return Err(Error::IncompatibleDType),
+        };
+        Ok(uniform)
+    }
+
+    /// Generates floats from the standard normal distribution, using the Box-Muller transform.
+    pub fn random_normal(&self, ty: ArrayTy) -> Result<Noxpr, Error> {
+        let len = ty.shape.iter().product::<i64>();
+        let uniform = self.random_uniform(ArrayTy::new(ty.element_type, smallvec![2 * len]))?;
+        let half = |i: i64| {
+            uniform
+                .clone()
+                .slice(smallvec![i * len], smallvec![(i + 1) * len], smallvec![1])
+        };
+        // `1 - u` lies in `(0, 1]`, which keeps the log finite
+        let radius = (scalar(ty.element_type, -2.0)?
+            * (scalar(ty.element_type, 1.0)? - half(0)).log())
+        .sqrt();
+        let angle = scalar(ty.element_type, std::f64::consts::TAU)? * half(1);
+        Ok((radius * angle.cos()).reshape(ty.shape))
+    }
+}
+
+/// A key for the counter-based threefry PRNG.
+///
+/// Drawing from a key is a pure function of the key, so the same key always produces the same
+/// numbers. Use [`RngKey::split`] or [`RngKey::fold_in`] to derive independent keys instead of
+/// drawing from one key twice.
+pub struct RngKey(pub Vector<u32, 2>);
+
+impl RngKey {
+    /// Creates a key from the high and low words of `seed`.
+    ///
+    /// The key is a constant, so it can be pushed into a host column.
+    pub fn new(seed: u64) -> Self {
+        let words = u32::create_r1(&[(seed >> 32) as u32, seed as u32]);
+        RngKey::from_op(Noxpr::constant(
+            words,
+            ArrayTy::new(ElementType::U32, smallvec![2]),
+        ))
+    }
+
+    /// Derives `N` new keys from this key.
+    pub fn split<const N: usize>(&self) -> [RngKey; N] {
+        let keys = self
+            .0
+            .inner
+            .random_split(N)
+            .expect("a key is always a u32 vector");
+        std::array::from_fn(|i| {
+            let i = i as i64;
+            RngKey::from_op(
+                keys.clone()
+                    .slice(smallvec![i, 0], smallvec![i + 1, 2], smallvec![1, 1])
+                    .reshape(smallvec![2]),
+            )
+        })
+    }
+
+    /// Derives a new key by hashing `data` into this key.
+    pub fn fold_in(&self, data: &Scalar<u32>) -> RngKey {
+        RngKey::from_op(
+            self.0
+                .inner
+                .random_fold_in(data.inner.clone())
+                .expect("a key is always a u32 vector"),
+        )
+    }
+
+    /// Generates a tensor of random `u32` words.
+    pub fn bits<D: Dim>(&self) -> Tensor<u32, D> {
+        Tensor {
+            inner: self
+                .0
+                .inner
+                .random_bits(D::shape())
+                .expect("a key is always a u32 vector"),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Generates a tensor of floats uniformly distributed over `[0, 1)`.
+    pub fn uniform<T: Field + RealField, D: Dim>(&self) -> Tensor<T, D> {
+        Tensor {
+            inner: self
+                .0
+                .inner
+                .random_uniform(ArrayTy::new(T::ELEM, D::shape()))
+                .expect("real fields are always floats"),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Generates a tensor of floats from the standard normal distribution.
+    pub fn normal<T: Field + RealField, D: Dim>(&self) -> Tensor<T, D> {
+        Tensor {
+            inner: self
+                .0
+                .inner
+                .random_normal(ArrayTy::new(T::ELEM, D::shape()))
+                .expect("real fields are always floats"),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl Clone for RngKey {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl std::fmt::Debug for RngKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("RngKey").finish()
+    }
+}
+
+impl FromOp for RngKey {
+    fn from_op(op: Noxpr) -> Self {
+        Self(Vector::from_op(op))
+    }
+}
+
+impl IntoOp for RngKey {
+    fn into_op(self) -> Noxpr {
+        self.0.into_op()
+    }
+}
+
+impl FromBuilder for RngKey {
+    type Item<'a> = Self;
+
+    fn from_builder(builder: &Builder) -> Self::Item<'_> {
+        RngKey(Vector::from_builder(builder))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Client, CompFn, ToHost};
+    use nalgebra::vector;
+
+    #[test]
+    fn test_threefry_known_answers() {
+        let client = Client::cpu().unwrap();
+        fn hash(key: Vector<u32, 2>, count: Vector<u32, 2>) -> Vector<u32, 2> {
+            Vector::from_op(threefry_2x32(&key.inner, &count.inner).unwrap())
+        }
+        let comp = hash.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let cases = [
+            ([0, 0], [0, 0], [0x6b200159, 0x99ba4efe]),
+            (
+                [u32::MAX, u32::MAX],
+                [u32::MAX, u32::MAX],
+                [0x1cb996fc, 0xbb002be7],
+            ),
+            (
+                [0x13198a2e, 0x03707344],
+                [0x243f6a88, 0x85a308d3],
+                [0xc4923a9c, 0x483df7a0],
+            ),
+        ];
+        for (key, count, expected) in cases {
+            let out = exec
+                .run(
+                    &client,
+                    vector![key[0], key[1]],
+                    vector![count[0], count[1]],
+                )
+                .unwrap()
+                .to_host();
+            assert_eq!(out, vector![expected[0], expected[1]]);
+        }
+    }
+
+    #[test]
+    fn test_uniform_normal() {
+        let client = Client::cpu().unwrap();
+        fn draw(key: Vector<u32, 2>) -> Vector<f64, 4> {
+            let [a, b] = RngKey(key).split::<2>();
+            a.uniform::<f64, nalgebra::Const<2>>()
+                .concat(b.normal::<f64, nalgebra::Const<2>>())
+        }
+        let comp = draw.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec.run(&client, vector![0u32, 42]).unwrap().to_host();
+        assert!(out.iter().all(|x| x.is_finite()));
+        assert!(out.iter().take(2).all(|x| (0.0..1.0).contains(x)));
+        assert_ne!(out[0], out[1]);
+        let again = exec.run(&client, vector![0u32, 42]).unwrap().to_host();
+        assert_eq!(out, again);
+    }
+}
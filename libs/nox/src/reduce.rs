@@ -0,0 +1,226 @@
+//! Provides axis reductions over tensors, such as sums and maximums.
+use crate::local_backend::{ArrayBufUnit, ArrayDim};
+use crate::{Dim, Field, NoxprScalarExt, Op, ReduceOp, Repr, Tensor, XlaDim};
+use core::mem::MaybeUninit;
+use nalgebra::{constraint::ShapeConstraint, Const};
+use std::marker::PhantomData;
+
+/// Trait for removing the dimension at `AXIS`, used to compute the output dimension of a reduction.
+pub trait DimReduce<D: Dim, const AXIS: usize> {
+    type Output: Dim;
+}
+
+pub type ReducedDim<D, const AXIS: usize> = <ShapeConstraint as DimReduce<D, AXIS>>::Output;
+
+impl<const N: usize> DimReduce<Const<N>, 0> for ShapeConstraint {
+    type Output = ();
+}
+
+impl<D1: Dim, D2: Dim> DimReduce<(D1, D2), 0> for ShapeConstraint
+where
+    (D1, D2): Dim,
+{
+    type Output = D2;
+}
+
+impl<D1: Dim, D2: Dim> DimReduce<(D1, D2), 1> for ShapeConstraint
+where
+    (D1, D2): Dim,
+{
+    type Output = D1;
+}
+
+impl<D1: Dim, D2: Dim, D3: Dim> DimReduce<(D1, D2, D3), 0> for ShapeConstraint
+where
+    (D1, D2, D3): Dim,
+    (D2, D3): Dim,
+{
+    type Output = (D2, D3);
+}
+
+impl<D1: Dim, D2: Dim, D3: Dim> DimReduce<(D1, D2, D3), 1> for ShapeConstraint
+where
+    (D1, D2, D3): Dim,
+    (D1, D3): Dim,
+{
+    type Output = (D1, D3);
+}
+
+impl<D1: Dim, D2: Dim, D3: Dim> DimReduce<(D1, D2, D3), 2> for ShapeConstraint
+where
+    (D1, D2, D3): Dim,
+    (D1, D2): Dim,
+{
+    type Output = (D1, D2);
+}
+
+impl<T: Field + PartialOrd, D: Dim, R: Repr> Tensor<T, D, R> {
+    /// Sums the elements along `AXIS`, removing that dimension.
+    pub fn sum<const AXIS: usize>(&self) -> Tensor<T, ReducedDim<D, AXIS>, R>
+    where
+        ShapeConstraint: DimReduce<D, AXIS>,
+        <ReducedDim<D, AXIS> as ArrayDim>::Buf<MaybeUninit<T>>:
+            ArrayBufUnit<T, Init = <ReducedDim<D, AXIS> as ArrayDim>::Buf<T>>,
+    {
+        self.reduce::<AXIS>(ReduceOp::Sum)
+    }
+
+    /// Multiplies the elements along `AXIS`, removing that dimension.
+    pub fn product<const AXIS: usize>(&self) -> Tensor<T, ReducedDim<D, AXIS>, R>
+    where
+        ShapeConstraint: DimReduce<D, AXIS>,
+        <ReducedDim<D, AXIS> as ArrayDim>::Buf<MaybeUninit<T>>:
+            ArrayBufUnit<T, Init = <ReducedDim<D, AXIS> as ArrayDim>::Buf<T>>,
+    {
+        self.reduce::<AXIS>(ReduceOp::Prod)
+    }
+
+    /// Takes the largest element along `AXIS`, removing that dimension.
+    pub fn reduce_max<const AXIS: usize>(&self) -> Tensor<T, ReducedDim<D, AXIS>, R>
+    where
+        ShapeConstraint: DimReduce<D, AXIS>,
+        <ReducedDim<D, AXIS> as ArrayDim>::Buf<MaybeUninit<T>>:
+            ArrayBufUnit<T, Init = <ReducedDim<D, AXIS> as ArrayDim>::Buf<T>>,
+    {
+        self.reduce::<AXIS>(ReduceOp::Max)
+    }
+
+    /// Takes the smallest element along `AXIS`, removing that dimension.
+    pub fn reduce_min<const AXIS: usize>(&self) -> Tensor<T, ReducedDim<D, AXIS>, R>
+    where
+        ShapeConstraint: DimReduce<D, AXIS>,
+        <ReducedDim<D, AXIS> as ArrayDim>::Buf<MaybeUninit<T>>:
+            ArrayBufUnit<T, Init = <ReducedDim<D, AXIS> as ArrayDim>::Buf<T>>,
+    {
+        self.reduce::<AXIS>(ReduceOp::Min)
+    }
+
+    fn reduce<const AXIS: usize>(&self, op: ReduceOp) -> Tensor<T, ReducedDim<D, AXIS>, R>
+    where
+        ShapeConstraint: DimReduce<D, AXIS>,
+        <ReducedDim<D, AXIS> as ArrayDim>::Buf<MaybeUninit<T>>:
+            ArrayBufUnit<T, Init = <ReducedDim<D, AXIS> as ArrayDim>::Buf<T>>,
+    {
+        Tensor {
+            inner: R::reduce::<T, D, AXIS>(&self.inner, op),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: Field + PartialOrd, D: Dim> Tensor<T, D, Op> {
+    /// Averages the elements along `AXIS`, removing that dimension. Averaging an empty axis
+    /// divides by zero, which gives NaN for floats.
+    pub fn mean<const AXIS: usize>(&self) -> Tensor<T, ReducedDim<D, AXIS>>
+    where
+        ShapeConstraint: DimReduce<D, AXIS>,
+        <ReducedDim<D, AXIS> as ArrayDim>::Buf<MaybeUninit<T>>:
+            ArrayBufUnit<T, Init = <ReducedDim<D, AXIS> as ArrayDim>::Buf<T>>,
+    {
+        let len = D::shape()[AXIS];
+        let count = (len as i64).constant().convert(T::ELEM);
+        Tensor {
+            inner: self.sum::<AXIS>().inner / count,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the index of the first largest element along `AXIS`, removing that dimension.
+    pub fn argmax<const AXIS: usize>(&self) -> Tensor<i64, ReducedDim<D, AXIS>>
+    where
+        ShapeConstraint: DimReduce<D, AXIS>,
+    {
+        Tensor {
+            inner: self.inner.clone().argmax_in_shape(AXIS as i64, D::shape()),
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Client, CompFn, Matrix, Scalar, ToHost, Vector};
+    use nalgebra::{matrix, vector};
+
+    #[test]
+    fn test_sum_mean() {
+        let client = Client::cpu().unwrap();
+        fn sum(m: Matrix<f32, 2, 3>) -> Vector<f32, 3> {
+            m.sum::<0>()
+        }
+        let comp = sum.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec
+            .run(&client, matrix![1.0f32, 2.0, 3.0; 4.0, 5.0, 6.0])
+            .unwrap()
+            .to_host();
+        assert_eq!(out, vector![5.0, 7.0, 9.0]);
+
+        fn mean(m: Matrix<f32, 2, 3>) -> Vector<f32, 2> {
+            m.mean::<1>()
+        }
+        let comp = mean.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec
+            .run(&client, matrix![1.0f32, 2.0, 3.0; 4.0, 5.0, 6.0])
+            .unwrap()
+            .to_host();
+        assert_eq!(out, vector![2.0, 5.0]);
+    }
+
+    #[test]
+    fn test_empty_axis() {
+        let client = Client::cpu().unwrap();
+        fn reduce_empty(_: Matrix<f32, 2, 3>) -> Matrix<f32, 2, 3> {
+            use crate::{NoxprScalarExt, Tensor};
+            use nalgebra::Const;
+            use smallvec::smallvec;
+            let empty: Tensor<f32, (Const<2>, Const<0>)> =
+                Tensor::from_op(0f32.constant().broadcast(smallvec![2, 0]));
+            let columns = [
+                empty.mean::<1>().inner,
+                empty.reduce_max::<1>().inner,
+                empty.reduce_min::<1>().inner,
+            ];
+            let columns = columns.map(|c| c.reshape(smallvec![2, 1])).to_vec();
+            Matrix::from_op(crate::Noxpr::concat_in_dim(columns, 1))
+        }
+        let comp = reduce_empty.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec
+            .run(&client, matrix![1.0f32, 1.0, 1.0; 1.0, 1.0, 1.0])
+            .unwrap()
+            .to_host();
+        for row in 0..2 {
+            assert!(out[(row, 0)].is_nan());
+            assert_eq!(out[(row, 1)], f32::NEG_INFINITY);
+            assert_eq!(out[(row, 2)], f32::INFINITY);
+        }
+    }
+
+    #[test]
+    fn test_reduce_max_argmax() {
+        let client = Client::cpu().unwrap();
+        fn max(v: Vector<f32, 4>) -> Scalar<f32> {
+            v.reduce_max::<0>()
+        }
+        let comp = max.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec
+            .run(&client, vector![1.0f32, 4.0, -2.0, 4.0])
+            .unwrap()
+            .to_host();
+        assert_eq!(out, 4.0);
+
+        fn argmax(m: Matrix<f32, 2, 3>) -> Vector<i64, 2> {
+            m.argmax::<1>()
+        }
+        let comp = argmax.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec
+            .run(&client, matrix![1.0f32, 3.0, 3.0; 4.0, 0.0, 2.0])
+            .unwrap()
+            .to_host();
+        assert_eq!(out, vector![1, 0]);
+    }
+}
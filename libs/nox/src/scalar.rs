@@ -89,4 +89,20 @@ mod tests {
         let out = exec.run(&client, 3.141592653589793).unwrap().to_host();
         assert_eq!(out, 0.5723649);
     }
+
+    #[test]
+    fn test_atan2_exp_clamp() {
+        let client = Client::cpu().unwrap();
+        let comp = (|y: Scalar<f64>, x: Scalar<f64>| {
+            let (min, max) = (ScalarExt::constant(1.0), ScalarExt::constant(2.0));
+            y.atan2(&x).exp().clamp(&min, &max)
+        })
+        .build()
+        .unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec.run(&client, 1.0, 2.0).unwrap().to_host();
+        approx::assert_relative_eq!(out, 0.5f64.atan().exp(), epsilon = 1e-9);
+        let out = exec.run(&client, 1.0, -1.0).unwrap().to_host();
+        assert_eq!(out, 2.0);
+    }
 }
@@ -0,0 +1,871 @@
+//! Provides a stable, versioned on-disk format for [`Noxpr`] graphs.
+//!
+//! A graph is flattened into a table of nodes in topological order, where every node refers to
+//! its inputs by their index in the table. Nodes that are shared in the graph are stored once, so
+//! a decoded graph has the same shape as the graph that was encoded. Nested functions, like the
+//! body of a scan, are stored as tables of their own.
+//!
+//! The table is wrapped in a versioned enum, so an old archive can still be decoded after the
+//! format changes. New node kinds must only ever be appended to `NodeDef`, and a breaking change
+//! to an existing node requires a new `NoxprArchive` version.
+use crate::{
+    ArrayTy, BinaryOp, Broadcast, BroadcastInDim, Clamp, Concat, Cond, Convert, DotDimensionNums,
+    DotGeneral, DynamicSlice, DynamicUpdateSlice, Error, Gather, GetTupleElement, Iota, Noxpr,
+    NoxprFn, NoxprId, NoxprNode, NoxprTy, ParamExpr, Reduce, ReduceOp, Reshape, Scan, Scatter,
+    Select, Slice, Transpose, TriangularSolve, While,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::ops::Deref;
+use xla::{ElementType, Literal};
+
+impl NoxprFn {
+    /// Encodes this function in the compact binary format.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        Ok(postcard::to_allocvec(&NoxprArchive::new(self)?)?)
+    }
+
+    /// Decodes a function from the compact binary format.
+    pub fn from_bytes(buf: &[u8]) -> Result<Self, Error> {
+        postcard::from_bytes::<NoxprArchive>(buf)?.into_fn()
+    }
+
+    /// Encodes this function in the human readable json format, which is useful for diffing.
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(&NoxprArchive::new(self)?)?)
+    }
+
+    /// Decodes a function from the human readable json format.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str::<NoxprArchive>(json)?.into_fn()
+    }
+}
+
+impl Serialize for NoxprFn {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        NoxprArchive::new(self)
+            .map_err(serde::ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for NoxprFn {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        NoxprArchive::deserialize(deserializer)?
+            .into_fn()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for Noxpr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        NoxprFn::new(vec![], self.clone()).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Noxpr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(NoxprFn::deserialize(deserializer)?.inner)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum NoxprArchive {
+    V1(FnDef),
+}
+
+impl NoxprArchive {
+    fn new(func: &NoxprFn) -> Result<Self, Error> {
+        Ok(NoxprArchive::V1(FnDef::new(func)?))
+    }
+
+    fn into_fn(self) -> Result<NoxprFn, Error> {
+        match self {
+            NoxprArchive::V1(def) => def.into_fn(),
+        }
+    }
+}
+
+type NodeRef = usize;
+
+#[derive(Serialize, Deserialize)]
+struct FnDef {
+    nodes: Vec<NodeDef>,
+    args: Vec<NodeRef>,
+    inner: NodeRef,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "ElementType")]
+enum ElementTypeDef {
+    Pred,
+    S8,
+    S16,
+    S32,
+    S64,
+    U8,
+    U16,
+    U32,
+    U64,
+    F16,
+    F32,
+    Bf16,
+    F64,
+    C64,
+    C128,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "ReduceOp")]
+enum ReduceOpDef {
+    Sum,
+    Prod,
+    Max,
+    Min,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ArrayTyDef {
+    #[serde(with = "ElementTypeDef")]
+    element_type: ElementType,
+    shape: Vec<i64>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum TyDef {
+    Tuple(Vec<TyDef>),
+    Array(ArrayTyDef),
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryDef {
+    lhs: NodeRef,
+    rhs: NodeRef,
+}
+
+#[derive(Serialize, Deserialize)]
+enum NodeDef {
+    Param {
+        number: i64,
+        name: String,
+        ty: TyDef,
+    },
+    Tuple(Vec<NodeRef>),
+    GetTupleElement {
+        expr: NodeRef,
+        index: usize,
+    },
+    Constant {
+        ty: ArrayTyDef,
+        data: Vec<u8>,
+    },
+    Iota {
+        ty: ArrayTyDef,
+        dim: usize,
+    },
+    Add(BinaryDef),
+    Sub(BinaryDef),
+    Mul(BinaryDef),
+    Div(BinaryDef),
+    And(BinaryDef),
+    Or(BinaryDef),
+    Xor(BinaryDef),
+    ShiftLeft(BinaryDef),
+    ShiftRightLogical(BinaryDef),
+    GreaterOrEqual(BinaryDef),
+    LessOrEqual(BinaryDef),
+    Less(BinaryDef),
+    Greater(BinaryDef),
+    Equal(BinaryDef),
+    NotEqual(BinaryDef),
+    Atan2(BinaryDef),
+    Pow(BinaryDef),
+    Max(BinaryDef),
+    Min(BinaryDef),
+    Dot(BinaryDef),
+    DotGeneral {
+        lhs: NodeRef,
+        rhs: NodeRef,
+        lhs_contracting_dimensions: Vec<i64>,
+        rhs_contracting_dimensions: Vec<i64>,
+        lhs_batch_dimensions: Vec<i64>,
+        rhs_batch_dimensions: Vec<i64>,
+    },
+    Sqrt(NodeRef),
+    Neg(NodeRef),
+    Log(NodeRef),
+    Sin(NodeRef),
+    Cos(NodeRef),
+    Exp(NodeRef),
+    Tanh(NodeRef),
+    Abs(NodeRef),
+    Floor(NodeRef),
+    Ceil(NodeRef),
+    Rsqrt(NodeRef),
+    Convert {
+        expr: NodeRef,
+        #[serde(with = "ElementTypeDef")]
+        element_type: ElementType,
+    },
+    Select {
+        cond: NodeRef,
+        on_true: NodeRef,
+        on_false: NodeRef,
+    },
+    Clamp {
+        min: NodeRef,
+        expr: NodeRef,
+        max: NodeRef,
+    },
+    Concat {
+        nodes: Vec<NodeRef>,
+        dimension: usize,
+    },
+    Reshape {
+        expr: NodeRef,
+        new_sizes: Vec<i64>,
+    },
+    Broadcast {
+        expr: NodeRef,
+        sizes: Vec<i64>,
+    },
+    BroadcastInDim {
+        expr: NodeRef,
+        sizes: Vec<i64>,
+        broadcast_dims: Vec<i64>,
+    },
+    Transpose {
+        expr: NodeRef,
+        permutation: Vec<i64>,
+    },
+    Gather {
+        expr: NodeRef,
+        indices: NodeRef,
+        offset_dims: Vec<i64>,
+        collapsed_slice_dims: Vec<i64>,
+        start_index_map: Vec<i64>,
+        slice_sizes: Vec<i64>,
+        index_vector_dim: i64,
+    },
+    Slice {
+        expr: NodeRef,
+        start_indices: Vec<i64>,
+        stop_indices: Vec<i64>,
+        strides: Vec<i64>,
+    },
+    DynamicSlice {
+        expr: NodeRef,
+        start_indices: Vec<NodeRef>,
+        size_indices: Vec<i64>,
+    },
+    DynamicUpdateSlice {
+        expr: NodeRef,
+        start_indices: Vec<NodeRef>,
+        update: NodeRef,
+    },
+    Scatter {
+        expr: NodeRef,
+        indices: NodeRef,
+        updates: NodeRef,
+        update_window_dims: Vec<i64>,
+        inserted_window_dims: Vec<i64>,
+        scatter_dims_to_operand_dims: Vec<i64>,
+        index_vector_dim: i64,
+    },
+    Reduce {
+        expr: NodeRef,
+        #[serde(with = "ReduceOpDef")]
+        op: ReduceOp,
+        dims: Vec<i64>,
+    },
+    Cholesky(NodeRef),
+    TriangularSolve {
+        a: NodeRef,
+        b: NodeRef,
+        left_side: bool,
+        lower: bool,
+        unit_diagonal: bool,
+        transpose_a: bool,
+    },
+    Qr(NodeRef),
+    Eigh(NodeRef),
+    Svd(NodeRef),
+    Scan {
+        inputs: Vec<NodeRef>,
+        initial_state: NodeRef,
+        scan_fn: FnDef,
+    },
+    Cond {
+        pred: NodeRef,
+        operands: Vec<NodeRef>,
+        on_true: FnDef,
+        on_false: FnDef,
+    },
+    While {
+        initial_state: NodeRef,
+        cond_fn: FnDef,
+        body_fn: FnDef,
+    },
+}
+
+impl FnDef {
+    fn new(func: &NoxprFn) -> Result<Self, Error> {
+        let mut writer = GraphWriter::default();
+        let args = writer.write_all(&func.args)?;
+        let inner = writer.write(&func.inner)?;
+        Ok(FnDef {
+            nodes: writer.nodes,
+            args,
+            inner,
+        })
+    }
+
+    fn into_fn(self) -> Result<NoxprFn, Error> {
+        let mut exprs: Vec<Noxpr> = Vec::with_capacity(self.nodes.len());
+        for node in self.nodes {
+            // nodes may only refer to nodes before them, which rules out cycles
+            let expr = GraphReader { exprs: &exprs }.read(node)?;
+            exprs.push(expr);
+        }
+        let reader = GraphReader { exprs: &exprs };
+        let args = reader.get_all(&self.args)?;
+        let inner = reader.get(self.inner)?;
+        Ok(NoxprFn::new(args, inner))
+    }
+}
+
+impl From<&ArrayTy> for ArrayTyDef {
+    fn from(ty: &ArrayTy) -> Self {
+        ArrayTyDef {
+            element_type: ty.element_type,
+            shape: ty.shape.to_vec(),
+        }
+    }
+}
+
+impl From<ArrayTyDef> for ArrayTy {
+    fn from(ty: ArrayTyDef) -> Self {
+        ArrayTy::new(ty.element_type, ty.shape.into())
+    }
+}
+
+impl From<&NoxprTy> for TyDef {
+    fn from(ty: &NoxprTy) -> Self {
+        match ty {
+            NoxprTy::Tuple(tys) => TyDef::Tuple(tys.iter().map(TyDef::from).collect()),
+            NoxprTy::ArrayTy(ty) => TyDef::Array(ty.into()),
+        }
+    }
+}
+
+impl From<TyDef> for NoxprTy {
+    fn from(ty: TyDef) -> Self {
+        match ty {
+            TyDef::Tuple(tys) => NoxprTy::Tuple(tys.into_iter().map(NoxprTy::from).collect()),
+            TyDef::Array(ty) => NoxprTy::ArrayTy(ty.into()),
+        }
+    }
+}
+
+#[derive(Default)]
+struct GraphWriter {
+    nodes: Vec<NodeDef>,
+    refs: HashMap<NoxprId, NodeRef>,
+}
+
+impl GraphWriter {
+    fn write_all(&mut self, exprs: &[Noxpr]) -> Result<Vec<NodeRef>, Error> {
+        exprs.iter().map(|e| self.write(e)).collect()
+    }
+
+    fn write_binary(&mut self, op: &BinaryOp) -> Result<BinaryDef, Error> {
+        Ok(BinaryDef {
+            lhs: self.write(&op.lhs)?,
+            rhs: self.write(&op.rhs)?,
+        })
+    }
+
+    fn write(&mut self, expr: &Noxpr) -> Result<NodeRef, Error> {
+        if let Some(node_ref) = self.refs.get(&expr.id()) {
+            return Ok(*node_ref);
+        }
+        let node = match expr.deref() {
+            NoxprNode::Param(p) => NodeDef::Param {
+                number: p.number,
+                name: p.name.clone(),
+                ty: (&p.ty).into(),
+            },
+            NoxprNode::Tuple(exprs) => NodeDef::Tuple(self.write_all(exprs)?),
+            NoxprNode::GetTupleElement(g) => NodeDef::GetTupleElement {
+                expr: self.write(&g.expr)?,
+                index: g.index,
+            },
+            NoxprNode::Constant(c) => NodeDef::Constant {
+                ty: (&c.ty).into(),
+                data: c.data.raw_buf().to_vec(),
+            },
+            NoxprNode::Iota(i) => NodeDef::Iota {
+                ty: (&i.shape).into(),
+                dim: i.dim,
+            },
+            NoxprNode::Add(op) => NodeDef::Add(self.write_binary(op)?),
+            NoxprNode::Sub(op) => NodeDef::Sub(self.write_binary(op)?),
+            NoxprNode::Mul(op) => NodeDef::Mul(self.write_binary(op)?),
+            NoxprNode::Div(op) => NodeDef::Div(self.write_binary(op)?),
+            NoxprNode::And(op) => NodeDef::And(self.write_binary(op)?),
+            NoxprNode::Or(op) => NodeDef::Or(self.write_binary(op)?),
+            NoxprNode::Xor(op) => NodeDef::Xor(self.write_binary(op)?),
+            NoxprNode::ShiftLeft(op) => NodeDef::ShiftLeft(self.write_binary(op)?),
+            NoxprNode::ShiftRightLogical(op) => NodeDef::ShiftRightLogical(self.write_binary(op)?),
+            NoxprNode::GreaterOrEqual(op) => NodeDef::GreaterOrEqual(self.write_binary(op)?),
+            NoxprNode::LessOrEqual(op) => NodeDef::LessOrEqual(self.write_binary(op)?),
+            NoxprNode::Less(op) => NodeDef::Less(self.write_binary(op)?),
+            NoxprNode::Greater(op) => NodeDef::Greater(self.write_binary(op)?),
+            NoxprNode::Equal(op) => NodeDef::Equal(self.write_binary(op)?),
+            NoxprNode::NotEqual(op) => NodeDef::NotEqual(self.write_binary(op)?),
+            NoxprNode::Atan2(op) => NodeDef::Atan2(self.write_binary(op)?),
+            NoxprNode::Pow(op) => NodeDef::Pow(self.write_binary(op)?),
+            NoxprNode::Max(op) => NodeDef::Max(self.write_binary(op)?),
+            NoxprNode::Min(op) => NodeDef::Min(self.write_binary(op)?),
+            NoxprNode::Dot(op) => NodeDef::Dot(self.write_binary(op)?),
+            NoxprNode::DotGeneral(d) => NodeDef::DotGeneral {
+                lhs: self.write(&d.lhs)?,
+                rhs: self.write(&d.rhs)?,
+                lhs_contracting_dimensions: d.dimensions.lhs_contracting_dimensions.to_vec(),
+                rhs_contracting_dimensions: d.dimensions.rhs_contracting_dimensions.to_vec(),
+                lhs_batch_dimensions: d.dimensions.lhs_batch_dimensions.to_vec(),
+                rhs_batch_dimensions: d.dimensions.rhs_batch_dimensions.to_vec(),
+            },
+            NoxprNode::Sqrt(e) => NodeDef::Sqrt(self.write(e)?),
+            NoxprNode::Neg(e) => NodeDef::Neg(self.write(e)?),
+            NoxprNode::Log(e) => NodeDef::Log(self.write(e)?),
+            NoxprNode::Sin(e) => NodeDef::Sin(self.write(e)?),
+            NoxprNode::Cos(e) => NodeDef::Cos(self.write(e)?),
+            NoxprNode::Exp(e) => NodeDef::Exp(self.write(e)?),
+            NoxprNode::Tanh(e) => NodeDef::Tanh(self.write(e)?),
+            NoxprNode::Abs(e) => NodeDef::Abs(self.write(e)?),
+            NoxprNode::Floor(e) => NodeDef::Floor(self.write(e)?),
+            NoxprNode::Ceil(e) => NodeDef::Ceil(self.write(e)?),
+            NoxprNode::Rsqrt(e) => NodeDef::Rsqrt(self.write(e)?),
+            NoxprNode::Convert(c) => NodeDef::Convert {
+                expr: self.write(&c.expr)?,
+                element_type: c.element_type,
+            },
+            NoxprNode::Select(s) => NodeDef::Select {
+                cond: self.write(&s.cond)?,
+                on_true: self.write(&s.on_true)?,
+                on_false: self.write(&s.on_false)?,
+            },
+            NoxprNode::Clamp(c) => NodeDef::Clamp {
+                min: self.write(&c.min)?,
+                expr: self.write(&c.expr)?,
+                max: self.write(&c.max)?,
+            },
+            NoxprNode::Concat(c) => NodeDef::Concat {
+                nodes: self.write_all(&c.nodes)?,
+                dimension: c.dimension,
+            },
+            NoxprNode::Reshape(r) => NodeDef::Reshape {
+                expr: self.write(&r.expr)?,
+                new_sizes: r.new_sizes.to_vec(),
+            },
+            NoxprNode::Broadcast(b) => NodeDef::Broadcast {
+                expr: self.write(&b.expr)?,
+                sizes: b.sizes.to_vec(),
+            },
+            NoxprNode::BroadcastInDim(b) => NodeDef::BroadcastInDim {
+                expr: self.write(&b.expr)?,
+                sizes: b.sizes.to_vec(),
+                broadcast_dims: b.broadcast_dims.to_vec(),
+            },
+            NoxprNode::Transpose(t) => NodeDef::Transpose {
+                expr: self.write(&t.expr)?,
+                permutation: t.permutation.to_vec(),
+            },
+            NoxprNode::Gather(g) => NodeDef::Gather {
+                expr: self.write(&g.expr)?,
+                indices: self.write(&g.indices)?,
+                offset_dims: g.offset_dims.to_vec(),
+                collapsed_slice_dims: g.collapsed_slice_dims.to_vec(),
+                start_index_map: g.start_index_map.to_vec(),
+                slice_sizes: g.slice_sizes.to_vec(),
+                index_vector_dim: g.index_vector_dim,
+            },
+            NoxprNode::Slice(s) => NodeDef::Slice {
+                expr: self.write(&s.expr)?,
+                start_indices: s.start_indices.to_vec(),
+                stop_indices: s.stop_indices.to_vec(),
+                strides: s.strides.to_vec(),
+            },
+            NoxprNode::DynamicSlice(d) => NodeDef::DynamicSlice {
+                expr: self.write(&d.expr)?,
+                start_indices: self.write_all(&d.start_indices)?,
+                size_indices: d.size_indices.to_vec(),
+            },
+            NoxprNode::DynamicUpdateSlice(d) => NodeDef::DynamicUpdateSlice {
+                expr: self.write(&d.expr)?,
+                start_indices: self.write_all(&d.start_indicies)?,
+                update: self.write(&d.update)?,
+            },
+            NoxprNode::Scatter(s) => NodeDef::Scatter {
+                expr: self.write(&s.expr)?,
+                indices: self.write(&s.indices)?,
+                updates: self.write(&s.updates)?,
+                update_window_dims: s.update_window_dims.to_vec(),
+                inserted_window_dims: s.inserted_window_dims.to_vec(),
+                scatter_dims_to_operand_dims: s.scatter_dims_to_operand_dims.to_vec(),
+                index_vector_dim: s.index_vector_dim,
+            },
+            NoxprNode::Reduce(r) => NodeDef::Reduce {
+                expr: self.write(&r.expr)?,
+                op: r.op,
+                dims: r.dims.to_vec(),
+            },
+            NoxprNode::Cholesky(e) => NodeDef::Cholesky(self.write(e)?),
+            NoxprNode::TriangularSolve(t) => NodeDef::TriangularSolve {
+                a: self.write(&t.a)?,
+                b: self.write(&t.b)?,
+                left_side: t.left_side,
+                lower: t.lower,
+                unit_diagonal: t.unit_diagonal,
+                transpose_a: t.transpose_a,
+            },
+            NoxprNode::Qr(e) => NodeDef::Qr(self.write(e)?),
+            NoxprNode::Eigh(e) => NodeDef::Eigh(self.write(e)?),
+            NoxprNode::Svd(e) => NodeDef::Svd(self.write(e)?),
+            NoxprNode::Scan(s) => NodeDef::Scan {
+                inputs: self.write_all(&s.inputs)?,
+                initial_state: self.write(&s.initial_state)?,
+                scan_fn: FnDef::new(&s.scan_fn)?,
+            },
+            NoxprNode::Cond(c) => NodeDef::Cond {
+                pred: self.write(&c.pred)?,
+                operands: self.write_all(&c.operands)?,
+                on_true: FnDef::new(&c.on_true)?,
+                on_false: FnDef::new(&c.on_false)?,
+            },
+            NoxprNode::While(w) => NodeDef::While {
+                initial_state: self.write(&w.initial_state)?,
+                cond_fn: FnDef::new(&w.cond_fn)?,
+                body_fn: FnDef::new(&w.body_fn)?,
+            },
+            #[cfg(feature = "jax")]
+            NoxprNode::Jax(_) => return Err(Error::UnserializableNode("jax")),
+        };
+        let node_ref = self.nodes.len();
+        self.nodes.push(node);
+        self.refs.insert(expr.id(), node_ref);
+        Ok(node_ref)
+    }
+}
+
+struct GraphReader<'a> {
+    exprs: &'a [Noxpr],
+}
+
+impl GraphReader<'_> {
+    fn get(&self, node_ref: NodeRef) -> Result<Noxpr, Error> {
+        self.exprs
+            .get(node_ref)
+            .cloned()
+            .ok_or(Error::MalformedNoxprArchive)
+    }
+
+    fn get_all(&self, node_refs: &[NodeRef]) -> Result<Vec<Noxpr>, Error> {
+        node_refs.iter().map(|r| self.get(*r)).collect()
+    }
+
+    fn binary(&self, op: BinaryDef) -> Result<BinaryOp, Error> {
+        Ok(BinaryOp {
+            lhs: self.get(op.lhs)?,
+            rhs: self.get(op.rhs)?,
+        })
+    }
+
+    fn read(&self, node: NodeDef) -> Result<Noxpr, Error> {
+        let node = match node {
+            NodeDef::Param { number, name, ty } => NoxprNode::Param(ParamExpr {
+                number,
+                name,
+                ty: ty.into(),
+            }),
+            NodeDef::Tuple(refs) => NoxprNode::Tuple(self.get_all(&refs)?),
+            NodeDef::GetTupleElement { expr, index } => {
+                NoxprNode::GetTupleElement(GetTupleElement {
+                    expr: self.get(expr)?,
+                    index,
+                })
+            }
+            NodeDef::Constant { ty, data } => {
+                let data = Literal::create_from_raw_buf(ty.element_type, &ty.shape, &data)?;
+                return Ok(Noxpr::constant(data, ty.into()));
+            }
+            NodeDef::Iota { ty, dim } => NoxprNode::Iota(Iota {
+                shape: ty.into(),
+                dim,
+            }),
+            NodeDef::Add(op) => NoxprNode::Add(self.binary(op)?),
+            NodeDef::Sub(op) => NoxprNode::Sub(self.binary(op)?),
+            NodeDef::Mul(op) => NoxprNode::Mul(self.binary(op)?),
+            NodeDef::Div(op) => NoxprNode::Div(self.binary(op)?),
+            NodeDef::And(op) => NoxprNode::And(self.binary(op)?),
+            NodeDef::Or(op) => NoxprNode::Or(self.binary(op)?),
+            NodeDef::Xor(op) => NoxprNode::Xor(self.binary(op)?),
+            NodeDef::ShiftLeft(op) => NoxprNode::ShiftLeft(self.binary(op)?),
+            NodeDef::ShiftRightLogical(op) => NoxprNode::ShiftRightLogical(self.binary(op)?),
+            NodeDef::GreaterOrEqual(op) => NoxprNode::GreaterOrEqual(self.binary(op)?),
+            NodeDef::LessOrEqual(op) => NoxprNode::LessOrEqual(self.binary(op)?),
+            NodeDef::Less(op) => NoxprNode::Less(self.binary(op)?),
+            NodeDef::Greater(op) => NoxprNode::Greater(self.binary(op)?),
+            NodeDef::Equal(op) => NoxprNode::Equal(self.binary(op)?),
+            NodeDef::NotEqual(op) => NoxprNode::NotEqual(self.binary(op)?),
+            NodeDef::Atan2(op) => NoxprNode::Atan2(self.binary(op)?),
+            NodeDef::Pow(op) => NoxprNode::Pow(self.binary(op)?),
+            NodeDef::Max(op) => NoxprNode::Max(self.binary(op)?),
+            NodeDef::Min(op) => NoxprNode::Min(self.binary(op)?),
+            NodeDef::Dot(op) => NoxprNode::Dot(self.binary(op)?),
+            NodeDef::DotGeneral {
+                lhs,
+                rhs,
+                lhs_contracting_dimensions,
+                rhs_contracting_dimensions,
+                lhs_batch_dimensions,
+                rhs_batch_dimensions,
+            } => NoxprNode::DotGeneral(DotGeneral {
+                lhs: self.get(lhs)?,
+                rhs: self.get(rhs)?,
+                dimensions: DotDimensionNums {
+                    lhs_contracting_dimensions: lhs_contracting_dimensions.into(),
+                    rhs_contracting_dimensions: rhs_contracting_dimensions.into(),
+                    lhs_batch_dimensions: lhs_batch_dimensions.into(),
+                    rhs_batch_dimensions: rhs_batch_dimensions.into(),
+                },
+            }),
+            NodeDef::Sqrt(e) => NoxprNode::Sqrt(self.get(e)?),
+            NodeDef::Neg(e) => NoxprNode::Neg(self.get(e)?),
+            NodeDef::Log(e) => NoxprNode::Log(self.get(e)?),
+            NodeDef::Sin(e) => NoxprNode::Sin(self.get(e)?),
+            NodeDef::Cos(e) => NoxprNode::Cos(self.get(e)?),
+            NodeDef::Exp(e) => NoxprNode::Exp(self.get(e)?),
+            NodeDef::Tanh(e) => NoxprNode::Tanh(self.get(e)?),
+            NodeDef::Abs(e) => NoxprNode::Abs(self.get(e)?),
+            NodeDef::Floor(e) => NoxprNode::Floor(self.get(e)?),
+            NodeDef::Ceil(e) => NoxprNode::Ceil(self.get(e)?),
+            NodeDef::Rsqrt(e) => NoxprNode::Rsqrt(self.get(e)?),
+            NodeDef::Convert { expr, element_type } => NoxprNode::Convert(Convert {
+                expr: self.get(expr)?,
+                element_type,
+            }),
+            NodeDef::Select {
+                cond,
+                on_true,
+                on_false,
+            } => NoxprNode::Select(Select {
+                cond: self.get(cond)?,
+                on_true: self.get(on_true)?,
+                on_false: self.get(on_false)?,
+            }),
+            NodeDef::Clamp { min, expr, max } => NoxprNode::Clamp(Clamp {
+                min: self.get(min)?,
+                expr: self.get(expr)?,
+                max: self.get(max)?,
+            }),
+            NodeDef::Concat { nodes, dimension } => NoxprNode::Concat(Concat {
+                nodes: self.get_all(&nodes)?,
+                dimension,
+            }),
+            NodeDef::Reshape { expr, new_sizes } => NoxprNode::Reshape(Reshape {
+                expr: self.get(expr)?,
+                new_sizes: new_sizes.into(),
+            }),
+            NodeDef::Broadcast { expr, sizes } => NoxprNode::Broadcast(Broadcast {
+                expr: self.get(expr)?,
+                sizes: sizes.into(),
+            }),
+            NodeDef::BroadcastInDim {
+                expr,
+                sizes,
+                broadcast_dims,
+            } => NoxprNode::BroadcastInDim(BroadcastInDim {
+                expr: self.get(expr)?,
+                sizes: sizes.into(),
+                broadcast_dims: broadcast_dims.into(),
+            }),
+            NodeDef::Transpose { expr, permutation } => NoxprNode::Transpose(Transpose {
+                expr: self.get(expr)?,
+                permutation: permutation.into(),
+            }),
+            NodeDef::Gather {
+                expr,
+                indices,
+                offset_dims,
+                collapsed_slice_dims,
+                start_index_map,
+                slice_sizes,
+                index_vector_dim,
+            } => NoxprNode::Gather(Gather {
+                expr: self.get(expr)?,
+                indices: self.get(indices)?,
+                offset_dims: offset_dims.into(),
+                collapsed_slice_dims: collapsed_slice_dims.into(),
+                start_index_map: start_index_map.into(),
+                slice_sizes: slice_sizes.into(),
+                index_vector_dim,
+            }),
+            NodeDef::Slice {
+                expr,
+                start_indices,
+                stop_indices,
+                strides,
+            } => NoxprNode::Slice(Slice {
+                expr: self.get(expr)?,
+                start_indices: start_indices.into(),
+                stop_indices: stop_indices.into(),
+                strides: strides.into(),
+            }),
+            NodeDef::DynamicSlice {
+                expr,
+                start_indices,
+                size_indices,
+            } => NoxprNode::DynamicSlice(DynamicSlice {
+                expr: self.get(expr)?,
+                start_indices: self.get_all(&start_indices)?,
+                size_indices: size_indices.into(),
+            }),
+            NodeDef::DynamicUpdateSlice {
+                expr,
+                start_indices,
+                update,
+            } => NoxprNode::DynamicUpdateSlice(DynamicUpdateSlice {
+                expr: self.get(expr)?,
+                start_indicies: self.get_all(&start_indices)?,
+                update: self.get(update)?,
+            }),
+            NodeDef::Scatter {
+                expr,
+                indices,
+                updates,
+                update_window_dims,
+                inserted_window_dims,
+                scatter_dims_to_operand_dims,
+                index_vector_dim,
+            } => NoxprNode::Scatter(Scatter {
+                expr: self.get(expr)?,
+                indices: self.get(indices)?,
+                updates: self.get(updates)?,
+                update_window_dims: update_window_dims.into(),
+                inserted_window_dims: inserted_window_dims.into(),
+                scatter_dims_to_operand_dims: scatter_dims_to_operand_dims.into(),
+                index_vector_dim,
+            }),
+            NodeDef::Reduce { expr, op, dims } => NoxprNode::Reduce(Reduce {
+                expr: self.get(expr)?,
+                op,
+                dims: dims.into(),
+            }),
+            NodeDef::Cholesky(e) => NoxprNode::Cholesky(self.get(e)?),
+            NodeDef::TriangularSolve {
+                a,
+                b,
+                left_side,
+                lower,
+                unit_diagonal,
+                transpose_a,
+            } => NoxprNode::TriangularSolve(TriangularSolve {
+                a: self.get(a)?,
+                b: self.get(b)?,
+                left_side,
+                lower,
+                unit_diagonal,
+                transpose_a,
+            }),
+            NodeDef::Qr(e) => NoxprNode::Qr(self.get(e)?),
+            NodeDef::Eigh(e) => NoxprNode::Eigh(self.get(e)?),
+            NodeDef::Svd(e) => NoxprNode::Svd(self.get(e)?),
+            NodeDef::Scan {
+                inputs,
+                initial_state,
+                scan_fn,
+            } => NoxprNode::Scan(Scan {
+                inputs: self.get_all(&inputs)?,
+                initial_state: self.get(initial_state)?,
+                scan_fn: scan_fn.into_fn()?,
+            }),
+            NodeDef::Cond {
+                pred,
+                operands,
+                on_true,
+                on_false,
+            } => NoxprNode::Cond(Cond {
+                pred: self.get(pred)?,
+                operands: self.get_all(&operands)?,
+                on_true: on_true.into_fn()?,
+                on_false: on_false.into_fn()?,
+            }),
+            NodeDef::While {
+                initial_state,
+                cond_fn,
+                body_fn,
+            } => NoxprNode::While(While {
+                initial_state: self.get(initial_state)?,
+                cond_fn: cond_fn.into_fn()?,
+                body_fn: body_fn.into_fn()?,
+            }),
+        };
+        Ok(Noxpr::new(node))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Client, Comp, CompFn, FromOp, NoxprScalarExt, ToHost, Vector};
+    use nalgebra::vector;
+    use smallvec::smallvec;
+    use std::marker::PhantomData;
+
+    fn shifted(x: Vector<f64, 3>) -> Vector<f64, 3> {
+        let offset = Vector::<f64, 3>::from_op(2.0f64.constant().broadcast(smallvec![3]));
+        let a = x.clone() + offset;
+        a.clone() * a
+    }
+
+    #[test]
+    fn test_roundtrip_bytes() {
+        let func = shifted.build_expr().unwrap();
+        let decoded = NoxprFn::from_bytes(&func.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded.node_count(), func.node_count());
+        assert_eq!(decoded.to_string(), func.to_string());
+
+        let client = Client::cpu().unwrap();
+        let comp = Comp::<(Vector<f64, 3>,), Vector<f64, 3>> {
+            comp: decoded.build("decoded").unwrap().build().unwrap(),
+            phantom: PhantomData,
+        };
+        let exec = comp.compile(&client).unwrap();
+        let out = exec
+            .run(&client, vector![1.0, -2.0, 3.0])
+            .unwrap()
+            .to_host();
+        assert_eq!(out, vector![9.0, 0.0, 25.0]);
+    }
+
+    #[test]
+    fn test_roundtrip_json() {
+        let func = shifted.build_expr().unwrap();
+        let json = func.to_json().unwrap();
+        assert!(json.contains("\"V1\""));
+        let decoded = NoxprFn::from_json(&json).unwrap();
+        assert_eq!(decoded.to_string(), func.to_string());
+        assert_eq!(decoded.to_json().unwrap(), json);
+    }
+
+    #[test]
+    fn test_rejects_unknown_version() {
+        let func = shifted.build_expr().unwrap();
+        let mut buf = func.to_bytes().unwrap();
+        // the first byte is the archive version's variant index
+        buf[0] = 0x7f;
+        assert!(NoxprFn::from_bytes(&buf).is_err());
+    }
+}
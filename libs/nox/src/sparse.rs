@@ -0,0 +1,454 @@
+//! Provides sparse matrices, for systems too large to store densely.
+//!
+//! The sparsity pattern of a [`SparseMatrix`] is fixed when it's built, and only its values are
+//! traced, so every operation lowers to a gather from the dense operand followed by a scatter-add
+//! into the result.
+//!
+//! [`SparseMatrix::solve`] uses the conjugate gradient method, so it only converges for square,
+//! symmetric positive-definite matrices. Only the shape is checked, since the values are traced,
+//! so callers should check the returned residual to catch a solve that didn't converge.
+use crate::grad::scalar;
+use crate::{
+    ArrayTy, Dim, Error, FromOp, Noxpr, NoxprFn, NoxprScalarExt, NoxprTy, Scalar, Tensor,
+    TensorItem,
+};
+use nalgebra::Dyn;
+use smallvec::{smallvec, SmallVec};
+use std::marker::PhantomData;
+use xla::{ElementType, NativeType};
+
+/// A sparse `nrows` x `ncols` matrix, stored as coordinate (COO) triplets.
+///
+/// Each stored entry is at `(rows[i], cols[i])` with the value `values[i]`, and duplicate
+/// coordinates are summed. The indices live on the host, while the values are a rank-1 tensor.
+pub struct SparseMatrix<T: TensorItem> {
+    nrows: usize,
+    ncols: usize,
+    rows: Vec<i64>,
+    cols: Vec<i64>,
+    values: Noxpr,
+    phantom: PhantomData<T>,
+}
+
+/// The result of [`SparseMatrix::solve`].
+///
+/// The solve converged if `residual` is below the tolerance. Otherwise it ran out of iterations,
+/// and `x` is just the last iterate.
+pub struct CgSolution<T: TensorItem> {
+    /// The solution `x`.
+    pub x: Tensor<T, Dyn>,
+    /// The norm of the residual `b - a * x`.
+    pub residual: Scalar<T>,
+    /// The number of iterations run, at most `max_iters`.
+    pub iterations: Scalar<i64>,
+}
+
+impl<T: TensorItem> Clone for SparseMatrix<T> {
+    fn clone(&self) -> Self {
+        Self {
+            nrows: self.nrows,
+            ncols: self.ncols,
+            rows: self.rows.clone(),
+            cols: self.cols.clone(),
+            values: self.values.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: TensorItem> std::fmt::Debug for SparseMatrix<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SparseMatrix")
+            .field("nrows", &self.nrows)
+            .field("ncols", &self.ncols)
+            .field("rows", &self.rows)
+            .field("cols", &self.cols)
+            .field("values", &self.values)
+            .finish()
+    }
+}
+
+impl<T: TensorItem> SparseMatrix<T> {
+    /// Builds a matrix from coordinate triplets.
+    pub fn from_coo<D: Dim>(
+        nrows: usize,
+        ncols: usize,
+        rows: Vec<i64>,
+        cols: Vec<i64>,
+        values: Tensor<T, D>,
+    ) -> Result<Self, Error> {
+        let values = values.inner;
+        let len = match values.shape().ok_or(Error::UnknownShape)?.as_slice() {
+            [len] => *len,
+            _ => return Err(Error::InvalidSparseIndices),
+        };
+        let in_bounds =
+            |indices: &[i64], len: usize| indices.iter().all(|i| (0..len as i64).contains(i));
+        if rows.len() as i64 != len
+            || cols.len() as i64 != len
+            || !in_bounds(&rows, nrows)
+            || !in_bounds(&cols, ncols)
+        {
+            return Err(Error::InvalidSparseIndices);
+        }
+        Ok(Self {
+            nrows,
+            ncols,
+            rows,
+            cols,
+            values,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Builds a matrix with a one at each of the coordinates, like the adjacency matrix of a graph.
+    pub fn from_pattern(
+        nrows: usize,
+        ncols: usize,
+        rows: Vec<i64>,
+        cols: Vec<i64>,
+    ) -> Result<Self, Error> {
+        let ones = scalar(T::ELEM, 1.0)?.broadcast(smallvec![rows.len() as i64]);
+        Self::from_coo(nrows, ncols, rows, cols, Tensor::<T, Dyn>::from_op(ones))
+    }
+
+    /// Builds a matrix from compressed sparse row (CSR) arrays, where the entries of row `i` are
+    /// `row_offsets[i]..row_offsets[i + 1]`.
+    pub fn from_csr<D: Dim>(
+        nrows: usize,
+        ncols: usize,
+        row_offsets: &[i64],
+        cols: Vec<i64>,
+        values: Tensor<T, D>,
+    ) -> Result<Self, Error> {
+        if row_offsets.len() != nrows + 1
+            || row_offsets.first() != Some(&0)
+            || row_offsets.last() != Some(&(cols.len() as i64))
+            || row_offsets.windows(2).any(|w| w[0] > w[1])
+        {
+            return Err(Error::InvalidSparseIndices);
+        }
+        let rows = row_offsets
+            .windows(2)
+            .enumerate()
+            .flat_map(|(row, w)| std::iter::repeat(row as i64).take((w[1] - w[0]) as usize))
+            .collect();
+        Self::from_coo(nrows, ncols, rows, cols, values)
+    }
+
+    pub fn nrows(&self) -> usize {
+        self.nrows
+    }
+
+    pub fn ncols(&self) -> usize {
+        self.ncols
+    }
+
+    /// Returns the number of stored entries.
+    pub fn nnz(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn rows(&self) -> &[i64] {
+        &self.rows
+    }
+
+    pub fn cols(&self) -> &[i64] {
+        &self.cols
+    }
+
+    pub fn values(&self) -> Tensor<T, Dyn> {
+        Tensor::from_op(self.values.clone())
+    }
+
+    /// Returns a matrix with the same pattern and new values.
+    pub fn with_values<D: Dim>(&self, values: Tensor<T, D>) -> Result<Self, Error> {
+        Self::from_coo(
+            self.nrows,
+            self.ncols,
+            self.rows.clone(),
+            self.cols.clone(),
+            values,
+        )
+    }
+
+    pub fn transpose(&self) -> Self {
+        Self {
+            nrows: self.ncols,
+            ncols: self.nrows,
+            rows: self.cols.clone(),
+            cols: self.rows.clone(),
+            values: self.values.clone(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Multiplies the matrix by a dense vector or matrix, whose leading axis must have a length
+    /// of `ncols`.
+    pub fn matmul<D: Dim>(&self, rhs: &Tensor<T, D>) -> Result<Tensor<T, Dyn>, Error> {
+        self.matmul_expr(&rhs.inner).map(Tensor::from_op)
+    }
+
+    /// Returns the matrix as a dense `nrows` x `ncols` tensor.
+    pub fn to_dense(&self) -> Result<Tensor<T, Dyn>, Error> {
+        let indices = Noxpr::concat_in_dim(
+            vec![
+                index_constant(&self.rows).reshape(smallvec![self.nnz() as i64, 1]),
+                index_constant(&self.cols).reshape(smallvec![self.nnz() as i64, 1]),
+            ],
+            1,
+        );
+        let zeros =
+            scalar(T::ELEM, 0.0)?.broadcast(smallvec![self.nrows as i64, self.ncols as i64]);
+        let dense = zeros.scatter(
+            indices,
+            self.values.clone(),
+            smallvec![],
+            smallvec![0, 1],
+            smallvec![0, 1],
+            1,
+        );
+        Ok(Tensor::from_op(dense))
+    }
+
+    /// Solves `self * x = b` for a symmetric positive-definite matrix using the conjugate
+    /// gradient method.
+    ///
+    /// Iteration stops once the norm of the residual drops below `tolerance`, or after
+    /// `max_iters` iterations, whichever comes first. The returned [`CgSolution`] holds the final
+    /// residual norm and iteration count, so a solve that didn't converge can be detected.
+    pub fn solve<D: Dim>(
+        &self,
+        b: &Tensor<T, D>,
+        max_iters: usize,
+        tolerance: f64,
+    ) -> Result<CgSolution<T>, Error> {
+        let n = self.nrows as i64;
+        if self.nrows != self.ncols {
+            return Err(Error::ShapeMismatch);
+        }
+        if b.inner.shape().ok_or(Error::UnknownShape)?.as_slice() != [n] {
+            return Err(Error::ShapeMismatch);
+        }
+        if !matches!(
+            T::ELEM,
+            ElementType::F16 | ElementType::Bf16 | ElementType::F32 | ElementType::F64
+        ) {
+            return Err(Error::IncompatibleDType);
+        }
+        let vector_ty = NoxprTy::ArrayTy(ArrayTy::new(T::ELEM, smallvec![n]));
+        let scalar_ty = NoxprTy::ArrayTy(ArrayTy::new(T::ELEM, smallvec![]));
+        let count_ty = NoxprTy::ArrayTy(ArrayTy::new(ElementType::S64, smallvec![]));
+        // the state is (x, residual, search direction, squared residual norm, iteration)
+        let state_ty = NoxprTy::Tuple(vec![
+            vector_ty.clone(),
+            vector_ty.clone(),
+            vector_ty,
+            scalar_ty,
+            count_ty,
+        ]);
+
+        let state = Noxpr::parameter(0, state_ty.clone(), "state".to_string());
+        let (rs, i) = (state.get_tuple_element(3), state.get_tuple_element(4));
+        let cond = NoxprFn::new(
+            vec![state],
+            i.less((max_iters as i64).constant())
+                .and(rs.greater(scalar(T::ELEM, tolerance * tolerance)?)),
+        );
+
+        let state = Noxpr::parameter(0, state_ty, "state".to_string());
+        let [x, r, p, rs, i] = [0, 1, 2, 3, 4].map(|i| state.get_tuple_element(i));
+        let ap = self.matmul_expr(&p)?;
+        let alpha = (rs.clone() / p.clone().dot(&ap)).broadcast(smallvec![n]);
+        let x = x + alpha.clone() * p.clone();
+        let r = r - alpha * ap;
+        let rs_next = r.clone().dot(&r);
+        let beta = (rs_next.clone() / rs).broadcast(smallvec![n]);
+        let p = r.clone() + beta * p;
+        let body = NoxprFn::new(
+            vec![state],
+            Noxpr::tuple(vec![x, r, p, rs_next, i + 1i64.constant()]),
+        );
+
+        let b = b.inner.clone();
+        let initial_state = Noxpr::tuple(vec![
+            scalar(T::ELEM, 0.0)?.broadcast(smallvec![n]),
+            b.clone(),
+            b.clone(),
+            b.clone().dot(&b),
+            0i64.constant(),
+        ]);
+        let out = Noxpr::while_loop(initial_state, cond, body);
+        Ok(CgSolution {
+            x: Tensor::from_op(out.get_tuple_element(0)),
+            residual: Tensor::from_op(out.get_tuple_element(3).sqrt()),
+            iterations: Tensor::from_op(out.get_tuple_element(4)),
+        })
+    }
+
+    fn matmul_expr(&self, rhs: &Noxpr) -> Result<Noxpr, Error> {
+        let shape = rhs.shape().ok_or(Error::UnknownShape)?;
+        if shape.first() != Some(&(self.ncols as i64)) {
+            return Err(Error::ShapeMismatch);
+        }
+        // gather the rhs row for every entry, scale it, then sum each entry into its output row
+        let gathered = rhs.take_axis(0, &index_constant(&self.cols))?;
+        let mut products_shape = shape.clone();
+        products_shape[0] = self.nnz() as i64;
+        let products = gathered
+            * self
+                .values
+                .clone()
+                .broadcast_in_dim(products_shape, smallvec![0]);
+        let mut out_shape = shape;
+        out_shape[0] = self.nrows as i64;
+        let rank = out_shape.len() as i64;
+        let zeros = scalar(T::ELEM, 0.0)?.broadcast(out_shape);
+        Ok(zeros.scatter(
+            index_constant(&self.rows).reshape(smallvec![self.nnz() as i64, 1]),
+            products,
+            (1..rank).collect(),
+            smallvec![0],
+            smallvec![0],
+            1,
+        ))
+    }
+}
+
+fn index_constant(indices: &[i64]) -> Noxpr {
+    let shape: SmallVec<[i64; 4]> = smallvec![indices.len() as i64];
+    Noxpr::constant(
+        i64::create_r1(indices),
+        ArrayTy::new(ElementType::S64, shape),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Client, CompFn, Matrix, Scalar, ToHost, Vector};
+    use nalgebra::{matrix, vector};
+
+    // [[4, 1, 0], [1, 3, 0], [0, 0, 2]]
+    fn laplacian(values: Vector<f64, 5>) -> SparseMatrix<f64> {
+        SparseMatrix::from_csr(3, 3, &[0, 2, 4, 5], vec![0, 1, 0, 1, 2], values).unwrap()
+    }
+
+    #[test]
+    fn test_from_csr() {
+        let a = laplacian(Vector::zeros());
+        assert_eq!(a.rows(), &[0, 0, 1, 1, 2]);
+        assert_eq!(a.nnz(), 5);
+        assert!(
+            SparseMatrix::from_coo(2, 2, vec![0, 2], vec![0, 1], Vector::<f64, 2>::zeros())
+                .is_err()
+        );
+        assert!(
+            SparseMatrix::from_csr(2, 2, &[0, 2], vec![0, 1], Vector::<f64, 2>::zeros()).is_err()
+        );
+    }
+
+    #[test]
+    fn test_matmul() {
+        let client = Client::cpu().unwrap();
+        fn matmul(values: Vector<f64, 5>, x: Matrix<f64, 3, 2>) -> Matrix<f64, 3, 2> {
+            laplacian(values).matmul(&x).unwrap().reshape()
+        }
+        fn to_dense(values: Vector<f64, 5>) -> Matrix<f64, 3, 3> {
+            laplacian(values).transpose().to_dense().unwrap().reshape()
+        }
+        let values = vector![4.0, 1.0, 1.0, 3.0, 2.0];
+        let exec = matmul.build().unwrap().compile(&client).unwrap();
+        let out = exec
+            .run(&client, values, matrix![1.0, 0.0; 2.0, 1.0; 3.0, -1.0])
+            .unwrap()
+            .to_host();
+        assert_eq!(out, matrix![6.0, 1.0; 7.0, 3.0; 6.0, -2.0]);
+        let exec = to_dense.build().unwrap().compile(&client).unwrap();
+        let out = exec.run(&client, values).unwrap().to_host();
+        assert_eq!(out, matrix![4.0, 1.0, 0.0; 1.0, 3.0, 0.0; 0.0, 0.0, 2.0]);
+    }
+
+    #[test]
+    fn test_solve() {
+        let client = Client::cpu().unwrap();
+        fn cg<const N: usize>(values: Vector<f64, 5>, b: Vector<f64, 3>) -> CgSolution<f64> {
+            laplacian(values).solve(&b, N, 1e-12).unwrap()
+        }
+        fn solve<const N: usize>(values: Vector<f64, 5>, b: Vector<f64, 3>) -> Vector<f64, 3> {
+            cg::<N>(values, b).x.reshape()
+        }
+        fn residual<const N: usize>(values: Vector<f64, 5>, b: Vector<f64, 3>) -> Scalar<f64> {
+            cg::<N>(values, b).residual
+        }
+        fn iterations<const N: usize>(values: Vector<f64, 5>, b: Vector<f64, 3>) -> Scalar<i64> {
+            cg::<N>(values, b).iterations
+        }
+        let values = vector![4.0, 1.0, 1.0, 3.0, 2.0];
+        let b = vector![1.0, 2.0, 4.0];
+        let expected = vector![1.0 / 11.0, 7.0 / 11.0, 2.0];
+        let x = solve::<10>
+            .build()
+            .unwrap()
+            .compile(&client)
+            .unwrap()
+            .run(&client, values, b)
+            .unwrap()
+            .to_host();
+        approx::assert_relative_eq!(x, expected, epsilon = 1e-9);
+        let residual_norm = residual::<10>
+            .build()
+            .unwrap()
+            .compile(&client)
+            .unwrap()
+            .run(&client, values, b)
+            .unwrap()
+            .to_host();
+        assert!(residual_norm < 1e-12);
+        let iters = iterations::<10>
+            .build()
+            .unwrap()
+            .compile(&client)
+            .unwrap()
+            .run(&client, values, b)
+            .unwrap()
+            .to_host();
+        assert!(iters <= 3);
+
+        // a single iteration can't converge, which shows up in the residual and iteration count
+        let x = solve::<1>
+            .build()
+            .unwrap()
+            .compile(&client)
+            .unwrap()
+            .run(&client, values, b)
+            .unwrap()
+            .to_host();
+        assert!((x - expected).norm() > 1e-3);
+        let residual_norm = residual::<1>
+            .build()
+            .unwrap()
+            .compile(&client)
+            .unwrap()
+            .run(&client, values, b)
+            .unwrap()
+            .to_host();
+        assert!(residual_norm > 1e-3);
+        let iters = iterations::<1>
+            .build()
+            .unwrap()
+            .compile(&client)
+            .unwrap()
+            .run(&client, values, b)
+            .unwrap()
+            .to_host();
+        assert_eq!(iters, 1);
+
+        let wide = SparseMatrix::from_coo(2, 3, vec![0, 1], vec![0, 2], Vector::<f64, 2>::zeros())
+            .unwrap();
+        assert!(matches!(
+            wide.solve(&Vector::<f64, 2>::zeros(), 10, 1e-12),
+            Err(Error::ShapeMismatch)
+        ));
+    }
+}
@@ -2,8 +2,8 @@ use crate::Field;
 use crate::FixedSliceExt;
 use crate::Tensor;
 use crate::TensorItem;
-use crate::{Quaternion, Scalar, Vector};
-use nalgebra::Const;
+use crate::{Matrix, Quaternion, Scalar, Vector};
+use nalgebra::{Const, RealField};
 use nox_ecs_macros::{FromBuilder, FromOp, IntoOp};
 use std::ops::Div;
 use std::ops::{Add, Mul};
@@ -55,6 +55,29 @@ impl<T: TensorItem + Field> SpatialTransform<T> {
             inner: Tensor::zeros(),
         }
     }
+
+    /// Create a spatial transform with no rotation or translation
+    pub fn identity() -> Self {
+        Self::from_angular(Quaternion::identity())
+    }
+
+    /// Get the transform that undoes this one, such that `x.inverse() * x` is the identity
+    pub fn inverse(&self) -> Self {
+        let angular = self.angular().inverse();
+        let linear = -(angular.clone() * self.linear());
+        SpatialTransform::new(angular, linear)
+    }
+
+    /// Transforms a point from the child frame into the parent frame, by rotating and then
+    /// translating it
+    pub fn transform_point(&self, point: impl Into<Vector<T, 3>>) -> Vector<T, 3> {
+        self.angular() * point.into() + self.linear()
+    }
+
+    /// Rotates a direction from the child frame into the parent frame, ignoring the translation
+    pub fn transform_vector(&self, vector: impl Into<Vector<T, 3>>) -> Vector<T, 3> {
+        self.angular() * vector.into()
+    }
 }
 
 impl<T: TensorItem + ArrayElement + NativeType + Field> Mul for SpatialTransform<T> {
@@ -124,22 +147,58 @@ impl<T: Field> Add for SpatialForce<T> {
     }
 }
 
+/// A spatial inertia is a 10D vector that holds the mass properties of a rigid body.
+///
+/// The layout is `[Ixx, Iyy, Izz, hx, hy, hz, m, Ixy, Ixz, Iyz]`. The inertia tensor is taken about
+/// the body origin, and `h` is the first mass moment, which is the mass times the offset of the
+/// center of mass from the body origin.
 #[derive(FromBuilder, IntoOp, Clone, Debug, FromOp)]
 pub struct SpatialInertia<T: TensorItem> {
-    pub inner: Vector<T, 7>,
+    pub inner: Vector<T, 10>,
 }
 
 impl<T: TensorItem + Field + NativeType + ArrayElement> SpatialInertia<T> {
+    /// Create a spatial inertia from the diagonal of an inertia tensor about the body origin
     pub fn new(
         inertia: impl Into<Vector<T, 3>>,
         momentum: impl Into<Vector<T, 3>>,
         mass: impl Into<Scalar<T>>,
     ) -> Self {
-        let inertia = inertia.into();
-        let momentum = momentum.into();
-        let mass = mass.into().reshape::<Const<1>>();
-        let inner = inertia.concat(momentum).concat(mass);
-        SpatialInertia { inner }
+        Self::from_parts(
+            inertia.into(),
+            momentum.into(),
+            mass.into(),
+            Vector::zeros(),
+        )
+    }
+
+    /// Create a spatial inertia from a full symmetric inertia tensor about the body origin
+    pub fn from_tensor(
+        inertia: impl Into<Matrix<T, 3, 3>>,
+        momentum: impl Into<Vector<T, 3>>,
+        mass: impl Into<Scalar<T>>,
+    ) -> Self {
+        let (diag, products) = symmetric_parts(&inertia.into());
+        Self::from_parts(diag, momentum.into(), mass.into(), products)
+    }
+
+    /// Create a spatial inertia from an inertia tensor about the center of mass, and the offset of
+    /// the center of mass from the body origin
+    pub fn from_com(
+        inertia: impl Into<Matrix<T, 3, 3>>,
+        com: impl Into<Vector<T, 3>>,
+        mass: impl Into<Scalar<T>>,
+    ) -> Self {
+        let (diag, products) = symmetric_parts(&inertia.into());
+        let com = com.into();
+        let mass = mass.into();
+        let (shift_diag, shift_products) = point_inertia(&mass, &com);
+        Self::from_parts(
+            diag + shift_diag,
+            com * mass.clone(),
+            mass,
+            products + shift_products,
+        )
     }
 
     pub fn from_mass(mass: impl Into<Scalar<T>>) -> Self {
@@ -151,6 +210,17 @@ impl<T: TensorItem + Field + NativeType + ArrayElement> SpatialInertia<T> {
         )
     }
 
+    fn from_parts(
+        diag: Vector<T, 3>,
+        momentum: Vector<T, 3>,
+        mass: Scalar<T>,
+        products: Vector<T, 3>,
+    ) -> Self {
+        let mass = mass.reshape::<Const<1>>();
+        let inner = diag.concat(momentum).concat(mass).concat(products);
+        SpatialInertia { inner }
+    }
+
     pub fn inertia_diag(&self) -> Vector<T, 3> {
         self.inner.fixed_slice(&[0])
     }
@@ -160,14 +230,92 @@ impl<T: TensorItem + Field + NativeType + ArrayElement> SpatialInertia<T> {
     pub fn mass(&self) -> Scalar<T> {
         self.inner.fixed_slice::<Const<1>>(&[6]).reshape()
     }
+
+    /// Get the off-diagonal `[Ixy, Ixz, Iyz]` entries of the inertia tensor
+    pub fn inertia_products(&self) -> Vector<T, 3> {
+        self.inner.fixed_slice(&[7])
+    }
+
+    /// Get the inertia tensor about the body origin
+    pub fn inertia_tensor(&self) -> Matrix<T, 3, 3> {
+        symmetric(&self.inertia_diag(), &self.inertia_products())
+    }
+
+    /// Get the offset of the center of mass from the body origin
+    ///
+    /// A massless body has no first mass moment, so its center of mass sits at the body origin.
+    pub fn com(&self) -> Vector<T, 3> {
+        let mass = self.mass();
+        let mass = mass.equal(&T::zero()).select(&T::one(), &mass);
+        self.momentum() / mass
+    }
+
+    /// Get the inertia tensor about the center of mass
+    pub fn com_inertia_tensor(&self) -> Matrix<T, 3, 3> {
+        let (shift_diag, shift_products) = point_inertia(&self.mass(), &self.com());
+        symmetric(
+            &(self.inertia_diag() - shift_diag),
+            &(self.inertia_products() - shift_products),
+        )
+    }
+}
+
+impl<T: TensorItem + Field + RealField + NativeType + ArrayElement> SpatialInertia<T> {
+    /// Solves the Newton-Euler equations for a body moving at `vel` and acted on by `force`.
+    ///
+    /// `force` and `vel` must be expressed in the same axes as the inertia, about the body origin.
+    /// The result holds the angular acceleration and the acceleration of the body origin, and
+    /// includes the gyroscopic torque `ω × Iω` and the centripetal acceleration of an offset
+    /// center of mass.
+    pub fn accel(&self, force: &SpatialForce<T>, vel: &SpatialMotion<T>) -> SpatialMotion<T> {
+        let omega = vel.angular();
+        let com = self.com();
+        let inertia = self.com_inertia_tensor();
+        let gyro = omega.cross(&inertia.dot(&omega));
+        let torque = force.torque() - com.cross(&force.force()) - gyro;
+        let ang_accel = inertia.solve(&torque);
+        let accel =
+            force.force() / self.mass() + com.cross(&ang_accel) - omega.cross(&omega.cross(&com));
+        SpatialMotion::new(ang_accel, accel)
+    }
+}
+
+/// Returns the diagonal and the `[xy, xz, yz]` off-diagonal entries of a symmetric 3x3 matrix.
+fn symmetric_parts<T: Field>(matrix: &Matrix<T, 3, 3>) -> (Vector<T, 3>, Vector<T, 3>) {
+    let [xx, xy, xz, _, yy, yz, _, _, zz] = matrix.clone().reshape::<Const<9>>().parts();
+    (
+        Vector::from_arr([&xx, &yy, &zz]),
+        Vector::from_arr([&xy, &xz, &yz]),
+    )
+}
+
+/// Builds a symmetric 3x3 matrix from its diagonal and `[xy, xz, yz]` off-diagonal entries.
+fn symmetric<T: Field>(diag: &Vector<T, 3>, products: &Vector<T, 3>) -> Matrix<T, 3, 3> {
+    let [xx, yy, zz] = diag.parts();
+    let [xy, xz, yz] = products.parts();
+    Vector::<T, 9>::from_arr([&xx, &xy, &xz, &xy, &yy, &yz, &xz, &yz, &zz]).reshape()
 }
 
-impl<T: TensorItem + Field + NativeType + ArrayElement> Div<SpatialInertia<T>> for SpatialForce<T> {
+/// Returns the diagonal and off-diagonal entries of `m (|c|² E - c cᵀ)`, the inertia about the
+/// origin of a point mass `m` at `c`.
+fn point_inertia<T: Field>(mass: &Scalar<T>, c: &Vector<T, 3>) -> (Vector<T, 3>, Vector<T, 3>) {
+    let [x, y, z] = c.parts();
+    let (xx, yy, zz) = (&x * &x, &y * &y, &z * &z);
+    let diag = Vector::from_arr([&(&yy + &zz), &(&xx + &zz), &(&xx + &yy)]);
+    let products = Vector::from_arr([&(&x * &y), &(&x * &z), &(&y * &z)]);
+    (diag * mass, -(products * mass))
+}
+
+impl<T: TensorItem + Field + RealField + NativeType + ArrayElement> Div<SpatialInertia<T>>
+    for SpatialForce<T>
+{
     type Output = SpatialMotion<T>;
 
     fn div(self, rhs: SpatialInertia<T>) -> Self::Output {
-        let accel = self.force() / rhs.mass();
-        let ang_accel = self.torque() / rhs.inertia_diag();
+        let com = rhs.com();
+        let torque = self.torque() - com.cross(&self.force());
+        let ang_accel = rhs.com_inertia_tensor().solve(&torque);
+        let accel = self.force() / rhs.mass() + com.cross(&ang_accel);
         SpatialMotion::new(ang_accel, accel)
     }
 }
@@ -180,7 +328,8 @@ impl<T: TensorItem + ArrayElement + NativeType + Field> Mul<SpatialMotion<T>>
     fn mul(self, rhs: SpatialMotion<T>) -> Self::Output {
         let force: Vector<T, 3> =
             self.mass() * rhs.linear() - self.momentum().cross(&rhs.angular());
-        let torque = self.inertia_diag() * rhs.angular() + self.momentum().cross(&rhs.linear());
+        let torque =
+            self.inertia_tensor().dot(&rhs.angular()) + self.momentum().cross(&rhs.linear());
         SpatialForce::new(torque, force)
     }
 }
@@ -332,9 +481,35 @@ impl<T: Field> Mul<SpatialForce<T>> for Quaternion<T> {
     }
 }
 
+/// Changes the frame of a spatial motion from the child frame to the parent frame, which is the
+/// Plücker `X` operator. The linear part is moved from the child origin to the parent origin.
+impl<T: Field> Mul<SpatialMotion<T>> for SpatialTransform<T> {
+    type Output = SpatialMotion<T>;
+
+    fn mul(self, rhs: SpatialMotion<T>) -> Self::Output {
+        let rhs = self.angular() * rhs;
+        let angular = rhs.angular();
+        let linear = rhs.linear() + self.linear().cross(&angular);
+        SpatialMotion::new(angular, linear)
+    }
+}
+
+/// Changes the frame of a spatial force from the child frame to the parent frame, which is the
+/// Plücker `X*` operator. The torque is moved from the child origin to the parent origin.
+impl<T: Field> Mul<SpatialForce<T>> for SpatialTransform<T> {
+    type Output = SpatialForce<T>;
+
+    fn mul(self, rhs: SpatialForce<T>) -> Self::Output {
+        let rhs = self.angular() * rhs;
+        let force = rhs.force();
+        let torque = rhs.torque() + self.linear().cross(&force);
+        SpatialForce::new(torque, force)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{CompFn, ToHost};
+    use crate::{CompFn, ScalarExt, ToHost};
     use nalgebra::{vector, Vector3};
 
     use super::*;
@@ -402,4 +577,91 @@ mod tests {
             ]
         )
     }
+
+    #[test]
+    fn test_spatial_inertia_accel() {
+        let gyro = || -> Vector<f64, 6> {
+            let inertia = SpatialInertia::new(
+                vector![1.0, 2.0, 3.0],
+                vector![0.0, 0.0, 0.0],
+                1.0f64.constant(),
+            );
+            let vel = SpatialMotion::new(vector![1.0, 1.0, 0.0], vector![0.0, 0.0, 0.0]);
+            inertia.accel(&SpatialForce::zero(), &vel).inner
+        };
+        let offset = || -> Vector<f64, 6> {
+            let inertia = SpatialInertia::from_com(
+                nalgebra::Matrix3::identity(),
+                vector![1.0, 0.0, 0.0],
+                2.0f64.constant(),
+            );
+            let force = SpatialForce::new(vector![0.0, 0.0, 0.0], vector![0.0, 2.0, 0.0]);
+            (force / inertia).inner
+        };
+        let client = crate::Client::cpu().unwrap();
+        let exec = gyro.build().unwrap().compile(&client).unwrap();
+        let res = exec.run(&client).unwrap().to_host();
+        approx::assert_relative_eq!(
+            res,
+            vector![0.0, 0.0, -1.0 / 3.0, 0.0, 0.0, 0.0],
+            epsilon = 1e-9
+        );
+        let exec = offset.build().unwrap().compile(&client).unwrap();
+        let res = exec.run(&client).unwrap().to_host();
+        approx::assert_relative_eq!(res, vector![0.0, 0.0, -2.0, 0.0, 3.0, 0.0], epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_massless_com() {
+        let com = || -> Vector<f64, 3> {
+            SpatialInertia::new(
+                vector![0.0, 0.0, 0.0],
+                vector![0.0, 0.0, 0.0],
+                0.0f64.constant(),
+            )
+            .com()
+        };
+        let client = crate::Client::cpu().unwrap();
+        let exec = com.build().unwrap().compile(&client).unwrap();
+        let res = exec.run(&client).unwrap().to_host();
+        assert_eq!(res, vector![0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_spatial_transform_frames() {
+        let transform = || {
+            SpatialTransform::new(
+                nalgebra::UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 90f64.to_radians())
+                    .into_inner(),
+                vector![1.0, 0.0, 0.0],
+            )
+        };
+        let motion = || SpatialMotion::new(vector![0.0, 0.0, 1.0], vector![0.0, 0.0, 0.0]);
+        let client = crate::Client::cpu().unwrap();
+
+        let f = move || -> Vector<f64, 6> { (transform() * motion()).inner };
+        let exec = f.build().unwrap().compile(&client).unwrap();
+        let res = exec.run(&client).unwrap().to_host();
+        approx::assert_relative_eq!(res, vector![0.0, 0.0, 1.0, 0.0, -1.0, 0.0], epsilon = 1e-9);
+
+        let f = move || -> Vector<f64, 3> {
+            let x = transform();
+            x.inverse()
+                .transform_point(x.transform_point(vector![1.0, 2.0, 3.0]))
+        };
+        let exec = f.build().unwrap().compile(&client).unwrap();
+        let res = exec.run(&client).unwrap().to_host();
+        approx::assert_relative_eq!(res, vector![1.0, 2.0, 3.0], epsilon = 1e-9);
+
+        // the power of a force acting on a motion doesn't depend on the frame they're expressed in
+        let f = move || -> Scalar<f64> {
+            let force = SpatialForce::new(vector![0.5, -1.0, 2.0], vector![3.0, 1.0, -2.0]);
+            let x = transform();
+            let power = motion().inner.dot(&force.inner);
+            (x.clone() * motion()).inner.dot(&(x * force).inner) - power
+        };
+        let exec = f.build().unwrap().compile(&client).unwrap();
+        let res = exec.run(&client).unwrap().to_host();
+        approx::assert_relative_eq!(res, 0.0, epsilon = 1e-9);
+    }
 }
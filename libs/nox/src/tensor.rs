@@ -1,8 +1,8 @@
 //! Provides the core functionality for manipulating tensors.
 use crate::local_backend::{ArrayBufUnit, ArrayDim};
 use crate::{
-    AsBuffer, Buffer, Dim, DimGet, Field, FromOp, GetDim, IntoOp, MatMul, Noxpr, NoxprScalarExt,
-    Op, Repr, Scalar, Vector,
+    AsBuffer, Buffer, Dim, DimGet, Error, Field, FromOp, GetDim, IntoOp, MatMul, Noxpr,
+    NoxprScalarExt, Op, Repr, Scalar, Vector,
 };
 use core::mem::MaybeUninit;
 use nalgebra::{constraint::ShapeConstraint, ClosedMul, Const, Dyn, Scalar as NalgebraScalar};
@@ -152,6 +152,56 @@ impl<T: TensorItem, D: Dim> Tensor<T, D, Op> {
     pub fn cos(&self) -> Self {
         Self::from_op(self.inner.clone().cos())
     }
+
+    pub fn exp(&self) -> Self {
+        Self::from_op(self.inner.clone().exp())
+    }
+
+    pub fn tanh(&self) -> Self {
+        Self::from_op(self.inner.clone().tanh())
+    }
+
+    pub fn abs(&self) -> Self {
+        Self::from_op(self.inner.clone().abs())
+    }
+
+    pub fn floor(&self) -> Self {
+        Self::from_op(self.inner.clone().floor())
+    }
+
+    pub fn ceil(&self) -> Self {
+        Self::from_op(self.inner.clone().ceil())
+    }
+
+    pub fn rsqrt(&self) -> Self {
+        Self::from_op(self.inner.clone().rsqrt())
+    }
+
+    /// Computes the four-quadrant arctangent of `self / other`, element-wise.
+    pub fn atan2(&self, other: &Self) -> Self {
+        Self::from_op(self.inner.clone().atan2(other.inner.clone()))
+    }
+
+    pub fn pow(&self, exp: &Self) -> Self {
+        Self::from_op(self.inner.clone().pow(exp.inner.clone()))
+    }
+
+    pub fn max(&self, other: &Self) -> Self {
+        Self::from_op(self.inner.clone().max(other.inner.clone()))
+    }
+
+    pub fn min(&self, other: &Self) -> Self {
+        Self::from_op(self.inner.clone().min(other.inner.clone()))
+    }
+
+    /// Clamps every element to the range `[min, max]`.
+    pub fn clamp(&self, min: &Scalar<T>, max: &Scalar<T>) -> Self {
+        Self::from_op(
+            self.inner
+                .clone()
+                .clamp(min.inner.clone(), max.inner.clone()),
+        )
+    }
 }
 
 impl<T: Field, D: Dim> Tensor<T, D, Op> {
@@ -610,6 +660,17 @@ impl<T: TensorItem, D: Dim> Tensor<T, D> {
             phantom: PhantomData,
         }
     }
+
+    /// Converts every element to `U`, e.g from `f32` storage to `f64` for integration.
+    ///
+    /// Narrowing float conversions round to nearest, and float to integer conversions truncate
+    /// towards zero.
+    pub fn cast<U: TensorItem<Dim = T::Dim>>(&self) -> Tensor<U, D> {
+        Tensor {
+            inner: self.inner.clone().convert(U::ELEM),
+            phantom: PhantomData,
+        }
+    }
 }
 
 pub type AddDim<A, B> = <A as nalgebra::DimAdd<B>>::Output;
@@ -823,6 +884,40 @@ impl<T: Field, D1: Dim, R: Repr> Tensor<T, D1, R> {
             phantom: PhantomData,
         }
     }
+
+    /// Reinterprets the elements, in row-major order, with dimensions `D2`, failing if the
+    /// element counts differ.
+    pub fn try_reshape<D2: Dim>(&self) -> Result<Tensor<T, D2, R>, Error>
+    where
+        <D2 as ArrayDim>::Buf<MaybeUninit<T>>: ArrayBufUnit<T, Init = <D2 as ArrayDim>::Buf<T>>,
+    {
+        Ok(Tensor {
+            inner: R::reshape::<T, D1, D2>(&self.inner)?,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Broadcasts to dimensions `D2`, aligning the trailing dimensions like numpy does.
+    pub fn try_broadcast<D2: Dim>(&self) -> Result<Tensor<T, D2, R>, Error>
+    where
+        <D2 as ArrayDim>::Buf<MaybeUninit<T>>: ArrayBufUnit<T, Init = <D2 as ArrayDim>::Buf<T>>,
+    {
+        Ok(Tensor {
+            inner: R::broadcast::<T, D1, D2>(&self.inner)?,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Returns the slice with dimensions `D2` starting at `offsets`, failing if it doesn't fit.
+    pub fn try_fixed_slice<D2: Dim>(&self, offsets: &[usize]) -> Result<Tensor<T, D2, R>, Error>
+    where
+        <D2 as ArrayDim>::Buf<MaybeUninit<T>>: ArrayBufUnit<T, Init = <D2 as ArrayDim>::Buf<T>>,
+    {
+        Ok(Tensor {
+            inner: R::fixed_slice::<T, D1, D2>(&self.inner, offsets)?,
+            phantom: PhantomData,
+        })
+    }
 }
 
 /// Marker trait for types not equivalent to `Const<1>`, used in broadcasting logic.
@@ -1,4 +1,4 @@
-use nalgebra::{ArrayStorage, Const, DimMul, Scalar as NalgebraScalar, ToTypenum};
+use nalgebra::{ArrayStorage, Const, DimMul, RealField, Scalar as NalgebraScalar, ToTypenum};
 use num_traits::Zero;
 use smallvec::smallvec;
 use std::{marker::PhantomData, mem::MaybeUninit};
@@ -6,7 +6,7 @@ use xla::{ArrayElement, NativeType};
 
 use crate::{
     ArrayBufUnit, ArrayDim, ArrayTy, Buffer, BufferArg, Client, ConcatManyDim, Dim, Field,
-    FromHost, MaybeOwned, Noxpr, Op, Repr, Scalar, Tensor, TensorItem, ToHost,
+    FromHost, LocalBackend, MaybeOwned, Noxpr, Op, Repr, Scalar, Tensor, TensorItem, ToHost,
 };
 
 pub type Vector<T, const N: usize, P = Op> = Tensor<T, Const<N>, P>;
@@ -125,6 +125,28 @@ impl<T: Field, const N: usize> Vector<T, N, Op> {
     }
 }
 
+impl<T: Field + RealField> Vector<T, 3, LocalBackend> {
+    pub fn cross(&self, other: &Self) -> Self {
+        let [ax, ay, az] = *self.buf();
+        let [bx, by, bz] = *other.buf();
+        Vector::from_buf([ay * bz - az * by, az * bx - ax * bz, ax * by - ay * bx])
+    }
+}
+
+impl<T: Field + RealField, const N: usize> Vector<T, N, LocalBackend> {
+    pub fn norm_squared(&self) -> Scalar<T, LocalBackend> {
+        self.dot(self)
+    }
+
+    pub fn norm(&self) -> Scalar<T, LocalBackend> {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(&self) -> Self {
+        self.clone() / self.norm()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use nalgebra::vector;
@@ -145,6 +167,24 @@ mod tests {
         assert_eq!(out, vector![1.0, 2.0, 3.0]);
     }
 
+    #[test]
+    fn test_vector_cast() {
+        let client = Client::cpu().unwrap();
+        fn cast(a: Vector<f32, 3>) -> Vector<f32, 3> {
+            let wide = a.cast::<f64>();
+            let narrow = a.cast::<half::f16>().cast::<f64>();
+            (wide + narrow).cast()
+        }
+        let comp = cast.build().unwrap();
+        let exec = comp.compile(&client).unwrap();
+        let out = exec
+            .run(&client, vector![1.0f32, 0.5, 2049.0])
+            .unwrap()
+            .to_host();
+        // 2049 isn't representable in f16, and rounds to 2048
+        assert_eq!(out, vector![2.0, 1.0, 4097.0]);
+    }
+
     #[test]
     fn test_vector_scalar_mult() {
         let client = Client::cpu().unwrap();
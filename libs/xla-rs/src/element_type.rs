@@ -147,24 +147,22 @@ impl ElementType {
     }
 }
 
-// Dummy F16 type.
-#[derive(Copy, Clone, Debug)]
-pub struct F16;
+/// IEEE 754 half precision float, backed by [`half::f16`].
+pub type F16 = half::f16;
 
 impl ArrayElement for F16 {
     const TY: ElementType = ElementType::F16;
     const ELEMENT_SIZE_IN_BYTES: usize = 2;
-    const ZERO: Self = Self;
+    const ZERO: Self = half::f16::ZERO;
 }
 
-// Dummy BF16 type.
-#[derive(Copy, Clone, Debug)]
-pub struct Bf16;
+/// Brain float, a 16 bit float with the exponent range of an `f32`, backed by [`half::bf16`].
+pub type Bf16 = half::bf16;
 
 impl ArrayElement for Bf16 {
     const TY: ElementType = ElementType::Bf16;
     const ELEMENT_SIZE_IN_BYTES: usize = 2;
-    const ZERO: Self = Self;
+    const ZERO: Self = half::bf16::ZERO;
 }
 
 macro_rules! element_type {
@@ -1,4 +1,6 @@
-use crate::{ArrayElement, Error, NativeType, PrimitiveType, RawShape, Result, Shape, Status};
+use crate::{
+    ArrayElement, ElementType, Error, NativeType, PrimitiveType, RawShape, Result, Shape, Status,
+};
 use bytemuck::AnyBitPattern;
 use cpp::{cpp, cpp_class};
 
@@ -77,6 +79,35 @@ impl Literal {
         Ok(lit)
     }
 
+    /// Creates an array literal by copying `data`, which must hold exactly the bytes of every
+    /// element in row-major order.
+    pub fn create_from_raw_buf(ty: ElementType, dims: &[i64], data: &[u8]) -> Result<Literal> {
+        let element_count = dims.iter().product::<i64>();
+        if dims.iter().any(|d| *d < 0)
+            || element_count as usize * ty.element_size_in_bytes() != data.len()
+        {
+            return Err(Error::CannotCreateLiteralWithData {
+                data_len_in_bytes: data.len(),
+                ty: ty.primitive_type(),
+                dims: dims.iter().map(|d| *d as usize).collect(),
+            });
+        }
+        let dims_ptr = dims.as_ptr();
+        let dims_len = dims.len();
+        let prim_type = ty.primitive_type() as i32;
+        let data_ptr = data.as_ptr();
+        let data_len = data.len();
+        let lit = unsafe {
+            cpp!([dims_ptr as "const int64_t*", dims_len as "size_t", prim_type as "int32_t", data_ptr as "const uint8_t*", data_len as "size_t"] -> Literal as "std::shared_ptr<Literal>" {
+                auto shape = ShapeUtil::MakeShape((PrimitiveType)prim_type, absl::Span(dims_ptr, dims_len));
+                auto lit = std::make_shared<Literal>(shape);
+                std::memcpy(lit->untyped_data(), data_ptr, data_len);
+                return lit;
+            })
+        };
+        Ok(lit)
+    }
+
     pub fn vector<T: NativeType>(vals: &[T]) -> Literal {
         T::create_r1(vals)
     }
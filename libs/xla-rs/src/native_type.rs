@@ -1,4 +1,4 @@
-use crate::{Literal, XlaBuilder, XlaOp, XlaOpRaw};
+use crate::{Bf16, Literal, XlaBuilder, XlaOp, XlaOpRaw, F16};
 use bytemuck::Pod;
 use cpp::cpp;
 
@@ -8,6 +8,7 @@ cpp! {{
     #include "xla/client/lib/matrix.h"
     #include "xla/statusor.h"
     #include "xla/literal_util.h"
+    #include "xla/types.h"
     #include "xla/pjrt/pjrt_api.h"
     #include "xla/pjrt/pjrt_c_api_client.h"
     #include "xla/pjrt/pjrt_client.h"
@@ -390,3 +391,99 @@ impl NativeType for i16 {
         }
     }
 }
+
+impl NativeType for F16 {
+    fn constant_r0(builder: &XlaBuilder, value: Self) -> XlaOp {
+        let bits = value.to_bits();
+        let raw = unsafe {
+            cpp!([builder as "std::shared_ptr<XlaBuilder>*", bits as "uint16_t"] -> XlaOpRaw as "XlaOp" {
+                return XlaOp(ConstantR0<Eigen::half>(builder->get(), Eigen::numext::bit_cast<Eigen::half>(bits)));
+            })
+        };
+        XlaOp {
+            raw,
+            builder: builder.clone(),
+        }
+    }
+
+    fn constant_r1(builder: &XlaBuilder, value: &[Self]) -> XlaOp {
+        let value_ptr = value.as_ptr();
+        let value_len = value.len();
+        let raw = unsafe {
+            cpp!([builder as "std::shared_ptr<XlaBuilder>*", value_ptr as "const Eigen::half*", value_len as "size_t"] -> XlaOpRaw as "XlaOp" {
+                return XlaOp(ConstantR1<Eigen::half>(builder->get(), absl::Span<const Eigen::half>(value_ptr, value_len)));
+            })
+        };
+        XlaOp {
+            raw,
+            builder: builder.clone(),
+        }
+    }
+
+    fn literal(self) -> Literal {
+        let bits = self.to_bits();
+        unsafe {
+            cpp!([bits as "uint16_t"] -> Literal as "std::shared_ptr<Literal>" {
+                return std::make_shared<Literal>(LiteralUtil::CreateR0<Eigen::half>(Eigen::numext::bit_cast<Eigen::half>(bits)));
+            })
+        }
+    }
+
+    fn create_r1(slice: &[Self]) -> Literal {
+        let value_ptr = slice.as_ptr();
+        let value_len = slice.len();
+        unsafe {
+            cpp!([value_ptr as "const Eigen::half*", value_len as "size_t"] -> Literal as "std::shared_ptr<Literal>" {
+                return std::make_shared<Literal>(LiteralUtil::CreateR1<Eigen::half>(absl::Span<const Eigen::half>(value_ptr, value_len)));
+            })
+        }
+    }
+}
+
+impl NativeType for Bf16 {
+    fn constant_r0(builder: &XlaBuilder, value: Self) -> XlaOp {
+        let bits = value.to_bits();
+        let raw = unsafe {
+            cpp!([builder as "std::shared_ptr<XlaBuilder>*", bits as "uint16_t"] -> XlaOpRaw as "XlaOp" {
+                return XlaOp(ConstantR0<Eigen::bfloat16>(builder->get(), Eigen::numext::bit_cast<Eigen::bfloat16>(bits)));
+            })
+        };
+        XlaOp {
+            raw,
+            builder: builder.clone(),
+        }
+    }
+
+    fn constant_r1(builder: &XlaBuilder, value: &[Self]) -> XlaOp {
+        let value_ptr = value.as_ptr();
+        let value_len = value.len();
+        let raw = unsafe {
+            cpp!([builder as "std::shared_ptr<XlaBuilder>*", value_ptr as "const Eigen::bfloat16*", value_len as "size_t"] -> XlaOpRaw as "XlaOp" {
+                return XlaOp(ConstantR1<Eigen::bfloat16>(builder->get(), absl::Span<const Eigen::bfloat16>(value_ptr, value_len)));
+            })
+        };
+        XlaOp {
+            raw,
+            builder: builder.clone(),
+        }
+    }
+
+    fn literal(self) -> Literal {
+        let bits = self.to_bits();
+        unsafe {
+            cpp!([bits as "uint16_t"] -> Literal as "std::shared_ptr<Literal>" {
+                return std::make_shared<Literal>(LiteralUtil::CreateR0<Eigen::bfloat16>(Eigen::numext::bit_cast<Eigen::bfloat16>(bits)));
+            })
+        }
+    }
+
+    fn create_r1(slice: &[Self]) -> Literal {
+        let value_ptr = slice.as_ptr();
+        let value_len = slice.len();
+        unsafe {
+            cpp!([value_ptr as "const Eigen::bfloat16*", value_len as "size_t"] -> Literal as "std::shared_ptr<Literal>" {
+                return std::make_shared<Literal>(LiteralUtil::CreateR1<Eigen::bfloat16>(absl::Span<const Eigen::bfloat16>(value_ptr, value_len)));
+            })
+        }
+    }
+}
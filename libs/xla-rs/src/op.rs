@@ -12,6 +12,9 @@ cpp! {{
     #include "xla/client/xla_builder.h"
     #include "xla/client/lib/constants.h"
     #include "xla/client/lib/matrix.h"
+    #include "xla/client/lib/qr.h"
+    #include "xla/client/lib/self_adjoint_eig.h"
+    #include "xla/client/lib/svd.h"
     #include "xla/statusor.h"
     #include "xla/literal_util.h"
     using namespace xla;
@@ -305,6 +308,34 @@ impl XlaOp {
         self.wrap(raw)
     }
 
+    pub fn shift_left(&self, rhs: &Self) -> Self {
+        let op = &self.raw;
+        let raw = unsafe {
+            cpp!([op as "const XlaOp*", rhs as "const XlaOp*"] -> XlaOpRaw as "XlaOp" {
+                try {
+                    return XlaOp(ShiftLeft(*op, *rhs));
+                }catch(std::exception& e) {
+                    return XlaOp(op->builder()->ReportError(tsl::errors::Internal(e.what())));
+                }
+            })
+        };
+        self.wrap(raw)
+    }
+
+    pub fn shift_right_logical(&self, rhs: &Self) -> Self {
+        let op = &self.raw;
+        let raw = unsafe {
+            cpp!([op as "const XlaOp*", rhs as "const XlaOp*"] -> XlaOpRaw as "XlaOp" {
+                try {
+                    return XlaOp(ShiftRightLogical(*op, *rhs));
+                }catch(std::exception& e) {
+                    return XlaOp(op->builder()->ReportError(tsl::errors::Internal(e.what())));
+                }
+            })
+        };
+        self.wrap(raw)
+    }
+
     pub fn eq(&self, rhs: &Self) -> Self {
         let op = &self.raw;
         let raw = unsafe {
@@ -683,6 +714,101 @@ impl XlaOp {
         self.wrap(raw)
     }
 
+    pub fn cholesky(&self, lower: bool) -> Self {
+        let op = &self.raw;
+        let raw = unsafe {
+            cpp!([op as "const XlaOp*", lower as "bool"] -> XlaOpRaw as "XlaOp" {
+                try {
+                    return XlaOp(Cholesky(*op, lower));
+                }catch(std::exception& e) {
+                    return XlaOp(op->builder()->ReportError(tsl::errors::Internal(e.what())));
+                }
+            })
+        };
+        self.wrap(raw)
+    }
+
+    pub fn triangular_solve(
+        &self,
+        b: &Self,
+        left_side: bool,
+        lower: bool,
+        unit_diagonal: bool,
+        transpose_a: bool,
+    ) -> Self {
+        let op = &self.raw;
+        let b = &b.raw;
+        let raw = unsafe {
+            cpp!([
+                op as "const XlaOp*",
+                b as "const XlaOp*",
+                left_side as "bool",
+                lower as "bool",
+                unit_diagonal as "bool",
+                transpose_a as "bool"
+            ] -> XlaOpRaw as "XlaOp" {
+                try {
+                    auto transpose = transpose_a ? TriangularSolveOptions::TRANSPOSE : TriangularSolveOptions::NO_TRANSPOSE;
+                    return XlaOp(TriangularSolve(*op, *b, left_side, lower, unit_diagonal, transpose));
+                }catch(std::exception& e) {
+                    return XlaOp(op->builder()->ReportError(tsl::errors::Internal(e.what())));
+                }
+            })
+        };
+        self.wrap(raw)
+    }
+
+    /// Computes the full QR decomposition, returning a `(q, r)` tuple.
+    pub fn qr(&self) -> Self {
+        let op = &self.raw;
+        let raw = unsafe {
+            cpp!([op as "const XlaOp*"] -> XlaOpRaw as "XlaOp" {
+                try {
+                    XlaOp q, r;
+                    QrExplicit(*op, true, q, r);
+                    return XlaOp(Tuple(op->builder(), {q, r}));
+                }catch(std::exception& e) {
+                    return XlaOp(op->builder()->ReportError(tsl::errors::Internal(e.what())));
+                }
+            })
+        };
+        self.wrap(raw)
+    }
+
+    /// Computes the eigen decomposition of a symmetric matrix, returning an `(eigenvectors, eigenvalues)` tuple
+    /// with the eigenvalues in ascending order.
+    pub fn eigh(&self, lower: bool) -> Self {
+        let op = &self.raw;
+        let raw = unsafe {
+            cpp!([op as "const XlaOp*", lower as "bool"] -> XlaOpRaw as "XlaOp" {
+                try {
+                    auto result = SelfAdjointEig(*op, lower);
+                    return XlaOp(Tuple(op->builder(), {result.v, result.w}));
+                }catch(std::exception& e) {
+                    return XlaOp(op->builder()->ReportError(tsl::errors::Internal(e.what())));
+                }
+            })
+        };
+        self.wrap(raw)
+    }
+
+    /// Computes the singular value decomposition, returning a `(u, s, vt)` tuple
+    /// with the singular values in descending order.
+    pub fn svd(&self) -> Self {
+        let op = &self.raw;
+        let raw = unsafe {
+            cpp!([op as "const XlaOp*"] -> XlaOpRaw as "XlaOp" {
+                try {
+                    auto result = SVD(*op);
+                    return XlaOp(Tuple(op->builder(), {result.u, result.d, TransposeInMinorDims(result.v)}));
+                }catch(std::exception& e) {
+                    return XlaOp(op->builder()->ReportError(tsl::errors::Internal(e.what())));
+                }
+            })
+        };
+        self.wrap(raw)
+    }
+
     /*pub fn einsum1(&self, config: &str,) -> Self {
      * let op = &self.raw;
         let raw = unsafe {